@@ -0,0 +1,407 @@
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
+
+use crate::compress::{longest_match, update_hash_chains, BitWriter, HASH_SIZE, MIN_MATCH, WINDOW_SIZE};
+
+// Brotli (RFC 7932) encoder: the same LZ77 matching as deflate, with one set of prefix codes
+// built for each meta-block. No static dictionary, block splitting or context modeling
+
+// Largest amount of input per meta-block, well below the insert length limit
+const META_BLOCK_SIZE: usize = 1 << 20;
+
+const INSERT_BASE: [u32; 24] = [
+    0, 1, 2, 3, 4, 5, 6, 8, 10, 14, 18, 26, 34, 50, 66, 98, 130, 194, 322, 578, 1090, 2114, 6210, 22594,
+];
+const INSERT_EXTRA: [u8; 24] = [0, 0, 0, 0, 0, 0, 1, 1, 2, 2, 3, 3, 4, 4, 5, 5, 6, 7, 8, 9, 10, 12, 14, 24];
+const COPY_BASE: [u32; 24] = [
+    2, 3, 4, 5, 6, 7, 8, 9, 10, 12, 14, 18, 22, 30, 38, 54, 70, 102, 134, 198, 326, 582, 1094, 2118,
+];
+const COPY_EXTRA: [u8; 24] = [0, 0, 0, 0, 0, 0, 0, 0, 1, 1, 2, 2, 3, 3, 4, 4, 5, 5, 6, 7, 8, 9, 10, 24];
+
+const LITERAL_ALPHABET: usize = 256;
+const COMMAND_ALPHABET: usize = 704;
+// 16 special codes and 48 distance codes, with NPOSTFIX = 0 and NDIRECT = 0
+const DISTANCE_ALPHABET: usize = 64;
+
+// Order in which the code lengths of the code length alphabet are stored
+const CODE_LENGTH_ORDER: [usize; 18] = [1, 2, 3, 4, 0, 5, 17, 6, 16, 7, 8, 9, 10, 11, 12, 13, 14, 15];
+// Code length symbol repeating a zero length 3 to 10 times
+const REPEAT_ZERO: u8 = 17;
+
+// Literals followed by a backward copy. The last command of a meta-block may have no copy
+struct Command {
+    literals_start: usize,
+    insert: usize,
+    copy: usize,
+    distance: usize,
+}
+
+// Compress data into a brotli stream
+pub fn brotli(data: &[u8]) -> Vec<u8> {
+    let mut writer = BitWriter::default();
+    // WBITS = 16: a window of 65520 bytes, more than the matcher looks back
+    writer.write_bits(0, 1);
+
+    if data.is_empty() {
+        // ISLAST, ISLASTEMPTY
+        writer.write_bits(1, 1);
+        writer.write_bits(1, 1);
+        return writer.finish();
+    }
+
+    let mut head = vec![usize::MAX; HASH_SIZE];
+    let mut prev = vec![usize::MAX; WINDOW_SIZE];
+    let mut start = 0;
+    while start < data.len() {
+        let end = (start + META_BLOCK_SIZE).min(data.len());
+        let commands = find_commands(data, start, end, &mut head, &mut prev);
+        write_meta_block(&mut writer, data, &commands, end - start, end == data.len());
+        start = end;
+    }
+    writer.finish()
+}
+
+// Split data[start..end] into commands. Copies may reach back into earlier meta-blocks
+fn find_commands(data: &[u8], start: usize, end: usize, head: &mut [usize], prev: &mut [usize]) -> Vec<Command> {
+    let mut commands = Vec::new();
+    let mut literals_start = start;
+    let mut pos = start;
+
+    while pos < end {
+        let (length, distance) = longest_match(&data[..end], pos, head, prev);
+        let advance = if length >= MIN_MATCH {
+            commands.push(Command { literals_start, insert: pos - literals_start, copy: length, distance });
+            literals_start = pos + length;
+            length
+        } else {
+            1
+        };
+        update_hash_chains(data, pos, advance, head, prev);
+        pos += advance;
+    }
+    if literals_start < end {
+        commands.push(Command { literals_start, insert: end - literals_start, copy: 0, distance: 0 });
+    }
+    commands
+}
+
+fn write_meta_block(writer: &mut BitWriter, data: &[u8], commands: &[Command], length: usize, is_last: bool) {
+    writer.write_bits(is_last as u32, 1);
+    if is_last {
+        // ISLASTEMPTY
+        writer.write_bits(0, 1);
+    }
+    let mlen = (length - 1) as u32;
+    let nibbles = (32 - mlen.leading_zeros()).div_ceil(4).max(4);
+    writer.write_bits(nibbles - 4, 2);
+    writer.write_bits(mlen, (nibbles * 4) as u8);
+    if !is_last {
+        // ISUNCOMPRESSED
+        writer.write_bits(0, 1);
+    }
+
+    // One block type for literals, commands and distances
+    writer.write_bits(0, 1);
+    writer.write_bits(0, 1);
+    writer.write_bits(0, 1);
+    // NPOSTFIX, NDIRECT
+    writer.write_bits(0, 2);
+    writer.write_bits(0, 4);
+    // Context mode of the literal block type (unused with a single tree)
+    writer.write_bits(0, 2);
+    // NTREESL, NTREESD: one prefix code each, so no context maps
+    writer.write_bits(0, 1);
+    writer.write_bits(0, 1);
+
+    let mut literal_counts = vec![0u32; LITERAL_ALPHABET];
+    let mut command_counts = vec![0u32; COMMAND_ALPHABET];
+    let mut distance_counts = vec![0u32; DISTANCE_ALPHABET];
+    for command in commands {
+        for &byte in &data[command.literals_start..command.literals_start + command.insert] {
+            literal_counts[byte as usize] += 1;
+        }
+        command_counts[command_symbol(command)] += 1;
+        if command.copy > 0 {
+            distance_counts[distance_code(command.distance).0] += 1;
+        }
+    }
+
+    let literal_code = PrefixCode::new(&literal_counts);
+    let command_code = PrefixCode::new(&command_counts);
+    let distance_code_tree = PrefixCode::new(&distance_counts);
+    literal_code.write_description(writer, LITERAL_ALPHABET);
+    command_code.write_description(writer, COMMAND_ALPHABET);
+    distance_code_tree.write_description(writer, DISTANCE_ALPHABET);
+
+    for command in commands {
+        command_code.write_symbol(writer, command_symbol(command));
+        let insert_code = length_code(&INSERT_BASE, command.insert);
+        writer.write_bits(command.insert as u32 - INSERT_BASE[insert_code], INSERT_EXTRA[insert_code]);
+        let copy_code = length_code(&COPY_BASE, command.copy.max(2));
+        writer.write_bits(command.copy.max(2) as u32 - COPY_BASE[copy_code], COPY_EXTRA[copy_code]);
+
+        for &byte in &data[command.literals_start..command.literals_start + command.insert] {
+            literal_code.write_symbol(writer, byte as usize);
+        }
+        if command.copy > 0 {
+            let (symbol, extra_bits, extra) = distance_code(command.distance);
+            distance_code_tree.write_symbol(writer, symbol);
+            writer.write_bits(extra, extra_bits);
+        }
+    }
+}
+
+// Index of the largest base not above length
+fn length_code(base: &[u32], length: usize) -> usize {
+    base.iter().rposition(|&b| b as usize <= length).unwrap_or(0)
+}
+
+// Insert-and-copy length symbol of a command, always with an explicit distance
+fn command_symbol(command: &Command) -> usize {
+    let insert_code = length_code(&INSERT_BASE, command.insert);
+    let copy_code = length_code(&COPY_BASE, command.copy.max(2));
+    let low_bits = (copy_code & 7) | ((insert_code & 7) << 3);
+    // Cells of 64 symbols, ordered as in section 5 of RFC 7932
+    const CELLS: [[usize; 3]; 3] = [[128, 192, 384], [256, 320, 512], [448, 576, 640]];
+    CELLS[insert_code >> 3][copy_code >> 3] + low_bits
+}
+
+// Distance symbol, number of extra bits and their value for a distance of at least 1
+fn distance_code(distance: usize) -> (usize, u8, u32) {
+    let offset = distance + 3;
+    let extra_bits = (usize::BITS - 1 - offset.leading_zeros()) as usize - 1;
+    let prefix = (offset >> extra_bits) & 1;
+    let symbol = 16 + 2 * (extra_bits - 1) + prefix;
+    (symbol, extra_bits as u8, (offset - ((2 + prefix) << extra_bits)) as u32)
+}
+
+// A prefix code over an alphabet, with the canonical code of every used symbol
+struct PrefixCode {
+    lengths: Vec<u8>,
+    codes: Vec<u32>,
+    // Symbols with a non-zero count; a code with one of them uses zero bits per symbol
+    used: Vec<usize>,
+}
+
+impl PrefixCode {
+    fn new(counts: &[u32]) -> PrefixCode {
+        PrefixCode::new_limited(counts, 15)
+    }
+
+    fn new_limited(counts: &[u32], max_bits: u8) -> PrefixCode {
+        let used: Vec<usize> = (0..counts.len()).filter(|&s| counts[s] > 0).collect();
+        let lengths = if used.len() >= 2 { huffman_lengths(counts, max_bits) } else { vec![0; counts.len()] };
+        let codes = canonical_codes(&lengths);
+        PrefixCode { lengths, codes, used }
+    }
+
+    fn write_symbol(&self, writer: &mut BitWriter, symbol: usize) {
+        if self.lengths[symbol] > 0 {
+            writer.write_code(self.codes[symbol], self.lengths[symbol]);
+        }
+    }
+
+    fn write_description(&self, writer: &mut BitWriter, alphabet_size: usize) {
+        if self.used.len() < 2 {
+            // Simple prefix code with one symbol (HSKIP = 1, NSYM - 1 = 0)
+            let alphabet_bits = usize::BITS - (alphabet_size - 1).leading_zeros();
+            writer.write_bits(1, 2);
+            writer.write_bits(0, 2);
+            writer.write_bits(self.used.first().copied().unwrap_or(0) as u32, alphabet_bits as u8);
+            return;
+        }
+
+        // Code lengths up to the last used symbol, with runs of zeros shortened
+        let last = *self.used.last().unwrap();
+        let mut items: Vec<(u8, u8)> = Vec::new();
+        let mut i = 0;
+        while i <= last {
+            if self.lengths[i] == 0 {
+                let run = self.lengths[i..=last].iter().take_while(|&&l| l == 0).count();
+                push_zero_run(&mut items, run);
+                i += run;
+            } else {
+                items.push((self.lengths[i], 0));
+                i += 1;
+            }
+        }
+
+        let mut counts = [0u32; 18];
+        for &(symbol, _) in &items {
+            counts[symbol as usize] += 1;
+        }
+        let length_code = PrefixCode::new_limited(&counts, 5);
+
+        // HSKIP = 0, then the code length code lengths. The decoder stops reading them once the
+        // code is complete, which only happens with two or more of them in use
+        writer.write_bits(0, 2);
+        let stored = if length_code.used.len() >= 2 {
+            CODE_LENGTH_ORDER.iter().rposition(|&s| length_code.lengths[s] > 0).unwrap() + 1
+        } else {
+            CODE_LENGTH_ORDER.len()
+        };
+        for &symbol in &CODE_LENGTH_ORDER[..stored] {
+            let length = if length_code.used.len() >= 2 {
+                length_code.lengths[symbol]
+            } else if counts[symbol] > 0 {
+                // A lone code length symbol takes zero bits whatever length is stored for it
+                1
+            } else {
+                0
+            };
+            let (bits, count) = match length {
+                0 => (0, 2),
+                1 => (7, 4),
+                2 => (3, 3),
+                3 => (2, 2),
+                4 => (1, 2),
+                _ => (15, 4),
+            };
+            writer.write_bits(bits, count);
+        }
+
+        for (symbol, extra) in items {
+            length_code.write_symbol(writer, symbol as usize);
+            if symbol == REPEAT_ZERO {
+                writer.write_bits(extra as u32, 3);
+            }
+        }
+    }
+}
+
+// Append a run of zero code lengths. Consecutive repeat codes multiply: each one adds three
+// more bits to the count of the one before it, so the run is written most significant part first
+fn push_zero_run(items: &mut Vec<(u8, u8)>, mut run: usize) {
+    if run == 11 {
+        items.push((0, 0));
+        run -= 1;
+    }
+    if run < 3 {
+        items.extend(std::iter::repeat_n((0, 0), run));
+        return;
+    }
+    run -= 3;
+    let start = items.len();
+    loop {
+        items.push((REPEAT_ZERO, (run & 7) as u8));
+        run >>= 3;
+        if run == 0 {
+            break;
+        }
+        run -= 1;
+    }
+    items[start..].reverse();
+}
+
+// Huffman code lengths for the non-zero counts, at most max_bits long. When the tree is too
+// deep, small counts are raised and the tree is built again
+fn huffman_lengths(counts: &[u32], max_bits: u8) -> Vec<u8> {
+    let used: Vec<usize> = (0..counts.len()).filter(|&s| counts[s] > 0).collect();
+    let mut floor = 1u64;
+    loop {
+        let mut parent = vec![usize::MAX; 2 * used.len()];
+        let mut heap: BinaryHeap<Reverse<(u64, usize)>> =
+            used.iter().enumerate().map(|(node, &s)| Reverse(((counts[s] as u64).max(floor), node))).collect();
+        let mut next = used.len();
+        while heap.len() > 1 {
+            let Reverse((weight_a, a)) = heap.pop().unwrap();
+            let Reverse((weight_b, b)) = heap.pop().unwrap();
+            parent[a] = next;
+            parent[b] = next;
+            heap.push(Reverse((weight_a + weight_b, next)));
+            next += 1;
+        }
+
+        let mut lengths = vec![0u8; counts.len()];
+        let mut fits = true;
+        for (node, &symbol) in used.iter().enumerate() {
+            let mut depth = 0;
+            let mut n = node;
+            while parent[n] != usize::MAX {
+                n = parent[n];
+                depth += 1;
+            }
+            lengths[symbol] = depth;
+            fits &= depth <= max_bits;
+        }
+        if fits {
+            return lengths;
+        }
+        floor *= 2;
+    }
+}
+
+// Canonical codes: shorter codes first, symbols in order within a length
+fn canonical_codes(lengths: &[u8]) -> Vec<u32> {
+    let max = lengths.iter().copied().max().unwrap_or(0) as usize;
+    let mut length_counts = vec![0u32; max + 1];
+    for &length in lengths {
+        if length > 0 {
+            length_counts[length as usize] += 1;
+        }
+    }
+    let mut next_code = vec![0u32; max + 2];
+    for bits in 1..=max {
+        next_code[bits + 1] = (next_code[bits] + length_counts[bits]) << 1;
+    }
+    lengths
+        .iter()
+        .map(|&length| {
+            if length == 0 {
+                return 0;
+            }
+            let code = next_code[length as usize];
+            next_code[length as usize] += 1;
+            code
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn empty_input() {
+        // WBITS = 16, then an empty last meta-block
+        assert_eq!(brotli(b""), vec![0b110]);
+    }
+
+    #[test]
+    fn known_stream() {
+        // Checked against the reference decoder
+        let expected = [
+            194, 2, 0, 0, 128, 115, 44, 213, 247, 112, 193, 48, 16, 44, 114, 0, 220, 220, 250, 21, 82, 44, 200, 101, 41, 15,
+        ];
+        assert_eq!(brotli(b"hello hello hello world"), expected);
+    }
+
+    #[test]
+    fn distance_codes() {
+        assert_eq!(distance_code(1), (16, 1, 0));
+        assert_eq!(distance_code(2), (16, 1, 1));
+        assert_eq!(distance_code(3), (17, 1, 0));
+        assert_eq!(distance_code(5), (18, 2, 0));
+    }
+
+    #[test]
+    fn zero_runs_fold_into_repeat_codes() {
+        let mut items = Vec::new();
+        push_zero_run(&mut items, 2);
+        assert_eq!(items, vec![(0, 0), (0, 0)]);
+
+        items.clear();
+        push_zero_run(&mut items, 10);
+        assert_eq!(items, vec![(REPEAT_ZERO, 7)]);
+
+        // 11 cannot be written with two repeat codes, so a plain zero comes first
+        items.clear();
+        push_zero_run(&mut items, 11);
+        assert_eq!(items, vec![(0, 0), (REPEAT_ZERO, 7)]);
+
+        // The first code repeats 1 + 3 = 4 times, the second makes it (4 - 2) * 8 + 2 + 3 = 21
+        items.clear();
+        push_zero_run(&mut items, 21);
+        assert_eq!(items, vec![(REPEAT_ZERO, 1), (REPEAT_ZERO, 2)]);
+    }
+}
@@ -0,0 +1,161 @@
+use std::env;
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::process::Command;
+
+use crate::config::{SiteConfig, CONFIG_FILE, VARS_FILE};
+use crate::date::Date;
+use crate::fingerprint::content_hash;
+use crate::json::Json;
+use crate::stats::SiteStats;
+
+// Written at the top of the public directory, last, so that deployments can take it as the sign
+// that the build completed
+pub const BUILD_INFO_FILE: &str = ".build_info.json";
+
+// Hash of the sources of a build, written at the top of the public directory, so that a deployed site
+// can be matched with a source state
+pub const BUILD_VERSION_FILE: &str = ".build-version";
+
+// Every file under dir, with its path relative to the project root (/ separated) and the hash of its
+// content. Symlinks are not followed: a link is hashed by its target path, so that a link loop among
+// entries is left for the build to report. Errors name the path they are about
+fn hash_files(dir: &Path, relative: &str, files: &mut Vec<(String, u32)>) -> io::Result<()> {
+    let with_path = |path: &Path, e: io::Error| io::Error::new(e.kind(), format!("{}: {}", path.display(), e));
+    for entry in fs::read_dir(dir).map_err(|e| with_path(dir, e))? {
+        let path = entry.map_err(|e| with_path(dir, e))?.path();
+        let name = format!("{}/{}", relative, path.file_name().unwrap_or_default().to_string_lossy());
+        let file_type = fs::symlink_metadata(&path).map_err(|e| with_path(&path, e))?.file_type();
+        if file_type.is_symlink() {
+            let target = fs::read_link(&path).map_err(|e| with_path(&path, e))?;
+            files.push((name, content_hash(format!("-> {}", target.display()).as_bytes())));
+        } else if file_type.is_dir() {
+            hash_files(&path, &name, files)?;
+        } else {
+            files.push((name, content_hash(&fs::read(&path).map_err(|e| with_path(&path, e))?)));
+        }
+    }
+    Ok(())
+}
+
+// Short hash of the sources of the site: entries, static files and templates, images, ironite.toml and
+// vars.txt. Files are taken by path in sorted order, so the same sources always give the same hash
+// whatever their modification times or the order directories are read in
+pub fn build_hash(config: &SiteConfig) -> io::Result<String> {
+    let mut files = Vec::new();
+    for dir in [&config.entries_dir, &config.static_dir, &config.images_dir] {
+        let path = config.path(dir);
+        if path.is_dir() {
+            hash_files(&path, &dir.to_string_lossy(), &mut files)?;
+        }
+    }
+    for file in [CONFIG_FILE, VARS_FILE] {
+        let path = config.path(file);
+        if path.is_file() {
+            files.push((file.to_string(), content_hash(&fs::read(&path)?)));
+        }
+    }
+    files.sort();
+    let listing: String = files.iter().map(|(name, hash)| format!("{} {:08x}\n", name, hash)).collect();
+    Ok(format!("{:08x}", content_hash(listing.as_bytes())))
+}
+
+// Commit the site is built from: the GIT_COMMIT environment variable, set by most CI systems, or
+// what git rev-parse HEAD says in the project root. None outside a repository
+pub fn git_commit(root: &Path) -> Option<String> {
+    if let Some(commit) = env::var("GIT_COMMIT").ok().filter(|commit| !commit.trim().is_empty()) {
+        return Some(commit.trim().to_string());
+    }
+    let output = Command::new("git").arg("-C").arg(root).args(["rev-parse", "HEAD"]).output().ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    Some(String::from_utf8_lossy(&output.stdout).trim().to_string()).filter(|commit| !commit.is_empty())
+}
+
+// Metadata of a build. The timestamp is the build date, so SOURCE_DATE_EPOCH applies to it too
+pub fn build_info(config: &SiteConfig, stats: &SiteStats, git_commit: Option<&str>) -> Json {
+    let timestamp = config.build_date.unwrap_or_else(|| Date::from_unix(0));
+    let mut fields = vec![
+        ("build_timestamp", Json::string(timestamp.to_rfc3339())),
+        ("entries_count", Json::Number(stats.entry_count as i64)),
+        ("tags_count", Json::Number(stats.tag_count as i64)),
+        ("generator_version", Json::string(env!("CARGO_PKG_VERSION"))),
+    ];
+    if let Some(commit) = git_commit {
+        fields.push(("git_commit", Json::string(commit)));
+    }
+    Json::object(fields)
+}
+
+// Write .build_info.json to output_path
+pub fn generate_build_info(config: &SiteConfig, stats: &SiteStats, output_path: &Path) -> io::Result<()> {
+    let info = build_info(config, stats, git_commit(&config.root).as_deref());
+    fs::write(output_path, info.to_pretty_string() + "\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::{env_lock, fixture_project, write, TempDir};
+
+    #[test]
+    fn build_info_fields() {
+        let config = SiteConfig { build_date: Date::parse("2024-05-01"), ..SiteConfig::default() };
+        let stats = SiteStats { entry_count: 3, tag_count: 2, ..SiteStats::default() };
+        let info = build_info(&config, &stats, Some("abc123"));
+        assert_eq!(info.get("build_timestamp").and_then(Json::as_str), Some("2024-05-01T00:00:00Z"));
+        assert_eq!(info.get("entries_count"), Some(&Json::Number(3)));
+        assert_eq!(info.get("tags_count"), Some(&Json::Number(2)));
+        assert_eq!(info.get("generator_version").and_then(Json::as_str), Some(env!("CARGO_PKG_VERSION")));
+        assert_eq!(info.get("git_commit").and_then(Json::as_str), Some("abc123"));
+        assert_eq!(build_info(&config, &stats, None).get("git_commit"), None);
+    }
+
+    #[test]
+    fn git_commit_from_the_environment() {
+        let _lock = env_lock();
+        env::set_var("GIT_COMMIT", " 0123abcd\n");
+        let commit = git_commit(Path::new("/nonexistent"));
+        env::remove_var("GIT_COMMIT");
+        assert_eq!(commit.as_deref(), Some("0123abcd"));
+        assert_eq!(git_commit(Path::new("/nonexistent")), None);
+    }
+
+    #[test]
+    fn build_hash_follows_the_sources() {
+        let dir = TempDir::new("build-hash");
+        fixture_project(dir.path());
+        let config = SiteConfig { root: dir.path().to_path_buf(), ..SiteConfig::default() };
+        let hash = build_hash(&config).unwrap();
+        assert_eq!(hash.len(), 8);
+        assert_eq!(build_hash(&config).unwrap(), hash);
+
+        // Output is not a source
+        write(&dir.path().join("public").join("index.html"), "<html></html>");
+        assert_eq!(build_hash(&config).unwrap(), hash);
+
+        write(&dir.path().join("entries").join("first").join("content.html"), "<p>Edited</p>");
+        let edited = build_hash(&config).unwrap();
+        assert_ne!(edited, hash);
+        write(&dir.path().join("entries").join("first").join("content.html"), "<p>First entry</p>");
+        assert_eq!(build_hash(&config).unwrap(), hash);
+
+        // Links are hashed by their target, loops included
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::symlink;
+            let entries_dir = dir.path().join("entries");
+            for (name, target) in [("a", "../b/content.html"), ("b", "../a/content.html")] {
+                fs::create_dir_all(entries_dir.join(name)).unwrap();
+                symlink(target, entries_dir.join(name).join("content.html")).unwrap();
+            }
+            let looped = build_hash(&config).unwrap();
+            assert_ne!(looped, hash);
+            fs::remove_file(entries_dir.join("b").join("content.html")).unwrap();
+            symlink("../first/content.html", entries_dir.join("b").join("content.html")).unwrap();
+            assert_ne!(build_hash(&config).unwrap(), looped);
+        }
+    }
+}
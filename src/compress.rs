@@ -0,0 +1,342 @@
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use crate::brotli::brotli;
+use crate::generator::write_bytes_file;
+
+// Extensions worth precompressing. Images, fonts and archives are already compressed
+pub const COMPRESSIBLE_EXTENSIONS: &[&str] = &["html", "css", "js", "svg", "xml", "json", "txt"];
+
+// Result of a precompression pass over the output directory
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct CompressionStats {
+    // Compressed files written in this pass
+    pub written: usize,
+    // Compressed files that were already up to date
+    pub up_to_date: usize,
+    // Total size difference between the originals and their compressed siblings
+    pub bytes_saved: u64,
+}
+
+// Files smaller than this are not worth compressing by default: the saving is a few bytes
+pub const DEFAULT_MIN_SIZE: u64 = 256;
+
+// Compression formats that can be written next to output files
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Codec {
+    Gzip,
+    Brotli,
+}
+
+impl Codec {
+    // Suffix added to the name of the original file
+    pub fn extension(self) -> &'static str {
+        match self {
+            Codec::Gzip => "gz",
+            Codec::Brotli => "br",
+        }
+    }
+
+    pub fn compress(self, data: &[u8]) -> Vec<u8> {
+        match self {
+            Codec::Gzip => gzip(data),
+            Codec::Brotli => brotli(data),
+        }
+    }
+}
+
+// Write file.gz next to every compressible file under dir, when it is actually smaller.
+// Compressed files newer than their source are left as they are
+pub fn precompress_gzip(dir: &Path) -> io::Result<CompressionStats> {
+    let extensions: Vec<String> = COMPRESSIBLE_EXTENSIONS.iter().map(|ext| ext.to_string()).collect();
+    precompress(dir, Codec::Gzip, &extensions, 0)
+}
+
+// Write a compressed sibling (file.gz, file.br) of every file under dir with one of the given
+// extensions and at least min_size bytes, when it is actually smaller. Compressed files newer
+// than their source are left as they are
+pub fn precompress(dir: &Path, codec: Codec, extensions: &[String], min_size: u64) -> io::Result<CompressionStats> {
+    let mut stats = CompressionStats::default();
+    let mut files = Vec::new();
+    collect_files(dir, &mut files)?;
+
+    for path in files {
+        let compressible = path
+            .extension()
+            .and_then(|ext| ext.to_str())
+            .is_some_and(|ext| extensions.iter().any(|e| e == ext));
+        if !compressible {
+            continue;
+        }
+
+        let mut compressed_name = path.as_os_str().to_owned();
+        compressed_name.push(".");
+        compressed_name.push(codec.extension());
+        let compressed_path = PathBuf::from(compressed_name);
+
+        let source_meta = fs::metadata(&path)?;
+        if source_meta.len() < min_size {
+            // A stale sibling from an earlier build would be served instead of the new file
+            if compressed_path.exists() {
+                fs::remove_file(&compressed_path)?;
+            }
+            continue;
+        }
+        if let Ok(compressed_meta) = fs::metadata(&compressed_path) {
+            if compressed_meta.modified()? >= source_meta.modified()? {
+                stats.up_to_date += 1;
+                stats.bytes_saved += source_meta.len().saturating_sub(compressed_meta.len());
+                continue;
+            }
+        }
+
+        let compressed = codec.compress(&fs::read(&path)?);
+        if (compressed.len() as u64) < source_meta.len() {
+            write_bytes_file(&compressed_path, &compressed)?;
+            stats.written += 1;
+            stats.bytes_saved += source_meta.len() - compressed.len() as u64;
+        } else if compressed_path.exists() {
+            fs::remove_file(&compressed_path)?;
+        }
+    }
+
+    Ok(stats)
+}
+
+fn collect_files(dir: &Path, files: &mut Vec<PathBuf>) -> io::Result<()> {
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path.is_dir() {
+            collect_files(&path, files)?;
+        } else {
+            files.push(path);
+        }
+    }
+    Ok(())
+}
+
+// Wrap deflate-compressed data in a gzip member. The modification time is left
+// at zero so the same input always produces the same bytes
+pub fn gzip(data: &[u8]) -> Vec<u8> {
+    let mut out = vec![0x1f, 0x8b, 8, 0, 0, 0, 0, 0, 0, 0xff];
+    out.extend(deflate(data));
+    out.extend(crc32(data).to_le_bytes());
+    out.extend((data.len() as u32).to_le_bytes());
+    out
+}
+
+// CRC-32 (IEEE) as used by gzip
+pub fn crc32(data: &[u8]) -> u32 {
+    let mut crc = !0u32;
+    for byte in data {
+        crc ^= *byte as u32;
+        for _ in 0..8 {
+            let mask = (crc & 1).wrapping_neg();
+            crc = (crc >> 1) ^ (0xedb88320 & mask);
+        }
+    }
+    !crc
+}
+
+pub(crate) const WINDOW_SIZE: usize = 32768;
+pub(crate) const MIN_MATCH: usize = 3;
+const MAX_MATCH: usize = 258;
+const MAX_CHAIN: usize = 128;
+pub(crate) const HASH_SIZE: usize = 1 << 15;
+
+const LENGTH_BASE: [u16; 29] = [
+    3, 4, 5, 6, 7, 8, 9, 10, 11, 13, 15, 17, 19, 23, 27, 31, 35, 43, 51, 59, 67, 83, 99, 115, 131, 163, 195, 227, 258,
+];
+const LENGTH_EXTRA: [u8; 29] = [0, 0, 0, 0, 0, 0, 0, 0, 1, 1, 1, 1, 2, 2, 2, 2, 3, 3, 3, 3, 4, 4, 4, 4, 5, 5, 5, 5, 0];
+const DISTANCE_BASE: [u16; 30] = [
+    1, 2, 3, 4, 5, 7, 9, 13, 17, 25, 33, 49, 65, 97, 129, 193, 257, 385, 513, 769, 1025, 1537, 2049, 3073, 4097, 6145,
+    8193, 12289, 16385, 24577,
+];
+const DISTANCE_EXTRA: [u8; 30] = [0, 0, 0, 0, 1, 1, 2, 2, 3, 3, 4, 4, 5, 5, 6, 6, 7, 7, 8, 8, 9, 9, 10, 10, 11, 11, 12, 12, 13, 13];
+
+// Raw DEFLATE (RFC 1951): LZ77 matching encoded as a single block with the fixed Huffman codes
+pub fn deflate(data: &[u8]) -> Vec<u8> {
+    let mut writer = BitWriter::default();
+    // BFINAL = 1, BTYPE = 01 (fixed Huffman)
+    writer.write_bits(1, 1);
+    writer.write_bits(1, 2);
+
+    let mut head = vec![usize::MAX; HASH_SIZE];
+    let mut prev = vec![usize::MAX; WINDOW_SIZE];
+    let mut pos = 0;
+
+    while pos < data.len() {
+        let (length, distance) = longest_match(data, pos, &head, &prev);
+        let advance = if length >= MIN_MATCH {
+            write_length(&mut writer, length);
+            write_distance(&mut writer, distance);
+            length
+        } else {
+            write_literal(&mut writer, data[pos] as u16);
+            1
+        };
+        update_hash_chains(data, pos, advance, &mut head, &mut prev);
+        pos += advance;
+    }
+
+    // End of block
+    write_literal(&mut writer, 256);
+    writer.finish()
+}
+
+fn hash(data: &[u8], pos: usize) -> usize {
+    let value = (data[pos] as usize) << 16 | (data[pos + 1] as usize) << 8 | data[pos + 2] as usize;
+    (value.wrapping_mul(2654435761) >> 7) % HASH_SIZE
+}
+
+// Record the positions pos..pos + count, so later matches can refer back to them
+pub(crate) fn update_hash_chains(data: &[u8], pos: usize, count: usize, head: &mut [usize], prev: &mut [usize]) {
+    for p in pos..pos + count {
+        if p + MIN_MATCH <= data.len() {
+            let h = hash(data, p);
+            prev[p % WINDOW_SIZE] = head[h];
+            head[h] = p;
+        }
+    }
+}
+
+pub(crate) fn longest_match(data: &[u8], pos: usize, head: &[usize], prev: &[usize]) -> (usize, usize) {
+    if pos + MIN_MATCH > data.len() {
+        return (0, 0);
+    }
+    let max_length = MAX_MATCH.min(data.len() - pos);
+    let mut best = (0, 0);
+    let mut candidate = head[hash(data, pos)];
+    let mut chain = 0;
+
+    while candidate != usize::MAX && pos - candidate <= WINDOW_SIZE && chain < MAX_CHAIN {
+        let length = data[candidate..]
+            .iter()
+            .zip(&data[pos..pos + max_length])
+            .take_while(|(a, b)| a == b)
+            .count();
+        if length > best.0 {
+            best = (length, pos - candidate);
+            if length == max_length {
+                break;
+            }
+        }
+        let next = prev[candidate % WINDOW_SIZE];
+        // Older positions in the chain were overwritten once the window wrapped around
+        if next == usize::MAX || next >= candidate {
+            break;
+        }
+        candidate = next;
+        chain += 1;
+    }
+
+    best
+}
+
+fn write_literal(writer: &mut BitWriter, symbol: u16) {
+    let (code, bits) = match symbol {
+        0..=143 => (0x30 + symbol, 8),
+        144..=255 => (0x190 + symbol - 144, 9),
+        256..=279 => (symbol - 256, 7),
+        _ => (0xc0 + symbol - 280, 8),
+    };
+    writer.write_code(code as u32, bits);
+}
+
+fn write_length(writer: &mut BitWriter, length: usize) {
+    let index = LENGTH_BASE.iter().rposition(|&base| base as usize <= length).unwrap_or(0);
+    write_literal(writer, 257 + index as u16);
+    writer.write_bits((length - LENGTH_BASE[index] as usize) as u32, LENGTH_EXTRA[index]);
+}
+
+fn write_distance(writer: &mut BitWriter, distance: usize) {
+    let index = DISTANCE_BASE.iter().rposition(|&base| base as usize <= distance).unwrap_or(0);
+    writer.write_code(index as u32, 5);
+    writer.write_bits((distance - DISTANCE_BASE[index] as usize) as u32, DISTANCE_EXTRA[index]);
+}
+
+// Packs bits least-significant first, as DEFLATE and brotli require
+#[derive(Default)]
+pub(crate) struct BitWriter {
+    bytes: Vec<u8>,
+    buffer: u64,
+    count: u8,
+}
+
+impl BitWriter {
+    pub(crate) fn write_bits(&mut self, value: u32, bits: u8) {
+        self.buffer |= (value as u64) << self.count;
+        self.count += bits;
+        while self.count >= 8 {
+            self.bytes.push(self.buffer as u8);
+            self.buffer >>= 8;
+            self.count -= 8;
+        }
+    }
+
+    // Huffman codes are stored most-significant bit first
+    pub(crate) fn write_code(&mut self, code: u32, bits: u8) {
+        let reversed = code.reverse_bits() >> (32 - bits as u32);
+        self.write_bits(reversed, bits);
+    }
+
+    pub(crate) fn finish(mut self) -> Vec<u8> {
+        if self.count > 0 {
+            self.bytes.push(self.buffer as u8);
+        }
+        self.bytes
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::{write, TempDir};
+
+    #[test]
+    fn crc32_check_value() {
+        assert_eq!(crc32(b"123456789"), 0xcbf43926);
+    }
+
+    #[test]
+    fn precompress_only_compressible_and_smaller_files() {
+        let dir = TempDir::new("gzip");
+        let page = "<p>repeated content</p>\n".repeat(100);
+        write(&dir.path().join("index.html"), &page);
+        write(&dir.path().join("tiny.txt"), "a");
+        write(&dir.path().join("images").join("photo.png"), &page);
+
+        let stats = precompress_gzip(dir.path()).unwrap();
+        assert_eq!(stats.written, 1);
+        assert!(dir.path().join("index.html.gz").exists());
+        assert!(!dir.path().join("tiny.txt.gz").exists());
+        assert!(!dir.path().join("images").join("photo.png.gz").exists());
+
+        // The second pass finds the .gz newer than its source
+        let again = precompress_gzip(dir.path()).unwrap();
+        assert_eq!(again.written, 0);
+        assert_eq!(again.up_to_date, 1);
+        assert_eq!(again.bytes_saved, stats.bytes_saved);
+    }
+
+    #[test]
+    fn precompress_brotli_with_extensions_and_threshold() {
+        let dir = TempDir::new("brotli");
+        let page = "<p>repeated content</p>\n".repeat(100);
+        write(&dir.path().join("index.html"), &page);
+        write(&dir.path().join("style.css"), &page);
+        write(&dir.path().join("small.html"), "<p>small</p>");
+        // Left over from a build where the file was large enough
+        write(&dir.path().join("small.html.br"), "stale");
+
+        let extensions = vec!["html".to_string()];
+        let stats = precompress(dir.path(), Codec::Brotli, &extensions, 100).unwrap();
+        assert_eq!(stats.written, 1);
+        assert!(dir.path().join("index.html.br").exists());
+        assert!(!dir.path().join("style.css.br").exists());
+        assert!(!dir.path().join("small.html.br").exists());
+        assert!(!dir.path().join("index.html.gz").exists());
+    }
+
+}
@@ -0,0 +1,1153 @@
+use std::collections::{BTreeMap, HashMap};
+use std::env;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::{Component, Path, PathBuf};
+use std::time::{Duration, SystemTime};
+
+use crate::compress::{COMPRESSIBLE_EXTENSIONS, DEFAULT_MIN_SIZE};
+use crate::inline::DEFAULT_INLINE_MAX_SIZE;
+use crate::date::{Date, LOCALES};
+use crate::feed::{FeedContent, DEFAULT_MORE_MARKER};
+use crate::generator::UNTAGGED;
+use crate::humans::{read_authors, AuthorMeta};
+use crate::icons::{FAVICON_FILE, ICONS_DIR};
+use crate::meta::table_tags;
+use crate::permalink::DEFAULT_PERMALINK_PATTERN;
+use crate::retry::IoRetry;
+use crate::sitemap::{SitemapHints, SitemapRule, SitemapSettings, CHANGEFREQS, DEFAULT_SITEMAP_MAX_URLS};
+use crate::sort::IndexSort;
+use crate::toml::{self, expect_bool, expect_str, expect_usize, invalid_data, Table, Value};
+
+// Optional site configuration file, read from the project root
+pub const CONFIG_FILE: &str = "ironite.toml";
+
+// Project files copied into the public directory unless extra_files says otherwise
+pub const DEFAULT_EXTRA_FILES: &[&str] = &["humans.txt", ".well-known", FAVICON_FILE, ICONS_DIR];
+
+// Optional site-wide variables, KEY=value per line, read from the project root
+pub const VARS_FILE: &str = "vars.txt";
+
+// Placeholders starting with this are read from the environment when env_placeholders is enabled:
+// $ENV_ANALYTICS_ID is the ANALYTICS_ID variable
+pub const ENV_PREFIX: &str = "$ENV_";
+
+// Default limit on the size of content.html: 10 MiB
+pub const DEFAULT_MAX_FILE_SIZE: usize = 10 * 1024 * 1024;
+
+// Dates in placeholders look like 2024-03-15 unless date_format says otherwise
+pub const DEFAULT_DATE_FORMAT: &str = "%Y-%m-%d";
+
+// Kinds of generated pages, for placeholders scoped to one of them
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PageKind {
+    Entry,
+    Tag,
+    // The root index and the entries index
+    Index,
+    // Pages of no kind in particular, such as 404.html
+    Other,
+}
+
+// Site settings. Every field has a default, so a project without ironite.toml behaves as before
+#[derive(Debug, Clone)]
+pub struct SiteConfig {
+    // Project directory. All other paths are relative to it
+    pub root: PathBuf,
+    // Public URL of the site, without trailing slash (e.g. "https://example.com"). Used for absolute links in feeds
+    pub base_url: String,
+    // Default author of entries
+    pub author: Option<String>,
+    // People listed in the TEAM section of humans.txt, from [[authors]]. The author above is listed when empty
+    pub authors: Vec<AuthorMeta>,
+    // Names listed in the THANKS section of humans.txt
+    pub thanks: Vec<String>,
+    // Language of the site, as given in humans.txt
+    pub language: String,
+    // Languages entries are written in. Each is built to its own tree, public/<language>/, from the
+    // content.<language>.html of every entry. Empty builds a single site from content.html
+    pub languages: Vec<String>,
+    // Language whose content is used for entries missing a translation. The first of languages when not set
+    pub default_language: Option<String>,
+    // Language of the tree being built, set by the build for each of languages
+    pub content_language: Option<String>,
+    // Where the site is generated
+    pub public_dir: PathBuf,
+    // Blog entries, one subdirectory each
+    pub entries_dir: PathBuf,
+    // Read a content.html that is a symlink from the file it points to. Off, such entries are skipped with a warning
+    pub follow_symlinks: bool,
+    // Also look for entries in subdirectories of entries_dir, at any depth: every directory holding a
+    // content.html is an entry. Entry pages mirror the nesting, see {section} in permalink_pattern
+    pub recursive_entries: bool,
+    // base.html, about.html, CSS and other files copied as-is
+    pub static_dir: PathBuf,
+    pub images_dir: PathBuf,
+    // Threads copying the files of images_dir. 0 uses one per CPU
+    pub copy_workers: usize,
+    // Files copied as they are into the top of the public directory (CNAME, .nojekyll, favicon.ico, ...)
+    pub root_files_dir: PathBuf,
+    // Files and directories of the project copied to the same place under the public directory at the
+    // end of the build, when they exist
+    pub extra_files: Vec<PathBuf>,
+    // Copy static/ files under content-hashed names and rewrite references to them
+    pub fingerprint_assets: bool,
+    // Write precompressed .gz siblings of text files after the build
+    pub gzip: bool,
+    // Write precompressed .br siblings of text files after the build
+    pub brotli: bool,
+    // Extensions of the files compressed by gzip and brotli, without the dot
+    pub compress_extensions: Vec<String>,
+    // Files smaller than this many bytes are not compressed
+    pub compress_min_size: u64,
+    // Collapse whitespace and strip comments in generated pages
+    pub minify_html: bool,
+    // Indent generated pages by element nesting instead. Cannot be combined with minify_html
+    pub pretty_html: bool,
+    // Replace stylesheet links and scripts pointing at small files of static/ with their content
+    pub inline_assets: bool,
+    // Largest stylesheet or script inlined by inline_assets, in bytes
+    pub inline_max_size: u64,
+    // Make entry pages self-contained: their stylesheets and scripts of static/ are inlined whatever
+    // their size, and their images become data: URIs
+    pub embed_assets: bool,
+    // Maximum number of links in $RELATED on entry pages
+    pub related_limit: usize,
+    // Sets of tags from [[tag_intersections]], each getting a page of the entries carrying all of them
+    pub tag_intersections: Vec<Vec<String>>,
+    // Lowercase tags, merging the ones spelled in several ways (Rust, rust, RUST) into one tag page.
+    // Each merge is reported as a warning, to clean up the tags files
+    pub lowercase_tags: bool,
+    // Merge tags differing only in case like lowercase_tags, with URLs in lowercase, but show each in the
+    // spelling of the first entry using it. Merges are not reported
+    pub case_insensitive_tags: bool,
+    // Display form of tags, from [tag_display]: rust = "Rust". Tags missing from it show as they are
+    // named, or with case_insensitive_tags as first spelled
+    pub tag_display: HashMap<String, String>,
+    // Retired tags and the tag replacing each, from [tag_aliases], like the lines of tag-aliases.txt
+    pub tag_aliases: HashMap<String, String>,
+    // Write a redirect from the page of every tag retired in tag-aliases.txt to the tag replacing it
+    pub tag_alias_redirects: bool,
+    // Tags kept for the author's own organization (todo, private-ref): they get no page and are left
+    // out of $TAGS and $NAVCLOUD, while the entries carrying them still publish. Nested tags under them
+    // are excluded too
+    pub excluded_tags: Vec<String>,
+    // The only tags entries may carry. None allows any tag; with a list, every other tag stops the build
+    pub tags_whitelist: Option<Vec<String>>,
+    // List nested tags (programming/rust) in $NAVCLOUD too, instead of only the top-level ones
+    pub nav_cloud_nested_tags: bool,
+    // Fail the build when an entry has no tag, instead of listing it on the untagged page
+    pub require_tags: bool,
+    // Name of the tag page listing the entries without tags, "untagged" by default. Empty for no such page
+    pub untagged_tag: String,
+    // Write public/.build_info.json with the build date, counts, version and commit, last of all files
+    pub emit_build_info: bool,
+    // Show a teaser under each entry of the entries index: its content up to more_marker, or its summary
+    pub entries_index_teasers: bool,
+    // Comment separating the teaser of an entry from the rest. Removed from entry pages and feeds
+    pub more_marker: String,
+    // Most items in feeds, newest first. None lists every entry
+    pub feed_limit: Option<usize>,
+    // Build only the first entries of the entries index, for quick previews of large sites. Tags, feeds
+    // and listings only know these entries. None builds every entry
+    pub entry_limit: Option<usize>,
+    // Whether feed items carry the whole entry or its summary
+    pub feed_content: FeedContent,
+    // Order of the entries index and the feeds, see IndexSort
+    pub index_sort: IndexSort,
+    // List pinned entries first in the feeds too, instead of by date only
+    pub feed_pinned: bool,
+    // Written before the title of pinned entries in the entries index and on tag pages, e.g. "📌"
+    pub pinned_marker: String,
+    // Class of the links to pinned entries in the entries index and on tag pages
+    pub pinned_class: String,
+    // Written after the title of entries linking to another site (link.txt) in listings
+    pub external_link_marker: String,
+    // Give entries with a link.txt a page redirecting to their URL, instead of no page at all
+    pub external_link_stubs: bool,
+    // Add rel="noopener noreferrer" to links of entry content pointing to other sites
+    pub external_links_noopener: bool,
+    // Open links of entry content pointing to other sites in a new tab, with rel="noopener noreferrer" too
+    pub external_links_new_tab: bool,
+    // Add loading="lazy" and decoding="async" to the images of entry content
+    pub lazy_images: bool,
+    // Highlight <pre><code class="language-..."> blocks of entry content at build time, see highlight
+    pub highlight_code: bool,
+    // Curly quotes, en and em dashes and ellipses in the text of entries and tag descriptions, see
+    // html::smart_punctuation
+    pub smart_punctuation: bool,
+    // Link [^label] references to [^label]: definitions in entries and tag descriptions, see footnotes
+    pub footnotes: bool,
+    // Expand [[name:argument]] shortcodes of entries, see shortcode
+    pub shortcodes: bool,
+    // Give images of entry content stored in the entry directory their width and height, read from the files
+    pub image_dimensions: bool,
+    // Most URLs in sitemap.xml. Larger sites get partial sitemaps listed in sitemap_index.xml
+    pub sitemap_max_urls: usize,
+    // <changefreq> and <priority> of sitemap pages, from [sitemap], see SitemapSettings
+    pub sitemap: SitemapSettings,
+    // Link to pages as directories ("entries/my-post/") instead of "entries/my-post/index.html"
+    pub pretty_urls: bool,
+    // Path the site is served under ("/blog/"), making the links of every page absolute paths below it.
+    // Empty for sites at the root of their host, with relative links
+    pub path_prefix: String,
+    // Directory of entry pages under the public directory, see permalink::expand_permalink
+    pub permalink_pattern: String,
+    // Time of the build, for $BUILD_DATE and $BUILD_TIMESTAMP. Set it to pin the date for reproducible
+    // builds; otherwise SiteBuilder::build takes SOURCE_DATE_EPOCH or the current time
+    pub build_date: Option<Date>,
+    // Site-wide variables from vars.txt, as placeholders ($AUTHOR). Filled in by SiteBuilder::build
+    pub vars: HashMap<String, String>,
+    // Resolve $ENV_NAME placeholders from environment variables. Off so that $ENV_ text in content stays as it is
+    pub env_placeholders: bool,
+    // Extra placeholders from [placeholders], replaced on every page
+    pub placeholders: HashMap<String, String>,
+    // Placeholders from [entry.placeholders], [tag.placeholders] and [index.placeholders],
+    // replaced on pages of that kind on top of the global ones
+    pub entry_placeholders: HashMap<String, String>,
+    pub tag_placeholders: HashMap<String, String>,
+    pub index_placeholders: HashMap<String, String>,
+    // Check internal links after the build
+    pub check_links: bool,
+    // Fail the build on broken links instead of reporting them as warnings
+    pub strict_links: bool,
+    // Print how long each build step took to stderr
+    pub profile: bool,
+    // Report entry and tag names that cannot be used in URLs as warnings instead of failing the build
+    pub allow_unsafe_names: bool,
+    // Report pages written twice, or whose paths differ only in case, as warnings instead of failing the build
+    pub allow_output_collisions: bool,
+    // Build entries with draft = true, marked [DRAFT] in the entries index, to preview them
+    pub include_drafts: bool,
+    // Stop at the first entry that fails instead of reporting every failed entry at the end
+    pub fail_fast: bool,
+    // Largest content.html read, in bytes. Larger files fail their entry
+    pub max_file_size: usize,
+    // Times a page write or a file copy failing with a transient error (busy, permission denied,
+    // timed out) is tried again, see retry::IoRetry. 0 never retries
+    pub io_retry_count: usize,
+    // Wait before each retry, in milliseconds
+    pub io_retry_delay_ms: u64,
+    // Shell command run in the project root before a build, which does not start if it fails. Empty for none
+    pub pre_build: String,
+    // Shell command run in the project root once a build succeeded, e.g. to deploy public/. The build
+    // fails if it does. Empty for none
+    pub post_build: String,
+    // Format of the dates in placeholders such as $DATE and $UPDATED, see Date::format. Feeds and
+    // sitemaps use their own machine formats whatever this says
+    pub date_format: String,
+    // Language of month and weekday names in date_format, one of date::LOCALES
+    pub date_locale: String,
+    // Take the last change of entries from the git history instead of file modification times
+    pub git_dates: bool,
+}
+
+impl Default for SiteConfig {
+    fn default() -> Self {
+        SiteConfig {
+            root: PathBuf::from("."),
+            base_url: String::new(),
+            author: None,
+            authors: Vec::new(),
+            thanks: Vec::new(),
+            language: "en".to_string(),
+            languages: Vec::new(),
+            default_language: None,
+            content_language: None,
+            public_dir: PathBuf::from("public"),
+            entries_dir: PathBuf::from("entries"),
+            follow_symlinks: true,
+            recursive_entries: false,
+            static_dir: PathBuf::from("static"),
+            images_dir: PathBuf::from("images"),
+            copy_workers: 1,
+            root_files_dir: PathBuf::from("root_files"),
+            extra_files: DEFAULT_EXTRA_FILES.iter().map(PathBuf::from).collect(),
+            fingerprint_assets: false,
+            gzip: false,
+            brotli: false,
+            compress_extensions: COMPRESSIBLE_EXTENSIONS.iter().map(|ext| ext.to_string()).collect(),
+            compress_min_size: DEFAULT_MIN_SIZE,
+            minify_html: false,
+            pretty_html: false,
+            inline_assets: false,
+            inline_max_size: DEFAULT_INLINE_MAX_SIZE,
+            embed_assets: false,
+            related_limit: 5,
+            tag_intersections: Vec::new(),
+            lowercase_tags: false,
+            case_insensitive_tags: false,
+            tag_aliases: HashMap::new(),
+            tag_display: HashMap::new(),
+            tag_alias_redirects: false,
+            excluded_tags: Vec::new(),
+            tags_whitelist: None,
+            nav_cloud_nested_tags: false,
+            require_tags: false,
+            untagged_tag: UNTAGGED.to_string(),
+            emit_build_info: false,
+            entries_index_teasers: false,
+            more_marker: DEFAULT_MORE_MARKER.to_string(),
+            feed_limit: None,
+            entry_limit: None,
+            feed_content: FeedContent::Full,
+            index_sort: IndexSort::Alphabetical,
+            feed_pinned: false,
+            pinned_marker: String::new(),
+            pinned_class: String::new(),
+            external_link_marker: "\u{2197}".to_string(),
+            external_link_stubs: false,
+            external_links_noopener: false,
+            external_links_new_tab: false,
+            lazy_images: false,
+            highlight_code: false,
+            smart_punctuation: false,
+            footnotes: false,
+            shortcodes: false,
+            image_dimensions: false,
+            sitemap_max_urls: DEFAULT_SITEMAP_MAX_URLS,
+            sitemap: SitemapSettings::default(),
+            pretty_urls: false,
+            path_prefix: String::new(),
+            permalink_pattern: DEFAULT_PERMALINK_PATTERN.to_string(),
+            build_date: None,
+            vars: HashMap::new(),
+            env_placeholders: false,
+            placeholders: HashMap::new(),
+            entry_placeholders: HashMap::new(),
+            tag_placeholders: HashMap::new(),
+            index_placeholders: HashMap::new(),
+            check_links: false,
+            strict_links: false,
+            profile: false,
+            allow_unsafe_names: false,
+            allow_output_collisions: false,
+            include_drafts: false,
+            fail_fast: false,
+            max_file_size: DEFAULT_MAX_FILE_SIZE,
+            io_retry_count: 0,
+            io_retry_delay_ms: 100,
+            pre_build: String::new(),
+            post_build: String::new(),
+            date_format: DEFAULT_DATE_FORMAT.to_string(),
+            date_locale: "en".to_string(),
+            git_dates: false,
+        }
+    }
+}
+
+impl SiteConfig {
+    // Resolve a path against the project root
+    pub fn path<P: AsRef<Path>>(&self, path: P) -> PathBuf {
+        self.root.join(path)
+    }
+
+    // The public directory is written over on every build, so it must not be the project root, a source
+    // directory, inside one or around one. Paths are compared as written, after resolving . and ..
+    pub fn check_public_dir(&self) -> io::Result<()> {
+        let public = normalize(&self.path(&self.public_dir))?;
+        let root = normalize(&self.root)?;
+        if public == root {
+            return Err(invalid_data(format!("public_dir: {} is the project root", self.public_dir.display())));
+        }
+        if root.starts_with(&public) {
+            return Err(invalid_data(format!("public_dir: {} contains the project root", self.public_dir.display())));
+        }
+        let sources = [
+            ("entries", &self.entries_dir),
+            ("static", &self.static_dir),
+            ("images", &self.images_dir),
+            ("root files", &self.root_files_dir),
+        ];
+        for (name, dir) in sources {
+            let source = normalize(&self.path(dir))?;
+            if public == source {
+                return Err(invalid_data(format!("public_dir: {} is the {} directory", self.public_dir.display(), name)));
+            }
+            if public.starts_with(&source) {
+                return Err(invalid_data(format!("public_dir: {} is inside the {} directory {}", self.public_dir.display(), name, dir.display())));
+            }
+            if source.starts_with(&public) {
+                return Err(invalid_data(format!("public_dir: {} contains the {} directory {}", self.public_dir.display(), name, dir.display())));
+            }
+        }
+        Ok(())
+    }
+
+    // Site name, from projectname.txt
+    pub fn project_name(&self) -> String {
+        fs::read_to_string(self.path("projectname.txt")).map(|name| name.trim().to_string()).unwrap_or_default()
+    }
+
+    // Language of the content used when an entry lacks a translation: default_language, or the first of languages
+    pub fn fallback_language(&self) -> Option<&str> {
+        self.default_language.as_deref().or(self.languages.first().map(String::as_str))
+    }
+
+    // Threads copying files: copy_workers, or one per CPU when it is 0
+    pub fn copy_threads(&self) -> usize {
+        match self.copy_workers {
+            0 => std::thread::available_parallelism().map(|n| n.get()).unwrap_or(1),
+            workers => workers,
+        }
+    }
+
+    // Retry policy of the file operations of a build
+    pub fn io_retry(&self) -> IoRetry {
+        IoRetry { count: self.io_retry_count, delay: Duration::from_millis(self.io_retry_delay_ms) }
+    }
+
+    // Absolute URL of a path relative to the site root. Without base_url it is root-relative, under
+    // path_prefix when there is one; base_url already holds the prefix's path
+    pub fn url(&self, path: &str) -> String {
+        if self.base_url.is_empty() && !self.path_prefix.is_empty() {
+            format!("{}{}", self.path_prefix, path)
+        } else {
+            format!("{}/{}", self.base_url, path)
+        }
+    }
+
+    // Placeholders from the config for a kind of page: the build placeholders, the variables, the global
+    // placeholders, then the scoped ones, each overriding the previous
+    pub fn page_placeholders(&self, kind: PageKind) -> HashMap<String, String> {
+        let mut placeholders = self.build_placeholders();
+        placeholders.extend(self.vars.iter().map(|(name, value)| (name.clone(), value.clone())));
+        placeholders.extend(self.placeholders.iter().map(|(name, value)| (name.clone(), value.clone())));
+        let scoped = match kind {
+            PageKind::Entry => &self.entry_placeholders,
+            PageKind::Tag => &self.tag_placeholders,
+            PageKind::Index => &self.index_placeholders,
+            PageKind::Other => return placeholders,
+        };
+        placeholders.extend(scoped.iter().map(|(name, value)| (name.clone(), value.clone())));
+        placeholders
+    }
+
+    // Date as shown on pages, in date_format and date_locale
+    pub fn format_date(&self, date: &Date) -> String {
+        date.format_localized(&self.date_format, &self.date_locale)
+    }
+
+    // $IRONITE_VERSION, and $BUILD_DATE (RFC 3339, UTC) and $BUILD_TIMESTAMP (unix seconds) once the
+    // build date is known
+    pub fn build_placeholders(&self) -> HashMap<String, String> {
+        let mut placeholders = HashMap::new();
+        placeholders.insert("$IRONITE_VERSION".to_string(), env!("CARGO_PKG_VERSION").to_string());
+        if let Some(date) = &self.build_date {
+            placeholders.insert("$BUILD_DATE".to_string(), date.to_rfc3339());
+            placeholders.insert("$BUILD_TIMESTAMP".to_string(), date.to_unix().to_string());
+        }
+        placeholders
+    }
+
+    // How a tag is shown in titles and links: its form from tag_display, or the tag itself
+    pub fn tag_label<'a>(&'a self, tag: &'a str) -> &'a str {
+        self.tag_display.get(tag).map(String::as_str).unwrap_or(tag)
+    }
+
+    // Load the configuration from a file, IRONITE_* environment variables taking priority over it. A
+    // missing file yields the defaults
+    pub fn load<P: AsRef<Path>>(path: P) -> io::Result<SiteConfig> {
+        SiteConfig::load_with_vars(path.as_ref(), env::vars())
+    }
+
+    fn load_with_vars(path: &Path, vars: impl Iterator<Item = (String, String)>) -> io::Result<SiteConfig> {
+        let mut table = if path.exists() {
+            let content = fs::read_to_string(path)?;
+            toml::parse(&content).map_err(|e| invalid_data(format!("{}: {}", path.display(), e)))?
+        } else {
+            Table::new()
+        };
+        let overrides = env_overrides(vars)?;
+        for (key, (_, _, value)) in &overrides {
+            table.insert(key.clone(), value.clone());
+        }
+        SiteConfig::from_table(&table).map_err(|e| env_error(e, &overrides).into())
+    }
+
+    // Configuration from IRONITE_* environment variables alone, the defaults applying to the others
+    pub fn from_env() -> Result<SiteConfig, ConfigError> {
+        SiteConfig::from_vars(env::vars())
+    }
+
+    fn from_vars(vars: impl Iterator<Item = (String, String)>) -> Result<SiteConfig, ConfigError> {
+        let overrides = env_overrides(vars)?;
+        let table: Table = overrides.iter().map(|(key, (_, _, value))| (key.clone(), value.clone())).collect();
+        SiteConfig::from_table(&table).map_err(|e| env_error(e, &overrides))
+    }
+
+    // Build a configuration from a parsed TOML table, rejecting values of the wrong type
+    pub fn from_table(table: &Table) -> io::Result<SiteConfig> {
+        let mut config = SiteConfig::default();
+        if let Some(value) = table.get("base_url") {
+            config.base_url = expect_str("base_url", value)?.trim_end_matches('/').to_string();
+        }
+        if let Some(value) = table.get("author") {
+            config.author = Some(expect_str("author", value)?.to_string());
+        }
+        if let Some(value) = table.get("public_dir") {
+            config.public_dir = PathBuf::from(expect_str("public_dir", value)?);
+        }
+        if let Some(value) = table.get("entries_dir") {
+            config.entries_dir = PathBuf::from(expect_str("entries_dir", value)?);
+        }
+        if let Some(value) = table.get("follow_symlinks") {
+            config.follow_symlinks = expect_bool("follow_symlinks", value)?;
+        }
+        if let Some(value) = table.get("recursive_entries") {
+            config.recursive_entries = expect_bool("recursive_entries", value)?;
+        }
+        if let Some(value) = table.get("static_dir") {
+            config.static_dir = PathBuf::from(expect_str("static_dir", value)?);
+        }
+        if let Some(value) = table.get("images_dir") {
+            config.images_dir = PathBuf::from(expect_str("images_dir", value)?);
+        }
+        if let Some(value) = table.get("copy_workers") {
+            config.copy_workers = expect_usize("copy_workers", value)?;
+        }
+        if let Some(value) = table.get("root_files_dir") {
+            config.root_files_dir = PathBuf::from(expect_str("root_files_dir", value)?);
+        }
+        if let Some(value) = table.get("extra_files") {
+            let items = value.as_array().ok_or_else(|| invalid_data(format!("extra_files: expected array, found {}", value.type_name())))?;
+            config.extra_files = items
+                .iter()
+                .map(|item| {
+                    let path = PathBuf::from(expect_str("extra_files", item)?);
+                    // Copies must land inside the public directory
+                    if path.is_absolute() || path.components().any(|component| !matches!(component, Component::Normal(_))) {
+                        return Err(invalid_data(format!("extra_files: \"{}\" must be a relative path inside the project", path.display())));
+                    }
+                    Ok(path)
+                })
+                .collect::<io::Result<_>>()?;
+        }
+        if let Some(value) = table.get("fingerprint_assets") {
+            config.fingerprint_assets = expect_bool("fingerprint_assets", value)?;
+        }
+        if let Some(value) = table.get("gzip") {
+            config.gzip = expect_bool("gzip", value)?;
+        }
+        if let Some(value) = table.get("brotli") {
+            config.brotli = expect_bool("brotli", value)?;
+        }
+        if let Some(value) = table.get("compress_extensions") {
+            let items = value
+                .as_array()
+                .ok_or_else(|| invalid_data(format!("compress_extensions: expected array, found {}", value.type_name())))?;
+            config.compress_extensions = items
+                .iter()
+                .map(|item| expect_str("compress_extensions", item).map(|ext| ext.trim_start_matches('.').to_string()))
+                .collect::<io::Result<_>>()?;
+        }
+        if let Some(value) = table.get("compress_min_size") {
+            config.compress_min_size = expect_usize("compress_min_size", value)? as u64;
+        }
+        if let Some(value) = table.get("inline_assets") {
+            config.inline_assets = expect_bool("inline_assets", value)?;
+        }
+        if let Some(value) = table.get("embed_assets") {
+            config.embed_assets = expect_bool("embed_assets", value)?;
+        }
+        if let Some(value) = table.get("inline_max_size") {
+            config.inline_max_size = expect_usize("inline_max_size", value)? as u64;
+        }
+        if let Some(value) = table.get("minify_html") {
+            config.minify_html = expect_bool("minify_html", value)?;
+        }
+        if let Some(value) = table.get("pretty_html") {
+            config.pretty_html = expect_bool("pretty_html", value)?;
+        }
+        if let Some(value) = table.get("related_limit") {
+            config.related_limit = expect_usize("related_limit", value)?;
+        }
+        if let Some(value) = table.get("tag_intersections") {
+            let items = value
+                .as_array()
+                .ok_or_else(|| invalid_data(format!("tag_intersections: expected array of tables, found {}", value.type_name())))?;
+            for item in items {
+                let tags = item
+                    .as_table()
+                    .and_then(|table| table.get("tags"))
+                    .ok_or_else(|| invalid_data("tag_intersections: every entry needs tags = [...]".to_string()))?;
+                config.tag_intersections.push(table_tags(tags)?);
+            }
+        }
+        if let Some(value) = table.get("lowercase_tags") {
+            config.lowercase_tags = expect_bool("lowercase_tags", value)?;
+        }
+        if let Some(value) = table.get("case_insensitive_tags") {
+            config.case_insensitive_tags = expect_bool("case_insensitive_tags", value)?;
+        }
+        if let Some(value) = table.get("tag_aliases") {
+            let aliases = value.as_table().ok_or_else(|| invalid_data(format!("tag_aliases: expected table, found {}", value.type_name())))?;
+            for (alias, tag) in aliases {
+                let tag = expect_str(&format!("tag_aliases.{}", alias), tag)?.trim();
+                if tag.is_empty() {
+                    return Err(invalid_data(format!("tag_aliases.{}: expected a tag", alias)));
+                }
+                config.tag_aliases.insert(alias.clone(), tag.to_string());
+            }
+        }
+        if let Some(value) = table.get("tag_display") {
+            let names = value.as_table().ok_or_else(|| invalid_data(format!("tag_display: expected table, found {}", value.type_name())))?;
+            for (tag, display) in names {
+                config.tag_display.insert(tag.clone(), expect_str(&format!("tag_display.{}", tag), display)?.to_string());
+            }
+        }
+        if let Some(value) = table.get("tag_alias_redirects") {
+            config.tag_alias_redirects = expect_bool("tag_alias_redirects", value)?;
+        }
+        if let Some(value) = table.get("excluded_tags") {
+            let items = value.as_array().ok_or_else(|| invalid_data(format!("excluded_tags: expected array, found {}", value.type_name())))?;
+            config.excluded_tags = items.iter().map(|item| expect_str("excluded_tags", item).map(str::to_string)).collect::<io::Result<_>>()?;
+        }
+        if let Some(value) = table.get("tags_whitelist") {
+            let items = value.as_array().ok_or_else(|| invalid_data(format!("tags_whitelist: expected array, found {}", value.type_name())))?;
+            config.tags_whitelist = Some(items.iter().map(|item| expect_str("tags_whitelist", item).map(str::to_string)).collect::<io::Result<_>>()?);
+        }
+        if let Some(value) = table.get("nav_cloud_nested_tags") {
+            config.nav_cloud_nested_tags = expect_bool("nav_cloud_nested_tags", value)?;
+        }
+        if let Some(value) = table.get("require_tags") {
+            config.require_tags = expect_bool("require_tags", value)?;
+        }
+        if let Some(value) = table.get("untagged_tag") {
+            config.untagged_tag = expect_str("untagged_tag", value)?.trim().to_string();
+        }
+        if let Some(value) = table.get("emit_build_info") {
+            config.emit_build_info = expect_bool("emit_build_info", value)?;
+        }
+        if let Some(value) = table.get("entries_index_teasers") {
+            config.entries_index_teasers = expect_bool("entries_index_teasers", value)?;
+        }
+        if let Some(value) = table.get("more_marker") {
+            config.more_marker = expect_str("more_marker", value)?.to_string();
+        }
+        if let Some(value) = table.get("feed_limit") {
+            config.feed_limit = Some(expect_usize("feed_limit", value)?);
+        }
+        if let Some(value) = table.get("entry_limit") {
+            config.entry_limit = Some(expect_usize("entry_limit", value)?);
+        }
+        if let Some(value) = table.get("index_sort") {
+            config.index_sort = match expect_str("index_sort", value)? {
+                "alphabetical" => IndexSort::Alphabetical,
+                "newest" => IndexSort::Newest,
+                "oldest" => IndexSort::Oldest,
+                "manual" => IndexSort::Manual,
+                other => {
+                    return Err(invalid_data(format!(
+                        "index_sort: expected \"alphabetical\", \"newest\", \"oldest\" or \"manual\", found \"{}\"",
+                        other
+                    )))
+                }
+            };
+        }
+        if let Some(value) = table.get("feed_pinned") {
+            config.feed_pinned = expect_bool("feed_pinned", value)?;
+        }
+        if let Some(value) = table.get("pinned_marker") {
+            config.pinned_marker = expect_str("pinned_marker", value)?.to_string();
+        }
+        if let Some(value) = table.get("pinned_class") {
+            config.pinned_class = expect_str("pinned_class", value)?.to_string();
+        }
+        if let Some(value) = table.get("external_link_marker") {
+            config.external_link_marker = expect_str("external_link_marker", value)?.to_string();
+        }
+        if let Some(value) = table.get("external_link_stubs") {
+            config.external_link_stubs = expect_bool("external_link_stubs", value)?;
+        }
+        if let Some(value) = table.get("external_links_noopener") {
+            config.external_links_noopener = expect_bool("external_links_noopener", value)?;
+        }
+        if let Some(value) = table.get("external_links_new_tab") {
+            config.external_links_new_tab = expect_bool("external_links_new_tab", value)?;
+        }
+        if let Some(value) = table.get("lazy_images") {
+            config.lazy_images = expect_bool("lazy_images", value)?;
+        }
+        if let Some(value) = table.get("smart_punctuation") {
+            config.smart_punctuation = expect_bool("smart_punctuation", value)?;
+        }
+        if let Some(value) = table.get("footnotes") {
+            config.footnotes = expect_bool("footnotes", value)?;
+        }
+        if let Some(value) = table.get("shortcodes") {
+            config.shortcodes = expect_bool("shortcodes", value)?;
+        }
+        if let Some(value) = table.get("highlight_code") {
+            config.highlight_code = expect_bool("highlight_code", value)?;
+        }
+        if let Some(value) = table.get("image_dimensions") {
+            config.image_dimensions = expect_bool("image_dimensions", value)?;
+        }
+        if let Some(value) = table.get("sitemap_max_urls") {
+            config.sitemap_max_urls = expect_usize("sitemap_max_urls", value)?.max(1);
+        }
+        if let Some(value) = table.get("sitemap") {
+            config.sitemap = read_sitemap_settings(value)?;
+        }
+        if let Some(value) = table.get("feed_content") {
+            config.feed_content = match expect_str("feed_content", value)? {
+                "full" => FeedContent::Full,
+                "summary" => FeedContent::Summary,
+                other => return Err(invalid_data(format!("feed_content: expected \"full\" or \"summary\", found \"{}\"", other))),
+            };
+        }
+        if let Some(value) = table.get("pretty_urls") {
+            config.pretty_urls = expect_bool("pretty_urls", value)?;
+        }
+        if let Some(value) = table.get("path_prefix") {
+            let prefix = expect_str("path_prefix", value)?.trim_matches('/');
+            let bad_part = |part: &str| part.is_empty() || part == "." || part == "..";
+            if prefix.contains([':', '?', '#']) || (!prefix.is_empty() && prefix.split('/').any(bad_part)) {
+                return Err(invalid_data(format!("path_prefix: expected a path such as \"/blog/\", found {:?}", prefix)));
+            }
+            config.path_prefix = if prefix.is_empty() { String::new() } else { format!("/{}/", prefix) };
+        }
+        if let Some(value) = table.get("permalink_pattern") {
+            config.permalink_pattern = expect_str("permalink_pattern", value)?.to_string();
+        }
+        if let Some(value) = table.get("build_date") {
+            let text = expect_str("build_date", value)?;
+            let date = Date::parse(text).ok_or_else(|| invalid_data(format!("build_date: invalid date \"{}\"", text)))?;
+            config.build_date = Some(date);
+        }
+        if let Some(value) = table.get("env_placeholders") {
+            config.env_placeholders = expect_bool("env_placeholders", value)?;
+        }
+        if let Some(value) = table.get("placeholders") {
+            config.placeholders = read_placeholders("placeholders", value)?;
+        }
+        for (page, placeholders) in [
+            ("entry", &mut config.entry_placeholders),
+            ("tag", &mut config.tag_placeholders),
+            ("index", &mut config.index_placeholders),
+        ] {
+            if let Some(value) = table.get(page).and_then(|section| section.as_table()).and_then(|section| section.get("placeholders")) {
+                *placeholders = read_placeholders(&format!("{}.placeholders", page), value)?;
+            }
+        }
+        if let Some(value) = table.get("check_links") {
+            config.check_links = expect_bool("check_links", value)?;
+        }
+        if let Some(value) = table.get("strict_links") {
+            config.strict_links = expect_bool("strict_links", value)?;
+        }
+        if let Some(value) = table.get("profile") {
+            config.profile = expect_bool("profile", value)?;
+        }
+        if let Some(value) = table.get("fail_fast") {
+            config.fail_fast = expect_bool("fail_fast", value)?;
+        }
+        if let Some(value) = table.get("max_file_size") {
+            config.max_file_size = expect_usize("max_file_size", value)?;
+        }
+        if let Some(value) = table.get("pre_build") {
+            config.pre_build = expect_str("pre_build", value)?.to_string();
+        }
+        if let Some(value) = table.get("post_build") {
+            config.post_build = expect_str("post_build", value)?.to_string();
+        }
+        if let Some(value) = table.get("io_retry_count") {
+            config.io_retry_count = expect_usize("io_retry_count", value)?;
+        }
+        if let Some(value) = table.get("io_retry_delay_ms") {
+            config.io_retry_delay_ms = expect_usize("io_retry_delay_ms", value)? as u64;
+        }
+        if let Some(value) = table.get("date_format") {
+            config.date_format = expect_str("date_format", value)?.to_string();
+        }
+        if let Some(value) = table.get("date_locale") {
+            let locale = expect_str("date_locale", value)?;
+            if !LOCALES.contains(&locale) {
+                return Err(invalid_data(format!("date_locale: unsupported locale \"{}\", use one of {}", locale, LOCALES.join(", "))));
+            }
+            config.date_locale = locale.to_string();
+        }
+        if let Some(value) = table.get("allow_unsafe_names") {
+            config.allow_unsafe_names = expect_bool("allow_unsafe_names", value)?;
+        }
+        if let Some(value) = table.get("allow_output_collisions") {
+            config.allow_output_collisions = expect_bool("allow_output_collisions", value)?;
+        }
+        if let Some(value) = table.get("include_drafts") {
+            config.include_drafts = expect_bool("include_drafts", value)?;
+        }
+        if let Some(value) = table.get("git_dates") {
+            config.git_dates = expect_bool("git_dates", value)?;
+        }
+        if let Some(value) = table.get("authors") {
+            config.authors = read_authors(value)?;
+        }
+        if let Some(value) = table.get("thanks") {
+            let items = value.as_array().ok_or_else(|| invalid_data(format!("thanks: expected array, found {}", value.type_name())))?;
+            config.thanks = items.iter().map(|item| expect_str("thanks", item).map(str::to_string)).collect::<io::Result<_>>()?;
+        }
+        if let Some(value) = table.get("language") {
+            config.language = expect_str("language", value)?.to_string();
+        }
+        if let Some(value) = table.get("languages") {
+            let items = value.as_array().ok_or_else(|| invalid_data(format!("languages: expected array, found {}", value.type_name())))?;
+            config.languages = items.iter().map(|item| expect_str("languages", item).map(str::to_string)).collect::<io::Result<_>>()?;
+            // Each language names a directory of public/
+            if let Some(bad) = config.languages.iter().find(|language| language.is_empty() || !language.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')) {
+                return Err(invalid_data(format!("languages: \"{}\" is not a language code", bad)));
+            }
+        }
+        if let Some(value) = table.get("default_language") {
+            let language = expect_str("default_language", value)?;
+            if !config.languages.iter().any(|other| other == language) {
+                return Err(invalid_data(format!("default_language: \"{}\" is not in languages", language)));
+            }
+            config.default_language = Some(language.to_string());
+        }
+        Ok(config)
+    }
+}
+
+// Prefix of the environment variables overriding ironite.toml: IRONITE_BASE_URL sets base_url
+pub const ENV_CONFIG_PREFIX: &str = "IRONITE_";
+
+// Settings of ironite.toml read as text from environment variables, the value kept as written. Keep
+// in step with from_table
+const ENV_STRING_KEYS: &[&str] = &[
+    "author", "base_url", "build_date", "date_format", "date_locale", "default_language", "entries_dir", "external_link_marker",
+    "feed_content", "images_dir", "index_sort", "language", "more_marker", "path_prefix", "permalink_pattern", "pinned_class",
+    "pinned_marker", "post_build", "pre_build", "public_dir", "root_files_dir", "static_dir", "untagged_tag",
+];
+
+// Settings of ironite.toml read as numbers from environment variables: digits only
+const ENV_INTEGER_KEYS: &[&str] = &[
+    "compress_min_size", "copy_workers", "entry_limit", "feed_limit", "inline_max_size", "io_retry_count", "io_retry_delay_ms",
+    "max_file_size", "related_limit", "sitemap_max_urls",
+];
+
+// Settings of ironite.toml read as switches from environment variables: true or false
+const ENV_BOOL_KEYS: &[&str] = &[
+    "allow_output_collisions", "allow_unsafe_names", "brotli", "case_insensitive_tags", "check_links", "embed_assets",
+    "emit_build_info", "entries_index_teasers", "env_placeholders", "external_link_stubs", "external_links_new_tab",
+    "external_links_noopener", "fail_fast", "feed_pinned", "fingerprint_assets", "follow_symlinks", "footnotes", "git_dates",
+    "gzip", "highlight_code", "image_dimensions", "include_drafts", "inline_assets", "lazy_images", "lowercase_tags", "minify_html",
+    "nav_cloud_nested_tags", "pretty_html", "pretty_urls", "profile", "recursive_entries", "require_tags", "shortcodes",
+    "smart_punctuation", "strict_links", "tag_alias_redirects", "tags",
+];
+
+// Why the IRONITE_* environment variables give no configuration
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ConfigError {
+    // A variable whose value does not have the type of its setting, or not one of its values
+    InvalidEnvVar { name: String, value: String, expected: String },
+    // A setting rejected for another reason, such as a default_language missing from languages
+    Invalid(String),
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConfigError::InvalidEnvVar { name, value, expected } => write!(f, "environment variable {}={}: expected {}", name, value, expected),
+            ConfigError::Invalid(message) => write!(f, "{}", message),
+        }
+    }
+}
+
+impl std::error::Error for ConfigError {}
+
+impl From<ConfigError> for io::Error {
+    fn from(error: ConfigError) -> io::Error {
+        invalid_data(error.to_string())
+    }
+}
+
+// Settings given as environment variables, by key, with the variable and the value they came from
+type EnvOverrides = BTreeMap<String, (String, String, Value)>;
+
+// Read the settings of IRONITE_* variables, one for each key of ironite.toml, by the type of the
+// setting: text as written, so IRONITE_PUBLIC_DIR=2024 and IRONITE_BASE_URL=https://example.com need no
+// quotes, switches as true or false, numbers as digits. Lists and tables are read as TOML (["a", "b"]),
+// and as plain strings when they are not valid TOML, for from_table to reject
+fn env_overrides(vars: impl Iterator<Item = (String, String)>) -> Result<EnvOverrides, ConfigError> {
+    let mut overrides = EnvOverrides::new();
+    for (name, raw) in vars {
+        let Some(key) = name.strip_prefix(ENV_CONFIG_PREFIX).filter(|key| !key.is_empty()) else { continue };
+        let key = key.to_ascii_lowercase();
+        let invalid = |expected: &str| ConfigError::InvalidEnvVar { name: name.clone(), value: raw.clone(), expected: expected.to_string() };
+        let value = if ENV_STRING_KEYS.contains(&key.as_str()) {
+            Value::String(raw.clone())
+        } else if ENV_BOOL_KEYS.contains(&key.as_str()) {
+            match raw.as_str() {
+                "true" => Value::Boolean(true),
+                "false" => Value::Boolean(false),
+                _ => return Err(invalid("true or false")),
+            }
+        } else if ENV_INTEGER_KEYS.contains(&key.as_str()) {
+            match raw.parse::<i64>() {
+                Ok(number) if raw.bytes().all(|b| b.is_ascii_digit()) => Value::Integer(number),
+                _ => return Err(invalid("a non-negative integer")),
+            }
+        } else {
+            match toml::parse(&format!("value = {}", raw)) {
+                Ok(mut table) if table.len() == 1 && !raw.contains('\n') => table.remove("value").unwrap_or_else(|| Value::String(raw.clone())),
+                _ => Value::String(raw.clone()),
+            }
+        };
+        overrides.insert(key, (name, raw, value));
+    }
+    Ok(overrides)
+}
+
+// Name the environment variable behind an invalid setting. Errors of from_table start with the key,
+// and those of a wrong type or value go on with "expected ..., found ..."
+fn env_error(error: io::Error, overrides: &EnvOverrides) -> ConfigError {
+    let message = error.to_string();
+    let Some((key, (name, raw, _))) = overrides.iter().find(|(key, _)| message.starts_with(&format!("{}:", key))) else {
+        return ConfigError::Invalid(message);
+    };
+    let reason = message[key.len() + 1..].trim_start();
+    match reason.strip_prefix("expected ") {
+        Some(expected) => {
+            let expected = expected.rfind(", found").map_or(expected, |end| &expected[..end]);
+            ConfigError::InvalidEnvVar { name: name.clone(), value: raw.clone(), expected: expected.to_string() }
+        }
+        None => ConfigError::Invalid(format!("environment variable {}={}: {}", name, raw, reason)),
+    }
+}
+
+// Read a table of placeholders. Names get a leading $ when they do not have one,
+// so HEADERIMAGE = "..." defines $HEADERIMAGE
+fn read_placeholders(key: &str, value: &Value) -> io::Result<HashMap<String, String>> {
+    let table = value
+        .as_table()
+        .ok_or_else(|| invalid_data(format!("{}: expected table, found {}", key, value.type_name())))?;
+    let mut placeholders = HashMap::new();
+    for (name, value) in table {
+        let text = expect_str(&format!("{}.{}", key, name), value)?;
+        let name = if name.starts_with('$') { name.clone() } else { format!("${}", name) };
+        placeholders.insert(name, text.to_string());
+    }
+    Ok(placeholders)
+}
+
+// Read [sitemap]: its hints, those of [sitemap.index], [sitemap.entry] and [sitemap.tag], and the
+// [[sitemap.paths]] rules, each with a pattern
+fn read_sitemap_settings(value: &Value) -> io::Result<SitemapSettings> {
+    let table = value.as_table().ok_or_else(|| invalid_data(format!("sitemap: expected table, found {}", value.type_name())))?;
+    let mut settings = SitemapSettings { defaults: read_sitemap_hints("sitemap", table)?, ..SitemapSettings::default() };
+    for (kind, hints) in [("index", &mut settings.index), ("entry", &mut settings.entry), ("tag", &mut settings.tag)] {
+        if let Some(value) = table.get(kind) {
+            let key = format!("sitemap.{}", kind);
+            let kind_table = value.as_table().ok_or_else(|| invalid_data(format!("{}: expected table, found {}", key, value.type_name())))?;
+            *hints = read_sitemap_hints(&key, kind_table)?;
+        }
+    }
+    if let Some(value) = table.get("paths") {
+        let items = value.as_array().ok_or_else(|| invalid_data(format!("sitemap.paths: expected array of tables, found {}", value.type_name())))?;
+        for item in items {
+            let rule = item.as_table().ok_or_else(|| invalid_data(format!("sitemap.paths: expected table, found {}", item.type_name())))?;
+            let pattern = rule.get("pattern").ok_or_else(|| invalid_data("sitemap.paths: every rule needs a pattern".to_string()))?;
+            let pattern = expect_str("sitemap.paths.pattern", pattern)?.to_string();
+            settings.paths.push(SitemapRule { pattern, hints: read_sitemap_hints("sitemap.paths", rule)? });
+        }
+    }
+    Ok(settings)
+}
+
+// changefreq and priority of a [sitemap] table, key naming it in errors
+fn read_sitemap_hints(key: &str, table: &Table) -> io::Result<SitemapHints> {
+    let mut hints = SitemapHints::default();
+    if let Some(value) = table.get("changefreq") {
+        let changefreq = expect_str(&format!("{}.changefreq", key), value)?;
+        if !CHANGEFREQS.contains(&changefreq) {
+            return Err(invalid_data(format!("{}.changefreq: expected one of {}, found \"{}\"", key, CHANGEFREQS.join(", "), changefreq)));
+        }
+        hints.changefreq = Some(changefreq.to_string());
+    }
+    if let Some(value) = table.get("priority") {
+        let priority = match value {
+            Value::Float(priority) => *priority,
+            Value::Integer(priority) => *priority as f64,
+            other => return Err(invalid_data(format!("{}.priority: expected number, found {}", key, other.type_name()))),
+        };
+        if !(0.0..=1.0).contains(&priority) {
+            return Err(invalid_data(format!("{}.priority: expected a number from 0.0 to 1.0, found {}", key, priority)));
+        }
+        hints.priority = Some(priority);
+    }
+    Ok(hints)
+}
+
+// Read KEY=value variables, skipping blank lines and # comments. Keys are made of A-Z, 0-9 and _,
+// and are returned as placeholders: AUTHOR=Jane gives $AUTHOR. A missing file has no variables
+pub fn load_vars(path: &Path) -> io::Result<HashMap<String, String>> {
+    let mut vars = HashMap::new();
+    if !path.exists() {
+        return Ok(vars);
+    }
+    for (number, line) in fs::read_to_string(path)?.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let (key, value) = line
+            .split_once('=')
+            .ok_or_else(|| invalid_data(format!("{}: line {}: expected KEY=value", path.display(), number + 1)))?;
+        let key = key.trim();
+        if key.is_empty() || !key.bytes().all(|b| b.is_ascii_uppercase() || b.is_ascii_digit() || b == b'_') {
+            return Err(invalid_data(format!("{}: line {}: invalid variable name \"{}\", use A-Z, 0-9 and _", path.display(), number + 1, key)));
+        }
+        vars.insert(format!("${}", key), value.trim().to_string());
+    }
+    Ok(vars)
+}
+
+// Names of the environment variables referenced as $ENV_NAME in text. Names are made of A-Z, 0-9 and _,
+// like the variables in vars.txt
+pub fn env_placeholder_names(text: &str) -> Vec<String> {
+    let mut names = Vec::new();
+    let mut rest = text;
+    while let Some(start) = rest.find(ENV_PREFIX) {
+        rest = &rest[start + ENV_PREFIX.len()..];
+        let end = rest
+            .bytes()
+            .position(|b| !(b.is_ascii_uppercase() || b.is_ascii_digit() || b == b'_'))
+            .unwrap_or(rest.len());
+        if end > 0 {
+            names.push(rest[..end].to_string());
+        }
+        rest = &rest[end..];
+    }
+    names
+}
+
+// Look up environment variables as $ENV_NAME placeholders. Fails with the sorted names of the
+// variables that are not set
+pub fn env_placeholders<'a, I: IntoIterator<Item = &'a String>>(names: I) -> Result<HashMap<String, String>, Vec<String>> {
+    let mut placeholders = HashMap::new();
+    let mut missing = Vec::new();
+    for name in names {
+        match env::var(name) {
+            Ok(value) => {
+                placeholders.insert(format!("{}{}", ENV_PREFIX, name), value);
+            }
+            Err(_) => missing.push(name.clone()),
+        }
+    }
+    if missing.is_empty() {
+        Ok(placeholders)
+    } else {
+        missing.sort();
+        missing.dedup();
+        Err(missing)
+    }
+}
+
+// Environment variable holding the build time for reproducible builds, in unix seconds
+pub const SOURCE_DATE_EPOCH: &str = "SOURCE_DATE_EPOCH";
+
+// Build date from SOURCE_DATE_EPOCH, or None when it is not set
+pub fn source_date_epoch() -> io::Result<Option<Date>> {
+    match env::var(SOURCE_DATE_EPOCH) {
+        Ok(value) => {
+            let seconds = value
+                .trim()
+                .parse::<i64>()
+                .map_err(|_| invalid_data(format!("{}: expected unix seconds, found \"{}\"", SOURCE_DATE_EPOCH, value)))?;
+            Ok(Some(Date::from_unix(seconds)))
+        }
+        Err(_) => Ok(None),
+    }
+}
+
+// Date of a file modification time. With SOURCE_DATE_EPOCH set, later times are clamped to it, so that
+// a fresh checkout, which touches every file, still builds to the same output
+pub fn file_date(time: SystemTime) -> Date {
+    let date = Date::from_system_time(time);
+    match source_date_epoch() {
+        Ok(Some(epoch)) if epoch < date => epoch,
+        _ => date,
+    }
+}
+
+// Absolute form of path with . and .. resolved, without touching the filesystem
+fn normalize(path: &Path) -> io::Result<PathBuf> {
+    let mut normalized = PathBuf::new();
+    for component in env::current_dir()?.join(path).components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => {
+                normalized.pop();
+            }
+            other => normalized.push(other),
+        }
+    }
+    Ok(normalized)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::{write, TempDir};
+
+    fn vars(pairs: &[(&str, &str)]) -> impl Iterator<Item = (String, String)> {
+        pairs.iter().map(|(name, value)| (name.to_string(), value.to_string())).collect::<Vec<_>>().into_iter()
+    }
+
+    #[test]
+    fn environment_variables_override_the_file() {
+        let config = SiteConfig::from_vars(vars(&[
+            ("IRONITE_PUBLIC_DIR", "dist"),
+            ("IRONITE_BASE_URL", "https://example.com/"),
+            ("IRONITE_INCLUDE_DRAFTS", "true"),
+            ("IRONITE_LANGUAGES", "[\"en\", \"fr\"]"),
+            ("IRONITE_UNKNOWN_SETTING", "ignored"),
+            ("HOME", "/root"),
+        ]))
+        .unwrap();
+        assert_eq!(config.public_dir, PathBuf::from("dist"));
+        assert_eq!(config.base_url, "https://example.com");
+        assert!(config.include_drafts);
+        assert_eq!(config.languages, ["en", "fr"]);
+        assert!(!config.fingerprint_assets);
+
+        // Values are read by the type of their setting
+        let config = SiteConfig::from_vars(vars(&[
+            ("IRONITE_PUBLIC_DIR", "2024"),
+            ("IRONITE_AUTHOR", "true"),
+            ("IRONITE_BUILD_DATE", "2024-05-01"),
+            ("IRONITE_FEED_LIMIT", "5"),
+        ]))
+        .unwrap();
+        assert_eq!(config.public_dir, PathBuf::from("2024"));
+        assert_eq!(config.author.as_deref(), Some("true"));
+        assert_eq!(config.build_date, Date::parse("2024-05-01"));
+        assert_eq!(config.feed_limit, Some(5));
+
+        let invalid = |name: &str, value: &str, expected: &str| {
+            let error = SiteConfig::from_vars(vars(&[(name, value)])).unwrap_err();
+            assert_eq!(error, ConfigError::InvalidEnvVar { name: name.to_string(), value: value.to_string(), expected: expected.to_string() });
+        };
+        invalid("IRONITE_INCLUDE_DRAFTS", "maybe", "true or false");
+        invalid("IRONITE_INCLUDE_DRAFTS", "1", "true or false");
+        invalid("IRONITE_FEED_LIMIT", "-3", "a non-negative integer");
+        invalid("IRONITE_FEED_LIMIT", "\"5\"", "a non-negative integer");
+        invalid("IRONITE_LANGUAGES", "en", "array");
+        invalid("IRONITE_FEED_CONTENT", "everything", "\"full\" or \"summary\"");
+        let error = SiteConfig::from_vars(vars(&[("IRONITE_INCLUDE_DRAFTS", "maybe")])).unwrap_err();
+        assert_eq!(error.to_string(), "environment variable IRONITE_INCLUDE_DRAFTS=maybe: expected true or false");
+
+        let dir = TempDir::new("config-env");
+        let path = dir.path().join(CONFIG_FILE);
+        write(&path, "base_url = \"https://file.example\"\ninclude_drafts = true\n");
+        let config = SiteConfig::load_with_vars(&path, vars(&[("IRONITE_INCLUDE_DRAFTS", "false")])).unwrap();
+        assert_eq!(config.base_url, "https://file.example");
+        assert!(!config.include_drafts);
+        let config = SiteConfig::load_with_vars(&dir.path().join("missing.toml"), vars(&[("IRONITE_STATIC_DIR", "assets")])).unwrap();
+        assert_eq!(config.static_dir, PathBuf::from("assets"));
+        let error = SiteConfig::load_with_vars(&path, vars(&[("IRONITE_INCLUDE_DRAFTS", "yes")])).unwrap_err();
+        assert_eq!(error.to_string(), "environment variable IRONITE_INCLUDE_DRAFTS=yes: expected true or false");
+    }
+}
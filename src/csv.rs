@@ -0,0 +1,203 @@
+use std::fmt;
+use std::fs;
+use std::path::Path;
+
+use crate::html::{content_chunk_end, escape_html};
+
+// Why a CSV file gives no table
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CsvError {
+    // No rows at all
+    Empty,
+    // A quoted field still open at the end of the file, which started on line
+    UnterminatedQuote { line: usize },
+    // A row starting on line with another number of fields than the header
+    RaggedRow { line: usize, fields: usize, expected: usize },
+}
+
+impl fmt::Display for CsvError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CsvError::Empty => write!(f, "no rows"),
+            CsvError::UnterminatedQuote { line } => write!(f, "line {}: quoted field never closed", line),
+            CsvError::RaggedRow { line, fields, expected } => {
+                write!(f, "line {}: {} fields, where the header has {}", line, fields, expected)
+            }
+        }
+    }
+}
+
+impl std::error::Error for CsvError {}
+
+// Rows of CSV text: fields separated by commas, quoted with " to hold commas, line breaks and "" for a
+// quote. \r\n and \n both end rows, and blank lines are skipped
+pub fn parse_csv(text: &str) -> Result<Vec<Vec<String>>, CsvError> {
+    let mut rows = Vec::new();
+    let mut row: Vec<String> = Vec::new();
+    let mut field = String::new();
+    let mut chars = text.chars().peekable();
+    let mut line = 1;
+    let mut row_line = 1;
+    // Whether the current row has anything yet, even an empty quoted field
+    let mut started = false;
+
+    while let Some(c) = chars.next() {
+        match c {
+            '"' if field.is_empty() => {
+                started = true;
+                let quote_line = line;
+                loop {
+                    match chars.next() {
+                        Some('"') if chars.peek() == Some(&'"') => {
+                            chars.next();
+                            field.push('"');
+                        }
+                        Some('"') => break,
+                        Some(c) => {
+                            line += usize::from(c == '\n');
+                            field.push(c);
+                        }
+                        None => return Err(CsvError::UnterminatedQuote { line: quote_line }),
+                    }
+                }
+            }
+            ',' => {
+                started = true;
+                row.push(std::mem::take(&mut field));
+            }
+            '\r' if chars.peek() == Some(&'\n') => {}
+            '\n' => {
+                if started || !field.is_empty() {
+                    row.push(std::mem::take(&mut field));
+                    rows.push((row_line, std::mem::take(&mut row)));
+                }
+                started = false;
+                line += 1;
+                row_line = line;
+            }
+            c => field.push(c),
+        }
+    }
+    if started || !field.is_empty() {
+        row.push(field);
+        rows.push((row_line, row));
+    }
+
+    let expected = rows.first().map(|(_, header)| header.len()).ok_or(CsvError::Empty)?;
+    if let Some((line, row)) = rows.iter().find(|(_, row)| row.len() != expected) {
+        return Err(CsvError::RaggedRow { line: *line, fields: row.len(), expected });
+    }
+    Ok(rows.into_iter().map(|(_, row)| row).collect())
+}
+
+// HTML table of CSV text: the first row as the header, every cell escaped
+pub fn csv_to_table(text: &str) -> Result<String, CsvError> {
+    let rows = parse_csv(text)?;
+    let cells = |row: &[String], cell: &str| -> String { row.iter().map(|field| format!("<{0}>{1}</{0}>", cell, escape_html(field))).collect() };
+    let mut html = format!("<table><thead><tr>{}</tr></thead>", cells(&rows[0], "th"));
+    if rows.len() > 1 {
+        html.push_str("<tbody>");
+        for row in &rows[1..] {
+            html.push_str(&format!("<tr>{}</tr>", cells(row, "td")));
+        }
+        html.push_str("</tbody>");
+    }
+    html.push_str("</table>");
+    Ok(html)
+}
+
+// Content with its table directives expanded, see expand_csv_tables
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CsvTables {
+    pub html: String,
+    // Directives left as written, with the reason
+    pub failed: Vec<(String, String)>,
+}
+
+// Replace every {{table file.csv}} of an entry's content with the table of that file, in the entry
+// directory. Directives naming a file outside it, a missing file or one csv_to_table rejects are left as
+// written. Tags, comments and verbatim elements such as <code> are left alone
+pub fn expand_csv_tables(html: &str, entry_path: &Path) -> CsvTables {
+    let mut out = String::with_capacity(html.len());
+    let mut failed = Vec::new();
+    let mut rest = html;
+    while !rest.is_empty() {
+        let (chunk, next) = rest.split_at(content_chunk_end(rest));
+        if chunk.starts_with('<') {
+            out.push_str(chunk);
+        } else {
+            let mut text = chunk;
+            let directive_at = |text: &str| {
+                let start = text.find("{{table ")?;
+                Some((start, start + text[start..].find("}}")? + 2))
+            };
+            while let Some((start, end)) = directive_at(text) {
+                out.push_str(&text[..start]);
+                let directive = &text[start..end];
+                match table_of(entry_path, directive["{{table ".len()..directive.len() - 2].trim()) {
+                    Ok(table) => out.push_str(&table),
+                    Err(reason) => {
+                        out.push_str(directive);
+                        failed.push((directive.to_string(), reason));
+                    }
+                }
+                text = &text[end..];
+            }
+            out.push_str(text);
+        }
+        rest = next;
+    }
+    CsvTables { html: out, failed }
+}
+
+// Table of the CSV file at name in the entry directory, or why there is none
+fn table_of(entry_path: &Path, name: &str) -> Result<String, String> {
+    if name.is_empty() || name.starts_with('/') || name.split(['/', '\\']).any(|part| part == "..") {
+        return Err(format!("{} is not a file of the entry", name));
+    }
+    let text = fs::read_to_string(entry_path.join(name)).map_err(|e| format!("{}: {}", name, e))?;
+    csv_to_table(&text).map_err(|e| format!("{}: {}", name, e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::{write, TempDir};
+
+    #[test]
+    fn csv_to_table_escapes_cells() {
+        let table = csv_to_table("name,notes\r\nAda,\"first, \"\"programmer\"\"\"\n\nBob,<b>\n\"two\nlines\",\"\"\n").unwrap();
+        assert_eq!(
+            table,
+            "<table><thead><tr><th>name</th><th>notes</th></tr></thead><tbody>\
+             <tr><td>Ada</td><td>first, &quot;programmer&quot;</td></tr>\
+             <tr><td>Bob</td><td>&lt;b&gt;</td></tr>\
+             <tr><td>two\nlines</td><td></td></tr></tbody></table>"
+        );
+        assert_eq!(csv_to_table("a,b").unwrap(), "<table><thead><tr><th>a</th><th>b</th></tr></thead></table>");
+        assert_eq!(csv_to_table("\n\n"), Err(CsvError::Empty));
+        assert_eq!(csv_to_table("a,b\n\"x\ny,z\n1,2"), Err(CsvError::UnterminatedQuote { line: 2 }));
+        let error = csv_to_table("a,b\n1,2\n\n3\n").unwrap_err();
+        assert_eq!(error, CsvError::RaggedRow { line: 4, fields: 1, expected: 2 });
+        assert_eq!(error.to_string(), "line 4: 1 fields, where the header has 2");
+    }
+
+    #[test]
+    fn table_directives_read_entry_files() {
+        let dir = TempDir::new("csv-tables");
+        write(&dir.path().join("data.csv"), "x,y\n1,2\n");
+        write(&dir.path().join("ragged.csv"), "x,y\n1\n");
+        let tables = expand_csv_tables(
+            "<p>{{table data.csv}}</p><code>{{table data.csv}}</code>{{table ragged.csv}} {{table missing.csv}} {{table ../data.csv}}",
+            dir.path(),
+        );
+        assert_eq!(
+            tables.html,
+            "<p><table><thead><tr><th>x</th><th>y</th></tr></thead><tbody><tr><td>1</td><td>2</td></tr></tbody></table></p>\
+             <code>{{table data.csv}}</code>{{table ragged.csv}} {{table missing.csv}} {{table ../data.csv}}"
+        );
+        let failed: Vec<&str> = tables.failed.iter().map(|(directive, _)| directive.as_str()).collect();
+        assert_eq!(failed, ["{{table ragged.csv}}", "{{table missing.csv}}", "{{table ../data.csv}}"]);
+        assert_eq!(tables.failed[0].1, "ragged.csv: line 2: 1 fields, where the header has 2");
+    }
+}
@@ -0,0 +1,266 @@
+use std::fmt;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+// A UTC date and time with second precision. Fields are ordered so that
+// the derived ordering is chronological
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+pub struct Date {
+    pub year: i32,
+    pub month: u32,
+    pub day: u32,
+    pub hour: u32,
+    pub minute: u32,
+    pub second: u32,
+}
+
+impl Date {
+    // Parse "YYYY-MM-DD", optionally followed by a time ("T" or space separated, "HH:MM" or "HH:MM:SS",
+    // fractional seconds ignored) and a "Z" or "+HH:MM" offset, which is applied to get UTC
+    pub fn parse(text: &str) -> Option<Date> {
+        let text = text.trim();
+        let bytes = text.as_bytes();
+        if bytes.len() < 10 || bytes[4] != b'-' || bytes[7] != b'-' {
+            return None;
+        }
+        let year = text[0..4].parse().ok()?;
+        let month = text[5..7].parse().ok()?;
+        let day = text[8..10].parse().ok()?;
+        if !(1..=12).contains(&month) || day < 1 || day > days_in_month(year, month) {
+            return None;
+        }
+        let mut date = Date { year, month, day, ..Date::default() };
+
+        let rest = &text[10..];
+        if rest.is_empty() {
+            return Some(date);
+        }
+        let rest = rest.strip_prefix(['T', 't', ' '])?;
+        let offset_start = rest.find(['Z', 'z', '+', '-']).unwrap_or(rest.len());
+        let (time, offset) = rest.split_at(offset_start);
+
+        let mut parts = time.split(':');
+        date.hour = parts.next()?.parse().ok()?;
+        date.minute = parts.next()?.parse().ok()?;
+        if let Some(second) = parts.next() {
+            date.second = second.split('.').next()?.parse().ok()?;
+        }
+        if parts.next().is_some() || date.hour > 23 || date.minute > 59 || date.second > 60 {
+            return None;
+        }
+
+        let offset_seconds = match offset {
+            "" | "Z" | "z" => 0,
+            _ => {
+                let sign = if offset.starts_with('-') { -1 } else { 1 };
+                let (hours, minutes) = offset[1..].split_once(':')?;
+                sign * (hours.parse::<i64>().ok()? * 3600 + minutes.parse::<i64>().ok()? * 60)
+            }
+        };
+        Some(Date::from_unix(date.to_unix() - offset_seconds))
+    }
+
+    pub fn from_unix(seconds: i64) -> Date {
+        let days = seconds.div_euclid(86400);
+        let of_day = seconds.rem_euclid(86400) as u32;
+        let (year, month, day) = civil_from_days(days);
+        Date { year, month, day, hour: of_day / 3600, minute: of_day / 60 % 60, second: of_day % 60 }
+    }
+
+    pub fn to_unix(&self) -> i64 {
+        days_from_civil(self.year, self.month, self.day) * 86400
+            + self.hour as i64 * 3600
+            + self.minute as i64 * 60
+            + self.second as i64
+    }
+
+    // RFC 3339 timestamp in UTC, e.g. 2024-03-15T10:00:00Z
+    pub fn to_rfc3339(&self) -> String {
+        format!("{}T{:02}:{:02}:{:02}Z", self, self.hour, self.minute, self.second)
+    }
+
+    // Format with strftime-style fields: %Y, %m, %d, %e (day without padding), %H, %M, %S,
+    // %B and %b (month name, full and short), %A and %a (weekday, full and short) and %%.
+    // Anything else is copied as it is. Names are in English
+    pub fn format(&self, pattern: &str) -> String {
+        self.format_localized(pattern, "en")
+    }
+
+    // Same as format, with month and weekday names in one of LOCALES. Other locales get English names
+    pub fn format_localized(&self, pattern: &str, locale: &str) -> String {
+        let (months, weekdays) = locale_names(locale);
+        let month = months[self.month as usize - 1];
+        let weekday = weekdays[self.weekday() as usize];
+        let mut out = String::new();
+        let mut chars = pattern.chars();
+        while let Some(c) = chars.next() {
+            if c != '%' {
+                out.push(c);
+                continue;
+            }
+            match chars.next() {
+                Some('Y') => out.push_str(&format!("{:04}", self.year)),
+                Some('m') => out.push_str(&format!("{:02}", self.month)),
+                Some('d') => out.push_str(&format!("{:02}", self.day)),
+                Some('e') => out.push_str(&self.day.to_string()),
+                Some('H') => out.push_str(&format!("{:02}", self.hour)),
+                Some('M') => out.push_str(&format!("{:02}", self.minute)),
+                Some('S') => out.push_str(&format!("{:02}", self.second)),
+                Some('B') => out.push_str(month),
+                Some('b') => out.extend(month.chars().take(3)),
+                Some('A') => out.push_str(weekday),
+                Some('a') => out.extend(weekday.chars().take(3)),
+                Some('%') => out.push('%'),
+                Some(other) => {
+                    out.push('%');
+                    out.push(other);
+                }
+                None => out.push('%'),
+            }
+        }
+        out
+    }
+
+    // Day of the week, 0 for Sunday
+    pub fn weekday(&self) -> u32 {
+        // 1970-01-01 was a Thursday
+        (days_from_civil(self.year, self.month, self.day) + 4).rem_euclid(7) as u32
+    }
+
+    // RFC 822 date as used by RSS, e.g. Fri, 15 Mar 2024 10:00:00 +0000. The four-digit year also makes
+    // it an RFC 2822 date. Always in English
+    pub fn to_rfc822(&self) -> String {
+        self.format("%a, %d %b %Y %H:%M:%S +0000")
+    }
+
+    pub fn from_system_time(time: SystemTime) -> Date {
+        let seconds = match time.duration_since(UNIX_EPOCH) {
+            Ok(duration) => duration.as_secs() as i64,
+            Err(e) => -(e.duration().as_secs() as i64),
+        };
+        Date::from_unix(seconds)
+    }
+}
+
+// ISO 8601 date, e.g. 2024-03-15
+impl fmt::Display for Date {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:04}-{:02}-{:02}", self.year, self.month, self.day)
+    }
+}
+
+// Locales with month and weekday names for Date::format_localized
+pub const LOCALES: &[&str] = &["en", "de", "es", "fr", "it", "nl", "pt"];
+
+// Month names (January first) and weekday names (Sunday first) of a locale, English by default
+fn locale_names(locale: &str) -> ([&'static str; 12], [&'static str; 7]) {
+    match locale {
+        "de" => (
+            ["Januar", "Februar", "März", "April", "Mai", "Juni", "Juli", "August", "September", "Oktober", "November", "Dezember"],
+            ["Sonntag", "Montag", "Dienstag", "Mittwoch", "Donnerstag", "Freitag", "Samstag"],
+        ),
+        "es" => (
+            ["enero", "febrero", "marzo", "abril", "mayo", "junio", "julio", "agosto", "septiembre", "octubre", "noviembre", "diciembre"],
+            ["domingo", "lunes", "martes", "miércoles", "jueves", "viernes", "sábado"],
+        ),
+        "fr" => (
+            ["janvier", "février", "mars", "avril", "mai", "juin", "juillet", "août", "septembre", "octobre", "novembre", "décembre"],
+            ["dimanche", "lundi", "mardi", "mercredi", "jeudi", "vendredi", "samedi"],
+        ),
+        "it" => (
+            ["gennaio", "febbraio", "marzo", "aprile", "maggio", "giugno", "luglio", "agosto", "settembre", "ottobre", "novembre", "dicembre"],
+            ["domenica", "lunedì", "martedì", "mercoledì", "giovedì", "venerdì", "sabato"],
+        ),
+        "nl" => (
+            ["januari", "februari", "maart", "april", "mei", "juni", "juli", "augustus", "september", "oktober", "november", "december"],
+            ["zondag", "maandag", "dinsdag", "woensdag", "donderdag", "vrijdag", "zaterdag"],
+        ),
+        "pt" => (
+            ["janeiro", "fevereiro", "março", "abril", "maio", "junho", "julho", "agosto", "setembro", "outubro", "novembro", "dezembro"],
+            ["domingo", "segunda-feira", "terça-feira", "quarta-feira", "quinta-feira", "sexta-feira", "sábado"],
+        ),
+        _ => (
+            ["January", "February", "March", "April", "May", "June", "July", "August", "September", "October", "November", "December"],
+            ["Sunday", "Monday", "Tuesday", "Wednesday", "Thursday", "Friday", "Saturday"],
+        ),
+    }
+}
+
+fn is_leap_year(year: i32) -> bool {
+    (year % 4 == 0 && year % 100 != 0) || year % 400 == 0
+}
+
+pub fn days_in_month(year: i32, month: u32) -> u32 {
+    match month {
+        1 | 3 | 5 | 7 | 8 | 10 | 12 => 31,
+        4 | 6 | 9 | 11 => 30,
+        2 if is_leap_year(year) => 29,
+        _ => 28,
+    }
+}
+
+// Days since 1970-01-01 (Howard Hinnant's algorithm)
+fn days_from_civil(year: i32, month: u32, day: u32) -> i64 {
+    let year = if month <= 2 { year as i64 - 1 } else { year as i64 };
+    let era = year.div_euclid(400);
+    let year_of_era = year - era * 400;
+    let month = month as i64;
+    let day_of_year = (153 * (if month > 2 { month - 3 } else { month + 9 }) + 2) / 5 + day as i64 - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    era * 146097 + day_of_era - 719468
+}
+
+fn civil_from_days(days: i64) -> (i32, u32, u32) {
+    let days = days + 719468;
+    let era = days.div_euclid(146097);
+    let day_of_era = days - era * 146097;
+    let year_of_era = (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let mp = (5 * day_of_year + 2) / 153;
+    let day = (day_of_year - (153 * mp + 2) / 5 + 1) as u32;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+    let year = year_of_era + era * 400 + if month <= 2 { 1 } else { 0 };
+    (year as i32, month, day)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_date_and_time() {
+        let date = Date::parse("2024-03-15").unwrap();
+        assert_eq!((date.year, date.month, date.day), (2024, 3, 15));
+        let date = Date::parse("2024-03-15T23:30:00-02:00").unwrap();
+        assert_eq!(date, Date { year: 2024, month: 3, day: 16, hour: 1, minute: 30, second: 0 });
+        assert_eq!(Date::parse("2023-02-29"), None);
+        assert_eq!(Date::parse("15/03/2024"), None);
+    }
+
+    #[test]
+    fn unix_round_trip() {
+        assert_eq!(Date::from_unix(0).to_string(), "1970-01-01");
+        let date = Date::parse("2000-02-29T12:34:56Z").unwrap();
+        assert_eq!(date.to_unix(), 951827696);
+        assert_eq!(Date::from_unix(date.to_unix()), date);
+    }
+
+    #[test]
+    fn format_fields() {
+        let date = Date::parse("2024-03-05T09:07:01Z").unwrap();
+        assert_eq!(date.format("%Y-%m-%d %H:%M:%S"), "2024-03-05 09:07:01");
+        assert_eq!(date.format("%e %B %Y, %b"), "5 March 2024, Mar");
+        assert_eq!(date.format("100%% %q%"), "100% %q%");
+        assert_eq!(date.format("%A %a"), "Tuesday Tue");
+    }
+
+    #[test]
+    fn localized_names_and_machine_formats() {
+        let date = Date::parse("2024-03-15T10:00:00Z").unwrap();
+        assert_eq!(date.format_localized("%A %e %B %Y", "fr"), "vendredi 15 mars 2024");
+        assert_eq!(date.format_localized("%e. %b", "de"), "15. Mär");
+        assert_eq!(date.format_localized("%B", "xx"), "March");
+        // Machine formats do not follow the locale
+        assert_eq!(date.to_rfc822(), "Fri, 15 Mar 2024 10:00:00 +0000");
+        assert_eq!(date.to_rfc3339(), "2024-03-15T10:00:00Z");
+    }
+}
@@ -0,0 +1,160 @@
+use std::fmt;
+use std::io;
+use std::path::PathBuf;
+
+use crate::links::BrokenLink;
+use crate::outputs::OutputCollision;
+use crate::validate::NameProblem;
+
+// Errors returned by site generation
+#[derive(Debug)]
+pub enum IroniteError {
+    Io(io::Error),
+    // A plugin hook failed. Carries the plugin name and the hook that failed
+    Plugin { plugin: String, hook: &'static str, source: Box<IroniteError> },
+    // Internal links pointing nowhere, with strict_links enabled
+    BrokenLinks(Vec<BrokenLink>),
+    // An alias in aliases.txt points at a generated page, or at an alias of another entry
+    AliasConflict { alias: String, entry: String, other: Option<String> },
+    // Two entries set the same short_url in their meta.toml
+    ShortUrlConflict { short_url: String, first: String, second: String },
+    // Two entries of a series give the same part number in their series.txt
+    SeriesPartConflict { series: String, part: usize, first: String, second: String },
+    // Two entries expand permalink_pattern to the same path
+    PermalinkConflict { permalink: String, first: String, second: String },
+    // permalink_pattern has date tokens and an entry has no date in its metadata
+    UndatedPermalink { entry: String, pattern: String },
+    // Two entries in different sections have the same directory name, with recursive_entries
+    DuplicateEntryName { name: String, first: String, second: String },
+    // A symlinked content.html leads back to a link already followed. Carries every link of the loop
+    SymlinkLoop(Vec<PathBuf>),
+    // Aliases in tag-aliases.txt leading back to a tag of their own chain. Carries the chain
+    TagAliasCycle(Vec<String>),
+    // Entries without any tag, with require_tags enabled
+    UntaggedEntries(Vec<String>),
+    // Entries with both a link.txt and a content file, which leaves unclear whether they have a page
+    LinkWithContent(Vec<String>),
+    // $ENV_NAME placeholders whose environment variables are not set, with env_placeholders enabled
+    MissingEnvVars(Vec<String>),
+    // Entries that could not be generated, with the error of each, unless fail_fast is enabled
+    EntryErrors(Vec<(String, IroniteError)>),
+    // Entry directories or tags whose names cannot be used in URLs, unless allow_unsafe_names is enabled
+    InvalidNames(Vec<NameProblem>),
+    // An entry carries a tag missing from tags_whitelist
+    InvalidTag { tag: String, entry: PathBuf },
+    // Every InvalidTag of the build, found before anything is generated
+    InvalidTags(Vec<IroniteError>),
+    // Outputs written by more than one source, or differing only in case, unless allow_output_collisions is enabled
+    OutputCollisions(Vec<OutputCollision>),
+    // The pre_build or post_build command exited with a failure. Carries the hook, the command and its exit status
+    BuildHook { hook: &'static str, command: String, status: String },
+}
+
+impl fmt::Display for IroniteError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            IroniteError::Io(e) => write!(f, "{}", e),
+            IroniteError::Plugin { plugin, hook, source } => write!(f, "plugin {} failed in {}: {}", plugin, hook, source),
+            IroniteError::BrokenLinks(links) => {
+                write!(f, "{} broken internal links", links.len())?;
+                for link in links {
+                    write!(f, "\n  {}", link)?;
+                }
+                Ok(())
+            }
+            IroniteError::AliasConflict { alias, entry, other: Some(other) } => {
+                write!(f, "alias {} of entry {} is also an alias of entry {}", alias, entry, other)
+            }
+            IroniteError::AliasConflict { alias, entry, other: None } => {
+                write!(f, "alias {} of entry {} would overwrite a generated page", alias, entry)
+            }
+            IroniteError::ShortUrlConflict { short_url, first, second } => {
+                write!(f, "short URL {} is used by entries {} and {}", short_url, first, second)
+            }
+            IroniteError::SeriesPartConflict { series, part, first, second } => {
+                write!(f, "entries {} and {} are both part {} of series {}", first, second, part, series)
+            }
+            IroniteError::PermalinkConflict { permalink, first, second } => {
+                write!(f, "entries {} and {} both have the permalink {}", first, second, permalink)
+            }
+            IroniteError::UndatedPermalink { entry, pattern } => {
+                write!(f, "entry {} has no date for the permalink pattern {}", entry, pattern)
+            }
+            IroniteError::DuplicateEntryName { name, first, second } => {
+                let path = |section: &str| if section.is_empty() { name.clone() } else { format!("{}/{}", section, name) };
+                write!(f, "entries {} and {} have the same name", path(first), path(second))
+            }
+            IroniteError::SymlinkLoop(links) => {
+                let links: Vec<String> = links.iter().map(|link| link.display().to_string()).collect();
+                write!(f, "symlink loop: {}", links.join(" -> "))
+            }
+            IroniteError::TagAliasCycle(chain) => write!(f, "tag alias cycle: {}", chain.join(" -> ")),
+            IroniteError::UntaggedEntries(names) => write!(f, "entries without tags: {}", names.join(", ")),
+            IroniteError::LinkWithContent(names) => write!(f, "entries with both link.txt and content.html: {}", names.join(", ")),
+            IroniteError::MissingEnvVars(names) => write!(f, "environment variables not set: {}", names.join(", ")),
+            IroniteError::EntryErrors(errors) => {
+                write!(f, "{} entries failed", errors.len())?;
+                for (entry, error) in errors {
+                    write!(f, "\n  {}: {}", entry, error)?;
+                }
+                Ok(())
+            }
+            IroniteError::InvalidNames(problems) => {
+                write!(f, "{} invalid names", problems.len())?;
+                for problem in problems {
+                    write!(f, "\n  {}", problem)?;
+                }
+                Ok(())
+            }
+            IroniteError::InvalidTag { tag, entry } => write!(f, "tag {} of entry {} is not in tags_whitelist", tag, entry.display()),
+            IroniteError::InvalidTags(errors) => {
+                write!(f, "{} tags not in tags_whitelist", errors.len())?;
+                for error in errors {
+                    write!(f, "\n  {}", error)?;
+                }
+                Ok(())
+            }
+            IroniteError::OutputCollisions(collisions) => {
+                write!(f, "{} output path collisions", collisions.len())?;
+                for collision in collisions {
+                    write!(f, "\n  {}", collision)?;
+                }
+                Ok(())
+            }
+            IroniteError::BuildHook { hook, command, status } => write!(f, "{} command `{}` failed: {}", hook, command, status),
+        }
+    }
+}
+
+impl std::error::Error for IroniteError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            IroniteError::Io(e) => Some(e),
+            IroniteError::Plugin { source, .. } => Some(source.as_ref()),
+            IroniteError::BrokenLinks(_)
+            | IroniteError::AliasConflict { .. }
+            | IroniteError::ShortUrlConflict { .. }
+            | IroniteError::SeriesPartConflict { .. }
+            | IroniteError::PermalinkConflict { .. }
+            | IroniteError::UndatedPermalink { .. }
+            | IroniteError::DuplicateEntryName { .. }
+            | IroniteError::SymlinkLoop(_)
+            | IroniteError::TagAliasCycle(_)
+            | IroniteError::UntaggedEntries(_)
+            | IroniteError::LinkWithContent(_)
+            | IroniteError::MissingEnvVars(_)
+            | IroniteError::EntryErrors(_)
+            | IroniteError::InvalidNames(_)
+            | IroniteError::InvalidTag { .. }
+            | IroniteError::InvalidTags(_)
+            | IroniteError::OutputCollisions(_)
+            | IroniteError::BuildHook { .. } => None,
+        }
+    }
+}
+
+impl From<io::Error> for IroniteError {
+    fn from(e: io::Error) -> Self {
+        IroniteError::Io(e)
+    }
+}
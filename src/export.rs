@@ -0,0 +1,96 @@
+use std::io;
+use std::path::Path;
+
+use crate::config::SiteConfig;
+use crate::date::Date;
+use crate::frontmatter::read_content_file_with_limit;
+use crate::generator::{content_file, entry_dirs, entry_dirs_recursive, entry_section};
+use crate::json::Json;
+use crate::meta::EntryMeta;
+
+fn optional_string(value: &Option<String>) -> Json {
+    value.as_ref().map_or(Json::Null, |value| Json::string(value.as_str()))
+}
+
+fn optional_date(value: &Option<Date>) -> Json {
+    value.map_or(Json::Null, |date| Json::string(date.to_rfc3339()))
+}
+
+// Every field of an entry's metadata, missing ones as null, plus its content
+fn entry_json(meta: &EntryMeta, content_html: String) -> Json {
+    Json::object(vec![
+        ("slug", Json::string(meta.slug.as_str())),
+        ("section", Json::string(meta.section.as_str())),
+        ("title", optional_string(&meta.title)),
+        ("description", optional_string(&meta.description)),
+        ("author", optional_string(&meta.author)),
+        ("tags", Json::Array(meta.tags.iter().map(Json::string).collect())),
+        ("modified", optional_date(&meta.modified)),
+        ("date", optional_date(&meta.date)),
+        ("template", optional_string(&meta.template)),
+        ("short_url", optional_string(&meta.short_url)),
+        ("updated", optional_date(&meta.updated)),
+        ("draft", Json::Bool(meta.draft)),
+        ("pinned", Json::Bool(meta.pinned)),
+        ("weight", Json::Number(meta.weight as i64)),
+        ("link", optional_string(&meta.link)),
+        ("content_html", Json::string(content_html)),
+    ])
+}
+
+// The content of a site as one JSON object, for integrations such as search indexers: a site object with
+// its name and base URL, and an entries array with the metadata and the content HTML (front matter
+// removed, placeholders left as written) of each entry under entries_dir, sorted by path. Drafts are
+// only included with include_drafts, and entries linking elsewhere have an empty content_html. Nothing
+// is written
+pub fn export_content_as_json(entries_dir: &Path, config: &SiteConfig) -> io::Result<Json> {
+    let entry_paths = if config.recursive_entries { entry_dirs_recursive(entries_dir)? } else { entry_dirs(entries_dir)? };
+    let mut entries = Vec::new();
+    for entry_path in entry_paths {
+        let mut meta = EntryMeta::load(&entry_path)?;
+        if meta.draft && !config.include_drafts {
+            continue;
+        }
+        meta.section = entry_section(entries_dir, &entry_path);
+        let content_path = content_file(&entry_path, config);
+        let content_html = if meta.link.is_some() || !content_path.exists() {
+            String::new()
+        } else {
+            read_content_file_with_limit(&content_path, config.max_file_size)?
+        };
+        entries.push(entry_json(&meta, content_html));
+    }
+
+    Ok(Json::object(vec![
+        (
+            "site",
+            Json::object(vec![("project_name", Json::string(config.project_name())), ("base_url", Json::string(config.base_url.as_str()))]),
+        ),
+        ("entries", Json::Array(entries)),
+    ]))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::{fixture_project, TempDir};
+
+    #[test]
+    fn export_lists_every_entry_with_its_tags() {
+        let dir = TempDir::new("export-json");
+        fixture_project(dir.path());
+        let config = SiteConfig { root: dir.path().to_path_buf(), base_url: "https://example.com".to_string(), ..SiteConfig::default() };
+        let export = export_content_as_json(&dir.path().join("entries"), &config).unwrap();
+
+        let site = export.get("site").unwrap();
+        assert_eq!(site.get("base_url").and_then(Json::as_str), Some("https://example.com"));
+        assert_eq!(site.get("project_name").and_then(Json::as_str), Some("Test site"));
+        let entries = export.get("entries").and_then(Json::as_array).unwrap();
+        let slugs: Vec<&str> = entries.iter().filter_map(|entry| entry.get("slug").and_then(Json::as_str)).collect();
+        assert_eq!(slugs, ["first", "second"]);
+        assert_eq!(entries[0].get("tags"), Some(&Json::Array(vec![Json::string("rust"), Json::string("web")])));
+        assert_eq!(entries[1].get("tags"), Some(&Json::Array(vec![Json::string("rust")])));
+        assert_eq!(entries[0].get("content_html").and_then(Json::as_str), Some("<p>First entry</p>"));
+        assert!(!dir.path().join("public").exists());
+    }
+}
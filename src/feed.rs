@@ -0,0 +1,288 @@
+use std::collections::HashMap;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use crate::config::SiteConfig;
+use crate::date::Date;
+use crate::generator::index_link;
+use crate::html::escape_html;
+use crate::json::Json;
+use crate::meta::EntryMeta;
+use crate::permalink::expand_permalink;
+
+pub const JSON_FEED_VERSION: &str = "https://jsonfeed.org/version/1.1";
+pub const ATOM_NAMESPACE: &str = "http://www.w3.org/2005/Atom";
+
+// What the content_html of feed items holds
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FeedContent {
+    // The whole entry
+    Full,
+    // The entry's description, or else the first paragraph of its content, see entry_summary
+    Summary,
+}
+
+// URL of an entry page, relative to the site root. Undated entries that permalink_pattern cannot place
+// stop the build in entry_permalinks, before any feed is written
+pub fn entry_url_path(meta: &EntryMeta, config: &SiteConfig) -> String {
+    let permalink = expand_permalink(&config.permalink_pattern, meta, config).unwrap_or_else(|_| meta.slug.clone());
+    index_link(&permalink, config.pretty_urls)
+}
+
+// Short HTML summary of an entry: its description as a paragraph, or else its content up to the end
+// of the first paragraph (all of it when there is none)
+pub fn entry_summary(meta: &EntryMeta, content: &str) -> String {
+    if let Some(description) = &meta.description {
+        return format!("<p>{}</p>", escape_html(description));
+    }
+    match content.to_ascii_lowercase().find("</p>") {
+        Some(end) => content[..end + "</p>".len()].trim().to_string(),
+        None => content.trim().to_string(),
+    }
+}
+
+// Separates the teaser of an entry from the rest of its content unless more_marker says otherwise
+pub const DEFAULT_MORE_MARKER: &str = "<!--more-->";
+
+// Content of an entry before the more marker (e.g. <!--more-->), or None when it has none
+pub fn entry_teaser<'a>(content: &'a str, marker: &str) -> Option<&'a str> {
+    if marker.is_empty() {
+        return None;
+    }
+    content.find(marker).map(|end| content[..end].trim())
+}
+
+// One entry of a feed, shared by feed.json and atom.xml
+pub struct FeedItem<'a> {
+    pub meta: &'a EntryMeta,
+    // Absolute URL of the entry page, also used as its id
+    pub url: String,
+    // The whole entry or its summary, following config.feed_content
+    pub content_html: String,
+}
+
+// Feed items for the given entries, in order, keeping the first config.feed_limit of them.
+// content_map maps entry slugs to their rendered HTML
+pub fn feed_items<'a>(entries: &'a [EntryMeta], config: &SiteConfig, content_map: &HashMap<String, String>) -> Vec<FeedItem<'a>> {
+    entries
+        .iter()
+        .take(config.feed_limit.unwrap_or(usize::MAX))
+        .map(|meta| {
+            let content = content_map.get(&meta.slug).map(String::as_str).unwrap_or_default();
+            let content_html = match config.feed_content {
+                FeedContent::Full => content.to_string(),
+                FeedContent::Summary => entry_summary(meta, content),
+            };
+            FeedItem { meta, url: meta.link.clone().unwrap_or_else(|| config.url(&entry_url_path(meta, config))), content_html }
+        })
+        .collect()
+}
+
+// Build a JSON Feed 1.1 document (https://jsonfeed.org/version/1.1) from the feed items of the entries
+pub fn json_feed(entries: &[EntryMeta], config: &SiteConfig, content_map: &HashMap<String, String>) -> Json {
+    let items = feed_items(entries, config, content_map)
+        .into_iter()
+        .map(|item| {
+            let meta = item.meta;
+            let mut fields = vec![
+                ("id", Json::string(item.url.clone())),
+                ("url", Json::string(item.url)),
+                ("title", Json::string(meta.display_title())),
+                ("content_html", Json::string(item.content_html)),
+            ];
+            if let Some(description) = &meta.description {
+                fields.push(("summary", Json::string(description.as_str())));
+            }
+            if let Some(date) = &meta.date {
+                fields.push(("date_published", Json::string(date.to_rfc3339())));
+            }
+            if let Some(updated) = &meta.updated {
+                fields.push(("date_modified", Json::string(updated.to_rfc3339())));
+            }
+            fields.push(("tags", Json::Array(meta.tags.iter().map(Json::string).collect())));
+            let authors = meta
+                .author
+                .as_ref()
+                .or(config.author.as_ref())
+                .map(|name| vec![Json::object(vec![("name", Json::string(name.as_str()))])])
+                .unwrap_or_default();
+            fields.push(("authors", Json::Array(authors)));
+            Json::object(fields)
+        })
+        .collect();
+
+    Json::object(vec![
+        ("version", Json::string(JSON_FEED_VERSION)),
+        ("title", Json::string(config.project_name())),
+        ("home_page_url", Json::string(config.url(""))),
+        ("feed_url", Json::string(config.url("feed.json"))),
+        ("items", Json::Array(items)),
+    ])
+}
+
+// Write the JSON Feed to output_path, usually public/feed.json
+pub fn generate_feed_json(
+    entries: &[EntryMeta],
+    config: &SiteConfig,
+    content_map: &HashMap<String, String>,
+    output_path: &Path,
+) -> io::Result<()> {
+    let feed = json_feed(entries, config, content_map);
+    fs::write(output_path, feed.to_pretty_string() + "\n")
+}
+
+// Last change of an entry for <updated>: its git date, publication date or modification time,
+// else the build date, else the Unix epoch
+fn entry_updated(meta: &EntryMeta, config: &SiteConfig) -> Date {
+    meta.updated.or(meta.date).or(meta.modified).or(config.build_date).unwrap_or_else(|| Date::from_unix(0))
+}
+
+// Build an Atom 1.0 document (RFC 4287) from the same feed items as feed.json. The feed is as recent
+// as its newest entry, or the build when it has none
+pub fn atom_feed(entries: &[EntryMeta], config: &SiteConfig, content_map: &HashMap<String, String>) -> String {
+    let items = feed_items(entries, config, content_map);
+    let updated = items
+        .iter()
+        .map(|item| entry_updated(item.meta, config))
+        .max()
+        .or(config.build_date)
+        .unwrap_or_else(|| Date::from_unix(0));
+
+    let mut xml = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+    xml.push_str(&format!("<feed xmlns=\"{}\">\n", ATOM_NAMESPACE));
+    xml.push_str(&format!("  <title>{}</title>\n", escape_html(&config.project_name())));
+    xml.push_str(&format!("  <id>{}</id>\n", escape_html(&config.url(""))));
+    xml.push_str(&format!("  <link href=\"{}\"/>\n", escape_html(&config.url(""))));
+    xml.push_str(&format!("  <link rel=\"self\" href=\"{}\"/>\n", escape_html(&config.url("atom.xml"))));
+    xml.push_str(&format!("  <updated>{}</updated>\n", updated.to_rfc3339()));
+    if let Some(author) = &config.author {
+        xml.push_str(&format!("  <author><name>{}</name></author>\n", escape_html(author)));
+    }
+    for item in &items {
+        let meta = item.meta;
+        xml.push_str("  <entry>\n");
+        xml.push_str(&format!("    <title>{}</title>\n", escape_html(meta.display_title())));
+        xml.push_str(&format!("    <link href=\"{}\"/>\n", escape_html(&item.url)));
+        xml.push_str(&format!("    <id>{}</id>\n", escape_html(&item.url)));
+        xml.push_str(&format!("    <updated>{}</updated>\n", entry_updated(meta, config).to_rfc3339()));
+        if let Some(date) = &meta.date {
+            xml.push_str(&format!("    <published>{}</published>\n", date.to_rfc3339()));
+        }
+        if let Some(author) = &meta.author {
+            xml.push_str(&format!("    <author><name>{}</name></author>\n", escape_html(author)));
+        }
+        if let Some(description) = &meta.description {
+            xml.push_str(&format!("    <summary>{}</summary>\n", escape_html(description)));
+        }
+        for tag in &meta.tags {
+            xml.push_str(&format!("    <category term=\"{}\"/>\n", escape_html(tag)));
+        }
+        xml.push_str(&format!("    <content type=\"html\">{}</content>\n", escape_html(&item.content_html)));
+        xml.push_str("  </entry>\n");
+    }
+    xml.push_str("</feed>\n");
+    xml
+}
+
+// Write the Atom feed to output_path, usually public/atom.xml
+pub fn generate_atom_feed(
+    entries: &[EntryMeta],
+    config: &SiteConfig,
+    content_map: &HashMap<String, String>,
+    output_path: &Path,
+) -> io::Result<()> {
+    fs::write(output_path, atom_feed(entries, config, content_map))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::TempDir;
+
+    #[test]
+    fn json_feed_has_version_and_content() {
+        let dir = TempDir::new("feed-json");
+        let config = SiteConfig { base_url: "https://example.com".to_string(), ..SiteConfig::default() };
+        let entry = EntryMeta {
+            slug: "hello".to_string(),
+            tags: vec!["rust".to_string()],
+            date: Date::parse("2024-03-15"),
+            author: Some("Ana".to_string()),
+            ..EntryMeta::default()
+        };
+        let mut content_map = HashMap::new();
+        content_map.insert("hello".to_string(), "<p>Hello, feed</p>".to_string());
+
+        let output = dir.path().join("feed.json");
+        generate_feed_json(std::slice::from_ref(&entry), &config, &content_map, &output).unwrap();
+        assert!(fs::read_to_string(&output).unwrap().contains("\"version\": \"https://jsonfeed.org/version/1.1\""));
+
+        let feed = json_feed(&[entry], &config, &content_map);
+        assert_eq!(feed.get("version").and_then(Json::as_str), Some(JSON_FEED_VERSION));
+        let item = &feed.get("items").and_then(Json::as_array).unwrap()[0];
+        assert_eq!(item.get("content_html").and_then(Json::as_str), Some("<p>Hello, feed</p>"));
+        assert_eq!(item.get("url").and_then(Json::as_str), Some("https://example.com/entries/hello/index.html"));
+        assert_eq!(item.get("date_published").and_then(Json::as_str), Some("2024-03-15T00:00:00Z"));
+    }
+
+    #[test]
+    fn feed_limit_and_summaries() {
+        let entries: Vec<EntryMeta> = ["new", "mid", "old"]
+            .iter()
+            .map(|slug| EntryMeta { slug: slug.to_string(), ..EntryMeta::default() })
+            .collect();
+        let mut content_map = HashMap::new();
+        for slug in ["new", "mid", "old"] {
+            content_map.insert(slug.to_string(), format!("<P>First of {}</P>\n<p>Second</p>", slug));
+        }
+        let config = SiteConfig { feed_limit: Some(2), feed_content: FeedContent::Summary, ..SiteConfig::default() };
+
+        let feed = json_feed(&entries, &config, &content_map);
+        let items = feed.get("items").and_then(Json::as_array).unwrap();
+        assert_eq!(items.len(), 2);
+        assert_eq!(items[0].get("content_html").and_then(Json::as_str), Some("<P>First of new</P>"));
+        assert_eq!(items[1].get("content_html").and_then(Json::as_str), Some("<P>First of mid</P>"));
+
+        let described = EntryMeta { description: Some("Fish & chips".to_string()), ..EntryMeta::default() };
+        assert_eq!(entry_summary(&described, "<p>Body</p>"), "<p>Fish &amp; chips</p>");
+        assert_eq!(entry_summary(&EntryMeta::default(), " no paragraphs "), "no paragraphs");
+    }
+
+    #[test]
+    fn teaser_ends_at_the_more_marker() {
+        let content = "<p>Intro</p>\n<!--more-->\n<p>Rest</p>";
+        assert_eq!(entry_teaser(content, DEFAULT_MORE_MARKER), Some("<p>Intro</p>"));
+        assert_eq!(entry_teaser(content, "<!--cut-->"), None);
+        assert_eq!(entry_teaser(content, ""), None);
+    }
+
+    #[test]
+    fn atom_feed_has_rfc3339_dates_and_escaped_content() {
+        let config = SiteConfig {
+            base_url: "https://example.com".to_string(),
+            build_date: Date::parse("2024-05-01"),
+            ..SiteConfig::default()
+        };
+        let entries = vec![
+            EntryMeta { slug: "new".to_string(), date: Date::parse("2024-03-15T10:00:00Z"), tags: vec!["rust".to_string()], ..EntryMeta::default() },
+            EntryMeta { slug: "old".to_string(), date: Date::parse("2024-01-02"), updated: Date::parse("2024-04-01"), ..EntryMeta::default() },
+        ];
+        let mut content_map = HashMap::new();
+        content_map.insert("new".to_string(), "<p>Fish & chips</p>".to_string());
+
+        let atom = atom_feed(&entries, &config, &content_map);
+        assert!(atom.starts_with("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<feed xmlns=\"http://www.w3.org/2005/Atom\">\n"), "{}", atom);
+        assert!(atom.contains("  <id>https://example.com/</id>\n"), "{}", atom);
+        assert!(atom.contains("  <updated>2024-04-01T00:00:00Z</updated>\n"), "{}", atom);
+        assert_eq!(atom.matches("<entry>").count(), 2);
+        assert!(atom.contains(
+            "    <title>new</title>\n    <link href=\"https://example.com/entries/new/index.html\"/>\n    <id>https://example.com/entries/new/index.html</id>\n    <updated>2024-03-15T10:00:00Z</updated>\n"
+        ), "{}", atom);
+        assert!(atom.contains("<category term=\"rust\"/>"), "{}", atom);
+        assert!(atom.contains("<content type=\"html\">&lt;p&gt;Fish &amp; chips&lt;/p&gt;</content>"), "{}", atom);
+
+        let empty = atom_feed(&[], &config, &content_map);
+        assert!(empty.contains("<updated>2024-05-01T00:00:00Z</updated>") && !empty.contains("<entry>"), "{}", empty);
+    }
+}
@@ -0,0 +1,344 @@
+use std::collections::{BTreeMap, HashSet};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use crate::html::{next_link_attribute, resolve_reference};
+
+// Maps original asset paths to their fingerprinted paths, both relative to the public directory
+// (e.g. "static/style.css" => "static/style.1a2b3c4d.css")
+pub type AssetMap = BTreeMap<String, String>;
+
+// 32-bit FNV-1a hash of some content
+pub fn content_hash(bytes: &[u8]) -> u32 {
+    let mut hash: u32 = 0x811c9dc5;
+    for byte in bytes {
+        hash ^= *byte as u32;
+        hash = hash.wrapping_mul(0x01000193);
+    }
+    hash
+}
+
+// Insert the hash before the extension: "style.css" => "style.1a2b3c4d.css"
+pub fn fingerprinted_name(file_name: &str, hash: u32) -> String {
+    match file_name.rfind('.') {
+        Some(dot) if dot > 0 => format!("{}.{:08x}{}", &file_name[..dot], hash, &file_name[dot..]),
+        _ => format!("{}.{:08x}", file_name, hash),
+    }
+}
+
+// Copy a directory recursively, renaming every file to its fingerprinted name.
+// HTML files are templates rather than assets and keep their names. Stylesheets are copied last, with
+// their url() references already pointing at the fingerprinted names, so that their own hash changes
+// with the assets they use. Fingerprinted files of earlier builds that are no longer assets are removed.
+// prefix is the path of destination relative to the public directory (e.g. "static")
+pub fn copy_fingerprinted<P: AsRef<Path>>(source: P, destination: P, prefix: &str) -> io::Result<AssetMap> {
+    let (source, destination) = (source.as_ref(), destination.as_ref());
+    if !source.is_dir() {
+        return Err(io::Error::new(io::ErrorKind::NotFound, "Source is not a directory"));
+    }
+    let mut files = Vec::new();
+    collect_files(source, prefix, &mut files)?;
+    let target = |relative: &str| destination.join(relative.strip_prefix(prefix).unwrap_or(relative).trim_start_matches('/'));
+    let public_dir = destination.ancestors().nth(prefix.split('/').filter(|part| !part.is_empty()).count()).unwrap_or(destination);
+
+    let mut assets = AssetMap::new();
+    let (stylesheets, others): (Vec<_>, Vec<_>) = files.into_iter().partition(|(_, relative)| relative.ends_with(".css"));
+    for (path, relative) in others {
+        let destination = target(&relative);
+        fs::create_dir_all(destination.parent().unwrap_or(public_dir))?;
+        if relative.ends_with(".html") {
+            fs::copy(&path, destination)?;
+        } else {
+            let content = fs::read(&path)?;
+            write_fingerprinted(&destination, &relative, &content, &mut assets)?;
+        }
+    }
+
+    // A stylesheet importing another one waits for it, unless they import each other
+    let mut pending: Vec<(String, String)> = Vec::new();
+    for (path, relative) in stylesheets {
+        pending.push((fs::read_to_string(&path)?, relative));
+    }
+    while !pending.is_empty() {
+        let waits_for_another = |(css, relative): &(String, String)| {
+            let mut references = Vec::new();
+            map_css_urls(css, |reference| {
+                references.extend(resolve_reference(parent_dir(relative), reference));
+                None
+            });
+            references.iter().any(|reference| reference != relative && pending.iter().any(|(_, other)| other == reference))
+        };
+        let next = pending.iter().position(|stylesheet| !waits_for_another(stylesheet)).unwrap_or(0);
+        let (css, relative) = pending.remove(next);
+        let css = rewrite_css_references(&css, parent_dir(&relative), public_dir, &assets, &mut Vec::new());
+        let destination = target(&relative);
+        fs::create_dir_all(destination.parent().unwrap_or(public_dir))?;
+        write_fingerprinted(&destination, &relative, css.as_bytes(), &mut assets)?;
+    }
+
+    remove_stale_fingerprints(destination, &assets, prefix)?;
+    Ok(assets)
+}
+
+// Every file under dir, with its path relative to the public directory, prefix being the one of dir
+fn collect_files(dir: &Path, prefix: &str, files: &mut Vec<(PathBuf, String)>) -> io::Result<()> {
+    let mut entries: Vec<_> = fs::read_dir(dir)?.collect::<io::Result<_>>()?;
+    entries.sort_by_key(|entry| entry.file_name());
+    for entry in entries {
+        let path = entry.path();
+        let relative = format!("{}/{}", prefix, entry.file_name().to_string_lossy());
+        if path.is_dir() {
+            collect_files(&path, &relative, files)?;
+        } else {
+            files.push((path, relative));
+        }
+    }
+    Ok(())
+}
+
+// Write content next to destination under its fingerprinted name, and record it in assets
+fn write_fingerprinted(destination: &Path, relative: &str, content: &[u8], assets: &mut AssetMap) -> io::Result<()> {
+    let file_name = destination.file_name().unwrap_or_default().to_string_lossy();
+    let new_name = fingerprinted_name(&file_name, content_hash(content));
+    fs::write(destination.with_file_name(&new_name), content)?;
+    assets.insert(relative.to_string(), format!("{}/{}", parent_dir(relative), new_name));
+    Ok(())
+}
+
+// Directory part of a relative path: "static/css" for "static/css/style.css"
+fn parent_dir(relative: &str) -> &str {
+    relative.rfind('/').map(|slash| &relative[..slash]).unwrap_or("")
+}
+
+// Whether a file name carries a hash from fingerprinted_name: "style.1a2b3c4d.css" or "LICENSE.1a2b3c4d"
+fn is_fingerprinted(file_name: &str) -> bool {
+    let is_hash = |part: &str| part.len() == 8 && part.bytes().all(|b| b.is_ascii_digit() || (b'a'..=b'f').contains(&b));
+    let parts: Vec<&str> = file_name.split('.').collect();
+    match parts.len() {
+        0 | 1 => false,
+        2 => !parts[0].is_empty() && is_hash(parts[1]),
+        n => is_hash(parts[n - 2]) || is_hash(parts[n - 1]),
+    }
+}
+
+// Remove fingerprinted files under destination that the current assets no longer name, left by
+// earlier builds with other contents
+fn remove_stale_fingerprints(destination: &Path, assets: &AssetMap, prefix: &str) -> io::Result<()> {
+    let mut files = Vec::new();
+    collect_files(destination, prefix, &mut files)?;
+    let current: HashSet<&str> = assets.values().map(String::as_str).collect();
+    for (path, relative) in files {
+        let file_name = relative.rsplit('/').next().unwrap_or_default();
+        if is_fingerprinted(file_name) && !current.contains(relative.as_str()) {
+            fs::remove_file(path)?;
+        }
+    }
+    Ok(())
+}
+
+// Rewrite a single reference if it points at a fingerprinted asset. References into the
+// asset directories that are neither fingerprinted nor present on disk are reported as missing
+fn rewrite_reference(reference: &str, page_dir: &str, public_dir: &Path, assets: &AssetMap, missing: &mut Vec<String>) -> Option<String> {
+    let resolved = resolve_reference(page_dir, reference)?;
+    match assets.get(&resolved) {
+        Some(fingerprinted) => {
+            let new_name = fingerprinted.rsplit('/').next().unwrap_or(fingerprinted);
+            let path_end = reference.find(['?', '#']).unwrap_or(reference.len());
+            let (path, suffix) = reference.split_at(path_end);
+            let name_start = path.rfind('/').map(|i| i + 1).unwrap_or(0);
+            Some(format!("{}{}{}", &path[..name_start], new_name, suffix))
+        }
+        None => {
+            let is_asset = assets.keys().any(|key| key.split('/').next() == resolved.split('/').next());
+            if is_asset && !public_dir.join(&resolved).exists() {
+                missing.push(resolved);
+            }
+            None
+        }
+    }
+}
+
+// Rewrite href="..." and src="..." attribute values in an HTML document
+pub fn rewrite_html_references(html: &str, page_dir: &str, public_dir: &Path, assets: &AssetMap, missing: &mut Vec<String>) -> String {
+    let mut result = String::with_capacity(html.len());
+    let mut rest = html;
+
+    while let Some((start, end)) = next_link_attribute(rest) {
+        let value = &rest[start..end];
+        result.push_str(&rest[..start]);
+        match rewrite_reference(value, page_dir, public_dir, assets, missing) {
+            Some(rewritten) => result.push_str(&rewritten),
+            None => result.push_str(value),
+        }
+        rest = &rest[end..];
+    }
+
+    result.push_str(rest);
+    result
+}
+
+// Rewrite url(...) references in a stylesheet
+pub fn rewrite_css_references(css: &str, css_dir: &str, public_dir: &Path, assets: &AssetMap, missing: &mut Vec<String>) -> String {
+    map_css_urls(css, |reference| rewrite_reference(reference, css_dir, public_dir, assets, missing))
+}
+
+// Replace the reference of every url(...) of css, quoted or not, for which rewrite gives a new one
+fn map_css_urls(css: &str, mut rewrite: impl FnMut(&str) -> Option<String>) -> String {
+    let mut result = String::with_capacity(css.len());
+    let mut rest = css;
+
+    while let Some(start) = rest.find("url(") {
+        let after = start + "url(".len();
+        let end = match rest[after..].find(')') {
+            Some(end) => after + end,
+            None => break,
+        };
+        let raw = &rest[after..end];
+        let trimmed = raw.trim().trim_matches(|c| c == '"' || c == '\'');
+
+        result.push_str(&rest[..after]);
+        match rewrite(trimmed) {
+            Some(rewritten) => result.push_str(&raw.replacen(trimmed, &rewritten, 1)),
+            None => result.push_str(raw),
+        }
+        rest = &rest[end..];
+    }
+
+    result.push_str(rest);
+    result
+}
+
+// Rewrite references to fingerprinted assets in every HTML and CSS file under the public directory.
+// Returns a warning for every reference to an asset that does not exist
+pub fn rewrite_asset_references(public_dir: &Path, assets: &AssetMap) -> io::Result<Vec<String>> {
+    let mut warnings = Vec::new();
+    rewrite_directory(public_dir, public_dir, "", assets, &mut warnings)?;
+    Ok(warnings)
+}
+
+fn rewrite_directory(public_dir: &Path, dir: &Path, relative_dir: &str, assets: &AssetMap, warnings: &mut Vec<String>) -> io::Result<()> {
+    let mut entries: Vec<_> = fs::read_dir(dir)?.filter_map(Result::ok).collect();
+    entries.sort_by_key(|entry| entry.file_name());
+
+    for entry in entries {
+        let path = entry.path();
+        let file_name = entry.file_name().to_string_lossy().into_owned();
+        let relative = if relative_dir.is_empty() { file_name.clone() } else { format!("{}/{}", relative_dir, file_name) };
+
+        if path.is_dir() {
+            rewrite_directory(public_dir, &path, &relative, assets, warnings)?;
+            continue;
+        }
+
+        let is_html = file_name.ends_with(".html");
+        let is_css = file_name.ends_with(".css");
+        if !is_html && !is_css {
+            continue;
+        }
+
+        let content = fs::read_to_string(&path)?;
+        let mut missing = Vec::new();
+        let rewritten = if is_html {
+            rewrite_html_references(&content, relative_dir, public_dir, assets, &mut missing)
+        } else {
+            rewrite_css_references(&content, relative_dir, public_dir, assets, &mut missing)
+        };
+        if rewritten != content {
+            fs::write(&path, rewritten)?;
+        }
+        for asset in missing {
+            warnings.push(format!("Missing asset {} referenced from {}", asset, relative));
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::{write, TempDir};
+
+    #[test]
+    fn names_carry_the_content_hash() {
+        assert_eq!(content_hash(b""), 0x811c9dc5);
+        assert_eq!(content_hash(b"a"), 0xe40c292c);
+        assert_eq!(fingerprinted_name("style.css", 0x1a2b3c4d), "style.1a2b3c4d.css");
+        assert_eq!(fingerprinted_name("app.min.js", 0xff), "app.min.000000ff.js");
+        assert_eq!(fingerprinted_name("LICENSE", 1), "LICENSE.00000001");
+        assert_eq!(fingerprinted_name(".htaccess", 1), ".htaccess.00000001");
+        assert!(is_fingerprinted("style.1a2b3c4d.css") && is_fingerprinted("LICENSE.00000001"));
+        assert!(!is_fingerprinted("style.css") && !is_fingerprinted("jquery.min.js") && !is_fingerprinted("logo.1a2b3c.png"));
+    }
+
+    #[test]
+    fn references_are_rewritten() {
+        let dir = TempDir::new("fingerprint-references");
+        let public = dir.path();
+        let assets: AssetMap = [("static/style.css", "static/style.11111111.css"), ("static/img/bg.png", "static/img/bg.22222222.png")]
+            .into_iter()
+            .map(|(original, fingerprinted)| (original.to_string(), fingerprinted.to_string()))
+            .collect();
+        write(&public.join("static/here.png"), "");
+
+        let mut missing = Vec::new();
+        let html = rewrite_html_references(
+            "<link href=\"../static/style.css?v=1\"><img src='/static/img/bg.png#top'><a href=\"https://example.com/static/style.css\">\
+             <script src=\"//cdn.example.com/static/style.css\"></script><img src=\"../static/here.png\"><img src=\"../static/gone.png\">",
+            "entries",
+            public,
+            &assets,
+            &mut missing,
+        );
+        assert_eq!(
+            html,
+            "<link href=\"../static/style.11111111.css?v=1\"><img src='/static/img/bg.22222222.png#top'><a href=\"https://example.com/static/style.css\">\
+             <script src=\"//cdn.example.com/static/style.css\"></script><img src=\"../static/here.png\"><img src=\"../static/gone.png\">"
+        );
+        assert_eq!(missing, ["static/gone.png"]);
+
+        let css = rewrite_css_references(
+            "a { background: url(img/bg.png) } b { background: url( \"img/bg.png\" ) } i { background: url('//cdn.example.com/bg.png') }",
+            "static",
+            public,
+            &assets,
+            &mut Vec::new(),
+        );
+        assert_eq!(
+            css,
+            "a { background: url(img/bg.22222222.png) } b { background: url( \"img/bg.22222222.png\" ) } i { background: url('//cdn.example.com/bg.png') }"
+        );
+
+        write(&public.join("entries/first/index.html"), "<img src=\"../../static/missing.png\">");
+        let warnings = rewrite_asset_references(public, &assets).unwrap();
+        assert_eq!(warnings, ["Missing asset static/missing.png referenced from entries/first/index.html"]);
+    }
+
+    #[test]
+    fn stylesheets_change_name_with_their_assets() {
+        let dir = TempDir::new("fingerprint-copy");
+        let (source, destination) = (dir.path().join("static"), dir.path().join("public/static"));
+        write(&source.join("bg.png"), "first");
+        write(&source.join("style.css"), "body { background: url(bg.png) }");
+        write(&source.join("all.css"), "@import url(\"style.css\");");
+        write(&source.join("page.html"), "<p>kept</p>");
+
+        let first = copy_fingerprinted(&source, &destination, "static").unwrap();
+        assert!(destination.join("page.html").exists());
+        let style = fs::read_to_string(dir.path().join("public").join(&first["static/style.css"])).unwrap();
+        assert_eq!(style, format!("body {{ background: url({}) }}", first["static/bg.png"].trim_start_matches("static/")));
+        let all = fs::read_to_string(dir.path().join("public").join(&first["static/all.css"])).unwrap();
+        assert!(all.contains(first["static/style.css"].trim_start_matches("static/")), "{}", all);
+
+        // Only the image changes, yet every stylesheet using it gets a new name, and the old files go
+        write(&source.join("bg.png"), "second");
+        let second = copy_fingerprinted(&source, &destination, "static").unwrap();
+        for asset in ["static/bg.png", "static/style.css", "static/all.css"] {
+            assert_ne!(first[asset], second[asset]);
+            assert!(!dir.path().join("public").join(&first[asset]).exists(), "{}", first[asset]);
+            assert!(dir.path().join("public").join(&second[asset]).exists(), "{}", second[asset]);
+        }
+        assert_eq!(fs::read_dir(&destination).unwrap().count(), 4);
+    }
+}
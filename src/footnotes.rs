@@ -0,0 +1,162 @@
+use std::collections::HashMap;
+
+use crate::html::{closing_tag_end, content_chunk_end, opening_element};
+
+// Content with its footnotes linked, see footnotes
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Footnotes {
+    pub html: String,
+    // Labels of [^label] references without a definition, once each, in the order they appear
+    pub undefined: Vec<String>,
+}
+
+// Turn the footnotes of content into links. A paragraph starting with [^label]: defines the note
+// label, and a [^label] in the text refers to it: the reference becomes a superscript number linking
+// to the note, and the definitions move to a list at the end, each linking back to its first
+// reference. Notes are numbered in the order of their first reference, unreferenced ones after the
+// rest, which gives every page the same fn-1, fnref-1, ... ids on every build. A second reference to
+// a note gets fnref-1-2 and so on. Comments and the text of <pre>, <code> and the other verbatim
+// elements are left alone, as are references to labels no paragraph defines
+pub fn footnotes(html: &str) -> Footnotes {
+    let (body, definitions) = take_definitions(html);
+    let mut numbers: HashMap<&str, usize> = HashMap::new();
+    // Label of each number, from 1, and how many times it was referenced
+    let mut notes: Vec<(&str, usize)> = Vec::new();
+    let mut undefined: Vec<String> = Vec::new();
+    let mut out = String::with_capacity(body.len());
+
+    let mut rest = body.as_str();
+    while !rest.is_empty() {
+        let end = content_chunk_end(rest);
+        let (chunk, next) = rest.split_at(end);
+        if chunk.starts_with('<') {
+            out.push_str(chunk);
+        } else {
+            let mut text = chunk;
+            while let Some(start) = text.find("[^") {
+                let Some((label, len)) = reference(&text[start..]) else {
+                    out.push_str(&text[..start + 2]);
+                    text = &text[start + 2..];
+                    continue;
+                };
+                out.push_str(&text[..start]);
+                match definitions.iter().find(|(defined, _)| *defined == label) {
+                    Some((label, _)) => {
+                        let number = *numbers.entry(label).or_insert_with(|| {
+                            notes.push((label, 0));
+                            notes.len()
+                        });
+                        notes[number - 1].1 += 1;
+                        let id = match notes[number - 1].1 {
+                            1 => format!("fnref-{}", number),
+                            count => format!("fnref-{}-{}", number, count),
+                        };
+                        out.push_str(&format!("<sup class=\"footnote-ref\"><a href=\"#fn-{}\" id=\"{}\">{}</a></sup>", number, id, number));
+                    }
+                    None => {
+                        if !undefined.iter().any(|seen| seen == label) {
+                            undefined.push(label.to_string());
+                        }
+                        out.push_str(&text[start..start + len]);
+                    }
+                }
+                text = &text[start + len..];
+            }
+            out.push_str(text);
+        }
+        rest = next;
+    }
+
+    if !definitions.is_empty() {
+        out.push_str("<section class=\"footnotes\"><ol>");
+        for (i, (label, _)) in notes.iter().enumerate() {
+            let text = definitions.iter().find(|(defined, _)| defined == label).map(|(_, text)| text.as_str()).unwrap_or_default();
+            out.push_str(&format!("<li id=\"fn-{0}\">{1} <a href=\"#fnref-{0}\" class=\"footnote-backref\">&#8617;</a></li>", i + 1, text));
+        }
+        let unreferenced = definitions.iter().filter(|(label, _)| !numbers.contains_key(label.as_str()));
+        for (i, (_, text)) in unreferenced.enumerate() {
+            out.push_str(&format!("<li id=\"fn-{}\">{}</li>", notes.len() + i + 1, text));
+        }
+        out.push_str("</ol></section>");
+    }
+    Footnotes { html: out, undefined }
+}
+
+// Content without its definition paragraphs, and the label and text of each definition. Only the
+// first definition of a label counts
+fn take_definitions(html: &str) -> (String, Vec<(String, String)>) {
+    let mut body = String::with_capacity(html.len());
+    let mut definitions: Vec<(String, String)> = Vec::new();
+    let mut rest = html;
+    while !rest.is_empty() {
+        let mut end = content_chunk_end(rest);
+        if opening_element(rest, &["p"]).is_some() {
+            let paragraph_end = closing_tag_end(rest, "p");
+            let inner_end = rest[..paragraph_end].to_ascii_lowercase().rfind("</p").unwrap_or(paragraph_end).max(end);
+            if let Some((label, text)) = definition(&rest[end..inner_end]) {
+                if !definitions.iter().any(|(defined, _)| defined == label) {
+                    definitions.push((label.to_string(), text.to_string()));
+                }
+                end = paragraph_end;
+                rest = &rest[end..];
+                continue;
+            }
+        }
+        body.push_str(&rest[..end]);
+        rest = &rest[end..];
+    }
+    (body, definitions)
+}
+
+// Label and text of a paragraph defining a note: [^label]: text
+fn definition(paragraph: &str) -> Option<(&str, &str)> {
+    let (label, len) = reference(paragraph.trim_start())?;
+    let text = paragraph.trim_start()[len..].strip_prefix(':')?;
+    Some((label, text.trim()))
+}
+
+// Label and length of the [^label] starting text. Labels are one word, without brackets
+fn reference(text: &str) -> Option<(&str, usize)> {
+    let rest = text.strip_prefix("[^")?;
+    let end = rest.find(']')?;
+    let label = &rest[..end];
+    let valid = !label.is_empty() && !label.contains(|c: char| c.is_whitespace() || matches!(c, '[' | '^' | '<' | '>'));
+    valid.then_some((label, end + 3))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::generator::txt_to_html;
+
+    #[test]
+    fn references_link_to_numbered_notes() {
+        let notes = footnotes(
+            "<p>Rust[^rust] and Go[^go], Rust again[^rust], Zig[^zig].</p>\
+             <pre><code>let a = b[^rust];</code></pre>\
+             <p>[^go]: A language by Google.</p>\n<p>[^spare]: Not referenced.</p><p>[^rust]: <em>Fast</em>.</p>",
+        );
+        assert_eq!(
+            notes.html,
+            "<p>Rust<sup class=\"footnote-ref\"><a href=\"#fn-1\" id=\"fnref-1\">1</a></sup> and \
+             Go<sup class=\"footnote-ref\"><a href=\"#fn-2\" id=\"fnref-2\">2</a></sup>, \
+             Rust again<sup class=\"footnote-ref\"><a href=\"#fn-1\" id=\"fnref-1-2\">1</a></sup>, Zig[^zig].</p>\
+             <pre><code>let a = b[^rust];</code></pre>\n\
+             <section class=\"footnotes\"><ol>\
+             <li id=\"fn-1\"><em>Fast</em>. <a href=\"#fnref-1\" class=\"footnote-backref\">&#8617;</a></li>\
+             <li id=\"fn-2\">A language by Google. <a href=\"#fnref-2\" class=\"footnote-backref\">&#8617;</a></li>\
+             <li id=\"fn-3\">Not referenced.</li></ol></section>"
+        );
+        assert_eq!(notes.undefined, ["zig"]);
+
+        // Text files get theirs from a line each
+        let text = String::from_utf8(txt_to_html(b"See [^1].\n[^1]: At https://example.com\n".to_vec())).unwrap();
+        assert_eq!(
+            footnotes(&text).html,
+            "<p>See <sup class=\"footnote-ref\"><a href=\"#fn-1\" id=\"fnref-1\">1</a></sup>.</p>\
+             <section class=\"footnotes\"><ol><li id=\"fn-1\">At <a href=\"https://example.com\">https://example.com</a> \
+             <a href=\"#fnref-1\" class=\"footnote-backref\">&#8617;</a></li></ol></section>"
+        );
+        assert_eq!(footnotes("<p>No notes, [^ not one]</p>"), Footnotes { html: "<p>No notes, [^ not one]</p>".to_string(), undefined: Vec::new() });
+    }
+}
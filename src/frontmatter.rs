@@ -0,0 +1,175 @@
+use std::ffi::OsStr;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use crate::generator::{default_content_file, read_with_limit, txt_to_html};
+use crate::markdown::markdown_to_html;
+use crate::toml::{self, invalid_data, Table, Value};
+
+// Line opening and closing a front matter block at the top of a content file
+pub const DELIMITER: &str = "---";
+
+// Split a front matter block off the top of a content file. The block may be written in TOML
+// (title = "Hello") or in simple YAML (title: Hello). Content without one is returned unchanged
+pub fn split_front_matter(content: &str) -> io::Result<(Option<Table>, &str)> {
+    let text = content.strip_prefix('\u{feff}').unwrap_or(content);
+    let first_line_end = text.find('\n').map(|i| i + 1).unwrap_or(text.len());
+    if text[..first_line_end].trim_end() != DELIMITER {
+        return Ok((None, content));
+    }
+
+    let mut offset = first_line_end;
+    for line in text[first_line_end..].split_inclusive('\n') {
+        if line.trim_end() == DELIMITER {
+            let block = &text[first_line_end..offset];
+            let body = &text[offset + line.len()..];
+            return Ok((Some(parse_block(block)?), body));
+        }
+        offset += line.len();
+    }
+    Err(invalid_data("front matter: missing closing ---".to_string()))
+}
+
+fn parse_block(block: &str) -> io::Result<Table> {
+    match toml::parse(block) {
+        Ok(table) => Ok(table),
+        Err(toml_error) => parse_yaml(block).map_err(|yaml_error| {
+            invalid_data(format!("front matter is neither TOML ({}) nor YAML ({})", toml_error, yaml_error))
+        }),
+    }
+}
+
+// The part of YAML front matter is written in: "key: value" lines with strings, booleans,
+// integers and lists, either inline ([a, b]) or as "- item" lines below the key
+fn parse_yaml(block: &str) -> Result<Table, String> {
+    let mut table = Table::new();
+    // Key whose value is a block list still being read
+    let mut list_key: Option<String> = None;
+
+    for (number, line) in block.lines().enumerate() {
+        let trimmed = line.trim();
+        if trimmed.is_empty() || trimmed.starts_with('#') {
+            continue;
+        }
+
+        if let Some(item) = trimmed.strip_prefix("- ").or(if trimmed == "-" { Some("") } else { None }) {
+            let key = list_key.as_ref().ok_or_else(|| format!("line {}: list item without a key", number + 1))?;
+            if let Some(Value::Array(items)) = table.get_mut(key) {
+                items.push(yaml_scalar(item.trim()));
+            }
+            continue;
+        }
+
+        let (key, value) = trimmed.split_once(':').ok_or_else(|| format!("line {}: expected key: value", number + 1))?;
+        let key = key.trim().trim_matches(|c| c == '"' || c == '\'').to_string();
+        let value = value.trim();
+        if value.is_empty() {
+            table.insert(key.clone(), Value::Array(Vec::new()));
+            list_key = Some(key);
+        } else {
+            table.insert(key, yaml_value(value));
+            list_key = None;
+        }
+    }
+    Ok(table)
+}
+
+fn yaml_value(text: &str) -> Value {
+    match text.strip_prefix('[').and_then(|rest| rest.strip_suffix(']')) {
+        Some(items) => Value::Array(
+            items.split(',').map(str::trim).filter(|item| !item.is_empty()).map(yaml_scalar).collect(),
+        ),
+        None => yaml_scalar(text),
+    }
+}
+
+fn yaml_scalar(text: &str) -> Value {
+    let quoted = text.len() >= 2
+        && ((text.starts_with('"') && text.ends_with('"')) || (text.starts_with('\'') && text.ends_with('\'')));
+    if quoted {
+        return Value::String(text[1..text.len() - 1].to_string());
+    }
+    match text {
+        "true" => Value::Boolean(true),
+        "false" => Value::Boolean(false),
+        _ => match text.parse() {
+            Ok(i) => Value::Integer(i),
+            Err(_) => Value::String(text.to_string()),
+        },
+    }
+}
+
+// Read an entry's content file as HTML, without its front matter
+pub fn read_entry_content(entry_path: &Path) -> io::Result<String> {
+    let path = default_content_file(entry_path);
+    let bytes = fs::read(&path)?;
+    entry_body(&path, &bytes)
+}
+
+// Same as read_entry_content, failing with FileTooLarge for a content file over max_bytes
+pub fn read_entry_content_with_limit(entry_path: &Path, max_bytes: usize) -> io::Result<String> {
+    read_content_file_with_limit(&default_content_file(entry_path), max_bytes)
+}
+
+// Same as read_entry_content_with_limit for a content file given by its path, such as a translation
+pub fn read_content_file_with_limit(path: &Path, max_bytes: usize) -> io::Result<String> {
+    let bytes = read_with_limit(path, max_bytes)?;
+    entry_body(path, &bytes)
+}
+
+// Content after the front matter, as HTML: a .md file goes through markdown_to_html and a .txt file
+// through txt_to_html
+fn entry_body(path: &Path, bytes: &[u8]) -> io::Result<String> {
+    let content = String::from_utf8_lossy(bytes);
+    let (_, body) = split_front_matter(&content)?;
+    Ok(match path.extension().and_then(OsStr::to_str) {
+        Some("md") => markdown_to_html(body),
+        Some("txt") => String::from_utf8_lossy(&txt_to_html(body.as_bytes().to_vec())).into_owned(),
+        _ => body.to_string(),
+    })
+}
+
+// Front matter of an entry's content file, if it has any
+pub fn read_front_matter(entry_path: &Path) -> io::Result<Option<Table>> {
+    let path = default_content_file(entry_path);
+    if !path.exists() {
+        return Ok(None);
+    }
+    let bytes = fs::read(&path)?;
+    let (table, _) = split_front_matter(&String::from_utf8_lossy(&bytes))
+        .map_err(|e| invalid_data(format!("{}: {}", path.display(), e)))?;
+    Ok(table)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn yaml_and_toml_front_matter() {
+        let yaml = "---\ntitle: \"Hello: world\"\ndate: 2024-03-15\ntags: [rust, web]\ndescription: A post\n---\n<p>Body</p>\n";
+        let (table, body) = split_front_matter(yaml).unwrap();
+        let table = table.unwrap();
+        assert_eq!(body, "<p>Body</p>\n");
+        assert_eq!(table["title"], Value::String("Hello: world".to_string()));
+        assert_eq!(table["date"].as_str(), Some("2024-03-15"));
+        assert_eq!(table["tags"], Value::Array(vec![Value::String("rust".to_string()), Value::String("web".to_string())]));
+
+        let block_list = "---\ntags:\n  - rust\n  - cli\n---\nBody";
+        let (table, _) = split_front_matter(block_list).unwrap();
+        assert_eq!(table.unwrap()["tags"].as_array().unwrap().len(), 2);
+
+        let toml = "---\ntitle = \"Hello\"\ntags = [\"rust\"]\n---\nBody";
+        let (table, body) = split_front_matter(toml).unwrap();
+        assert_eq!(table.unwrap()["title"].as_str(), Some("Hello"));
+        assert_eq!(body, "Body");
+    }
+
+    #[test]
+    fn content_without_front_matter_is_unchanged() {
+        let content = "<p>No front matter</p>\n---\n";
+        assert_eq!(split_front_matter(content).unwrap(), (None, content));
+        assert!(split_front_matter("---\ntitle: x\n<p>never closed</p>").is_err());
+    }
+}
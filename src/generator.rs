@@ -1,32 +1,130 @@
 use std::collections::HashMap;
 use std::collections::HashSet;
+use std::ffi::OsStr;
+use std::fmt;
 use std::fs;
-use std::fs::File;
-use std::io::{self, Read, Write};
+use std::io::{self, Read};
 use std::path::{Path, PathBuf};
-use std::io::prelude::*;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::thread;
+
+use crate::config::{file_date, PageKind, SiteConfig};
+use crate::csv::expand_csv_tables;
+use crate::date::Date;
+use crate::error::IroniteError;
+use crate::html::{escape_html, external_link_attributes, lazy_images, linkify_urls, root_relative_links, smart_punctuation, url_host};
+use crate::images::entry_image_size;
+use crate::markdown::markdown_to_html;
+use crate::inline::embed_assets_in_html;
+use crate::footnotes::footnotes;
+use crate::frontmatter::{read_content_file_with_limit, read_front_matter};
+use crate::git::UpdatedDates;
+use crate::highlight::highlight_code_blocks;
+use crate::meta::{read_link, table_tags, EntryMeta, LINK_FILE, META_FILE};
+use crate::permalink::{entry_permalinks, root_prefix, slugify, PermalinkMap};
+use crate::redirect::redirect_page;
+use crate::retry::{current_io_retry, retry_io, with_io_retry};
+use crate::series::{collect_series, series_nav, Series};
+use crate::shortcode::{expand_shortcodes, ShortcodeContext, SHORTCODES};
+use crate::site::Site;
+use crate::sitemap::pattern_matches;
+use crate::sort::{index_order, read_order, IndexSort, ORDER_FILE};
+use crate::stats::SiteStats;
+use crate::toml::{self, invalid_data, Table};
 
 // Read file content into a String
 pub fn read_file_to_string<P: AsRef<Path>>(path: P) -> io::Result<String> {
     fs::read_to_string(path)
 }
 
-// Replace placeholders in HTML content
+// Read file content into a String, failing with FileTooLarge instead of reading more than max_bytes
+pub fn read_file_to_string_with_limit<P: AsRef<Path>>(path: P, max_bytes: usize) -> io::Result<String> {
+    let bytes = read_with_limit(path.as_ref(), max_bytes)?;
+    String::from_utf8(bytes).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+}
+
+// Read at most max_bytes of a file. One byte more is read to tell a file of exactly max_bytes
+// from a larger one, so endless files such as /dev/urandom stop there too
+pub(crate) fn read_with_limit(path: &Path, max_bytes: usize) -> io::Result<Vec<u8>> {
+    let mut bytes = Vec::new();
+    fs::File::open(path)?.take(max_bytes as u64 + 1).read_to_end(&mut bytes)?;
+    if bytes.len() > max_bytes {
+        return Err(io::Error::new(
+            io::ErrorKind::FileTooLarge,
+            format!("{} is larger than {} bytes", path.display(), max_bytes),
+        ));
+    }
+    Ok(bytes)
+}
+
+// Replace placeholders in HTML content in a single pass: at each position the longest matching name
+// wins, so $TAGS is not taken for $TAG, and replaced values are never scanned again. The result does
+// not depend on the order of the map
 pub fn replace_placeholders(html_content: &str, placeholders: &HashMap<String, String>) -> String {
-    let mut result = html_content.to_string();
-    for (placeholder, replacement) in placeholders {
-        result = result.replace(placeholder, replacement);
+    let mut names: Vec<&String> = placeholders.keys().filter(|name| !name.is_empty()).collect();
+    names.sort_by(|a, b| b.len().cmp(&a.len()).then_with(|| a.cmp(b)));
+    let mut first_chars: Vec<char> = names.iter().filter_map(|name| name.chars().next()).collect();
+    first_chars.sort();
+    first_chars.dedup();
+
+    let mut result = String::with_capacity(html_content.len());
+    let mut rest = html_content;
+    while let Some(start) = rest.find(first_chars.as_slice()) {
+        result.push_str(&rest[..start]);
+        rest = &rest[start..];
+        match names.iter().find(|name| rest.starts_with(name.as_str())) {
+            Some(name) => {
+                result.push_str(&placeholders[*name]);
+                rest = &rest[name.len()..];
+            }
+            None => {
+                let c = rest.chars().next().unwrap_or_default();
+                result.push(c);
+                rest = &rest[c.len_utf8()..];
+            }
+        }
     }
+    result.push_str(rest);
     result
 }
 
+// Placeholders for a page: the ones from the config for its kind, overridden by the page's own.
+// $TAG_DESCRIPTION, $DESCRIPTION, $FILES_LIST and $SERIES_NAV, only set on some pages, are empty unless the config defines them
+pub fn page_placeholders(config: &SiteConfig, kind: PageKind, own: &[(String, String)]) -> HashMap<String, String> {
+    let mut placeholders = HashMap::from([
+        ("$TAG_DESCRIPTION".to_string(), String::new()),
+        ("$DESCRIPTION".to_string(), String::new()),
+        ("$FILES_LIST".to_string(), String::new()),
+        ("$SERIES_NAV".to_string(), String::new()),
+        ("$ENTRY_INDEX".to_string(), String::new()),
+        ("$ENTRY_TOTAL".to_string(), String::new()),
+    ]);
+    placeholders.extend(config.page_placeholders(kind));
+    placeholders.extend(own.iter().cloned());
+    placeholders
+}
+
 // Write HTML content to a file
 pub fn write_html_file<P: AsRef<Path>>(path: P, content: &str) -> io::Result<()> {
-    fs::write(path, content.as_bytes())
+    write_bytes_file(path, content.as_bytes())
+}
+
+// Write bytes to a file atomically: they go to a temporary file next to it, renamed over the file
+// once complete, so that a reader or an interrupted build never sees a half-written page
+pub fn write_bytes_file<P: AsRef<Path>>(path: P, content: &[u8]) -> io::Result<()> {
+    let path = path.as_ref();
+    let file_name = path.file_name().ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, format!("{} is not a file path", path.display())))?;
+    let temp_path = path.with_file_name(format!(".{}.{}.tmp", file_name.to_string_lossy(), std::process::id()));
+    if let Err(e) = retry_io(path, || fs::write(&temp_path, content).and_then(|()| fs::rename(&temp_path, path))) {
+        let _ = fs::remove_file(&temp_path);
+        return Err(e);
+    }
+    Ok(())
 }
 
 // Create directories if they don't exist
-fn create_directories<P: AsRef<Path>>(path: P) -> io::Result<()> {
+pub(crate) fn create_directories<P: AsRef<Path>>(path: P) -> io::Result<()> {
     if !path.as_ref().exists() {
         fs::create_dir_all(path)
     } else {
@@ -34,66 +132,546 @@ fn create_directories<P: AsRef<Path>>(path: P) -> io::Result<()> {
     }
 }
 
-// Convert text to HTML
+// Convert text to HTML: every non-blank line becomes a paragraph, its text escaped and its URLs linked.
+// Blank lines, trailing newlines and \r of Windows line endings add nothing, so empty input gives empty output
 pub fn txt_to_html(content: Vec<u8>) -> Vec<u8> {
     let text = String::from_utf8_lossy(&content);
-    let html_content = format!("<p>{}</p>", text.replace("\n", "</p><p>"));
+    let html_content: String = text
+        .lines()
+        .map(str::trim_end)
+        .filter(|line| !line.is_empty())
+        .map(|line| format!("<p>{}</p>", linkify_urls(&escape_html(line))))
+        .collect();
     html_content.into_bytes()
 }
 
+// Content without the more marker, which only matters to teasers
+pub fn strip_more_marker(content: &str, config: &SiteConfig) -> String {
+    if config.more_marker.is_empty() {
+        content.to_string()
+    } else {
+        content.replacen(&config.more_marker, "", 1)
+    }
+}
+
+// Descriptions of tag pages, in the project root: tagdesc/<tag>.html, .md or .txt, named like
+// the tag's directory in public/ (tagdesc/machine-learning.html for "machine learning")
+pub const TAG_DESCRIPTIONS_DIR: &str = "tagdesc";
+
+// Description of a tag as HTML: tagdesc/<tag>.html, else tagdesc/<tag>.md through markdown_to_html,
+// else tagdesc/<tag>.txt through txt_to_html. Empty when there is none
+pub fn tag_description(config: &SiteConfig, tag: &str) -> io::Result<String> {
+    let dir = config.path(TAG_DESCRIPTIONS_DIR);
+    let slug = tag_slug(tag);
+    let html_path = dir.join(format!("{}.html", slug));
+    let md_path = dir.join(format!("{}.md", slug));
+    let txt_path = dir.join(format!("{}.txt", slug));
+    let description = if html_path.is_file() {
+        fs::read_to_string(html_path)?.trim().to_string()
+    } else if md_path.is_file() {
+        markdown_to_html(&String::from_utf8_lossy(&fs::read(md_path)?)).trim().to_string()
+    } else if txt_path.is_file() {
+        String::from_utf8_lossy(&txt_to_html(fs::read(txt_path)?)).trim().to_string()
+    } else {
+        String::new()
+    };
+    let description = if config.footnotes { footnotes(&description).html } else { description };
+    Ok(if config.smart_punctuation { smart_punctuation(&description) } else { description })
+}
+
+// Text of an HTML fragment for a meta description: tags removed, whitespace collapsed and quotes escaped
+pub fn meta_description(html: &str) -> String {
+    let mut text = String::new();
+    let mut in_tag = false;
+    for c in html.chars() {
+        match c {
+            '<' => in_tag = true,
+            '>' if in_tag => {
+                in_tag = false;
+                text.push(' ');
+            }
+            c if !in_tag => text.push(c),
+            _ => {}
+        }
+    }
+    text.split_whitespace().collect::<Vec<_>>().join(" ").replace('"', "&quot;")
+}
+
+// Default name of the pseudo-tag of the entries without any tag (untagged_tag). They are listed at
+// public/untagged/index.html, rendered like a tag page
+pub const UNTAGGED: &str = "untagged";
+
 // Get unique tags from a tags file
-pub fn get_tags(tags_file_path: &str) -> HashSet<String> {
+pub fn get_tags(tags_file_path: &Path) -> HashSet<String> {
     let content = match fs::read_to_string(tags_file_path) {
         Ok(content) => content,
         Err(e) => {
-            eprintln!("Error reading tags file {}: {}", tags_file_path, e);
+            eprintln!("Error reading tags file {}: {}", tags_file_path.display(), e);
             return HashSet::new();
         }
     };
 
-    let mut unique_tags = HashSet::new();
-    for tag in content.split_whitespace() {
-        let trimmed = tag.trim();
-        if !trimmed.is_empty() {
-            unique_tags.insert(trimmed.to_string());
+    parse_tags(&content).into_iter().collect()
+}
+
+// Split the content of a tags file into tags. Tags are separated by whitespace; a tag holding spaces
+// is written in double or single quotes, as in `"machine learning" rust`. Whitespace inside quotes
+// is collapsed to single spaces, and an unclosed quote runs to the end of the file
+pub fn parse_tags(content: &str) -> Vec<String> {
+    let mut tags = Vec::new();
+    let mut chars = content.chars().peekable();
+    loop {
+        while chars.next_if(|c| c.is_whitespace()).is_some() {}
+        let Some(first) = chars.next() else { break };
+        let mut tag = String::new();
+        if first == '"' || first == '\'' {
+            for c in chars.by_ref() {
+                if c == first {
+                    break;
+                }
+                tag.push(c);
+            }
+            tag = tag.split_whitespace().collect::<Vec<_>>().join(" ");
+        } else {
+            tag.push(first);
+            while let Some(c) = chars.next_if(|c| !c.is_whitespace()) {
+                tag.push(c);
+            }
+        }
+        if !tag.is_empty() {
+            tags.push(tag);
         }
     }
+    tags
+}
 
-    unique_tags
+// Directory of a tag page, relative to the public directory. Single-word tags are used as they are;
+// a multi-word tag is slugified, "machine learning" living at machine-learning/. Each segment of a
+// nested tag is a directory: "programming/machine learning" lives at programming/machine-learning/
+pub fn tag_slug(tag: &str) -> String {
+    tag.split('/')
+        .map(|segment| if segment.contains(char::is_whitespace) { slugify(segment) } else { segment.to_string() })
+        .collect::<Vec<_>>()
+        .join("/")
 }
 
-// Filter entries by tags
-pub fn filter_entries_by_tag() -> HashMap<String, Vec<PathBuf>> {
-    let entries_dir = Path::new("entries");
-    let mut tags_map = HashMap::new();
+// Ancestors of a nested tag, outermost first: programming/rust/async has programming and programming/rust
+pub fn tag_ancestors(tag: &str) -> Vec<&str> {
+    tag.match_indices('/').map(|(i, _)| &tag[..i]).collect()
+}
+
+// Tags directly nested under tag, sorted
+fn child_tags<'a>(tag: &str, tags: &'a [String]) -> Vec<&'a String> {
+    tags.iter()
+        .filter(|other| other.strip_prefix(tag).and_then(|rest| rest.strip_prefix('/')).is_some_and(|rest| !rest.contains('/')))
+        .collect()
+}
+
+// Name of an entry directory, or None when it is not valid UTF-8
+pub fn entry_name(entry_path: &Path) -> Option<&str> {
+    entry_path.file_name().and_then(OsStr::to_str)
+}
+
+// Entry directories under entries_dir, sorted by path. Entries whose name is not valid UTF-8 cannot
+// have a page or a URL and are left out; see skipped_entries
+pub fn entry_dirs(entries_dir: &Path) -> io::Result<Vec<PathBuf>> {
+    let mut entry_paths = Vec::new();
+    for entry in fs::read_dir(entries_dir)? {
+        let path = entry?.path();
+        if path.is_dir() && entry_name(&path).is_some() {
+            entry_paths.push(path);
+        }
+    }
+    entry_paths.sort();
+    Ok(entry_paths)
+}
+
+// Entry directories left out by entry_dirs, as lossy paths for warnings
+pub fn skipped_entries(entries_dir: &Path) -> io::Result<Vec<String>> {
+    let mut skipped = Vec::new();
+    for entry in fs::read_dir(entries_dir)? {
+        let path = entry?.path();
+        if path.is_dir() && entry_name(&path).is_none() {
+            skipped.push(path.to_string_lossy().into_owned());
+        }
+    }
+    skipped.sort();
+    Ok(skipped)
+}
+
+// Entry directories at any depth under entries_dir, sorted by path: every directory holding a
+// content.html is an entry, and the others are searched for entries. Directories whose name is not
+// valid UTF-8 are left out, with what they hold; see skipped_entries_recursive
+pub fn entry_dirs_recursive(entries_dir: &Path) -> io::Result<Vec<PathBuf>> {
+    let mut entry_paths = Vec::new();
+    walk_entry_dirs(entries_dir, &mut entry_paths, &mut Vec::new())?;
+    entry_paths.sort();
+    Ok(entry_paths)
+}
+
+// Directories left out by entry_dirs_recursive, as lossy paths for warnings
+pub fn skipped_entries_recursive(entries_dir: &Path) -> io::Result<Vec<String>> {
+    let mut skipped = Vec::new();
+    walk_entry_dirs(entries_dir, &mut Vec::new(), &mut skipped)?;
+    skipped.sort();
+    Ok(skipped)
+}
+
+// Whether a directory holds an entry: a content file, or a link.txt for entries published elsewhere
+fn has_content(dir: &Path) -> bool {
+    has_content_file(dir) || dir.join(LINK_FILE).is_file()
+}
+
+// Extensions of content files, the first one winning when an entry has several: HTML is used as
+// written, Markdown goes through markdown_to_html and plain text through txt_to_html
+pub const CONTENT_EXTENSIONS: &[&str] = &["html", "md", "txt"];
 
+// Whether a directory holds a content file (content.html, content.md or content.txt), or a translation
+// of one such as content.es.html
+pub fn has_content_file(dir: &Path) -> bool {
+    fs::read_dir(dir).is_ok_and(|entries| {
+        entries.flatten().any(|entry| {
+            let name = entry.file_name();
+            let name = name.to_string_lossy();
+            name.starts_with("content.") && CONTENT_EXTENSIONS.iter().any(|extension| name.ends_with(&format!(".{}", extension))) && entry.path().is_file()
+        })
+    })
+}
+
+// File holding an entry's content: content.html, .md or .txt, or when building one of languages its
+// content.<language>.html, .md or .txt, falling back to the default language's and then to the
+// entry's content file. content.html when there is none
+pub fn content_file(entry_path: &Path, config: &SiteConfig) -> PathBuf {
+    config
+        .content_language
+        .as_deref()
+        .into_iter()
+        .chain(config.fallback_language())
+        .flat_map(|language| CONTENT_EXTENSIONS.iter().map(move |extension| entry_path.join(format!("content.{}.{}", language, extension))))
+        .find(|path| fs::symlink_metadata(path).is_ok())
+        .unwrap_or_else(|| default_content_file(entry_path))
+}
+
+// Content file of an entry in the default language: content.html, else content.md, else content.txt.
+// content.html when there is none
+pub fn default_content_file(entry_path: &Path) -> PathBuf {
+    CONTENT_EXTENSIONS
+        .iter()
+        .map(|extension| entry_path.join(format!("content.{}", extension)))
+        .find(|path| fs::symlink_metadata(path).is_ok())
+        .unwrap_or_else(|| entry_path.join("content.html"))
+}
+
+// Depth-first walk below dir. Entries are not searched for nested entries
+fn walk_entry_dirs(dir: &Path, entry_paths: &mut Vec<PathBuf>, skipped: &mut Vec<String>) -> io::Result<()> {
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        if !path.is_dir() {
+            continue;
+        }
+        if entry_name(&path).is_none() {
+            skipped.push(path.to_string_lossy().into_owned());
+        } else if has_content(&path) {
+            entry_paths.push(path);
+        } else {
+            walk_entry_dirs(&path, entry_paths, skipped)?;
+        }
+    }
+    Ok(())
+}
+
+// Entry directories of the site, nested ones included with recursive_entries
+pub fn site_entry_dirs(config: &SiteConfig) -> io::Result<Vec<PathBuf>> {
+    let entries_dir = config.path(&config.entries_dir);
+    if config.recursive_entries {
+        entry_dirs_recursive(&entries_dir)
+    } else {
+        entry_dirs(&entries_dir)
+    }
+}
+
+// Directories between entries_dir and an entry, e.g. "rust" for entries/rust/fearless-concurrency.
+// Empty for entries directly in entries_dir
+pub fn entry_section(entries_dir: &Path, entry_path: &Path) -> String {
+    entry_path
+        .parent()
+        .and_then(|parent| parent.strip_prefix(entries_dir).ok())
+        .map(|section| section.to_string_lossy().replace('\\', "/"))
+        .unwrap_or_default()
+}
+
+// Tags of an entry: the tags key of meta.toml and of the front matter, merged with tags.txt. An entry
+// with none of them has no tags. Files that cannot be read are reported and left out
+pub fn get_entry_tags(entry_path: &Path) -> HashSet<String> {
+    let mut tags = HashSet::new();
+    let meta_path = entry_path.join(META_FILE);
+    if meta_path.exists() {
+        let table = fs::read_to_string(&meta_path)
+            .and_then(|content| toml::parse(&content).map_err(|e| invalid_data(format!("{}: {}", meta_path.display(), e))));
+        match table {
+            Ok(table) => extend_table_tags(&mut tags, &table, &meta_path),
+            Err(e) => eprintln!("{}", e),
+        }
+    }
+    match read_front_matter(entry_path) {
+        Ok(Some(table)) => extend_table_tags(&mut tags, &table, &default_content_file(entry_path)),
+        Ok(None) => {}
+        Err(e) => eprintln!("{}", e),
+    }
+    let tags_file_path = entry_path.join("tags.txt");
+    if tags_file_path.exists() {
+        tags.extend(get_tags(&tags_file_path));
+    }
+    tags
+}
+
+fn extend_table_tags(tags: &mut HashSet<String>, table: &Table, path: &Path) {
+    if let Some(value) = table.get("tags") {
+        match table_tags(value) {
+            Ok(table_tags) => tags.extend(table_tags),
+            Err(e) => eprintln!("{}: {}", path.display(), e),
+        }
+    }
+}
+
+// Entry directories the build generates: drafts are left out unless include_drafts is set, entries
+// whose content.html is a symlink unless follow_symlinks is set, and with entry_limit the entries past
+// the first ones of the entries index. Entries whose metadata cannot be read are kept, so that their
+// error is reported, unless entry_limit needs it to order them
+pub fn published_entry_dirs(config: &SiteConfig) -> io::Result<Vec<PathBuf>> {
+    let mut entry_paths = site_entry_dirs(config)?;
+    if !config.include_drafts {
+        entry_paths.retain(|path| !is_draft(path));
+    }
+    if !config.follow_symlinks {
+        entry_paths.retain(|path| !has_symlinked_content(path));
+    }
+    if let Some(limit) = config.entry_limit.filter(|limit| *limit < entry_paths.len()) {
+        let manual_order = if config.index_sort == IndexSort::Manual { read_order(&config.path(ORDER_FILE))? } else { Vec::new() };
+        let kept: HashSet<PathBuf> = index_order(&entry_paths, config.index_sort, &manual_order)?.into_iter().take(limit).collect();
+        entry_paths.retain(|path| kept.contains(path));
+    }
+    Ok(entry_paths)
+}
+
+pub fn has_symlinked_content(entry_path: &Path) -> bool {
+    fs::symlink_metadata(default_content_file(entry_path)).is_ok_and(|metadata| metadata.file_type().is_symlink())
+}
+
+// Follow a chain of symlinks to the file at its end. Each link is tracked by its canonical directory
+// and name, so a chain coming back to a link already followed is a SymlinkLoop instead of an endless walk
+pub fn resolve_symlinks(path: &Path) -> Result<PathBuf, IroniteError> {
+    let mut visited: HashSet<PathBuf> = HashSet::new();
+    // Canonical paths of the links followed, for the error
+    let mut chain = Vec::new();
+    let mut current = path.to_path_buf();
+    while fs::symlink_metadata(&current).is_ok_and(|metadata| metadata.file_type().is_symlink()) {
+        let parent = current.parent().filter(|parent| !parent.as_os_str().is_empty()).unwrap_or(Path::new("."));
+        let canonical = fs::canonicalize(parent)?.join(current.file_name().unwrap_or_default());
+        chain.push(canonical.clone());
+        if !visited.insert(canonical) {
+            return Err(IroniteError::SymlinkLoop(chain));
+        }
+        current = parent.join(fs::read_link(&current)?);
+    }
+    Ok(current)
+}
+
+pub fn is_draft(entry_path: &Path) -> bool {
+    EntryMeta::load(entry_path).is_ok_and(|meta| meta.draft)
+}
+
+pub fn is_pinned(entry_path: &Path) -> bool {
+    EntryMeta::load(entry_path).is_ok_and(|meta| meta.pinned)
+}
+
+// Entries of a tag page in the order they are listed: pinned ones first, then by weight, ties keeping
+// the order they were given in
+pub fn listing_order(paths: &[PathBuf]) -> Vec<PathBuf> {
+    let mut keyed: Vec<((bool, i32), &PathBuf)> = paths
+        .iter()
+        .map(|path| {
+            let meta = EntryMeta::load(path).unwrap_or_default();
+            ((!meta.pinned, meta.weight), path)
+        })
+        .collect();
+    keyed.sort_by_key(|(key, _)| *key);
+    keyed.into_iter().map(|(_, path)| path.clone()).collect()
+}
+
+// Link to an entry in a listing. Pinned entries get pinned_marker before their label and pinned_class on the link
+pub fn listing_link(href: &str, label: &str, pinned: bool, config: &SiteConfig) -> String {
+    if !pinned {
+        return format!("<a href=\"{}\">{}</a><br>", href, label);
+    }
+    let class = if config.pinned_class.is_empty() { String::new() } else { format!(" class=\"{}\"", escape_html(&config.pinned_class)) };
+    let marker = if config.pinned_marker.is_empty() { String::new() } else { format!("{} ", config.pinned_marker) };
+    format!("<a href=\"{}\"{}>{}{}</a><br>", href, class, marker, label)
+}
+
+// Label of an entry in a listing: its name, followed by external_link_marker when it links to another site
+pub fn listing_label(entry_path: &Path, config: &SiteConfig) -> String {
+    let title = entry_name(entry_path).unwrap_or("Untitled");
+    match read_link(entry_path) {
+        Ok(Some(_)) if !config.external_link_marker.is_empty() => format!("{} {}", title, config.external_link_marker),
+        _ => title.to_string(),
+    }
+}
+
+// Filter entries by tags. Entries of each tag are sorted by path, whatever order the directory is read in
+pub fn filter_entries_by_tag(entries_dir: &Path) -> HashMap<String, Vec<PathBuf>> {
+    filter_entries_by_tag_with(entries_dir, entry_dirs)
+}
+
+// Same as filter_entries_by_tag, for entries at any depth as found by entry_dirs_recursive
+pub fn filter_entries_by_tag_recursive(entries_dir: &Path) -> HashMap<String, Vec<PathBuf>> {
+    filter_entries_by_tag_with(entries_dir, entry_dirs_recursive)
+}
+
+fn filter_entries_by_tag_with(entries_dir: &Path, list_entries: fn(&Path) -> io::Result<Vec<PathBuf>>) -> HashMap<String, Vec<PathBuf>> {
     if !entries_dir.exists() || !entries_dir.is_dir() {
         eprintln!("Entries directory does not exist or is not a directory.");
-        return tags_map;
+        return HashMap::new();
     }
 
-    if let Ok(entries) = fs::read_dir(entries_dir) {
-        for entry in entries.filter_map(Result::ok) {
-            let path = entry.path();
-            if !path.is_dir() {
-                continue;
-            }
-            let tags_file_path = path.join("tags.txt");
-            let tags = get_tags(tags_file_path.to_str().unwrap_or(""));
+    match list_entries(entries_dir) {
+        Ok(entry_paths) => entries_by_tag(&entry_paths),
+        Err(_) => {
+            eprintln!("Failed to read the entries directory.");
+            HashMap::new()
+        }
+    }
+}
 
-            for tag in tags {
-                tags_map.entry(tag)
+// Group entry directories by tag, each tag's entries sorted by path. Entries of nested tags are
+// grouped under every ancestor of the tag as well
+pub fn entries_by_tag(entry_paths: &[PathBuf]) -> HashMap<String, Vec<PathBuf>> {
+    let mut tags_map = HashMap::new();
+    for path in entry_paths {
+        for tag in get_entry_tags(path) {
+            // An entry tagged programming/rust is listed on the programming page too
+            for ancestor in tag_ancestors(&tag) {
+                tags_map.entry(ancestor.to_string())
                     .or_insert_with(Vec::new)
                     .push(path.clone());
             }
+            tags_map.entry(tag)
+                .or_insert_with(Vec::new)
+                .push(path.clone());
         }
-    } else {
-        eprintln!("Failed to read the entries directory.");
     }
 
+    for paths in tags_map.values_mut() {
+        paths.sort();
+        paths.dedup();
+    }
     tags_map
 }
 
+// Tags spelled in several ways that were merged into one by normalize_tags
+#[derive(Debug, Clone, PartialEq)]
+pub struct TagMerge {
+    // The tag they became, whose page lists every entry of the originals
+    pub tag: String,
+    // Each original spelling, sorted, with the names of the entries using it
+    pub originals: Vec<(String, Vec<String>)>,
+}
+
+impl fmt::Display for TagMerge {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let originals: Vec<String> = self
+            .originals
+            .iter()
+            .map(|(original, entries)| format!("{} ({})", original, entries.join(", ")))
+            .collect();
+        write!(f, "tags {} were merged into {}", originals.join(", "), self.tag)
+    }
+}
+
+// Display form of each tag as normalize merges them: the spelling of the first entry carrying it, by
+// path, the smallest spelling winning within one entry. Rust in entries/a and rust in entries/b show as Rust
+pub fn first_spellings(tags_map: &HashMap<String, Vec<PathBuf>>, normalize: &dyn Fn(&str) -> String) -> HashMap<String, String> {
+    let mut first: HashMap<String, (&PathBuf, &String)> = HashMap::new();
+    for (tag, paths) in tags_map {
+        let Some(path) = paths.iter().min() else { continue };
+        let name = normalize(tag);
+        if first.get(&name).is_none_or(|current| (path, tag) < *current) {
+            first.insert(name, (path, tag));
+        }
+    }
+    first.into_iter().map(|(name, (_, tag))| (name, tag.clone())).collect()
+}
+
+// Merge tags that normalize to the same name, e.g. with str::to_lowercase Rust, rust and RUST all
+// become rust. Returns the new map, and every tag that more than one original was merged into
+pub fn normalize_tags(tags_map: &HashMap<String, Vec<PathBuf>>, normalize: &dyn Fn(&str) -> String) -> (HashMap<String, Vec<PathBuf>>, Vec<TagMerge>) {
+    let mut normalized: HashMap<String, Vec<PathBuf>> = HashMap::new();
+    let mut originals: HashMap<String, Vec<(String, Vec<String>)>> = HashMap::new();
+    for (tag, paths) in tags_map {
+        let name = normalize(tag);
+        normalized.entry(name.clone()).or_default().extend(paths.iter().cloned());
+        let entries = paths.iter().filter_map(|path| entry_name(path)).map(str::to_string).collect();
+        originals.entry(name).or_default().push((tag.clone(), entries));
+    }
+
+    for paths in normalized.values_mut() {
+        paths.sort();
+        paths.dedup();
+    }
+    let mut merges: Vec<TagMerge> = originals
+        .into_iter()
+        .filter(|(_, originals)| originals.len() > 1)
+        .map(|(tag, mut originals)| {
+            originals.sort();
+            TagMerge { tag, originals }
+        })
+        .collect();
+    merges.sort_by(|a, b| a.tag.cmp(&b.tag));
+    (normalized, merges)
+}
+
+// Drop the tags of excluded, and the tags nested under them, from a map of tags. Returns the new map
+// and how many entries the dropped tags listed in all, counting an entry once per tag
+pub fn exclude_tags(tags_map: &HashMap<String, Vec<PathBuf>>, excluded: &[String]) -> (HashMap<String, Vec<PathBuf>>, usize) {
+    let is_excluded = |tag: &str| excluded.iter().any(|name| tag == name || tag_ancestors(tag).contains(&name.as_str()));
+    let mut suppressed = 0;
+    let mut kept = HashMap::new();
+    for (tag, paths) in tags_map {
+        if is_excluded(tag) {
+            suppressed += paths.len();
+        } else {
+            kept.insert(tag.clone(), paths.clone());
+        }
+    }
+    (kept, suppressed)
+}
+
+// List entry directories from newest to oldest.
+// Entries with a date in meta.toml come first, newest first. Entries without one follow,
+// ordered by the modification time of their directory, newest first. Ties are broken by name.
+// An empty entries directory yields an empty list
+pub fn entries_sorted_by_date(entries_dir: &Path) -> io::Result<Vec<PathBuf>> {
+    sort_entries_by_date(entry_dirs(entries_dir)?)
+}
+
+// Order entry directories as entries_sorted_by_date does
+pub fn sort_entries_by_date(entry_paths: Vec<PathBuf>) -> io::Result<Vec<PathBuf>> {
+    // (has no explicit date, date, path): sorting on the first field puts dated entries first
+    let mut entries: Vec<(bool, Date, PathBuf)> = Vec::new();
+
+    for entry_path in entry_paths {
+        let meta = EntryMeta::load(&entry_path)?;
+        let sort_key = match meta.date {
+            Some(date) => (false, date),
+            None => (true, file_date(entry_path.metadata()?.modified()?)),
+        };
+        entries.push((sort_key.0, sort_key.1, entry_path));
+    }
+
+    entries.sort_by(|a, b| a.0.cmp(&b.0).then(b.1.cmp(&a.1)).then(a.2.cmp(&b.2)));
+    Ok(entries.into_iter().map(|(_, _, path)| path).collect())
+}
+
 // Copy directory and its contents recursively
 pub fn copy_directory<P: AsRef<Path>>(source: P, destination: P) -> io::Result<()> {
     let source = source.as_ref();
@@ -118,195 +696,1504 @@ pub fn copy_directory<P: AsRef<Path>>(source: P, destination: P) -> io::Result<(
         if path.is_dir() {
             copy_directory(path, new_destination)?;
         } else {
-            fs::copy(path, new_destination)?;
+            retry_io(&path, || fs::copy(&path, &new_destination))?;
         }
     }
 
     Ok(())
 }
 
-// Generate pages for entries
-pub fn generate_entry_pages(base_html: &str, entries_dir: &Path, public_entries_dir: &Path) -> io::Result<()> {
-    for entry in fs::read_dir(entries_dir)? {
-        let entry = entry?;
-        let entry_path = entry.path();
+// Totals of a copy_directory_with_progress or copy_directory_incremental run
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct CopyStats {
+    pub files_copied: usize,
+    pub bytes_copied: u64,
+    // Files left alone because the destination was already up to date
+    pub skipped: usize,
+}
+
+// Copy a directory recursively like copy_directory, calling on_file with the source path
+// and size of every file once it has been copied
+pub fn copy_directory_with_progress<P, F>(source: P, destination: P, on_file: F) -> io::Result<CopyStats>
+where
+    P: AsRef<Path>,
+    F: Fn(&Path, u64),
+{
+    copy_directory_excluding(source, destination, &[], on_file)
+}
+
+// Copy a directory like copy_directory_with_progress, leaving out what matches one of the exclude
+// patterns, where * matches any run of characters. A pattern with a / matches paths relative to
+// source ("drafts/*"), one without matches the name of any file or directory ("*.psd", ".DS_Store").
+// Excluded directories are skipped whole; excluded files reach neither on_file nor the totals
+pub fn copy_directory_excluding<P, F>(source: P, destination: P, exclude: &[&str], on_file: F) -> io::Result<CopyStats>
+where
+    P: AsRef<Path>,
+    F: Fn(&Path, u64),
+{
+    let mut stats = CopyStats::default();
+    copy_with_progress(source.as_ref(), destination.as_ref(), "", exclude, &on_file, &mut stats)?;
+    Ok(stats)
+}
 
-        if !entry_path.is_dir() {
+fn copy_with_progress<F: Fn(&Path, u64)>(
+    source: &Path,
+    destination: &Path,
+    relative_dir: &str,
+    exclude: &[&str],
+    on_file: &F,
+    stats: &mut CopyStats,
+) -> io::Result<()> {
+    if !source.is_dir() {
+        return Err(io::Error::new(io::ErrorKind::NotFound, "Source is not a directory"));
+    }
+    create_directories(destination)?;
+
+    for entry in fs::read_dir(source)? {
+        let path = entry?.path();
+        let name = path.file_name().unwrap();
+        let new_destination = destination.join(name);
+        let name = name.to_string_lossy();
+        let relative = if relative_dir.is_empty() { name.to_string() } else { format!("{}/{}", relative_dir, name) };
+        let excluded = exclude.iter().any(|pattern| {
+            if pattern.contains('/') { pattern_matches(pattern.trim_matches('/'), &relative) } else { pattern_matches(pattern, &name) }
+        });
+        if excluded {
             continue;
         }
 
-        let title = entry_path.file_name().unwrap().to_str().unwrap_or("Untitled");
-        let new_entry_dir = public_entries_dir.join(title);
-        create_directories(&new_entry_dir)?;
-
-        let content_file_path = entry_path.join("content.html");
-        if content_file_path.exists() {
-            let content_html = fs::read(&content_file_path)?;
-            //let content_html = txt_to_html(content);
-            let final_html_content = replace_placeholders(
-                &base_html,
-                &[
-                    ("$CONTENT".to_string(), String::from_utf8_lossy(&content_html).to_string()),
-                    ("$TITLE".to_string(), title.to_string()),
-		    ("$NAVCLOUD".to_string(), "".to_string()),
-                ].iter().cloned().collect()
-            );
-            write_html_file(new_entry_dir.join("index.html"), &final_html_content)?;
+        if path.is_dir() {
+            copy_with_progress(&path, &new_destination, &relative, exclude, on_file, stats)?;
         } else {
-            eprintln!("No content.html found in {:?}", entry_path);
+            let bytes = retry_io(&path, || fs::copy(&path, &new_destination))?;
+            stats.files_copied += 1;
+            stats.bytes_copied += bytes;
+            on_file(&path, bytes);
         }
     }
+
     Ok(())
 }
 
-pub fn generate_tag_pages(base_html: &str, tags_map: &HashMap<String, Vec<PathBuf>>, public_dir: &Path) -> io::Result<()> {
-    // Collect tags and sort them
-    let mut tags: Vec<String> = tags_map.keys().cloned().collect();
-    tags.sort();
+// Copy a directory recursively like copy_directory, skipping files whose destination is at least
+// as new as the source
+pub fn copy_directory_incremental<P: AsRef<Path>>(source: P, destination: P) -> io::Result<CopyStats> {
+    let mut stats = CopyStats::default();
+    copy_incremental(source.as_ref(), destination.as_ref(), &mut stats)?;
+    Ok(stats)
+}
 
-    // Iterate over sorted tags
-    for tag in tags {
-        if let Some(paths) = tags_map.get(&tag) {
-            let tag_dir = public_dir.join(&tag);
-            create_directories(&tag_dir)?;
+fn copy_incremental(source: &Path, destination: &Path, stats: &mut CopyStats) -> io::Result<()> {
+    if !source.is_dir() {
+        return Err(io::Error::new(io::ErrorKind::NotFound, "Source is not a directory"));
+    }
+    create_directories(destination)?;
 
-            let mut tag_content = String::new();
-            for path in paths {
-                let entry_title = path.file_name().unwrap().to_str().unwrap_or("Untitled");
-                let entry_link = format!("<a href=\"../entries/{}/index.html\">{}</a><br>", entry_title, entry_title);
-                tag_content.push_str(&entry_link);
-            }
+    for entry in fs::read_dir(source)? {
+        let path = entry?.path();
+        let new_destination = destination.join(path.file_name().unwrap());
 
-            let tag_html_content = replace_placeholders(
-                &base_html,
-                &[
-                    ("$CONTENT".to_string(), tag_content),
-                    ("$TITLE".to_string(), tag.to_string()),
-                    ("$NAVCLOUD".to_string(), "".to_string()),
-                ].iter().cloned().collect()
-            );
-            write_html_file(tag_dir.join("index.html"), &tag_html_content)?;
+        if path.is_dir() {
+            copy_incremental(&path, &new_destination, stats)?;
+        } else if is_up_to_date(&path, &new_destination)? {
+            stats.skipped += 1;
+        } else {
+            stats.bytes_copied += retry_io(&path, || fs::copy(&path, &new_destination))?;
+            stats.files_copied += 1;
         }
     }
+
     Ok(())
 }
 
-// Generate the site
-pub fn generate_site() -> io::Result<()> {
-    let public_dir = Path::new("public");
-    let entries_dir = public_dir.join("entries");
-    let root_entries_dir = Path::new("entries");
-    let base_html_path = Path::new("static").join("base.html");
-    let about_txt_path = Path::new("static").join("about.html");
-    let projectname_path = Path::new("projectname.txt");
+// Copy a directory like copy_directory_incremental, with the file copies spread over workers threads.
+// Directories are all created first. The first error, in the order files were found, is returned once
+// every worker has stopped; workers stop taking files after any error. Workers retry copies with the
+// policy of the calling thread
+pub fn copy_directory_parallel<P: AsRef<Path>>(source: P, destination: P, workers: usize) -> io::Result<CopyStats> {
+    let mut files = Vec::new();
+    plan_copy(source.as_ref(), destination.as_ref(), &mut files)?;
+    copy_files_parallel(&files, workers, |source, destination| fs::copy(source, destination))
+}
 
-    // Read base HTML
-    let base_html = match read_file_to_string(&base_html_path) {
-        Ok(content) => content,
-        Err(e) => {
-            eprintln!("Failed to read base HTML file: {}", e);
-            return Err(e);
+// Copy files, as (source, destination), with copy on workers threads, see copy_directory_parallel
+fn copy_files_parallel(files: &[(PathBuf, PathBuf)], workers: usize, copy: impl Fn(&Path, &Path) -> io::Result<u64> + Sync) -> io::Result<CopyStats> {
+    let retry = current_io_retry();
+    let next = AtomicUsize::new(0);
+    let failed = AtomicBool::new(false);
+    let results: Mutex<Vec<(usize, io::Result<Option<u64>>)>> = Mutex::new(Vec::new());
+    thread::scope(|scope| {
+        for _ in 0..workers.clamp(1, files.len().max(1)) {
+            scope.spawn(|| {
+                with_io_retry(retry, || {
+                    while !failed.load(Ordering::Relaxed) {
+                        let index = next.fetch_add(1, Ordering::Relaxed);
+                        let Some((source, destination)) = files.get(index) else { break };
+                        let result = is_up_to_date(source, destination).and_then(|up_to_date| {
+                            if up_to_date { Ok(None) } else { retry_io(source, || copy(source, destination)).map(Some) }
+                        });
+                        if result.is_err() {
+                            failed.store(true, Ordering::Relaxed);
+                        }
+                        results.lock().unwrap_or_else(|e| e.into_inner()).push((index, result));
+                    }
+                })
+            });
         }
-    };
+    });
 
-    // Read other static content
-    let about_txt_content = match read_file_to_string(&about_txt_path) {
-        Ok(content) => content,
-        Err(e) => {
-            eprintln!("Failed to read about.html: {}", e);
-            return Err(e);
+    let mut results = results.into_inner().unwrap_or_else(|e| e.into_inner());
+    results.sort_by_key(|(index, _)| *index);
+    let mut stats = CopyStats::default();
+    for (_, result) in results {
+        match result? {
+            Some(bytes) => {
+                stats.files_copied += 1;
+                stats.bytes_copied += bytes;
+            }
+            None => stats.skipped += 1,
         }
-    };
+    }
+    Ok(stats)
+}
 
-    let project_name = match read_file_to_string(&projectname_path) {
-        Ok(content) => content,
-        Err(e) => {
-            eprintln!("Failed to read projectname.txt: {}", e);
-            return Err(e);
+// Create the directories of a copy, listing the files to copy as (source, destination)
+fn plan_copy(source: &Path, destination: &Path, files: &mut Vec<(PathBuf, PathBuf)>) -> io::Result<()> {
+    if !source.is_dir() {
+        return Err(io::Error::new(io::ErrorKind::NotFound, "Source is not a directory"));
+    }
+    create_directories(destination)?;
+
+    for entry in fs::read_dir(source)? {
+        let path = entry?.path();
+        let new_destination = destination.join(path.file_name().unwrap());
+        if path.is_dir() {
+            plan_copy(&path, &new_destination, files)?;
+        } else {
+            files.push((path, new_destination));
         }
+    }
+    Ok(())
+}
+
+// Whether destination is a copy of source that does not need refreshing: newer or as new by
+// modification time, or of the same size where modification times are not available
+fn is_up_to_date(source: &Path, destination: &Path) -> io::Result<bool> {
+    let destination_meta = match fs::metadata(destination) {
+        Ok(meta) => meta,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(false),
+        Err(e) => return Err(e),
     };
+    let source_meta = fs::metadata(source)?;
+    match (source_meta.modified(), destination_meta.modified()) {
+        (Ok(source_time), Ok(destination_time)) => Ok(destination_time >= source_time),
+        _ => Ok(destination_meta.len() == source_meta.len()),
+    }
+}
+
+// Pick the layout of an entry: static/<template>.html when its meta.toml names one, base.html otherwise.
+// A missing or invalid template falls back to base.html with a warning
+pub fn entry_template(base_html: &str, meta: &EntryMeta, static_dir: &Path, entry_path: &Path) -> String {
+    let name = match &meta.template {
+        Some(name) => name,
+        None => return base_html.to_string(),
+    };
+
+    // Template names are file names inside static/, never paths
+    if name.is_empty() || name.contains(['/', '\\']) || name.starts_with('.') {
+        eprintln!("Invalid template name \"{}\" in {:?}, using base.html", name, entry_path);
+        return base_html.to_string();
+    }
+
+    let template_path = static_dir.join(format!("{}.html", name));
+    match read_file_to_string(&template_path) {
+        Ok(template) => template,
+        Err(_) => {
+            eprintln!("Template {:?} for {:?} not found, using base.html", template_path, entry_path);
+            base_html.to_string()
+        }
+    }
+}
+
+// Link to the index.html of a directory: "tag/index.html", or "tag/" with pretty URLs.
+// dir may be empty (the current directory) or end with a slash
+pub fn index_link(dir: &str, pretty: bool) -> String {
+    match (dir, pretty) {
+        ("", true) => "./".to_string(),
+        (dir, true) if dir.ends_with('/') => dir.to_string(),
+        (dir, true) => format!("{}/", dir),
+        (dir, false) if dir.is_empty() || dir.ends_with('/') => format!("{}index.html", dir),
+        (dir, false) => format!("{}/index.html", dir),
+    }
+}
 
-    // Create public directories
-    create_directories(&public_dir)?;
-    create_directories(&entries_dir)?;
+// Navigation trail for an entry page, e.g. Home > 2023 > My Post.
+// permalink is the directory of the page under the public directory; every segment but the
+// last links to its index. The leading entries/ of the default pattern is not shown
+pub fn breadcrumb(permalink: &Path, pretty: bool) -> String {
+    let segments: Vec<String> = permalink
+        .components()
+        .map(|component| component.as_os_str().to_string_lossy().into_owned())
+        .collect();
+    let depth = segments.len();
+
+    let mut trail = vec![format!("<a href=\"{}\">Home</a>", index_link(&"../".repeat(depth), pretty))];
+    for (i, segment) in segments.iter().enumerate() {
+        if i + 1 == depth {
+            trail.push(segment.clone());
+        } else if i > 0 || segment != "entries" {
+            trail.push(format!("<a href=\"{}\">{}</a>", index_link(&"../".repeat(depth - 1 - i), pretty), segment));
+        }
+    }
+    trail.join(" &gt; ")
+}
+
+// Entries sharing the most tags with the given one, at most limit of them. untagged is the pseudo-tag
+// of the entries without tags, see untagged_tag. Ties are broken by title so the result does not depend
+// on directory order
+pub fn related_entries(entry_path: &Path, tags_map: &HashMap<String, Vec<PathBuf>>, untagged: &str, limit: usize) -> Vec<PathBuf> {
+    let mut shared: HashMap<&PathBuf, usize> = HashMap::new();
+    // Having no tag is not something entries share
+    for paths in tags_map.iter().filter(|(tag, _)| *tag != untagged).map(|(_, paths)| paths) {
+        if !paths.iter().any(|path| path == entry_path) {
+            continue;
+        }
+        for path in paths {
+            if path != entry_path {
+                *shared.entry(path).or_insert(0) += 1;
+            }
+        }
+    }
 
-    // Copy static files
-    let static_source = Path::new("static");
-    let static_dest = public_dir.join("static");
-    copy_directory(static_source, &static_dest)?;
+    let mut related: Vec<(&PathBuf, usize)> = shared.into_iter().collect();
+    related.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.file_name().cmp(&b.0.file_name())));
+    related.into_iter().take(limit).map(|(path, _)| path.clone()).collect()
+}
 
-    // Copy images files
-    let static_source = Path::new("images");
-    let static_dest = public_dir.join("images");
-    copy_directory(static_source, &static_dest)?;
+// Link from a page to an entry. root is the path from the page to the site root
+pub fn entry_link(root: &str, title: &str, permalinks: &PermalinkMap, pretty: bool) -> String {
+    let permalink = permalinks.get(title).cloned().unwrap_or_else(|| format!("entries/{}", title));
+    index_link(&format!("{}{}", root, permalink), pretty)
+}
 
-    // Generate entry pages
-    generate_entry_pages(&base_html, &root_entries_dir, &entries_dir)?;
+// Link to an entry from a page at root: the URL of its link.txt when it has one, or else its page
+pub fn entry_href(root: &str, entry_path: &Path, permalinks: &PermalinkMap, pretty: bool) -> String {
+    match read_link(entry_path).ok().flatten() {
+        Some(link) => escape_html(&link),
+        None => entry_link(root, entry_name(entry_path).unwrap_or("Untitled"), permalinks, pretty),
+    }
+}
 
-    // Generate tag pages
-    let tags_map = filter_entries_by_tag();
-    generate_tag_pages(&base_html, &tags_map, &public_dir)?;
+// Links to related entries, relative to an entry page
+fn related_links(entry_path: &Path, tags_map: &HashMap<String, Vec<PathBuf>>, root: &str, permalinks: &PermalinkMap, config: &SiteConfig) -> String {
+    related_entries(entry_path, tags_map, &config.untagged_tag, config.related_limit)
+        .iter()
+        .map(|path| {
+            let title = entry_name(path).unwrap_or("Untitled");
+            format!("<a href=\"{}\">{}</a><br>", entry_href(root, path, permalinks, config.pretty_urls), title)
+        })
+        .collect()
+}
 
-    // Create navigation cloud. Contains links to each tag index
+// All tags, sorted
+pub fn sorted_tags(tags_map: &HashMap<String, Vec<PathBuf>>) -> Vec<String> {
     let mut tags: Vec<String> = tags_map.keys().cloned().collect();
     tags.sort();
+    tags
+}
 
-    let nav_cloud = tags
+// Number of entries carrying each tag, with tags as the site uses them (from get_entry_tags, so
+// an entry listing a tag twice counts once)
+pub fn tag_count(entries_dir: &Path) -> HashMap<String, usize> {
+    filter_entries_by_tag(entries_dir)
         .into_iter()
-        .map(|tag| format!("<a href=\"{}/index.html\">{}</a>", tag, tag))
-        .collect::<String>();
+        .map(|(tag, paths)| (tag, paths.len()))
+        .collect()
+}
 
+// The n tags on the most entries, most used first. Tags used equally often are sorted by name
+pub fn most_used_tags(entries_dir: &Path, n: usize) -> Vec<(String, usize)> {
+    let mut counts: Vec<(String, usize)> = tag_count(entries_dir).into_iter().collect();
+    counts.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+    counts.truncate(n);
+    counts
+}
+
+// Tags shown in $NAVCLOUD: the top-level ones, or all of them with nav_cloud_nested_tags. The
+// untagged page comes last
+pub fn cloud_tags(tags: &[String], config: &SiteConfig) -> Vec<String> {
+    let (mut cloud, untagged): (Vec<String>, Vec<String>) = tags
+        .iter()
+        .filter(|tag| config.nav_cloud_nested_tags || !tag.contains('/'))
+        .cloned()
+        .partition(|tag| *tag != config.untagged_tag);
+    cloud.extend(untagged);
+    cloud
+}
+
+// Entries carrying no tag, in the order of entry_paths
+pub fn untagged_entries(entry_paths: &[PathBuf], tags_map: &HashMap<String, Vec<PathBuf>>) -> Vec<PathBuf> {
+    entry_paths
+        .iter()
+        .filter(|path| !tags_map.values().any(|paths| paths.contains(path)))
+        .cloned()
+        .collect()
+}
+
+// Create navigation cloud. Contains links to each tag index.
+// root is the path from the page to the site root, e.g. "../../" for entry pages
+// Tags are shown in their display form, see SiteConfig::tag_label
+pub fn nav_cloud(tags: &[String], root: &str, config: &SiteConfig) -> String {
+    tags.iter()
+        .map(|tag| format!("<a href=\"{}\">{}</a>", index_link(&format!("{}{}", root, tag_slug(tag)), config.pretty_urls), config.tag_label(tag)))
+        .collect()
+}
 
-    // Replace the $NAVCLOUD placeholder in about_txt_content with tags
-    let parsed_about_txt_content = replace_placeholders(
-	&about_txt_content,
-	&[
-	    ("$NAVCLOUD".to_string(), nav_cloud),
-        ].iter().cloned().collect()
+// Links to the tag pages of one entry, relative to its page
+fn entry_tag_links(entry_path: &Path, tags: &[String], tags_map: &HashMap<String, Vec<PathBuf>>, root: &str, config: &SiteConfig) -> String {
+    tags.iter()
+        .filter(|tag| tags_map[*tag].iter().any(|path| path == entry_path))
+        .map(|tag| format!("<a href=\"{}\">{}</a>", index_link(&format!("{}{}", root, tag_slug(tag)), config.pretty_urls), config.tag_label(tag)))
+        .collect()
+}
+
+// Downloadable files of an entry, copied next to its page
+pub const ENTRY_FILES_DIR: &str = "files";
+
+// Paths of the files under dir, relative to it with / separators, sorted
+fn relative_file_paths(dir: &Path, prefix: &str, paths: &mut Vec<String>) -> io::Result<()> {
+    for entry in fs::read_dir(dir)? {
+        let entry = entry?;
+        let Some(name) = entry.file_name().to_str().map(str::to_string) else { continue };
+        if entry.file_type()?.is_dir() {
+            relative_file_paths(&entry.path(), &format!("{}{}/", prefix, name), paths)?;
+        } else {
+            paths.push(format!("{}{}", prefix, name));
+        }
+    }
+    Ok(())
+}
+
+// Copy the files/ directory of an entry to entry_dir/files/, and return $FILES_LIST: a list linking to
+// each of its files, sorted by path. Empty when the entry has no files/
+fn copy_entry_files(entry_path: &Path, entry_dir: &Path) -> io::Result<String> {
+    let files_dir = entry_path.join(ENTRY_FILES_DIR);
+    if !files_dir.is_dir() {
+        return Ok(String::new());
+    }
+    copy_directory(files_dir.as_path(), entry_dir.join(ENTRY_FILES_DIR).as_path())?;
+    let mut paths = Vec::new();
+    relative_file_paths(&files_dir, "", &mut paths)?;
+    paths.sort();
+    let links: String = paths
+        .iter()
+        .map(|path| format!("<li><a href=\"{}/{}\">{}</a></li>", ENTRY_FILES_DIR, escape_html(path), escape_html(path)))
+        .collect();
+    Ok(format!("<ul>{}</ul>", links))
+}
+
+// Generate pages for entries, each at public/<permalink>/index.html. Tags must be collected first:
+// entry pages link to their tags, carry the navigation cloud and list related entries.
+// Entries that fail are skipped and returned together as EntryErrors, unless fail_fast is set
+pub fn generate_entry_pages(
+    base_html: &str,
+    config: &SiteConfig,
+    tags_map: &HashMap<String, Vec<PathBuf>>,
+    updated: &UpdatedDates,
+) -> Result<(), IroniteError> {
+    generate_entry_pages_for(base_html, config, tags_map, updated, &published_entry_dirs(config)?)
+}
+
+// Generate the pages of the given entries only, as generate_entry_pages does. Links, series and the nav
+// cloud still come from every published entry, so the pages match those of a full build
+pub fn generate_entry_pages_for(
+    base_html: &str,
+    config: &SiteConfig,
+    tags_map: &HashMap<String, Vec<PathBuf>>,
+    updated: &UpdatedDates,
+    entry_paths: &[PathBuf],
+) -> Result<(), IroniteError> {
+    let permalinks = entry_permalinks(config)?;
+    // Short URLs already written, with the entry owning them
+    let mut short_urls: HashMap<String, String> = HashMap::new();
+    // Entries that failed, reported together once every other entry is written
+    let mut failed = Vec::new();
+    let published = published_entry_dirs(config)?;
+    let series = collect_series(&published)?;
+    // Position of every entry in the entries index, for $ENTRY_INDEX and $ENTRY_TOTAL
+    let manual_order = if config.index_sort == IndexSort::Manual { read_order(&config.path(ORDER_FILE))? } else { Vec::new() };
+    let positions: HashMap<PathBuf, usize> =
+        index_order(&published, config.index_sort, &manual_order)?.into_iter().enumerate().map(|(i, path)| (path, i + 1)).collect();
+
+    for entry_path in entry_paths {
+        let title = entry_name(entry_path).unwrap_or("Untitled");
+        let build = EntryBuild { tags_map, permalinks: &permalinks, series: &series, updated, positions: &positions };
+        match generate_entry_page(base_html, config, &build, entry_path, &mut short_urls) {
+            Ok(()) => {}
+            // A conflict between two entries is not a problem of either one, so it stops the build
+            Err(error @ IroniteError::ShortUrlConflict { .. }) => return Err(error),
+            Err(error) if config.fail_fast => return Err(error),
+            Err(error) => failed.push((title.to_string(), error)),
+        }
+    }
+
+    if failed.is_empty() {
+        Ok(())
+    } else {
+        Err(IroniteError::EntryErrors(failed))
+    }
+}
+
+// What the build knows about every entry, shared by the pages of all of them
+struct EntryBuild<'a> {
+    tags_map: &'a HashMap<String, Vec<PathBuf>>,
+    permalinks: &'a PermalinkMap,
+    series: &'a [Series],
+    updated: &'a UpdatedDates,
+    // Position of each entry in the entries index, from 1
+    positions: &'a HashMap<PathBuf, usize>,
+}
+
+// Write the page of one entry, and its short URL redirect
+fn generate_entry_page(
+    base_html: &str,
+    config: &SiteConfig,
+    build: &EntryBuild,
+    entry_path: &Path,
+    short_urls: &mut HashMap<String, String>,
+) -> Result<(), IroniteError> {
+    let EntryBuild { tags_map, permalinks, series, updated, positions } = *build;
+    let public_dir = config.path(&config.public_dir);
+    let tags = sorted_tags(tags_map);
+    let title = entry_name(entry_path).unwrap_or("Untitled");
+    let permalink = &permalinks[title];
+    let root = root_prefix(permalink);
+    let new_entry_dir = public_dir.join(permalink);
+    // Entries published elsewhere have no page, or with external_link_stubs one sending readers there
+    if let Some(link) = read_link(entry_path)? {
+        if config.external_link_stubs {
+            create_directories(&new_entry_dir)?;
+            write_html_file(new_entry_dir.join("index.html"), &redirect_page(&escape_html(&link)))?;
+        }
+        return Ok(());
+    }
+    create_directories(&new_entry_dir)?;
+
+    // Entries can share one content file through symlinks, read like any other. The link's name says
+    // how the content is written
+    let content_path = content_file(entry_path, config);
+    let content_file_path = resolve_symlinks(&content_path)?;
+    if !content_file_path.exists() {
+        eprintln!("No content file found in {:?}", entry_path);
+        return Ok(());
+    }
+
+    // Variables and placeholders of the site apply inside the entry too, but not the page's own
+    // ($TITLE, $CONTENT, ...), which belong to the template
+    let content_html = strip_more_marker(&read_content_file_with_limit(&content_path, config.max_file_size)?, config);
+    // Shortcodes come first, so that the HTML they give gets the placeholders and the passes below too
+    let content_html = if config.shortcodes {
+        let content_html = expand_csv_tables(&content_html, entry_path).html;
+        expand_shortcodes(&content_html, SHORTCODES, &ShortcodeContext { config, root: &root }).html
+    } else {
+        content_html
+    };
+    let content_html = replace_placeholders(&content_html, &config.page_placeholders(PageKind::Entry));
+    let content_html = if config.footnotes { footnotes(&content_html).html } else { content_html };
+    let content_html = if config.smart_punctuation { smart_punctuation(&content_html) } else { content_html };
+    let content_html = if config.highlight_code { highlight_code_blocks(&content_html) } else { content_html };
+    let content_html = if config.external_links_noopener || config.external_links_new_tab {
+        external_link_attributes(&content_html, url_host(&config.base_url).as_deref(), config.external_links_new_tab)
+    } else {
+        content_html
+    };
+    let content_html = if config.lazy_images {
+        let size = |src: &str| if config.image_dimensions { entry_image_size(entry_path, src) } else { None };
+        lazy_images(&content_html, &size)
+    } else {
+        content_html
+    };
+    // Dates of the build win, so that every page agrees; content.html is checked for entries missing from them
+    let updated = match updated.get(title) {
+        Some(date) => config.format_date(date),
+        None => fs::metadata(&content_file_path)?.modified().map(|time| config.format_date(&file_date(time))).unwrap_or_default(),
+    };
+    //let content_html = txt_to_html(content);
+    let meta = EntryMeta::load(entry_path)?;
+    let files_list = copy_entry_files(entry_path, &new_entry_dir)?;
+    let template = entry_template(base_html, &meta, &config.path(&config.static_dir), entry_path);
+    let final_html_content = replace_placeholders(
+        &template,
+        &page_placeholders(config, PageKind::Entry, &[
+            ("$CONTENT".to_string(), content_html),
+            ("$TITLE".to_string(), escape_html(meta.display_title())),
+            ("$NAVCLOUD".to_string(), nav_cloud(&cloud_tags(&tags, config), &root, config)),
+            ("$TAGS".to_string(), entry_tag_links(entry_path, &tags, tags_map, &root, config)),
+            ("$BREADCRUMB".to_string(), breadcrumb(Path::new(permalink), config.pretty_urls)),
+            ("$RELATED".to_string(), related_links(entry_path, tags_map, &root, permalinks, config)),
+            ("$UPDATED".to_string(), updated),
+            ("$DATE".to_string(), meta.date.map(|date| config.format_date(&date)).unwrap_or_default()),
+            ("$FILES_LIST".to_string(), files_list),
+            ("$SERIES_NAV".to_string(), series_nav(series, entry_path, &root, permalinks, config.pretty_urls)),
+            ("$ENTRY_INDEX".to_string(), positions.get(entry_path).map(usize::to_string).unwrap_or_default()),
+            ("$ENTRY_TOTAL".to_string(), positions.len().to_string()),
+        ])
     );
-    // Generate the root index.html
-    let root_index_html_content = replace_placeholders(
-        &base_html,
-        &[
-            ("$CONTENT".to_string(), parsed_about_txt_content),
-            ("$TITLE".to_string(), project_name),
-            ("$NAVCLOUD".to_string(), "".to_string()),
-        ].iter().cloned().collect()
+    // After every substitution, so that assets named by placeholders are embedded too
+    let final_html_content = if config.embed_assets {
+        embed_assets_in_html(&final_html_content, permalink, &config.path(&config.static_dir), &config.path(&config.images_dir))
+    } else {
+        final_html_content
+    };
+    write_html_file(new_entry_dir.join("index.html"), &final_html_content)?;
+
+    if let Some(short_url) = &meta.short_url {
+        if let Some(other) = short_urls.get(short_url) {
+            let (first, second) = if other.as_str() < title { (other.clone(), title.to_string()) } else { (title.to_string(), other.clone()) };
+            return Err(IroniteError::ShortUrlConflict { short_url: short_url.clone(), first, second });
+        }
+        short_urls.insert(short_url.clone(), title.to_string());
+
+        let short_dir = public_dir.join(short_url);
+        create_directories(&short_dir)?;
+        let canonical_url = config.url(&index_link(permalink, config.pretty_urls));
+        write_html_file(short_dir.join("index.html"), &redirect_page(&canonical_url))?;
+    }
+    Ok(())
+}
+
+// Generate a page listing the entries of each tag at public/<tag>/index.html. Nested tags get nested
+// directories, public/programming/rust/index.html, and the page of a tag starts with links to the
+// tags directly under it
+pub fn generate_tag_pages(base_html: &str, config: &SiteConfig, tags_map: &HashMap<String, Vec<PathBuf>>, permalinks: &PermalinkMap) -> io::Result<()> {
+    generate_tag_pages_for(base_html, config, tags_map, permalinks, &sorted_tags(tags_map))
+}
+
+// Generate the pages of the given tags only, as generate_tag_pages does. The nav cloud and the child
+// tags still come from the whole tags_map
+pub fn generate_tag_pages_for(
+    base_html: &str,
+    config: &SiteConfig,
+    tags_map: &HashMap<String, Vec<PathBuf>>,
+    permalinks: &PermalinkMap,
+    only: &[String],
+) -> io::Result<()> {
+    let public_dir = config.path(&config.public_dir);
+
+    // Collect tags and sort them
+    let tags = sorted_tags(tags_map);
+    let cloud = cloud_tags(&tags, config);
+
+    // Iterate over sorted tags
+    for tag in tags.iter().filter(|tag| only.contains(tag)) {
+        if let Some(paths) = tags_map.get(tag) {
+            let slug = tag_slug(tag);
+            let root = root_prefix(&slug);
+            let tag_dir = public_dir.join(&slug);
+            create_directories(&tag_dir)?;
+
+            let mut tag_content = String::new();
+            let children = child_tags(tag, &tags);
+            if !children.is_empty() {
+                tag_content.push_str("<nav class=\"child-tags\">");
+                for child in children {
+                    let name = &child[tag.len() + 1..];
+                    let label = config.tag_label(child).rsplit('/').next().unwrap_or(name);
+                    tag_content.push_str(&format!("<a href=\"{}\">{}</a>", index_link(&tag_slug(name), config.pretty_urls), label));
+                }
+                tag_content.push_str("</nav>");
+            }
+            for path in listing_order(paths) {
+                let href = entry_href(&root, &path, permalinks, config.pretty_urls);
+                tag_content.push_str(&listing_link(&href, &listing_label(&path, config), is_pinned(&path), config));
+            }
+
+            let description = tag_description(config, tag)?;
+            let mut own = vec![
+                ("$CONTENT".to_string(), tag_content),
+                ("$TITLE".to_string(), config.tag_label(tag).to_string()),
+                ("$NAVCLOUD".to_string(), nav_cloud(&cloud, &root, config)),
+                ("$TAGS".to_string(), "".to_string()),
+                ("$BREADCRUMB".to_string(), "".to_string()),
+                ("$RELATED".to_string(), "".to_string()),
+                ("$UPDATED".to_string(), "".to_string()),
+                ("$DATE".to_string(), "".to_string()),
+            ];
+            // A site-wide $DESCRIPTION is kept for tags without a description of their own
+            if !description.is_empty() {
+                own.push(("$DESCRIPTION".to_string(), meta_description(&description)));
+            }
+            own.push(("$TAG_DESCRIPTION".to_string(), description));
+            let tag_html_content = replace_placeholders(base_html, &page_placeholders(config, PageKind::Tag, &own));
+            write_html_file(tag_dir.join("index.html"), &tag_html_content)?;
+        }
+    }
+    Ok(())
+}
+
+// Directory of the page of entries carrying all the given tags, relative to the public directory:
+// tags/<tag1>+<tag2>, with the tags sorted so that the URL does not depend on their order
+pub fn tag_intersection_path(tags: &[&str]) -> String {
+    let mut sorted: Vec<String> = tags.iter().map(|tag| tag_slug(tag)).collect();
+    sorted.sort();
+    sorted.dedup();
+    format!("tags/{}", sorted.join("+"))
+}
+
+// Write the page listing the entries that carry every one of the given tags, at
+// public/tags/<tag1>+<tag2>/index.html, titled "<tag1> ∩ <tag2>"
+pub fn generate_tag_intersection_page(tags: &[&str], tags_map: &HashMap<String, Vec<PathBuf>>, config: &SiteConfig, base_html: &str) -> io::Result<()> {
+    let permalinks = entry_permalinks(config).map_err(|e| io::Error::other(e.to_string()))?;
+    write_tag_intersection_page(tags, tags_map, config, base_html, &permalinks)
+}
+
+pub(crate) fn write_tag_intersection_page(
+    tags: &[&str],
+    tags_map: &HashMap<String, Vec<PathBuf>>,
+    config: &SiteConfig,
+    base_html: &str,
+    permalinks: &PermalinkMap,
+) -> io::Result<()> {
+    let mut sorted = tags.to_vec();
+    sorted.sort();
+    sorted.dedup();
+
+    // Entries of the first tag that every other tag lists too. Entry lists are sorted, so the result is too
+    let mut paths: Vec<&PathBuf> = sorted.first().and_then(|tag| tags_map.get(*tag)).map(|paths| paths.iter().collect()).unwrap_or_default();
+    for tag in sorted.iter().skip(1) {
+        let others = tags_map.get(*tag).map(Vec::as_slice).unwrap_or_default();
+        paths.retain(|path| others.contains(path));
+    }
+
+    let page_dir = tag_intersection_path(&sorted);
+    let root = root_prefix(&page_dir);
+    let mut content = String::new();
+    let paths: Vec<PathBuf> = paths.into_iter().cloned().collect();
+    for path in listing_order(&paths) {
+        let href = entry_href(&root, &path, permalinks, config.pretty_urls);
+        content.push_str(&listing_link(&href, &listing_label(&path, config), is_pinned(&path), config));
+    }
+
+    let html = replace_placeholders(
+        base_html,
+        &page_placeholders(config, PageKind::Tag, &[
+            ("$CONTENT".to_string(), content),
+            ("$TITLE".to_string(), sorted.iter().map(|tag| config.tag_label(tag)).collect::<Vec<_>>().join(" \u{2229} ")),
+            ("$NAVCLOUD".to_string(), nav_cloud(&cloud_tags(&sorted_tags(tags_map), config), &root, config)),
+            ("$TAGS".to_string(), "".to_string()),
+            ("$BREADCRUMB".to_string(), "".to_string()),
+            ("$RELATED".to_string(), "".to_string()),
+            ("$UPDATED".to_string(), "".to_string()),
+            ("$DATE".to_string(), "".to_string()),
+        ])
     );
-    write_html_file(public_dir.join("index.html"), &root_index_html_content)?;
-
-
-// Generate entries index.html
-let mut entries: Vec<_> = fs::read_dir(entries_dir.clone())?
-    .filter_map(Result::ok)  // Filter out any errors
-    .filter(|entry| entry.path().is_dir())  // Only include directories
-    .collect();
-
-// Sort the entries by their titles
-entries.sort_by(|a, b| {
-    a.path().file_name()
-        .unwrap_or_else(|| std::ffi::OsStr::new("Untitled"))
-        .cmp(&b.path().file_name().unwrap_or_else(|| std::ffi::OsStr::new("Untitled")))
-});
-
-let mut entries_index_content = String::new();
-for entry in entries {
-    let entry_path = entry.path();
-    let title = entry_path.file_name().unwrap().to_str().unwrap_or("Untitled");
-    let entry_link = format!("<a href=\"{}/index.html\">{}</a><br>", title, title);
-    entries_index_content.push_str(&entry_link);
-}
-
-
-    let entries_index_html_content = replace_placeholders(
-        &base_html,
-        &[
-            ("$CONTENT".to_string(), entries_index_content),
-            ("$TITLE".to_string(), "Entries".to_string()),
-	    ("$NAVCLOUD".to_string(), "".to_string()),
-        ].iter().cloned().collect()
+    let dir = config.path(&config.public_dir).join(&page_dir);
+    create_directories(&dir)?;
+    write_html_file(dir.join("index.html"), &html)
+}
+
+// Body of the 404 page when static/404.html does not exist
+pub const DEFAULT_NOT_FOUND_CONTENT: &str = "<p>The page you are looking for does not exist.</p>";
+
+// Generate public/404.html from static/404.html, or a default message, in the base template.
+// Hosts serve it for any missing path, so every link in it is made absolute
+pub fn generate_not_found_page(base_html: &str, config: &SiteConfig, tags: &[String]) -> io::Result<()> {
+    let body_path = config.path(&config.static_dir).join("404.html");
+    let content = if body_path.exists() {
+        read_file_to_string(&body_path)?
+    } else {
+        DEFAULT_NOT_FOUND_CONTENT.to_string()
+    };
+
+    // Without base_url the links stay root-relative here, and get path_prefix with every other page
+    let root = if config.base_url.is_empty() { "/".to_string() } else { config.url("") };
+    let html = replace_placeholders(
+        base_html,
+        &page_placeholders(config, PageKind::Other, &[
+            ("$CONTENT".to_string(), content),
+            ("$TITLE".to_string(), "Not found".to_string()),
+            ("$NAVCLOUD".to_string(), nav_cloud(&cloud_tags(tags, config), &root, config)),
+            ("$TAGS".to_string(), "".to_string()),
+            ("$BREADCRUMB".to_string(), "".to_string()),
+            ("$RELATED".to_string(), "".to_string()),
+            ("$UPDATED".to_string(), "".to_string()),
+            ("$DATE".to_string(), "".to_string()),
+        ])
     );
-    write_html_file(entries_dir.join("index.html"), &entries_index_html_content)?;
+    write_html_file(config.path(&config.public_dir).join("404.html"), &root_relative_links(&html, &root))
+}
 
-    Ok(())
+// Generate the site from the current directory, reading ironite.toml when present
+#[deprecated(note = "use Site::builder().build()?.generate() instead")]
+pub fn generate_site() -> Result<SiteStats, IroniteError> {
+    Site::builder().build()?.generate()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::retry::IoRetry;
+    use crate::test_util::{add_entry, env_lock, fixture_project, write, TempDir};
+    use std::time::{Duration, SystemTime};
+
+    fn set_mtime(path: &Path, seconds_ago: u64) {
+        let time = SystemTime::now() - Duration::from_secs(seconds_ago);
+        fs::File::open(path).unwrap().set_modified(time).unwrap();
+    }
+
+    #[test]
+    fn placeholders_are_replaced_in_one_pass() {
+        let placeholders: HashMap<String, String> = [
+            ("$TAG", "tag"),
+            ("$TAGS", "<a>$TAG</a>"),
+            ("$CONTENT", "costs $TITLE"),
+            ("$TITLE", "Title"),
+        ]
+        .iter()
+        .map(|(name, value)| (name.to_string(), value.to_string()))
+        .collect();
+
+        // The longest name wins, and replaced values are left as they are
+        let html = replace_placeholders("$TITLE: $CONTENT $TAGS $TAG $TITLES $", &placeholders);
+        assert_eq!(html, "Title: costs $TITLE <a>$TAG</a> tag TitleS $");
+        assert_eq!(replace_placeholders("$TITLE", &HashMap::new()), "$TITLE");
+
+        // A title quoting $CONTENT is not substituted again, whichever name is replaced first
+        let placeholders: HashMap<String, String> = [("$TITLE", "Using $CONTENT"), ("$CONTENT", "<p>Body</p>")]
+            .iter()
+            .map(|(name, value)| (name.to_string(), value.to_string()))
+            .collect();
+        assert_eq!(replace_placeholders("$CONTENT $TITLE", &placeholders), "<p>Body</p> Using $CONTENT");
+        assert_eq!(replace_placeholders("$TITLE $CONTENT", &placeholders), "Using $CONTENT <p>Body</p>");
+    }
+
+    #[test]
+    fn placeholders_next_to_each_other_and_text() {
+        let placeholders: HashMap<String, String> = [("$A", "1"), ("$AB", "2"), ("$B", "3"), ("", "never")]
+            .iter()
+            .map(|(name, value)| (name.to_string(), value.to_string()))
+            .collect();
+        assert_eq!(replace_placeholders("$A$B$AB$ABC", &placeholders), "1322C");
+        assert_eq!(replace_placeholders("é$Aé $ $", &placeholders), "é1é $ $");
+        assert_eq!(replace_placeholders("", &placeholders), "");
+        assert_eq!(replace_placeholders("no placeholders", &placeholders), "no placeholders");
+    }
+
+    #[test]
+    fn txt_to_html_makes_a_paragraph_per_line() {
+        let html = |text: &str| String::from_utf8(txt_to_html(text.as_bytes().to_vec())).unwrap();
+        assert_eq!(html(""), "");
+        assert_eq!(html("\n\n"), "");
+        assert_eq!(html("One line"), "<p>One line</p>");
+        assert_eq!(html("First\n\nSecond\n"), "<p>First</p><p>Second</p>");
+        assert_eq!(html("Windows\r\nlines\r\n"), "<p>Windows</p><p>lines</p>");
+        assert_eq!(html("AT&T <rocks>"), "<p>AT&amp;T &lt;rocks&gt;</p>");
+        assert_eq!(
+            html("Docs (https://example.com/?a=1&b=2) are at <https://example.com>."),
+            "<p>Docs (<a href=\"https://example.com/?a=1&amp;b=2\">https://example.com/?a=1&amp;b=2</a>) are at \
+             &lt;<a href=\"https://example.com\">https://example.com</a>&gt;.</p>"
+        );
+    }
+
+    #[test]
+    fn tag_description_prefers_html_then_markdown() {
+        let dir = TempDir::new("tag-description-files");
+        let config = SiteConfig { root: dir.path().to_path_buf(), ..SiteConfig::default() };
+        let tagdesc = dir.path().join(TAG_DESCRIPTIONS_DIR);
+        assert_eq!(tag_description(&config, "rust").unwrap(), "");
+        write(&tagdesc.join("rust.txt"), "Plain <text>\n");
+        assert_eq!(tag_description(&config, "rust").unwrap(), "<p>Plain &lt;text&gt;</p>");
+        fs::remove_file(tagdesc.join("rust.txt")).unwrap();
+        write(&tagdesc.join("rust.md"), "Posts about **Rust**, see [the book](https://doc.rust-lang.org/book/).\n");
+        assert_eq!(
+            tag_description(&config, "rust").unwrap(),
+            "<p>Posts about <strong>Rust</strong>, see <a href=\"https://doc.rust-lang.org/book/\">the book</a>.</p>"
+        );
+        write(&tagdesc.join("rust.html"), "<p>Explicit <em>HTML</em></p>\n");
+        assert_eq!(tag_description(&config, "rust").unwrap(), "<p>Explicit <em>HTML</em></p>");
+        write(&tagdesc.join("rust.txt"), "Plain <text>\n");
+        assert_eq!(tag_description(&config, "rust").unwrap(), "<p>Explicit <em>HTML</em></p>");
+        fs::remove_file(tagdesc.join("rust.txt")).unwrap();
+        fs::remove_file(tagdesc.join("rust.md")).unwrap();
+        assert_eq!(tag_description(&config, "rust").unwrap(), "<p>Explicit <em>HTML</em></p>");
+    }
+
+    #[test]
+    fn smart_punctuation_reaches_escaped_text() {
+        let dir = TempDir::new("smart-tag-description");
+        write(&dir.path().join(TAG_DESCRIPTIONS_DIR).join("rust.txt"), "It's \"fast\" -- really...");
+        let config = SiteConfig { root: dir.path().to_path_buf(), smart_punctuation: true, ..SiteConfig::default() };
+        assert_eq!(tag_description(&config, "rust").unwrap(), "<p>It\u{2019}s \u{201c}fast\u{201d} \u{2013} really\u{2026}</p>");
+    }
+
+    #[test]
+    fn get_tags_ignores_blank_lines_and_missing_files() {
+        let dir = TempDir::new("get-tags");
+        let path = dir.path().join("tags.txt");
+        assert!(get_tags(&path).is_empty());
+        write(&path, "");
+        assert!(get_tags(&path).is_empty());
+        write(&path, "\n  \t\n\r\nrust\r\n\n  web rust  \n");
+        assert_eq!(get_tags(&path), tag_set(&["rust", "web"]));
+    }
+
+    fn tag_set(tags: &[&str]) -> HashSet<String> {
+        tags.iter().map(|tag| tag.to_string()).collect()
+    }
+
+    #[test]
+    fn quoted_tags_keep_their_spaces() {
+        let dir = TempDir::new("quoted-tags");
+        let path = dir.path().join("tags.txt");
+        write(&path, "rust \"machine learning\"  \n\n'static   site'\tweb  \n\"  deep\n learning \" \n");
+        assert_eq!(get_tags(&path), tag_set(&["rust", "machine learning", "static site", "web", "deep learning"]));
+
+        // Files without quotes split on whitespace as before
+        write(&path, "rust web\n\nlinux   \n");
+        assert_eq!(get_tags(&path), tag_set(&["rust", "web", "linux"]));
+
+        assert_eq!(parse_tags("\"\" rust \"unclosed tag"), vec!["rust", "unclosed tag"]);
+        assert_eq!(tag_slug("machine learning"), "machine-learning");
+        assert_eq!(tag_slug("C++"), "C++");
+        assert_eq!(tag_slug("programming/machine learning"), "programming/machine-learning");
+        assert_eq!(tag_ancestors("programming/rust/async"), vec!["programming", "programming/rust"]);
+    }
+
+    #[test]
+    fn entry_tags_from_meta_toml_and_tags_txt() {
+        let dir = TempDir::new("entry-tags");
+        let entry = dir.path().join("entry");
+        fs::create_dir_all(&entry).unwrap();
+        assert!(get_entry_tags(&entry).is_empty());
+
+        write(&entry.join("meta.toml"), "tags = [\"rust\", \"web\"]\n");
+        assert_eq!(get_entry_tags(&entry), tag_set(&["rust", "web"]));
+
+        write(&entry.join("tags.txt"), "web linux");
+        assert_eq!(get_entry_tags(&entry), tag_set(&["rust", "web", "linux"]));
+
+        fs::remove_file(entry.join("meta.toml")).unwrap();
+        assert_eq!(get_entry_tags(&entry), tag_set(&["web", "linux"]));
+
+        // meta.toml without a tags key leaves tags.txt alone
+        write(&entry.join("meta.toml"), "title = \"Entry\"\n");
+        assert_eq!(get_entry_tags(&entry), tag_set(&["web", "linux"]));
+    }
+
+    #[test]
+    fn tags_map_includes_meta_toml_tags() {
+        let dir = TempDir::new("tags-meta-toml");
+        fixture_project(dir.path());
+        let third = dir.path().join("entries").join("third");
+        write(&third.join("content.html"), "<p>Third</p>");
+        write(&third.join("meta.toml"), "tags = [\"web\", \"notes\"]\n");
+
+        let tags_map = filter_entries_by_tag(&dir.path().join("entries"));
+        assert_eq!(tags_map["notes"], vec![third.clone()]);
+        assert_eq!(tags_map["web"].len(), 2);
+    }
+
+    #[test]
+    fn recursive_entries_are_found_at_any_depth() {
+        let dir = TempDir::new("recursive-entries");
+        fixture_project(dir.path());
+        let entries_dir = dir.path().join("entries");
+        add_entry(dir.path(), "rust/fearless-concurrency", "<p>Threads</p>", "rust");
+        add_entry(dir.path(), "web/frameworks/sveltekit-intro", "<p>Svelte</p>", "web");
+        // An entry's own subdirectories are not entries
+        write(&entries_dir.join("first").join("nested").join("content.html"), "<p>Not an entry</p>");
+        write(&entries_dir.join("rust").join("ownership").join("content.md"), "# Ownership\n");
+
+        let relative: Vec<_> = entry_dirs_recursive(&entries_dir)
+            .unwrap()
+            .iter()
+            .map(|path| path.strip_prefix(&entries_dir).unwrap().to_string_lossy().into_owned())
+            .collect();
+        assert_eq!(relative, vec!["first", "rust/fearless-concurrency", "rust/ownership", "second", "web/frameworks/sveltekit-intro"]);
+        assert_eq!(entry_section(&entries_dir, &entries_dir.join("web").join("frameworks").join("sveltekit-intro")), "web/frameworks");
+        assert_eq!(entry_section(&entries_dir, &entries_dir.join("first")), "");
+
+        assert_eq!(filter_entries_by_tag(&entries_dir)["rust"].len(), 2);
+        let tags_map = filter_entries_by_tag_recursive(&entries_dir);
+        assert_eq!(tags_map["rust"].len(), 3);
+        assert_eq!(tags_map["web"], vec![entries_dir.join("first"), entries_dir.join("web").join("frameworks").join("sveltekit-intro")]);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn symlinked_content_is_followed() {
+        use std::os::unix::fs::symlink;
+        let dir = TempDir::new("symlinked-content");
+        fixture_project(dir.path());
+        let entries_dir = dir.path().join("entries");
+        write(&dir.path().join("shared").join("terms.html"), "<p>Terms of service</p>");
+        for name in ["terms", "tos"] {
+            fs::create_dir_all(entries_dir.join(name)).unwrap();
+            symlink("../../shared/terms.html", entries_dir.join(name).join("content.html")).unwrap();
+        }
+
+        let config = SiteConfig { root: dir.path().to_path_buf(), ..SiteConfig::default() };
+        generate_entry_pages("$CONTENT", &config, &filter_entries_by_tag(&entries_dir), &UpdatedDates::new()).unwrap();
+        for name in ["terms", "tos"] {
+            let page = fs::read_to_string(dir.path().join("public").join("entries").join(name).join("index.html")).unwrap();
+            assert_eq!(page, "<p>Terms of service</p>");
+        }
+
+        fs::remove_dir_all(dir.path().join("public")).unwrap();
+        let config = SiteConfig { follow_symlinks: false, ..config };
+        assert!(published_entry_dirs(&config).unwrap().iter().all(|path| !has_symlinked_content(path)));
+        generate_entry_pages("$CONTENT", &config, &filter_entries_by_tag(&entries_dir), &UpdatedDates::new()).unwrap();
+        assert!(dir.path().join("public").join("entries").join("first").join("index.html").exists());
+        assert!(!dir.path().join("public").join("entries").join("tos").exists());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn symlink_loops_are_reported() {
+        use std::os::unix::fs::symlink;
+        let dir = TempDir::new("symlink-loop");
+        fixture_project(dir.path());
+        let entries_dir = dir.path().join("entries");
+        for (name, target) in [("a", "../b/content.html"), ("b", "../a/content.html")] {
+            fs::create_dir_all(entries_dir.join(name)).unwrap();
+            symlink(target, entries_dir.join(name).join("content.html")).unwrap();
+        }
+
+        let a = entries_dir.join("a").join("content.html");
+        match resolve_symlinks(&a) {
+            Err(IroniteError::SymlinkLoop(links)) => {
+                assert_eq!(links.len(), 3, "{:?}", links);
+                assert!(links[0].ends_with("a/content.html") && links[1].ends_with("b/content.html"), "{:?}", links);
+                assert_eq!(links[0], links[2]);
+            }
+            other => panic!("expected a symlink loop, got {:?}", other),
+        }
+
+        let config = SiteConfig { root: dir.path().to_path_buf(), ..SiteConfig::default() };
+        match generate_entry_pages("$CONTENT", &config, &filter_entries_by_tag(&entries_dir), &UpdatedDates::new()) {
+            Err(IroniteError::EntryErrors(failed)) => {
+                let names: Vec<&str> = failed.iter().map(|(name, _)| name.as_str()).collect();
+                assert_eq!(names, vec!["a", "b"]);
+                assert!(failed[0].1.to_string().starts_with("symlink loop: "), "{}", failed[0].1);
+            }
+            other => panic!("expected entry errors, got {:?}", other.map(|_| ())),
+        }
+    }
+
+    // An entry named bad\xffname, tagged rust and bad, next to the fixture entries
+    #[cfg(unix)]
+    fn add_non_utf8_entry(root: &Path) -> PathBuf {
+        use std::os::unix::ffi::OsStrExt;
+        let entry = root.join("entries").join(OsStr::from_bytes(b"bad\xffname"));
+        write(&entry.join("content.html"), "<p>Bad</p>");
+        write(&entry.join("tags.txt"), "rust bad");
+        entry
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn non_utf8_entries_are_skipped() {
+        let dir = TempDir::new("non-utf8-entries");
+        fixture_project(dir.path());
+        let bad = add_non_utf8_entry(dir.path());
+        let entries_dir = dir.path().join("entries");
+
+        let names: Vec<_> = entry_dirs(&entries_dir).unwrap().iter().map(|path| entry_name(path).unwrap().to_string()).collect();
+        assert_eq!(names, vec!["first", "second"]);
+        assert_eq!(skipped_entries(&entries_dir).unwrap(), vec![bad.to_string_lossy().into_owned()]);
+        assert_eq!(entries_sorted_by_date(&entries_dir).unwrap().len(), 2);
+
+        let config = SiteConfig { root: dir.path().to_path_buf(), ..SiteConfig::default() };
+        let tags_map = filter_entries_by_tag(&entries_dir);
+        generate_entry_pages("$CONTENT", &config, &tags_map, &UpdatedDates::new()).unwrap();
+        assert!(dir.path().join("public").join("entries").join("first").join("index.html").exists());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn tags_of_non_utf8_entries_are_left_out() {
+        let dir = TempDir::new("non-utf8-tags");
+        fixture_project(dir.path());
+        let bad = add_non_utf8_entry(dir.path());
+
+        // The tags file itself can still be read
+        assert_eq!(get_tags(&bad.join("tags.txt")), tag_set(&["rust", "bad"]));
+        let tags_map = filter_entries_by_tag(&dir.path().join("entries"));
+        assert!(!tags_map.contains_key("bad"));
+        assert_eq!(tags_map["rust"].len(), 2);
+    }
+
+    #[test]
+    fn tag_intersection_pages() {
+        let dir = TempDir::new("tag-intersection");
+        fixture_project(dir.path());
+        add_entry(dir.path(), "third", "", "web rust notes");
+        let tags_map = filter_entries_by_tag(&dir.path().join("entries"));
+        let config = SiteConfig { root: dir.path().to_path_buf(), ..SiteConfig::default() };
+        let base = "<title>$TITLE</title>$CONTENT";
+
+        generate_tag_intersection_page(&["web", "rust"], &tags_map, &config, base).unwrap();
+        let page = fs::read_to_string(dir.path().join("public").join("tags").join("rust+web").join("index.html")).unwrap();
+        assert_eq!(
+            page,
+            "<title>rust \u{2229} web</title><a href=\"../../entries/first/index.html\">first</a><br>\
+             <a href=\"../../entries/third/index.html\">third</a><br>"
+        );
+
+        generate_tag_intersection_page(&["notes", "web", "linux"], &tags_map, &config, base).unwrap();
+        let page = fs::read_to_string(dir.path().join("public").join("tags").join("linux+notes+web").join("index.html")).unwrap();
+        assert_eq!(page, "<title>linux \u{2229} notes \u{2229} web</title>");
+    }
+
+    #[test]
+    fn tag_counts_and_most_used_tags() {
+        let dir = TempDir::new("tag-count");
+        add_entry(dir.path(), "a", "", "rust web rust");
+        add_entry(dir.path(), "b", "", "rust linux");
+        add_entry(dir.path(), "c", "", "web linux notes");
+        add_entry(dir.path(), "d", "", "rust");
+        let entries_dir = dir.path().join("entries");
+
+        let counts = tag_count(&entries_dir);
+        assert_eq!(counts.len(), 4);
+        assert_eq!((counts["rust"], counts["web"], counts["linux"], counts["notes"]), (3, 2, 2, 1));
+
+        let top = most_used_tags(&entries_dir, 3);
+        assert_eq!(top, vec![("rust".to_string(), 3), ("linux".to_string(), 2), ("web".to_string(), 2)]);
+        assert_eq!(most_used_tags(&entries_dir, 10).len(), 4);
+        assert!(most_used_tags(&dir.path().join("missing"), 3).is_empty());
+    }
+
+    #[test]
+    fn excluded_tags_and_their_nested_tags_are_dropped() {
+        let mut tags_map = HashMap::new();
+        tags_map.insert("todo".to_string(), vec![PathBuf::from("a"), PathBuf::from("b")]);
+        tags_map.insert("todo/later".to_string(), vec![PathBuf::from("b")]);
+        tags_map.insert("todos".to_string(), vec![PathBuf::from("c")]);
+        tags_map.insert("rust".to_string(), vec![PathBuf::from("a")]);
+
+        let (kept, suppressed) = exclude_tags(&tags_map, &["todo".to_string()]);
+        assert_eq!(sorted_tags(&kept), vec!["rust", "todos"]);
+        assert_eq!(suppressed, 3);
+        assert_eq!(exclude_tags(&tags_map, &[]).0, tags_map);
+    }
+
+    #[test]
+    fn normalized_tags_are_merged_and_reported() {
+        let dir = TempDir::new("normalize-tags");
+        add_entry(dir.path(), "a", "", "Rust web");
+        add_entry(dir.path(), "b", "", "rust RUST");
+        add_entry(dir.path(), "c", "", "RUST Web linux");
+        let entries_dir = dir.path().join("entries");
+
+        let (tags_map, merges) = normalize_tags(&filter_entries_by_tag(&entries_dir), &str::to_lowercase);
+        assert_eq!(sorted_tags(&tags_map), vec!["linux", "rust", "web"]);
+        assert_eq!(tags_map["rust"], vec![entries_dir.join("a"), entries_dir.join("b"), entries_dir.join("c")]);
+        assert_eq!(
+            merges.iter().map(TagMerge::to_string).collect::<Vec<_>>(),
+            vec![
+                "tags RUST (b, c), Rust (a), rust (b) were merged into rust",
+                "tags Web (c), web (a) were merged into web",
+            ]
+        );
+
+        // A caller-provided normalizer is used as it is
+        let (tags_map, merges) = normalize_tags(&filter_entries_by_tag(&entries_dir), &|tag: &str| tag.to_uppercase());
+        assert_eq!(sorted_tags(&tags_map), vec!["LINUX", "RUST", "WEB"]);
+        assert_eq!(merges.len(), 2);
+    }
+
+    #[test]
+    fn entries_of_each_tag_are_sorted() {
+        let dir = TempDir::new("tags-sorted");
+        for name in ["c", "a", "d", "b"] {
+            add_entry(dir.path(), name, "", "rust");
+        }
+        // The tag is listed twice, in tags.txt and the front matter
+        write(&dir.path().join("entries").join("a").join("content.html"), "---\ntags: [rust]\n---\n");
+
+        let tags_map = filter_entries_by_tag(&dir.path().join("entries"));
+        let names: Vec<_> = tags_map["rust"].iter().map(|path| path.file_name().unwrap().to_str().unwrap()).collect();
+        assert_eq!(names, vec!["a", "b", "c", "d"]);
+    }
+
+    #[test]
+    fn file_times_are_clamped_to_source_date_epoch() {
+        let _env = env_lock();
+        let dir = TempDir::new("clamped-mtime");
+        fixture_project(dir.path());
+        let tags_map = filter_entries_by_tag(&dir.path().join("entries"));
+        let config = SiteConfig { root: dir.path().to_path_buf(), ..SiteConfig::default() };
+
+        std::env::set_var("SOURCE_DATE_EPOCH", "1700000000");
+        let result = generate_entry_pages("$UPDATED", &config, &tags_map, &UpdatedDates::new());
+        std::env::remove_var("SOURCE_DATE_EPOCH");
+        result.unwrap();
+        let page = fs::read_to_string(dir.path().join("public").join("entries").join("first").join("index.html")).unwrap();
+        assert_eq!(page, "2023-11-14");
+    }
+
+    #[test]
+    fn entry_files_are_copied_and_listed() {
+        let dir = TempDir::new("entry-files");
+        fixture_project(dir.path());
+        let entries_dir = dir.path().join("entries");
+        write(&entries_dir.join("first").join("files").join("paper.pdf"), "pdf");
+        write(&entries_dir.join("first").join("files").join("data").join("a&b.csv"), "csv");
+        let config = SiteConfig { root: dir.path().to_path_buf(), ..SiteConfig::default() };
+        generate_entry_pages("$FILES_LIST", &config, &filter_entries_by_tag(&entries_dir), &UpdatedDates::new()).unwrap();
+
+        let first_dir = dir.path().join("public").join("entries").join("first");
+        assert_eq!(fs::read_to_string(first_dir.join("files").join("paper.pdf")).unwrap(), "pdf");
+        assert_eq!(fs::read_to_string(first_dir.join("files").join("data").join("a&b.csv")).unwrap(), "csv");
+        assert_eq!(
+            fs::read_to_string(first_dir.join("index.html")).unwrap(),
+            "<ul><li><a href=\"files/data/a&amp;b.csv\">data/a&amp;b.csv</a></li><li><a href=\"files/paper.pdf\">paper.pdf</a></li></ul>"
+        );
+        // Without files/, the list is empty
+        assert_eq!(fs::read_to_string(dir.path().join("public").join("entries").join("second").join("index.html")).unwrap(), "");
+    }
+
+    #[test]
+    fn entries_sorted_by_date_puts_dated_entries_first() {
+        let _env = env_lock();
+        let dir = TempDir::new("sorted-by-date");
+        add_entry(dir.path(), "undated_old", "", "");
+        add_entry(dir.path(), "undated_new", "", "");
+        add_entry(dir.path(), "dated", "", "");
+        let entries_dir = dir.path().join("entries");
+        write(&entries_dir.join("dated").join("meta.toml"), "date = 2001-01-01\n");
+        set_mtime(&entries_dir.join("undated_old"), 3600);
+        set_mtime(&entries_dir.join("undated_new"), 60);
+        set_mtime(&entries_dir.join("dated"), 7200);
+
+        let sorted = entries_sorted_by_date(&entries_dir).unwrap();
+        let names: Vec<_> = sorted.iter().map(|path| path.file_name().unwrap().to_str().unwrap()).collect();
+        assert_eq!(names, ["dated", "undated_new", "undated_old"]);
+    }
+
+    #[test]
+    fn related_entries_by_shared_tags() {
+        let entry = |name: &str| PathBuf::from("entries").join(name);
+        let mut tags_map = HashMap::new();
+        tags_map.insert("rust".to_string(), vec![entry("a"), entry("b"), entry("c"), entry("d")]);
+        tags_map.insert("web".to_string(), vec![entry("a"), entry("c")]);
+        tags_map.insert("misc".to_string(), vec![entry("e")]);
+        tags_map.insert(UNTAGGED.to_string(), vec![entry("f"), entry("g")]);
+
+        assert_eq!(related_entries(&entry("a"), &tags_map, UNTAGGED, 5), [entry("c"), entry("b"), entry("d")]);
+        assert_eq!(related_entries(&entry("a"), &tags_map, UNTAGGED, 2), [entry("c"), entry("b")]);
+        assert!(related_entries(&entry("untagged"), &tags_map, UNTAGGED, 5).is_empty());
+        // Having no tag is not something entries share
+        assert!(related_entries(&entry("f"), &tags_map, UNTAGGED, 5).is_empty());
+    }
+
+    #[test]
+    fn entries_sorted_by_date_with_no_entries() {
+        let dir = TempDir::new("sorted-by-date-empty");
+        assert!(entries_sorted_by_date(dir.path()).unwrap().is_empty());
+    }
+
+    #[test]
+    fn breadcrumb_for_top_level_entry() {
+        assert_eq!(breadcrumb(Path::new("entries/my_post"), false), "<a href=\"../../index.html\">Home</a> &gt; my_post");
+    }
+
+    #[test]
+    fn breadcrumb_for_nested_entry() {
+        assert_eq!(
+            breadcrumb(Path::new("entries/2023/my_post"), false),
+            "<a href=\"../../../index.html\">Home</a> &gt; <a href=\"../index.html\">2023</a> &gt; my_post"
+        );
+    }
+
+    #[test]
+    fn index_link_styles() {
+        assert_eq!(index_link("rust", false), "rust/index.html");
+        assert_eq!(index_link("../../", false), "../../index.html");
+        assert_eq!(index_link("", false), "index.html");
+        assert_eq!(index_link("../entries/my-post", true), "../entries/my-post/");
+        assert_eq!(index_link("../../", true), "../../");
+        assert_eq!(index_link("", true), "./");
+    }
+
+
+    #[test]
+    fn short_url_writes_redirect_page() {
+        let dir = TempDir::new("short-url");
+        fixture_project(dir.path());
+        write(&dir.path().join("entries").join("first").join("meta.toml"), "short_url = \"r1\"\n");
+        let config = SiteConfig { root: dir.path().to_path_buf(), base_url: "https://example.com".to_string(), ..SiteConfig::default() };
+        let tags_map = filter_entries_by_tag(&dir.path().join("entries"));
+
+        generate_entry_pages("$CONTENT", &config, &tags_map, &UpdatedDates::new()).unwrap();
+
+        let page = fs::read_to_string(dir.path().join("public").join("r1").join("index.html")).unwrap();
+        assert!(page.contains("<meta http-equiv=\"refresh\" content=\"0; url=https://example.com/entries/first/index.html\">"));
+        assert!(page.contains("<link rel=\"canonical\" href=\"https://example.com/entries/first/index.html\">"));
+        assert!(page.contains("<a href=\"https://example.com/entries/first/index.html\">"));
+    }
+
+    #[test]
+    fn entry_pages_know_their_position_in_the_index() {
+        let dir = TempDir::new("entry-index");
+        fixture_project(dir.path());
+        add_entry(dir.path(), "third", "<p>Third</p>", "web");
+        let entries_dir = dir.path().join("entries");
+        write(&entries_dir.join("first").join("meta.toml"), "date = 2024-01-01\n");
+        write(&entries_dir.join("second").join("meta.toml"), "date = 2024-03-01\n");
+        write(&entries_dir.join("third").join("meta.toml"), "date = 2024-02-01\n");
+        let config = SiteConfig { root: dir.path().to_path_buf(), index_sort: IndexSort::Newest, ..SiteConfig::default() };
+        let tags_map = filter_entries_by_tag(&entries_dir);
+
+        generate_entry_pages("$ENTRY_INDEX of $ENTRY_TOTAL", &config, &tags_map, &UpdatedDates::new()).unwrap();
+        let page = |name: &str| fs::read_to_string(dir.path().join("public").join("entries").join(name).join("index.html")).unwrap();
+        assert_eq!([page("second"), page("third"), page("first")], ["1 of 3", "2 of 3", "3 of 3"]);
+
+        fs::remove_dir_all(entries_dir.join("second")).unwrap();
+        fs::remove_dir_all(entries_dir.join("third")).unwrap();
+        generate_entry_pages("$ENTRY_INDEX of $ENTRY_TOTAL", &config, &tags_map, &UpdatedDates::new()).unwrap();
+        assert_eq!(page("first"), "1 of 1");
+    }
+
+    #[test]
+    fn short_url_conflict_names_both_entries() {
+        let dir = TempDir::new("short-url-conflict");
+        fixture_project(dir.path());
+        write(&dir.path().join("entries").join("first").join("meta.toml"), "short_url = \"r1\"\n");
+        write(&dir.path().join("entries").join("second").join("meta.toml"), "short_url = \"r1\"\n");
+        let config = SiteConfig { root: dir.path().to_path_buf(), ..SiteConfig::default() };
+        let tags_map = filter_entries_by_tag(&dir.path().join("entries"));
+
+        match generate_entry_pages("$CONTENT", &config, &tags_map, &UpdatedDates::new()) {
+            Err(IroniteError::ShortUrlConflict { short_url, first, second }) => {
+                assert_eq!((short_url.as_str(), first.as_str(), second.as_str()), ("r1", "first", "second"));
+            }
+            other => panic!("expected a short URL conflict, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn updated_from_content_modification_time() {
+        let _env = env_lock();
+        let dir = TempDir::new("entry-updated");
+        fixture_project(dir.path());
+        let content_path = dir.path().join("entries").join("first").join("content.html");
+        let modified = SystemTime::UNIX_EPOCH + Duration::from_secs(Date::parse("2024-03-05T10:00:00Z").unwrap().to_unix() as u64);
+        fs::File::open(&content_path).unwrap().set_modified(modified).unwrap();
+        let tags_map = filter_entries_by_tag(&dir.path().join("entries"));
+        let config = SiteConfig { root: dir.path().to_path_buf(), date_format: "%e %B %Y".to_string(), ..SiteConfig::default() };
+
+        generate_entry_pages("<p>Updated on $UPDATED</p>", &config, &tags_map, &UpdatedDates::new()).unwrap();
+        let page = fs::read_to_string(dir.path().join("public").join("entries").join("first").join("index.html")).unwrap();
+        assert_eq!(page, "<p>Updated on 5 March 2024</p>");
+    }
+
+    #[test]
+    fn date_in_configured_format_and_locale() {
+        let dir = TempDir::new("entry-date");
+        fixture_project(dir.path());
+        write(&dir.path().join("entries").join("first").join("meta.toml"), "date = 2024-03-15\n");
+        let tags_map = filter_entries_by_tag(&dir.path().join("entries"));
+        let config = SiteConfig {
+            root: dir.path().to_path_buf(),
+            date_format: "%A %e %B %Y".to_string(),
+            date_locale: "fr".to_string(),
+            ..SiteConfig::default()
+        };
+
+        generate_entry_pages("[$DATE]", &config, &tags_map, &UpdatedDates::new()).unwrap();
+        let public = dir.path().join("public").join("entries");
+        assert_eq!(fs::read_to_string(public.join("first").join("index.html")).unwrap(), "[vendredi 15 mars 2024]");
+        // No date, nothing to show
+        assert_eq!(fs::read_to_string(public.join("second").join("index.html")).unwrap(), "[]");
+    }
+
+    #[test]
+    fn write_bytes_file_keeps_binary_content() {
+        let dir = TempDir::new("write-bytes");
+        let png: Vec<u8> = vec![0x89, b'P', b'N', b'G', 0x0d, 0x0a, 0x1a, 0x0a, 0xff, 0x00, 0xfe];
+        let path = dir.path().join("image.png");
+        write_bytes_file(&path, &png).unwrap();
+        assert_eq!(fs::read(&path).unwrap(), png);
+
+        // Overwriting leaves no temporary file behind
+        write_html_file(&path, "<p>Now text</p>").unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(), "<p>Now text</p>");
+        assert_eq!(fs::read_dir(dir.path()).unwrap().count(), 1);
+        assert!(write_bytes_file(dir.path().join("missing").join("page.html"), b"x").is_err());
+    }
+
+    #[test]
+    fn read_with_limit_accepts_exactly_max_bytes() {
+        let dir = TempDir::new("read-limit");
+        write(&dir.path().join("exact.html"), &"a".repeat(64));
+        write(&dir.path().join("over.html"), &"a".repeat(65));
+
+        assert_eq!(read_file_to_string_with_limit(dir.path().join("exact.html"), 64).unwrap().len(), 64);
+        let error = read_file_to_string_with_limit(dir.path().join("over.html"), 64).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::FileTooLarge);
+    }
+
+    #[test]
+    fn oversized_content_fails_its_entry() {
+        let dir = TempDir::new("entry-too-large");
+        fixture_project(dir.path());
+        let tags_map = filter_entries_by_tag(&dir.path().join("entries"));
+        // "<p>First entry</p>" is 18 bytes, "<p>Second entry</p>" 19
+        let config = SiteConfig { root: dir.path().to_path_buf(), max_file_size: 18, ..SiteConfig::default() };
+
+        match generate_entry_pages("$CONTENT", &config, &tags_map, &UpdatedDates::new()) {
+            Err(IroniteError::EntryErrors(errors)) => {
+                assert_eq!(errors.len(), 1);
+                assert_eq!(errors[0].0, "second");
+                assert!(matches!(&errors[0].1, IroniteError::Io(e) if e.kind() == io::ErrorKind::FileTooLarge));
+            }
+            other => panic!("expected entry errors, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn failed_entries_are_reported_together() {
+        let dir = TempDir::new("entry-errors");
+        fixture_project(dir.path());
+        add_entry(dir.path(), "third", "<p>Third entry</p>", "rust");
+        // Entry pages cannot be written where a file stands in for their directory
+        write(&dir.path().join("public").join("entries").join("first"), "");
+        write(&dir.path().join("public").join("entries").join("third"), "");
+        let tags_map = filter_entries_by_tag(&dir.path().join("entries"));
+
+        let config = SiteConfig { root: dir.path().to_path_buf(), ..SiteConfig::default() };
+        match generate_entry_pages("$CONTENT", &config, &tags_map, &UpdatedDates::new()) {
+            Err(IroniteError::EntryErrors(errors)) => {
+                let entries: Vec<&str> = errors.iter().map(|(entry, _)| entry.as_str()).collect();
+                assert_eq!(entries, vec!["first", "third"]);
+            }
+            other => panic!("expected entry errors, got {:?}", other),
+        }
+        // The entry in between was still generated
+        assert!(dir.path().join("public").join("entries").join("second").join("index.html").exists());
+
+        let config = SiteConfig { root: dir.path().to_path_buf(), fail_fast: true, ..SiteConfig::default() };
+        assert!(matches!(generate_entry_pages("$CONTENT", &config, &tags_map, &UpdatedDates::new()), Err(IroniteError::Io(_))));
+    }
+
+    #[test]
+    fn unreadable_metadata_is_reported_for_every_entry() {
+        let dir = TempDir::new("entry-meta-errors");
+        fixture_project(dir.path());
+        write(&dir.path().join("entries").join("first").join("meta.toml"), "title = 1\n");
+        write(&dir.path().join("entries").join("second").join("meta.toml"), "date = \"someday\"\n");
+        let config = SiteConfig { root: dir.path().to_path_buf(), ..SiteConfig::default() };
+
+        match entry_permalinks(&config) {
+            Err(error @ IroniteError::EntryErrors(_)) => {
+                let message = error.to_string();
+                assert!(message.starts_with("2 entries failed"), "{}", message);
+                assert!(message.contains("\n  first: ") && message.contains("\n  second: "), "{}", message);
+            }
+            other => panic!("expected entry errors, got {:?}", other),
+        }
+    }
+
+
+    #[test]
+    fn copy_with_progress_reports_every_file() {
+        let dir = TempDir::new("copy-progress");
+        let source = dir.path().join("images");
+        write(&source.join("a.png"), "12345");
+        write(&source.join("nested").join("b.png"), "123");
+        write(&source.join("nested").join("deeper").join("c.txt"), "");
+
+        let seen = std::cell::RefCell::new(Vec::new());
+        let stats = copy_directory_with_progress(&source, &dir.path().join("copy"), |path, bytes| {
+            seen.borrow_mut().push((path.strip_prefix(&source).unwrap().to_path_buf(), bytes));
+        })
+        .unwrap();
+
+        let mut seen = seen.into_inner();
+        seen.sort();
+        assert_eq!(
+            seen,
+            vec![
+                (PathBuf::from("a.png"), 5),
+                (PathBuf::from("nested/b.png"), 3),
+                (PathBuf::from("nested/deeper/c.txt"), 0),
+            ]
+        );
+        assert_eq!(stats, CopyStats { files_copied: 3, bytes_copied: 8, skipped: 0 });
+        assert_eq!(fs::read_to_string(dir.path().join("copy").join("nested").join("b.png")).unwrap(), "123");
+
+        // Excluded files are neither reported nor counted, and excluded directories are not entered
+        let seen = std::cell::RefCell::new(Vec::new());
+        let destination = dir.path().join("filtered");
+        let stats = copy_directory_excluding(&source, &destination, &["*.txt", "nested/b*"], |path, _| {
+            seen.borrow_mut().push(path.strip_prefix(&source).unwrap().to_path_buf());
+        })
+        .unwrap();
+        assert_eq!(seen.into_inner(), vec![PathBuf::from("a.png")]);
+        assert_eq!(stats, CopyStats { files_copied: 1, bytes_copied: 5, skipped: 0 });
+        assert!(!destination.join("nested").join("b.png").exists() && !destination.join("nested").join("deeper").join("c.txt").exists());
+        let stats = copy_directory_excluding(&source, &dir.path().join("no-nested"), &["nested"], |_, _| {}).unwrap();
+        assert_eq!(stats, CopyStats { files_copied: 1, bytes_copied: 5, skipped: 0 });
+        assert!(!dir.path().join("no-nested").join("nested").exists());
+    }
+
+    #[test]
+    fn parallel_copy_matches_the_serial_one() {
+        let dir = TempDir::new("copy-parallel");
+        let source = dir.path().join("images");
+        for i in 0..20 {
+            write(&source.join(format!("dir{}", i % 3)).join(format!("{}.png", i)), &"x".repeat(i));
+        }
+
+        let destination = dir.path().join("public");
+        let first = copy_directory_parallel(&source, &destination, 4).unwrap();
+        assert_eq!(first, CopyStats { files_copied: 20, bytes_copied: 190, skipped: 0 });
+        assert_eq!(fs::read_to_string(destination.join("dir1").join("7.png")).unwrap(), "xxxxxxx");
+        let second = copy_directory_parallel(&source, &destination, 4).unwrap();
+        assert_eq!(second, CopyStats { files_copied: 0, bytes_copied: 0, skipped: 20 });
+
+        let error = copy_directory_parallel(&dir.path().join("missing"), &destination, 4).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::NotFound);
+
+        // Workers retry with the policy of the build that started them
+        let files: Vec<(PathBuf, PathBuf)> = (0..8).map(|i| (source.join(format!("{}.png", i)), dir.path().join("retried").join(format!("{}.png", i)))).collect();
+        let attempts = Mutex::new(HashMap::new());
+        let flaky_copy = |source: &Path, _: &Path| {
+            let mut attempts = attempts.lock().unwrap();
+            let attempt = attempts.entry(source.to_path_buf()).or_insert(0);
+            *attempt += 1;
+            if *attempt == 1 { Err(io::Error::new(io::ErrorKind::WouldBlock, "busy")) } else { Ok(1) }
+        };
+        let retry = IoRetry { count: 1, delay: Duration::from_millis(1) };
+        let stats = with_io_retry(retry, || copy_files_parallel(&files, 4, flaky_copy)).unwrap();
+        assert_eq!(stats, CopyStats { files_copied: 8, bytes_copied: 8, skipped: 0 });
+        attempts.lock().unwrap().clear();
+        assert_eq!(copy_files_parallel(&files, 4, flaky_copy).unwrap_err().kind(), io::ErrorKind::WouldBlock);
+    }
+
+    #[test]
+    fn copy_incremental_skips_newer_destination() {
+        let dir = TempDir::new("copy-incremental");
+        let source = dir.path().join("static");
+        let destination = dir.path().join("public");
+        write(&source.join("style.css"), "body {}");
+        write(&source.join("fonts").join("a.woff"), "font");
+
+        let first = copy_directory_incremental(&source, &destination).unwrap();
+        assert_eq!(first, CopyStats { files_copied: 2, bytes_copied: 11, skipped: 0 });
+
+        // The copy is newer than its source, the source of the other file changed since
+        set_mtime(&source.join("style.css"), 3600);
+        set_mtime(&destination.join("style.css"), 60);
+        set_mtime(&destination.join("fonts").join("a.woff"), 3600);
+        let second = copy_directory_incremental(&source, &destination).unwrap();
+        assert_eq!(second, CopyStats { files_copied: 1, bytes_copied: 4, skipped: 1 });
+
+        write(&source.join("style.css"), "body { margin: 0 }");
+        set_mtime(&destination.join("style.css"), 3600);
+        copy_directory_incremental(&source, &destination).unwrap();
+        assert_eq!(fs::read_to_string(destination.join("style.css")).unwrap(), "body { margin: 0 }");
+    }
 }
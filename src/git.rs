@@ -0,0 +1,128 @@
+use std::collections::HashMap;
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::process::Command;
+
+use crate::config::{file_date, SiteConfig};
+use crate::date::Date;
+use crate::generator::{content_file, entry_name, entry_section, site_entry_dirs};
+use crate::toml::invalid_data;
+
+// Last change of each entry, by entry directory name
+pub type UpdatedDates = HashMap<String, Date>;
+
+// Date of the last commit touching each directory under entries_dir (relative to root), by path
+// relative to entries_dir ("first", or "rust" and "rust/fearless-concurrency" for nested entries),
+// from a single git log over the whole directory. Directories without commits are left out. Fails
+// when git is not installed or root is not in a git repository
+pub fn git_commit_dates(root: &Path, entries_dir: &Path) -> io::Result<UpdatedDates> {
+    let output = Command::new("git")
+        .arg("-C")
+        .arg(root)
+        .args(["-c", "core.quotePath=false", "log", "--format=%x00%cI", "--name-only", "--relative", "--"])
+        .arg(entries_dir)
+        .output()?;
+    if !output.status.success() {
+        return Err(io::Error::other(String::from_utf8_lossy(&output.stderr).trim().to_string()));
+    }
+    parse_git_log(&String::from_utf8_lossy(&output.stdout), entries_dir)
+}
+
+// Read the output of git log --format=%x00%cI --name-only: a NUL and the commit date, then the
+// files it changed. Commits come newest first, so the first date seen for a directory is its last change
+fn parse_git_log(log: &str, entries_dir: &Path) -> io::Result<UpdatedDates> {
+    let mut dates = UpdatedDates::new();
+    let mut commit_date = None;
+    for line in log.lines() {
+        if let Some(date) = line.strip_prefix('\0') {
+            let date = Date::parse(date).ok_or_else(|| invalid_data(format!("git log: invalid date \"{}\"", date)))?;
+            commit_date = Some(date);
+            continue;
+        }
+        let (Some(date), Ok(relative)) = (commit_date, Path::new(line).strip_prefix(entries_dir)) else {
+            continue;
+        };
+        // Files directly in entries/ belong to no entry
+        if relative.components().count() < 2 {
+            continue;
+        }
+        for dir in relative.ancestors().skip(1) {
+            if dir.as_os_str().is_empty() {
+                break;
+            }
+            dates.entry(dir.to_string_lossy().replace('\\', "/")).or_insert(date);
+        }
+    }
+    Ok(dates)
+}
+
+// Last change of every entry: the modification time of its content.html, or with git_dates the date
+// of the last commit touching the entry. Without git, a warning is added once and modification times
+// are used for every entry
+pub fn entry_updated_dates(config: &SiteConfig, warnings: &mut Vec<String>) -> io::Result<UpdatedDates> {
+    let entries_dir = config.path(&config.entries_dir);
+    let entry_paths = site_entry_dirs(config)?;
+    let mut dates = UpdatedDates::new();
+    for entry_path in &entry_paths {
+        let modified = fs::metadata(content_file(entry_path, config)).and_then(|metadata| metadata.modified());
+        if let (Ok(modified), Some(slug)) = (modified, entry_name(entry_path)) {
+            dates.insert(slug.to_string(), file_date(modified));
+        }
+    }
+
+    if config.git_dates {
+        match git_commit_dates(&config.root, &config.entries_dir) {
+            Ok(commit_dates) => {
+                // Entries never committed keep their modification time
+                for entry_path in &entry_paths {
+                    let Some(slug) = entry_name(entry_path) else { continue };
+                    let section = entry_section(&entries_dir, entry_path);
+                    let relative = if section.is_empty() { slug.to_string() } else { format!("{}/{}", section, slug) };
+                    if let (Some(date), Some(updated)) = (commit_dates.get(&relative), dates.get_mut(slug)) {
+                        *updated = *date;
+                    }
+                }
+            }
+            Err(e) => warnings.push(format!("git_dates: cannot read the git history ({}), using file modification times", e)),
+        }
+    }
+    Ok(dates)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::{fixture_project, TempDir};
+
+    #[test]
+    fn last_commit_wins_per_entry() {
+        let log = "\x002024-03-20T10:00:00+01:00\n\nentries/first/content.html\nentries/index.txt\n\
+                   \x002024-03-10T10:00:00Z\n\nentries/first/meta.toml\nentries/second/content.html\nstatic/base.html\n";
+        let dates = parse_git_log(log, Path::new("entries")).unwrap();
+        assert_eq!(dates.len(), 2);
+        assert_eq!(dates["first"].to_rfc3339(), "2024-03-20T09:00:00Z");
+        assert_eq!(dates["second"].to_rfc3339(), "2024-03-10T10:00:00Z");
+    }
+
+    #[test]
+    fn nested_entries_are_dated_by_their_path() {
+        let log = "\x002024-03-20T10:00:00Z\n\nentries/rust/threads/content.html\n";
+        let dates = parse_git_log(log, Path::new("entries")).unwrap();
+        assert_eq!(dates.len(), 2);
+        assert_eq!(dates["rust/threads"].to_rfc3339(), "2024-03-20T10:00:00Z");
+        assert_eq!(dates["rust"].to_rfc3339(), "2024-03-20T10:00:00Z");
+    }
+
+    #[test]
+    fn outside_git_falls_back_to_modification_times_with_one_warning() {
+        let dir = TempDir::new("git-dates");
+        fixture_project(dir.path());
+        let config = SiteConfig { root: dir.path().to_path_buf(), git_dates: true, ..SiteConfig::default() };
+
+        let mut warnings = Vec::new();
+        let dates = entry_updated_dates(&config, &mut warnings).unwrap();
+        assert_eq!(dates.len(), 2);
+        assert_eq!(warnings.len(), 1, "{:?}", warnings);
+    }
+}
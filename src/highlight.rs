@@ -0,0 +1,327 @@
+use crate::html::{escape_html, tag_attributes};
+
+// Stylesheet for the classes of highlight_code_blocks, written to public/static/ unless static/ has its own
+pub const HIGHLIGHT_CSS_FILE: &str = "highlight.css";
+
+pub const HIGHLIGHT_CSS: &str = "\
+.hl-keyword { color: #8839ef; font-weight: bold; }
+.hl-literal { color: #d20f39; }
+.hl-number { color: #fe640b; }
+.hl-string { color: #40a02b; }
+.hl-comment { color: #7c7f93; font-style: italic; }
+";
+
+// What the highlighter knows of a language
+struct Language {
+    names: &'static [&'static str],
+    keywords: &'static [&'static str],
+    literals: &'static [&'static str],
+    line_comments: &'static [&'static str],
+    block_comment: Option<(&'static str, &'static str)>,
+    quotes: &'static [char],
+    // ' starts a char literal only when closed right after one character or escape, as in Rust
+    // where it is otherwise a lifetime
+    char_literals: bool,
+}
+
+const LANGUAGES: &[Language] = &[
+    Language {
+        names: &["rust", "rs"],
+        keywords: &[
+            "as", "async", "await", "break", "const", "continue", "crate", "dyn", "else", "enum", "extern", "fn", "for", "if", "impl", "in",
+            "let", "loop", "match", "mod", "move", "mut", "pub", "ref", "return", "self", "Self", "static", "struct", "super", "trait",
+            "type", "unsafe", "use", "where", "while",
+        ],
+        literals: &["true", "false", "None", "Some", "Ok", "Err"],
+        line_comments: &["//"],
+        block_comment: Some(("/*", "*/")),
+        quotes: &['"'],
+        char_literals: true,
+    },
+    Language {
+        names: &["python", "py"],
+        keywords: &[
+            "and", "as", "assert", "async", "await", "break", "class", "continue", "def", "del", "elif", "else", "except", "finally", "for",
+            "from", "global", "if", "import", "in", "is", "lambda", "nonlocal", "not", "or", "pass", "raise", "return", "try", "while",
+            "with", "yield",
+        ],
+        literals: &["True", "False", "None"],
+        line_comments: &["#"],
+        block_comment: None,
+        quotes: &['"', '\''],
+        char_literals: false,
+    },
+    Language {
+        names: &["javascript", "js", "typescript", "ts"],
+        keywords: &[
+            "async", "await", "break", "case", "catch", "class", "const", "continue", "default", "delete", "do", "else", "export",
+            "extends", "finally", "for", "function", "if", "import", "in", "instanceof", "interface", "let", "new", "of", "return",
+            "switch", "this", "throw", "try", "type", "typeof", "var", "void", "while", "yield",
+        ],
+        literals: &["true", "false", "null", "undefined"],
+        line_comments: &["//"],
+        block_comment: Some(("/*", "*/")),
+        quotes: &['"', '\'', '`'],
+        char_literals: false,
+    },
+    Language {
+        names: &["c", "cpp", "c++", "go"],
+        keywords: &[
+            "auto", "break", "case", "chan", "char", "class", "const", "continue", "default", "defer", "do", "double", "else", "enum",
+            "extern", "float", "for", "func", "go", "goto", "if", "import", "int", "interface", "long", "map", "namespace", "package",
+            "range", "return", "select", "short", "signed", "sizeof", "static", "struct", "switch", "template", "type", "typedef",
+            "union", "unsigned", "var", "void", "while",
+        ],
+        literals: &["true", "false", "NULL", "nullptr", "nil"],
+        line_comments: &["//"],
+        block_comment: Some(("/*", "*/")),
+        quotes: &['"'],
+        char_literals: true,
+    },
+    Language {
+        names: &["bash", "sh", "shell", "zsh"],
+        keywords: &[
+            "case", "do", "done", "elif", "else", "esac", "export", "fi", "for", "function", "if", "in", "local", "return", "then", "until",
+            "while",
+        ],
+        literals: &["true", "false"],
+        line_comments: &["#"],
+        block_comment: None,
+        quotes: &['"', '\''],
+        char_literals: false,
+    },
+    Language {
+        names: &["toml"],
+        keywords: &[],
+        literals: &["true", "false"],
+        line_comments: &["#"],
+        block_comment: None,
+        quotes: &['"', '\''],
+        char_literals: false,
+    },
+];
+
+fn language(name: &str) -> Option<&'static Language> {
+    let name = name.to_ascii_lowercase();
+    LANGUAGES.iter().find(|language| language.names.contains(&name.as_str()))
+}
+
+// Highlight the <pre><code class="language-..."> blocks of html with spans of the classes of
+// HIGHLIGHT_CSS. Blocks in other languages, without a language, holding markup or entities other than
+// the basic ones are left as they are
+pub fn highlight_code_blocks(html: &str) -> String {
+    let mut out = String::with_capacity(html.len());
+    let mut rest = html;
+    while let Some((start, code_start, code_end)) = next_code_block(rest) {
+        out.push_str(&rest[..code_start]);
+        let code = &rest[code_start..code_end];
+        match highlighted(&rest[start..code_start], code) {
+            Some(highlighted) => out.push_str(&highlighted),
+            None => out.push_str(code),
+        }
+        rest = &rest[code_end..];
+    }
+    out.push_str(rest);
+    out
+}
+
+// Next <pre><code ...>...</code></pre> of html: the start of its <code> tag, and the range of its content
+fn next_code_block(html: &str) -> Option<(usize, usize, usize)> {
+    let lower = html.to_ascii_lowercase();
+    let mut from = 0;
+    while let Some(offset) = lower[from..].find("<pre") {
+        let pre = from + offset;
+        from = pre + 4;
+        if !lower[from..].starts_with(|c: char| c == '>' || c.is_ascii_whitespace()) {
+            continue;
+        }
+        let code = pre + lower[pre..].find('>')? + 1;
+        if !lower[code..].starts_with("<code") || !lower[code + 5..].starts_with(|c: char| c == '>' || c.is_ascii_whitespace()) {
+            continue;
+        }
+        let content = code + lower[code..].find('>')? + 1;
+        let end = content + lower[content..].find("</code>")?;
+        if lower[end + 7..].trim_start().starts_with("</pre>") {
+            return Some((code, content, end));
+        }
+    }
+    None
+}
+
+// Highlighted content of a code block, from its <code> tag and content. None to leave it alone
+fn highlighted(code_tag: &str, code: &str) -> Option<String> {
+    let attributes = tag_attributes(code_tag);
+    let (_, classes, _) = attributes.iter().find(|(name, _, _)| name == "class")?;
+    let language = classes.split_whitespace().find_map(|class| class.strip_prefix("language-")).and_then(language)?;
+    if code.contains('<') {
+        return None;
+    }
+    Some(highlight(&decode_entities(code)?, language))
+}
+
+// Decode the entities escape_html writes, and numeric ones. None for other named entities, which
+// could not be written back the same
+fn decode_entities(text: &str) -> Option<String> {
+    let mut decoded = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(amp) = rest.find('&') {
+        decoded.push_str(&rest[..amp]);
+        let entity_end = rest[amp..].find(';')? + amp;
+        let entity = &rest[amp + 1..entity_end];
+        let c = match entity {
+            "lt" => '<',
+            "gt" => '>',
+            "amp" => '&',
+            "quot" => '"',
+            "apos" => '\'',
+            _ => {
+                let number = entity.strip_prefix('#')?;
+                let code = match number.strip_prefix(['x', 'X']) {
+                    Some(hex) => u32::from_str_radix(hex, 16).ok()?,
+                    None => number.parse().ok()?,
+                };
+                char::from_u32(code)?
+            }
+        };
+        decoded.push(c);
+        rest = &rest[entity_end + 1..];
+    }
+    decoded.push_str(rest);
+    Some(decoded)
+}
+
+// Split code into tokens and wrap keywords, literals, numbers, strings and comments in spans. Every
+// piece of text is escaped again
+fn highlight(code: &str, language: &Language) -> String {
+    let mut out = String::with_capacity(code.len() * 2);
+    let mut plain = String::new();
+    let mut rest = code;
+    let push_span = |out: &mut String, plain: &mut String, class: &str, text: &str| {
+        out.push_str(&escape_html(plain));
+        plain.clear();
+        out.push_str(&format!("<span class=\"hl-{}\">{}</span>", class, escape_html(text)));
+    };
+
+    while let Some(c) = rest.chars().next() {
+        let (class, len) = if let Some(len) = comment_len(rest, language) {
+            ("comment", len)
+        } else if language.quotes.contains(&c) {
+            ("string", string_len(rest, c))
+        } else if c == '\'' && language.char_literals && char_literal_len(rest).is_some() {
+            ("string", char_literal_len(rest).unwrap_or(1))
+        } else if c.is_ascii_digit() && !plain.ends_with(|p: char| p.is_alphanumeric() || p == '_') {
+            ("number", number_len(rest))
+        } else if c.is_alphabetic() || c == '_' {
+            let len = rest.find(|c: char| !(c.is_alphanumeric() || c == '_')).unwrap_or(rest.len());
+            let word = &rest[..len];
+            if language.keywords.contains(&word) {
+                ("keyword", len)
+            } else if language.literals.contains(&word) {
+                ("literal", len)
+            } else {
+                ("", len)
+            }
+        } else {
+            ("", c.len_utf8())
+        };
+        let (token, next) = rest.split_at(len);
+        if class.is_empty() {
+            plain.push_str(token);
+        } else {
+            push_span(&mut out, &mut plain, class, token);
+        }
+        rest = next;
+    }
+    out.push_str(&escape_html(&plain));
+    out
+}
+
+// Length of the comment starting code, if one does
+fn comment_len(code: &str, language: &Language) -> Option<usize> {
+    if language.line_comments.iter().any(|start| code.starts_with(start)) {
+        return Some(code.find('\n').unwrap_or(code.len()));
+    }
+    let (start, end) = language.block_comment?;
+    code.starts_with(start)
+        .then(|| code[start.len()..].find(end).map(|i| start.len() + i + end.len()).unwrap_or(code.len()))
+}
+
+// Length of the string starting code with quote, up to its closing quote or the end of the code
+fn string_len(code: &str, quote: char) -> usize {
+    let mut chars = code.char_indices().skip(1);
+    while let Some((i, c)) = chars.next() {
+        if c == '\\' {
+            chars.next();
+        } else if c == quote {
+            return i + c.len_utf8();
+        }
+    }
+    code.len()
+}
+
+// Length of a char literal such as 'a', '\n' or '\u{1F600}' starting code, None for a lifetime
+fn char_literal_len(code: &str) -> Option<usize> {
+    let body = &code[1..];
+    let len = if let Some(escape) = body.strip_prefix('\\') {
+        1 + escape.find('\'')?
+    } else {
+        body.chars().next()?.len_utf8()
+    };
+    (body[len..].starts_with('\'') && !body[..len].contains('\n') && len > 0).then_some(len + 2)
+}
+
+// Length of the number starting code: digits, letters for bases and suffixes, and a decimal point
+// followed by a digit
+fn number_len(code: &str) -> usize {
+    let bytes = code.as_bytes();
+    let mut len = 0;
+    while len < bytes.len() {
+        let b = bytes[len];
+        if b.is_ascii_alphanumeric() || b == b'_' || (b == b'.' && bytes.get(len + 1).is_some_and(u8::is_ascii_digit)) {
+            len += 1;
+        } else {
+            break;
+        }
+    }
+    len
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn code_blocks_are_highlighted() {
+        let html = "<p>Code:</p><pre><code class=\"language-rust\">fn main() {\n    // say hi\n    let s = &quot;a &lt; b&quot;; 'x'; &amp;'a str; 42\n}</code></pre>";
+        assert_eq!(
+            highlight_code_blocks(html),
+            "<p>Code:</p><pre><code class=\"language-rust\"><span class=\"hl-keyword\">fn</span> main() {\n    \
+             <span class=\"hl-comment\">// say hi</span>\n    <span class=\"hl-keyword\">let</span> s = \
+             <span class=\"hl-string\">&quot;a &lt; b&quot;</span>; <span class=\"hl-string\">&#39;x&#39;</span>; \
+             &amp;&#39;a str; <span class=\"hl-number\">42</span>\n}</code></pre>"
+        );
+        assert_eq!(
+            highlight_code_blocks("<pre class=\"x\"><code class=\"lang language-py\"># a\nx = None</code>\n</pre>"),
+            "<pre class=\"x\"><code class=\"lang language-py\"><span class=\"hl-comment\"># a</span>\nx = <span class=\"hl-literal\">None</span></code>\n</pre>"
+        );
+    }
+
+    #[test]
+    fn other_blocks_pass_through() {
+        for html in [
+            "<pre><code class=\"language-cobol\">MOVE A TO B &lt;</code></pre>",
+            "<pre><code>let x = 1;</code></pre>",
+            "<pre><code class=\"language-rust\">let <b>x</b> = 1;</code></pre>",
+            "<pre><code class=\"language-rust\">let x = &nbsp;1;</code></pre>",
+            "<code class=\"language-rust\">let x = 1;</code>",
+            "<preview><code class=\"language-rust\">let x = 1;</code></pre>",
+        ] {
+            assert_eq!(highlight_code_blocks(html), html);
+        }
+        // Entities round-trip, numeric ones coming back as escape_html writes them
+        assert_eq!(
+            highlight_code_blocks("<pre><code class=\"language-toml\">a &lt;&gt;&amp; b&#62;</code></pre>"),
+            "<pre><code class=\"language-toml\">a &lt;&gt;&amp; b&gt;</code></pre>"
+        );
+    }
+}
@@ -0,0 +1,41 @@
+use std::path::Path;
+use std::process::Command;
+
+use crate::error::IroniteError;
+
+// Run a pre_build or post_build command through the shell, in the project root, with the output going
+// where ironite's does. Exiting with a failure status is a BuildHook error
+pub fn run_build_hook(hook: &'static str, command: &str, root: &Path) -> Result<(), IroniteError> {
+    let mut shell = if cfg!(windows) {
+        let mut shell = Command::new("cmd");
+        shell.arg("/C");
+        shell
+    } else {
+        let mut shell = Command::new("sh");
+        shell.arg("-c");
+        shell
+    };
+    let status = shell.arg(command).current_dir(root).status()?;
+    if status.success() {
+        Ok(())
+    } else {
+        Err(IroniteError::BuildHook { hook, command: command.to_string(), status: status.to_string() })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::TempDir;
+
+    #[test]
+    fn hooks_run_in_the_project_root() {
+        let dir = TempDir::new("build-hook");
+        run_build_hook("post_build", "echo done> built.txt", dir.path()).unwrap();
+        assert!(dir.path().join("built.txt").exists());
+
+        let error = run_build_hook("pre_build", "exit 3", dir.path()).unwrap_err();
+        assert!(matches!(error, IroniteError::BuildHook { hook: "pre_build", .. }), "{:?}", error);
+        assert!(error.to_string().starts_with("pre_build command `exit 3` failed: "), "{}", error);
+    }
+}
@@ -0,0 +1,890 @@
+// Helpers for processing generated HTML
+
+// Elements whose content is whitespace-sensitive and must be copied verbatim
+const RAW_TEXT_ELEMENTS: &[&str] = &["pre", "textarea", "script"];
+
+// Elements kept in the line of the text around them when pretty printing
+const INLINE_ELEMENTS: &[&str] = &[
+    "a", "abbr", "b", "bdi", "bdo", "br", "cite", "code", "data", "dfn", "em", "i", "img", "input", "kbd", "label",
+    "mark", "q", "s", "samp", "small", "span", "strong", "sub", "sup", "time", "u", "var", "wbr",
+];
+
+// Elements without contents or closing tag
+const VOID_ELEMENTS: &[&str] = &[
+    "area", "base", "br", "col", "embed", "hr", "img", "input", "link", "meta", "source", "track", "wbr",
+];
+
+// Shrink an HTML document: runs of whitespace are collapsed (to a newline when the run
+// contained one, to a space otherwise), which also drops indentation, and comments are removed.
+// Conditional comments and the contents of <pre>, <textarea> and <script> are kept as they are
+pub fn minify_html(html: &str) -> String {
+    let mut out = String::with_capacity(html.len());
+    let mut rest = html;
+
+    while !rest.is_empty() {
+        if rest.starts_with("<!--") {
+            let end = rest.find("-->").map(|i| i + 3).unwrap_or(rest.len());
+            let comment = &rest[..end];
+            if is_conditional_comment(comment) {
+                out.push_str(comment);
+            }
+            rest = &rest[end..];
+        } else if let Some(name) = raw_text_element(rest) {
+            let end = closing_tag_end(rest, name);
+            out.push_str(&rest[..end]);
+            rest = &rest[end..];
+        } else if rest.starts_with('<') {
+            let end = tag_end(rest);
+            push_tag(&mut out, &rest[..end]);
+            rest = &rest[end..];
+        } else {
+            let end = rest.find('<').unwrap_or(rest.len());
+            push_text(&mut out, &rest[..end]);
+            rest = &rest[end..];
+        }
+    }
+
+    out.trim().to_string()
+}
+
+// Indent an HTML document by two spaces per level of nesting, one block element per line. Inline
+// elements (<a>, <em>, ...) stay in the line of their text, whose whitespace is collapsed, and a block
+// element holding a single line is kept on it (<p>Some <em>text</em></p>). Comments and <pre>,
+// <textarea> and <script> elements get a line of their own, with their contents kept as they are
+pub fn pretty_html(html: &str) -> String {
+    let mut out = String::with_capacity(html.len() + html.len() / 4);
+    // Text and inline elements waiting for the end of their line
+    let mut line = String::new();
+    let mut depth = 0usize;
+    // Block element opened on the last line written, while nothing else followed it
+    let mut open: Option<String> = None;
+    let mut rest = html;
+
+    while !rest.is_empty() {
+        let raw_end = match raw_text_element(rest) {
+            Some(name) => Some(closing_tag_end(rest, name)),
+            None if rest.starts_with("<!--") => Some(rest.find("-->").map(|i| i + 3).unwrap_or(rest.len())),
+            None => None,
+        };
+        if let Some(end) = raw_end {
+            flush_line(&mut out, &mut line, depth);
+            push_line(&mut out, &rest[..end], depth);
+            open = None;
+            rest = &rest[end..];
+        } else if rest.starts_with('<') {
+            let end = tag_end(rest);
+            let mut tag = String::new();
+            push_tag(&mut tag, &rest[..end]);
+            rest = &rest[end..];
+            let name = tag_name(&tag);
+            if INLINE_ELEMENTS.contains(&name.as_str()) {
+                line.push_str(&tag);
+                continue;
+            }
+
+            let closing = tag.starts_with("</");
+            if closing && open.as_deref() == Some(name.as_str()) {
+                // Join the opening tag, the pending line and the closing tag
+                out.pop();
+                out.push_str(line.trim());
+                out.push_str(&tag);
+                out.push('\n');
+                line.clear();
+                depth = depth.saturating_sub(1);
+                open = None;
+                continue;
+            }
+            flush_line(&mut out, &mut line, depth);
+            open = None;
+            if closing {
+                depth = depth.saturating_sub(1);
+            }
+            push_line(&mut out, &tag, depth);
+            let has_contents = !closing
+                && !tag.ends_with("/>")
+                && !tag.starts_with("<!")
+                && !tag.starts_with("<?")
+                && !VOID_ELEMENTS.contains(&name.as_str());
+            if has_contents {
+                depth += 1;
+                open = Some(name);
+            }
+        } else {
+            let end = rest.find('<').unwrap_or(rest.len());
+            for c in rest[..end].chars() {
+                if !c.is_whitespace() {
+                    line.push(c);
+                } else if !line.is_empty() && !line.ends_with(' ') {
+                    line.push(' ');
+                }
+            }
+            rest = &rest[end..];
+        }
+    }
+    flush_line(&mut out, &mut line, depth);
+    out
+}
+
+// Lowercased name of a tag, without the / of closing tags
+fn tag_name(tag: &str) -> String {
+    tag.trim_start_matches('<')
+        .trim_start_matches('/')
+        .split(|c: char| c.is_whitespace() || c == '/' || c == '>')
+        .next()
+        .unwrap_or("")
+        .to_ascii_lowercase()
+}
+
+fn push_line(out: &mut String, text: &str, depth: usize) {
+    for _ in 0..depth {
+        out.push_str("  ");
+    }
+    out.push_str(text);
+    out.push('\n');
+}
+
+// Write the pending line, if it has anything but whitespace
+fn flush_line(out: &mut String, line: &mut String, depth: usize) {
+    let text = line.trim();
+    if !text.is_empty() {
+        push_line(out, text, depth);
+    }
+    line.clear();
+}
+
+// Escape text for use in HTML or XML content and attribute values
+pub fn escape_html(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+// <!--[if IE]> ... <![endif]--> and its downlevel-revealed variants
+fn is_conditional_comment(comment: &str) -> bool {
+    comment.starts_with("<!--[if") || comment.contains("<![endif]")
+}
+
+// Name of the whitespace-sensitive element opening at the start of html, if any
+pub(crate) fn raw_text_element(html: &str) -> Option<&'static str> {
+    opening_element(html, RAW_TEXT_ELEMENTS)
+}
+
+// Which of names is the element opening at the start of html, if any
+pub(crate) fn opening_element(html: &str, names: &[&'static str]) -> Option<&'static str> {
+    let bytes = html.as_bytes();
+    if bytes.first() != Some(&b'<') {
+        return None;
+    }
+    names.iter().copied().find(|name| {
+        let end = 1 + name.len();
+        bytes.len() > end
+            && bytes[1..end].eq_ignore_ascii_case(name.as_bytes())
+            && matches!(bytes[end], b'>' | b'/' | b' ' | b'\t' | b'\n' | b'\r')
+    })
+}
+
+// Byte offset just past the closing tag of an element that opens at the start of html
+pub(crate) fn closing_tag_end(html: &str, name: &str) -> usize {
+    let lower = html.to_ascii_lowercase();
+    let closing = format!("</{}", name);
+    match lower[1..].find(&closing) {
+        Some(start) => {
+            let start = start + 1;
+            lower[start..].find('>').map(|i| start + i + 1).unwrap_or(html.len())
+        }
+        None => html.len(),
+    }
+}
+
+// Byte offset just past the '>' ending the tag that starts html, skipping quoted attribute values
+pub(crate) fn tag_end(html: &str) -> usize {
+    let mut quote = None;
+    for (i, c) in html.char_indices().skip(1) {
+        match quote {
+            Some(q) if c == q => quote = None,
+            Some(_) => {}
+            None if c == '"' || c == '\'' => quote = Some(c),
+            None if c == '>' => return i + 1,
+            None => {}
+        }
+    }
+    html.len()
+}
+
+// Copy a tag, collapsing whitespace between attributes but not inside quoted values
+fn push_tag(out: &mut String, tag: &str) {
+    let mut quote = None;
+    let mut pending_space = false;
+    for c in tag.chars() {
+        match quote {
+            Some(q) => {
+                out.push(c);
+                if c == q {
+                    quote = None;
+                }
+            }
+            None if c.is_whitespace() => pending_space = true,
+            None => {
+                if pending_space && c != '>' && !(c == '/' && tag.ends_with("/>")) {
+                    out.push(' ');
+                }
+                pending_space = false;
+                if c == '"' || c == '\'' {
+                    quote = Some(c);
+                }
+                out.push(c);
+            }
+        }
+    }
+}
+
+// Copy text, collapsing whitespace runs. Runs also merge across removed comments
+fn push_text(out: &mut String, text: &str) {
+    let mut chars = text.chars().peekable();
+    while let Some(c) = chars.next() {
+        if !c.is_whitespace() {
+            out.push(c);
+            continue;
+        }
+        let mut newline = c == '\n';
+        while let Some(next) = chars.peek() {
+            if !next.is_whitespace() {
+                break;
+            }
+            newline |= *next == '\n';
+            chars.next();
+        }
+        match out.chars().last() {
+            Some(' ') if newline => {
+                out.pop();
+                out.push('\n');
+            }
+            Some(' ') | Some('\n') => {}
+            _ => out.push(if newline { '\n' } else { ' ' }),
+        }
+    }
+}
+
+// Elements whose markup is shown as code rather than used, left alone by external_link_attributes and
+// lazy_images
+const CODE_ELEMENTS: &[&str] = &["pre", "code", "textarea", "script"];
+
+// Add rel="noopener noreferrer", and with target_blank target="_blank", to the <a> elements of html
+// linking to another host than site_host, the host of base_url. Without a site host every absolute link
+// is external. Anchors already having a rel or a target keep theirs, and the added attributes use the
+// quotes of the href. Comments and the contents of <pre>, <code>, <textarea> and <script> are copied as
+// they are
+pub fn external_link_attributes(html: &str, site_host: Option<&str>, target_blank: bool) -> String {
+    rewrite_content_tags(html, "a", |tag| external_anchor(tag, site_host, target_blank))
+}
+
+// Add the attributes of external_link_attributes to one <a ...> tag when its href is external
+fn external_anchor(tag: &str, site_host: Option<&str>, target_blank: bool) -> String {
+    let attributes = tag_attributes(tag);
+    let Some((_, href, quote)) = attributes.iter().find(|(name, _, _)| name == "href") else { return tag.to_string() };
+    let Some(host) = url_host(href) else { return tag.to_string() };
+    if site_host.is_some_and(|site| site.eq_ignore_ascii_case(&host)) {
+        return tag.to_string();
+    }
+    let has = |attribute: &str| attributes.iter().any(|(name, _, _)| name == attribute);
+    let quote = quote.unwrap_or('"');
+    let mut added = String::new();
+    if !has("rel") {
+        added.push_str(&format!(" rel={0}noopener noreferrer{0}", quote));
+    }
+    if target_blank && !has("target") {
+        added.push_str(&format!(" target={0}_blank{0}", quote));
+    }
+    insert_attributes(tag, &added)
+}
+
+// Add loading="lazy" and decoding="async" to the <img> elements of html that lack them, and width and
+// height from size(src) to those having neither. size is given the src as written and returns None
+// for images it cannot measure, such as remote ones. Malformed tags, unterminated or with a < outside
+// quotes, are copied as they are, like comments and the contents of <pre>, <code>, <textarea> and <script>
+pub fn lazy_images(html: &str, size: &dyn Fn(&str) -> Option<(u32, u32)>) -> String {
+    rewrite_content_tags(html, "img", |tag| lazy_image(tag, size))
+}
+
+fn lazy_image(tag: &str, size: &dyn Fn(&str) -> Option<(u32, u32)>) -> String {
+    if !well_formed_tag(tag) {
+        return tag.to_string();
+    }
+    let attributes = tag_attributes(tag);
+    let has = |attribute: &str| attributes.iter().any(|(name, _, _)| name == attribute);
+    let src = attributes.iter().find(|(name, _, _)| name == "src");
+    let quote = src.and_then(|(_, _, quote)| *quote).unwrap_or('"');
+    let mut added = String::new();
+    if !has("loading") {
+        added.push_str(&format!(" loading={0}lazy{0}", quote));
+    }
+    if !has("decoding") {
+        added.push_str(&format!(" decoding={0}async{0}", quote));
+    }
+    if !has("width") && !has("height") {
+        if let Some((width, height)) = src.and_then(|(_, src, _)| size(src)) {
+            added.push_str(&format!(" width={0}{1}{0} height={0}{2}{0}", quote, width, height));
+        }
+    }
+    insert_attributes(tag, &added)
+}
+
+// Wrap the http:// and https:// URLs of escaped text in links. The text is escaped first, so that an &
+// of a URL is already the &amp; its href needs. A URL ends at whitespace or an escaped quote or angle
+// bracket, and punctuation ending a sentence after it (.,;:!?) stays out, like a ) closing a parenthesis
+// opened before the URL
+pub fn linkify_urls(escaped: &str) -> String {
+    let mut out = String::with_capacity(escaped.len());
+    let mut rest = escaped;
+    let mut prev: Option<char> = None;
+    while let Some(c) = rest.chars().next() {
+        let lower: String = rest.chars().take(8).collect::<String>().to_ascii_lowercase();
+        let scheme = ["https://", "http://"].into_iter().find(|scheme| lower.starts_with(scheme));
+        let len = match scheme {
+            Some(scheme) if !prev.is_some_and(char::is_alphanumeric) => url_len(rest, scheme.len()),
+            _ => 0,
+        };
+        if len == 0 {
+            out.push(c);
+            prev = Some(c);
+            rest = &rest[c.len_utf8()..];
+            continue;
+        }
+        let url = &rest[..len];
+        out.push_str(&format!("<a href=\"{0}\">{0}</a>", url));
+        prev = url.chars().last();
+        rest = &rest[len..];
+    }
+    out
+}
+
+// Length of the URL starting escaped, whose scheme is scheme_len long. 0 when nothing follows the scheme
+fn url_len(escaped: &str, scheme_len: usize) -> usize {
+    let mut end = escaped.len();
+    for (i, c) in escaped.char_indices().skip(scheme_len) {
+        let escaped_delimiter = ["&quot;", "&#39;", "&lt;", "&gt;"].iter().any(|entity| escaped[i..].starts_with(entity));
+        if c.is_whitespace() || escaped_delimiter {
+            end = i;
+            break;
+        }
+    }
+    let mut url = &escaped[..end];
+    loop {
+        if url.ends_with(['.', ',', ';', ':', '!', '?']) || (url.ends_with(')') && url.matches('(').count() < url.matches(')').count()) {
+            url = &url[..url.len() - 1];
+        } else if url.ends_with("&amp;") {
+            url = &url[..url.len() - 5];
+        } else {
+            break;
+        }
+    }
+    if url.len() > scheme_len { url.len() } else { 0 }
+}
+
+// Elements whose text is taken as written by smart_punctuation
+const VERBATIM_ELEMENTS: &[&str] = &["pre", "code", "kbd", "samp", "textarea", "script", "style"];
+
+// Byte offset past the comment, verbatim element, tag or run of text starting html, for passes that
+// change text only
+pub(crate) fn content_chunk_end(html: &str) -> usize {
+    if html.starts_with("<!--") {
+        html.find("-->").map(|i| i + 3).unwrap_or(html.len())
+    } else if let Some(name) = opening_element(html, VERBATIM_ELEMENTS) {
+        closing_tag_end(html, name)
+    } else if html.starts_with('<') {
+        tag_end(html)
+    } else {
+        html.find('<').unwrap_or(html.len())
+    }
+}
+
+// Typographic punctuation for the text of html: straight quotes become curly ones, -- an en dash,
+// --- an em dash and ... an ellipsis. A quote opens after whitespace, an opening bracket or a dash and
+// at the start of a block, and closes otherwise, so that the ' of it's is an apostrophe. Tags and their
+// attributes, comments and the contents of <pre>, <code>, <kbd>, <samp>, <textarea>, <script> and <style>
+// are left alone. &quot; and &#39; count as quotes, so that escaped text works too
+pub fn smart_punctuation(html: &str) -> String {
+    let mut out = String::with_capacity(html.len());
+    let mut rest = html;
+    // Last character of text before the current position, None at the start of a block
+    let mut prev = None;
+
+    while !rest.is_empty() {
+        let is_tag = rest.starts_with('<') && rest[1..].starts_with(|c: char| c.is_ascii_alphabetic() || c == '/' || c == '!');
+        let end = if rest.starts_with("<!--") {
+            rest.find("-->").map(|i| i + 3).unwrap_or(rest.len())
+        } else if let Some(name) = opening_element(rest, VERBATIM_ELEMENTS) {
+            closing_tag_end(rest, name)
+        } else if is_tag {
+            tag_end(rest)
+        } else {
+            // Past the first character, which is a lone < or can be several bytes long
+            let first = rest.chars().next().map_or(1, char::len_utf8);
+            rest[first..].find('<').map(|i| i + first).unwrap_or(rest.len())
+        };
+        let (chunk, next) = rest.split_at(end);
+        if opening_element(chunk, VERBATIM_ELEMENTS).is_some() {
+            out.push_str(chunk);
+            // Code reads as a word: <code>Vec</code>'s
+            prev = Some('x');
+        } else if is_tag {
+            out.push_str(chunk);
+            // Inline elements are part of the text around them: the quote in <em>it</em>'s closes, the one
+            // in <p>'Tis or after a <br> opens
+            let name = tag_name(chunk);
+            if !chunk.starts_with("<!--") && (name == "br" || !INLINE_ELEMENTS.contains(&name.as_str())) {
+                prev = None;
+            }
+        } else {
+            prev = smarten_text(chunk, prev, &mut out);
+        }
+        rest = next;
+    }
+    out
+}
+
+// Write text to out with the replacements of smart_punctuation, prev being the character before it.
+// Returns the last character of text
+fn smarten_text(text: &str, mut prev: Option<char>, out: &mut String) -> Option<char> {
+    let opens = |prev: Option<char>| prev.is_none_or(|p| p.is_whitespace() || "([{\u{2018}\u{201c}\u{2013}\u{2014}-".contains(p));
+    let mut rest = text;
+    while let Some(c) = rest.chars().next() {
+        let (replacement, len, last) = if rest.starts_with("---") {
+            ("\u{2014}".to_string(), 3, '\u{2014}')
+        } else if rest.starts_with("--") {
+            ("\u{2013}".to_string(), 2, '\u{2013}')
+        } else if rest.starts_with("...") {
+            ("\u{2026}".to_string(), 3, '\u{2026}')
+        } else if c == '"' || rest.starts_with("&quot;") {
+            let quote = if opens(prev) { '\u{201c}' } else { '\u{201d}' };
+            (quote.to_string(), if c == '"' { 1 } else { 6 }, quote)
+        } else if c == '\'' || rest.starts_with("&#39;") {
+            let len = if c == '\'' { 1 } else { 5 };
+            // '90s is an apostrophe too
+            let quote = if opens(prev) && !rest[len..].starts_with(|c: char| c.is_ascii_digit()) { '\u{2018}' } else { '\u{2019}' };
+            (quote.to_string(), len, quote)
+        } else if c == '&' {
+            // Other entities are copied whole, &nbsp; reading as a space
+            let len = rest.find(';').filter(|end| rest[1..*end].chars().all(|c| c.is_ascii_alphanumeric() || c == '#')).map(|end| end + 1).unwrap_or(1);
+            (rest[..len].to_string(), len, if &rest[..len] == "&nbsp;" { ' ' } else { '&' })
+        } else {
+            (c.to_string(), c.len_utf8(), c)
+        };
+        out.push_str(&replacement);
+        prev = Some(last);
+        rest = &rest[len..];
+    }
+    prev
+}
+
+// Copy html, passing every opening tag named name through rewrite. Comments and the contents of <pre>,
+// <code>, <textarea> and <script> are copied as they are, so that code samples keep what was written
+fn rewrite_content_tags(html: &str, name: &str, rewrite: impl Fn(&str) -> String) -> String {
+    let mut out = String::with_capacity(html.len());
+    let mut rest = html;
+
+    while !rest.is_empty() {
+        let end = if rest.starts_with("<!--") {
+            rest.find("-->").map(|i| i + 3).unwrap_or(rest.len())
+        } else if let Some(name) = opening_element(rest, CODE_ELEMENTS) {
+            closing_tag_end(rest, name)
+        } else if rest.starts_with('<') {
+            tag_end(rest)
+        } else {
+            rest.find('<').unwrap_or(rest.len())
+        };
+        let (chunk, next) = rest.split_at(end);
+        if chunk.starts_with('<') && !chunk.starts_with("</") && tag_name(chunk) == name {
+            out.push_str(&rewrite(chunk));
+        } else {
+            out.push_str(chunk);
+        }
+        rest = next;
+    }
+    out
+}
+
+// Whether a tag ends with > and has no < outside its quoted values, as <img src="a.png" <p> has
+fn well_formed_tag(tag: &str) -> bool {
+    let mut quote = None;
+    for c in tag.chars().skip(1) {
+        match quote {
+            Some(q) if c == q => quote = None,
+            Some(_) => {}
+            None if c == '"' || c == '\'' => quote = Some(c),
+            None if c == '<' => return false,
+            None => {}
+        }
+    }
+    quote.is_none() && tag.ends_with('>')
+}
+
+// Insert attributes, each with its leading space, before the closing > or /> of a tag, keeping
+// whatever space the tag had there
+fn insert_attributes(tag: &str, added: &str) -> String {
+    let body = tag.strip_suffix('>').unwrap_or(tag);
+    let body = body.strip_suffix('/').unwrap_or(body);
+    let body = body.trim_end();
+    format!("{}{}{}", body, added, &tag[body.len()..])
+}
+
+// Attributes of a tag: lowercased name, value, and the quote around the value if any. Quoted values
+// may hold > and the other quote
+pub(crate) fn tag_attributes(tag: &str) -> Vec<(String, String, Option<char>)> {
+    let inner = tag.strip_prefix('<').unwrap_or(tag);
+    let inner = inner.strip_suffix('>').unwrap_or(inner);
+    let mut chars = inner.char_indices().skip_while(|(_, c)| !c.is_whitespace()).peekable();
+    let mut attributes = Vec::new();
+
+    loop {
+        while chars.next_if(|(_, c)| c.is_whitespace() || *c == '/').is_some() {}
+        let Some(&(start, _)) = chars.peek() else { break };
+        let mut end = inner.len();
+        while let Some(&(i, c)) = chars.peek() {
+            if c.is_whitespace() || c == '=' || c == '/' {
+                end = i;
+                break;
+            }
+            chars.next();
+        }
+        let name = inner[start..end].to_ascii_lowercase();
+        while chars.next_if(|(_, c)| c.is_whitespace()).is_some() {}
+        if chars.next_if(|(_, c)| *c == '=').is_none() {
+            attributes.push((name, String::new(), None));
+            continue;
+        }
+        while chars.next_if(|(_, c)| c.is_whitespace()).is_some() {}
+        let quote = chars.next_if(|(_, c)| *c == '"' || *c == '\'').map(|(_, c)| c);
+        let mut value = String::new();
+        while let Some((_, c)) = chars.next_if(|(_, c)| match quote {
+            Some(q) => *c != q,
+            None => !c.is_whitespace(),
+        }) {
+            value.push(c);
+        }
+        if quote.is_some() {
+            chars.next();
+        }
+        attributes.push((name, value, quote));
+    }
+    attributes
+}
+
+// Host of an absolute http(s) or protocol-relative URL, lowercased, without user or port. None for
+// other URLs
+pub fn url_host(url: &str) -> Option<String> {
+    let url = url.trim().to_ascii_lowercase();
+    let rest = url.strip_prefix("http://").or_else(|| url.strip_prefix("https://")).or_else(|| url.strip_prefix("//"))?;
+    let authority = rest.split(['/', '?', '#']).next().unwrap_or("");
+    let host = authority.rsplit('@').next().unwrap_or("");
+    let host = match host.strip_prefix('[') {
+        Some(ipv6) => ipv6.split(']').next().unwrap_or(""),
+        None => host.split(':').next().unwrap_or(""),
+    };
+    Some(host.to_string()).filter(|host| !host.is_empty())
+}
+
+// Resolve a reference found in a page to a path relative to the public directory.
+// page_dir is the directory of the referencing file, also relative to the public directory.
+// Returns None for external URLs (with a scheme or protocol-relative) and fragment-only links
+pub fn resolve_reference(page_dir: &str, reference: &str) -> Option<String> {
+    let path = reference.split(['?', '#']).next().unwrap_or("");
+    if path.is_empty() || path.starts_with("//") {
+        return None;
+    }
+    // A colon before the first slash means a scheme such as http:, mailto: or data:
+    if let Some(colon) = path.find(':') {
+        if !path[..colon].contains('/') {
+            return None;
+        }
+    }
+
+    let joined = match path.strip_prefix('/') {
+        Some(absolute) => absolute.to_string(),
+        None => format!("{}/{}", page_dir, path),
+    };
+
+    let mut segments: Vec<&str> = Vec::new();
+    for segment in joined.split('/') {
+        match segment {
+            "" | "." => {}
+            ".." => {
+                segments.pop();
+            }
+            _ => segments.push(segment),
+        }
+    }
+    Some(segments.join("/"))
+}
+
+// Make every internal href and src absolute by resolving it against the site root and prepending root
+// (e.g. "/" or "https://example.com/"). Used for pages that can be served from any path
+pub fn root_relative_links(html: &str, root: &str) -> String {
+    let mut result = String::with_capacity(html.len());
+    let mut rest = html;
+
+    while let Some((start, end)) = next_link_attribute(rest) {
+        let value = &rest[start..end];
+        result.push_str(&rest[..start]);
+        match resolve_reference("", value) {
+            Some(resolved) => {
+                let suffix = value.find(['?', '#']).map(|i| &value[i..]).unwrap_or("");
+                result.push_str(&format!("{}{}{}", root, resolved, suffix));
+            }
+            None => result.push_str(value),
+        }
+        rest = &rest[end..];
+    }
+
+    result.push_str(rest);
+    result
+}
+
+// Rewrite the internal href and src values of html, written for a page in from_dir, for a page in
+// to_dir. Both directories are relative to the public directory
+pub fn rebase_links(html: &str, from_dir: &str, to_dir: &str) -> String {
+    relink(html, from_dir, &"../".repeat(to_dir.split('/').filter(|segment| !segment.is_empty()).count()), |_| false)
+}
+
+// Make the internal href and src values of html, written for a page in page_dir, absolute paths under
+// prefix ("/blog/"), for sites served from a subdirectory of their host. Paths already under prefix,
+// such as the redirect targets of SiteConfig::url, are kept
+pub fn prefix_links(html: &str, page_dir: &str, prefix: &str) -> String {
+    relink(html, page_dir, prefix, |value| value.starts_with(prefix))
+}
+
+// Resolve the internal href and src values of html against from_dir and prepend root to them, except
+// the values keep accepts
+fn relink(html: &str, from_dir: &str, root: &str, keep: impl Fn(&str) -> bool) -> String {
+    let mut result = String::with_capacity(html.len());
+    let mut rest = html;
+
+    while let Some((start, end)) = next_link_attribute(rest) {
+        let value = &rest[start..end];
+        result.push_str(&rest[..start]);
+        match resolve_reference(from_dir, value).filter(|_| !keep(value)) {
+            Some(resolved) => {
+                let suffix = value.find(['?', '#']).map(|i| &value[i..]).unwrap_or("");
+                // Directory links keep their slash
+                let slash = if value[..value.len() - suffix.len()].ends_with('/') && !resolved.is_empty() { "/" } else { "" };
+                result.push_str(&format!("{}{}{}{}", root, resolved, slash, suffix));
+            }
+            None => result.push_str(value),
+        }
+        rest = &rest[end..];
+    }
+
+    result.push_str(rest);
+    result
+}
+
+// Find the next quoted href or src attribute value, returning its byte range
+pub(crate) fn next_link_attribute(html: &str) -> Option<(usize, usize)> {
+    let bytes = html.as_bytes();
+    let mut search_from = 0;
+
+    while search_from < bytes.len() {
+        let offset = html[search_from..].find('=')?;
+        let equals = search_from + offset;
+        search_from = equals + 1;
+
+        let name_end = html[..equals].trim_end().len();
+        let name_start = html[..name_end]
+            .rfind(|c: char| !(c.is_ascii_alphanumeric() || c == '-'))
+            .map(|i| i + 1)
+            .unwrap_or(0);
+        let name = &html[name_start..name_end];
+        if !name.eq_ignore_ascii_case("href") && !name.eq_ignore_ascii_case("src") {
+            continue;
+        }
+
+        let value_start = equals + 1 + (html[equals + 1..].len() - html[equals + 1..].trim_start().len());
+        let quote = match bytes.get(value_start) {
+            Some(b'"') => '"',
+            Some(b'\'') => '\'',
+            _ => continue,
+        };
+        let value_end = html[value_start + 1..].find(quote)? + value_start + 1;
+        return Some((value_start + 1, value_end));
+    }
+
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn minify_collapses_whitespace_and_indentation() {
+        let before = "<html>\n    <body>\n\n        <p>Hello    world</p>\n    </body>\n</html>\n";
+        assert_eq!(minify_html(before), "<html>\n<body>\n<p>Hello world</p>\n</body>\n</html>");
+    }
+
+    #[test]
+    fn minify_strips_comments_but_keeps_conditional_ones() {
+        let before = "<p>a <!-- note --> b</p><!--[if IE]><p>old</p><![endif]-->";
+        assert_eq!(minify_html(before), "<p>a b</p><!--[if IE]><p>old</p><![endif]-->");
+    }
+
+    #[test]
+    fn minify_keeps_pre_textarea_and_script_contents() {
+        let before = "<div>\n  <pre class=\"code\">fn main() {\n    println!(\"hi\");\n}</pre>\n  \
+                      <textarea>  a\n  b</textarea>\n  <script>\n  if (a  <  b) {}\n  </script>\n</div>";
+        let after = "<div>\n<pre class=\"code\">fn main() {\n    println!(\"hi\");\n}</pre>\n\
+                     <textarea>  a\n  b</textarea>\n<script>\n  if (a  <  b) {}\n  </script>\n</div>";
+        assert_eq!(minify_html(before), after);
+    }
+
+    #[test]
+    fn minify_collapses_attribute_whitespace_outside_quotes() {
+        let before = "<a   href=\"a  b\"\n     title='x > y'  >link</a><br  />";
+        assert_eq!(minify_html(before), "<a href=\"a  b\" title='x > y'>link</a><br/>");
+    }
+
+    #[test]
+    fn pretty_indents_blocks_and_keeps_inline_elements_and_pre() {
+        let before = "<!DOCTYPE html><html><head><title>T</title></head><body>\n<div class=\"a\">  <p>Hello   <a href=\"x\">world</a>!</p>\n\
+                      <pre>  a\n b</pre><br><img src=\"i.png\"/><hr></div><!-- c --></body></html>";
+        let after = "<!DOCTYPE html>\n<html>\n  <head>\n    <title>T</title>\n  </head>\n  <body>\n    <div class=\"a\">\n\
+                     \x20     <p>Hello <a href=\"x\">world</a>!</p>\n      <pre>  a\n b</pre>\n      <br><img src=\"i.png\"/>\n\
+                     \x20     <hr>\n    </div>\n    <!-- c -->\n  </body>\n</html>\n";
+        assert_eq!(pretty_html(before), after);
+        assert_eq!(pretty_html(after), after);
+    }
+
+    #[test]
+    fn external_links_get_rel_and_target() {
+        let site = Some("example.com");
+        assert_eq!(
+            external_link_attributes("<p><a href=\"https://rust-lang.org/learn\">Rust</a> <a href=\"/about\">about</a></p>", site, true),
+            "<p><a href=\"https://rust-lang.org/learn\" rel=\"noopener noreferrer\" target=\"_blank\">Rust</a> <a href=\"/about\">about</a></p>"
+        );
+        // Same host, with another case, a port or a user, is internal
+        for href in ["https://Example.com/x", "http://example.com:8080/", "//user@example.com/a", "#top", "mailto:me@example.org"] {
+            let html = format!("<a href=\"{}\">x</a>", href);
+            assert_eq!(external_link_attributes(&html, site, true), html);
+        }
+        // Single quotes, uppercase tags, self-closing anchors and a > inside a quoted value
+        assert_eq!(
+            external_link_attributes("<A class='x' title=\"a > b\" HREF='http://other.org/?q=\"1\"' >o</A><a href=\"//cdn.org\"/>", site, false),
+            "<A class='x' title=\"a > b\" HREF='http://other.org/?q=\"1\"' rel='noopener noreferrer' >o</A><a href=\"//cdn.org\" rel=\"noopener noreferrer\"/>"
+        );
+        // Existing attributes win, and <abbr> is not an anchor
+        assert_eq!(
+            external_link_attributes("<a rel=\"me\" href=\"https://social.org/@me\">me</a><abbr title=\"https://x.org\">x</abbr>", site, true),
+            "<a rel=\"me\" href=\"https://social.org/@me\" target=\"_blank\">me</a><abbr title=\"https://x.org\">x</abbr>"
+        );
+        // Code samples and comments are left alone; without a site host every absolute link is external
+        let code = "<pre><a href=\"https://a.org\">a</a></pre><code class=\"html\"><a href=\"https://b.org\">b</a></code><!-- <a href=\"https://c.org\"> -->";
+        assert_eq!(external_link_attributes(code, None, true), code);
+        assert_eq!(
+            external_link_attributes("<a href=https://example.com>e</a>", None, false),
+            "<a href=https://example.com rel=\"noopener noreferrer\">e</a>"
+        );
+        assert_eq!(url_host("https://[::1]:8080/x").as_deref(), Some("::1"));
+        assert_eq!(url_host("/relative"), None);
+    }
+
+    #[test]
+    fn lazy_images_get_loading_and_sizes() {
+        let size = |src: &str| (src == "photo.png").then_some((640, 480));
+        assert_eq!(
+            lazy_images("<p><img src=\"photo.png\" alt=\"a > b\"><img src='https://cdn.org/x.jpg'/></p>", &size),
+            "<p><img src=\"photo.png\" alt=\"a > b\" loading=\"lazy\" decoding=\"async\" width=\"640\" height=\"480\">\
+             <img src='https://cdn.org/x.jpg' loading='lazy' decoding='async'/></p>"
+        );
+        // Attributes already there are kept, and set sizes are not replaced
+        assert_eq!(
+            lazy_images("<IMG SRC=\"photo.png\" Loading=\"eager\" width=\"10\">", &size),
+            "<IMG SRC=\"photo.png\" Loading=\"eager\" width=\"10\" decoding=\"async\">"
+        );
+        // Malformed tags, code samples and other elements are left alone
+        for html in [
+            "<img src=\"photo.png\" <p>text</p>",
+            "<img src=\"photo.png",
+            "<img src=\"photo.png>",
+            "<pre><img src=\"photo.png\"></pre><code><img src=\"photo.png\"></code>",
+            "<imgx src=\"photo.png\"><picture></picture>",
+        ] {
+            assert_eq!(lazy_images(html, &size), html);
+        }
+    }
+
+    #[test]
+    fn prefixed_links_become_absolute() {
+        let before = "<link href=\"/static/style.css\"><a href=\"../../rust/\">r</a><a href=\"#top\">t</a>\
+                      <img src=\"photo.png\"><a href=\"https://example.com/\">e</a>";
+        let after = "<link href=\"/blog/static/style.css\"><a href=\"/blog/rust/\">r</a><a href=\"#top\">t</a>\
+                     <img src=\"/blog/entries/first/photo.png\"><a href=\"https://example.com/\">e</a>";
+        assert_eq!(prefix_links(before, "entries/first", "/blog/"), after);
+        assert_eq!(prefix_links("<a href=\"../\">home</a>", "entries", "/blog/"), "<a href=\"/blog/\">home</a>");
+    }
+
+    #[test]
+    fn bare_urls_become_links() {
+        let link = |url: &str| format!("<a href=\"{0}\">{0}</a>", url);
+        let cases = [
+            ("See https://example.com.", format!("See {}.", link("https://example.com"))),
+            ("(at http://example.com/a_(b)) and", format!("(at {}) and", link("http://example.com/a_(b)"))),
+            ("(see https://example.com/x), then", format!("(see {}), then", link("https://example.com/x"))),
+            ("https://example.com/?a=1&amp;b=2!", format!("{}!", link("https://example.com/?a=1&amp;b=2"))),
+            ("&quot;https://example.com/q&quot;", format!("&quot;{}&quot;", link("https://example.com/q"))),
+            ("&lt;HTTPS://example.com&gt;", format!("&lt;{}&gt;", link("HTTPS://example.com"))),
+            ("nohttps://example.com, https:// and http://.", "nohttps://example.com, https:// and http://.".to_string()),
+        ];
+        for (before, after) in cases {
+            assert_eq!(linkify_urls(before), after, "{}", before);
+        }
+    }
+
+    #[test]
+    fn smart_punctuation_cases() {
+        let cases = [
+            ("\"Hello,\" she said.", "\u{201c}Hello,\u{201d} she said."),
+            ("it's 'quoted' and the dogs' bowls", "it\u{2019}s \u{2018}quoted\u{2019} and the dogs\u{2019} bowls"),
+            ("back in '90s rock", "back in \u{2019}90s rock"),
+            ("pages 1--5 --- or more...", "pages 1\u{2013}5 \u{2014} or more\u{2026}"),
+            ("(\"nested 'quotes'\")", "(\u{201c}nested \u{2018}quotes\u{2019}\u{201d})"),
+            ("<p>'Tis</p><p>\"Yes\"</p>", "<p>\u{2018}Tis</p><p>\u{201c}Yes\u{201d}</p>"),
+            ("<em>it</em>'s", "<em>it</em>\u{2019}s"),
+            ("<a href=\"x\" title='a--b'>it's</a>", "<a href=\"x\" title='a--b'>it\u{2019}s</a>"),
+            ("<code>\"--\"</code>'s <pre>'...'</pre>", "<code>\"--\"</code>\u{2019}s <pre>'...'</pre>"),
+            ("<!-- don't -- touch --> ok", "<!-- don't -- touch --> ok"),
+            ("&quot;AT&amp;T&#39;s&quot;&nbsp;&#39;x&#39;", "\u{201c}AT&amp;T\u{2019}s\u{201d}&nbsp;\u{2018}x\u{2019}"),
+            ("a < b's", "a < b\u{2019}s"),
+            ("<p>\u{e9}t\u{e9}</p>\u{e9}l\u{e8}ve's", "<p>\u{e9}t\u{e9}</p>\u{e9}l\u{e8}ve\u{2019}s"),
+        ];
+        for (before, after) in cases {
+            assert_eq!(smart_punctuation(before), after, "{}", before);
+        }
+    }
+
+    #[test]
+    fn root_relative_links_keep_external_urls() {
+        let before = "<link href=\"../../static/style.css\"><a href=\"rust/index.html#top\">rust</a> \
+                      <a href=\"https://example.org/x\">x</a> <a href=\"#top\">top</a> <img src=\"/images/a.png\">";
+        let after = "<link href=\"/static/style.css\"><a href=\"/rust/index.html#top\">rust</a> \
+                     <a href=\"https://example.org/x\">x</a> <a href=\"#top\">top</a> <img src=\"/images/a.png\">";
+        assert_eq!(root_relative_links(before, "/"), after);
+    }
+
+    #[test]
+    fn rebased_links_point_at_the_same_files() {
+        let before = "<img src=\"../../images/a.png\"><a href=\"../second/index.html#end\">2</a> <a href=\"/rust/\">rust</a> \
+                      <a href=\"https://example.org/x\">x</a> <a href=\"#top\">top</a>";
+        let after = "<img src=\"../images/a.png\"><a href=\"../entries/second/index.html#end\">2</a> <a href=\"../rust/\">rust</a> \
+                     <a href=\"https://example.org/x\">x</a> <a href=\"#top\">top</a>";
+        assert_eq!(rebase_links(before, "entries/first", "entries"), after);
+        assert_eq!(rebase_links("<a href=\"first/index.html\">1</a>", "entries", ""), "<a href=\"entries/first/index.html\">1</a>");
+    }
+}
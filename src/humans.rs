@@ -0,0 +1,134 @@
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use crate::config::SiteConfig;
+use crate::toml::{expect_str, invalid_data, Table, Value};
+
+// One person of the TEAM section of humans.txt, from an [[authors]] table in ironite.toml
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct AuthorMeta {
+    pub name: String,
+    // What they did, e.g. "Developer". Listed as "Author" when not given
+    pub role: Option<String>,
+    // Twitter handle, with or without the @
+    pub twitter: Option<String>,
+    pub location: Option<String>,
+}
+
+impl AuthorMeta {
+    pub fn from_table(table: &Table) -> io::Result<AuthorMeta> {
+        let optional = |key: &str| -> io::Result<Option<String>> {
+            table.get(key).map(|value| expect_str(&format!("authors.{}", key), value).map(str::to_string)).transpose()
+        };
+        let name = optional("name")?.ok_or_else(|| invalid_data("authors: every author needs a name".to_string()))?;
+        Ok(AuthorMeta { name, role: optional("role")?, twitter: optional("twitter")?, location: optional("location")? })
+    }
+}
+
+// Read the [[authors]] array of tables
+pub fn read_authors(value: &Value) -> io::Result<Vec<AuthorMeta>> {
+    let items = value
+        .as_array()
+        .ok_or_else(|| invalid_data(format!("authors: expected array of tables, found {}", value.type_name())))?;
+    items
+        .iter()
+        .map(|item| {
+            let table = item
+                .as_table()
+                .ok_or_else(|| invalid_data(format!("authors: expected table, found {}", item.type_name())))?;
+            AuthorMeta::from_table(table)
+        })
+        .collect()
+}
+
+// Write humans.txt (https://humanstxt.org/) to output_path
+pub fn generate_humans_txt(config: &SiteConfig, authors: &[AuthorMeta], output_path: &Path) -> io::Result<()> {
+    fs::write(output_path, humans_txt(config, authors))
+}
+
+// TEAM lists the authors, or the site author when there are none. THANKS lists config.thanks, and
+// SITE the build date, the language and the tools
+fn humans_txt(config: &SiteConfig, authors: &[AuthorMeta]) -> String {
+    let mut text = String::from("/* TEAM */\n");
+    let site_author = config.author.iter().map(|name| AuthorMeta { name: name.clone(), ..AuthorMeta::default() }).collect::<Vec<_>>();
+    let team = if authors.is_empty() { &site_author[..] } else { authors };
+    for (i, author) in team.iter().enumerate() {
+        if i > 0 {
+            text.push('\n');
+        }
+        text.push_str(&format!("\t{}: {}\n", author.role.as_deref().unwrap_or("Author"), author.name));
+        if let Some(twitter) = &author.twitter {
+            text.push_str(&format!("\tTwitter: @{}\n", twitter.trim_start_matches('@')));
+        }
+        if let Some(location) = &author.location {
+            text.push_str(&format!("\tLocation: {}\n", location));
+        }
+    }
+
+    text.push_str("\n/* THANKS */\n");
+    for name in &config.thanks {
+        text.push_str(&format!("\tName: {}\n", name));
+    }
+
+    text.push_str("\n/* SITE */\n");
+    if let Some(date) = &config.build_date {
+        text.push_str(&format!("\tLast update: {}\n", date.to_rfc822()));
+    }
+    text.push_str(&format!("\tLanguage: {}\n", config.language));
+    text.push_str("\tStandards: HTML5, CSS3\n");
+    text.push_str(&format!("\tSoftware: ironite {}\n", env!("CARGO_PKG_VERSION")));
+    text
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::date::Date;
+    use crate::test_util::TempDir;
+    use crate::toml;
+
+    #[test]
+    fn sections_and_author_fields() {
+        let config = SiteConfig {
+            build_date: Date::parse("2024-03-15T10:00:00Z"),
+            language: "fr".to_string(),
+            thanks: vec!["Ada Lovelace".to_string()],
+            ..SiteConfig::default()
+        };
+        let table = toml::parse(
+            "[[authors]]\nname = \"Jane Doe\"\nrole = \"Developer\"\ntwitter = \"@jane\"\nlocation = \"Lyon, France\"\n\n\
+             [[authors]]\nname = \"John Roe\"\ntwitter = \"john\"\n",
+        )
+        .unwrap();
+        let authors = read_authors(&table["authors"]).unwrap();
+
+        let dir = TempDir::new("humans");
+        let path = dir.path().join("humans.txt");
+        generate_humans_txt(&config, &authors, &path).unwrap();
+        let text = fs::read_to_string(path).unwrap();
+        let team = text.find("/* TEAM */").unwrap();
+        let thanks = text.find("/* THANKS */").unwrap();
+        let site = text.find("/* SITE */").unwrap();
+        assert!(team < thanks && thanks < site, "{}", text);
+        for line in [
+            "\tDeveloper: Jane Doe\n\tTwitter: @jane\n\tLocation: Lyon, France\n",
+            "\tAuthor: John Roe\n\tTwitter: @john\n",
+            "\tName: Ada Lovelace\n",
+            "\tLast update: Fri, 15 Mar 2024 10:00:00 +0000\n",
+            "\tLanguage: fr\n",
+            "\tStandards: HTML5, CSS3\n",
+        ] {
+            assert!(text.contains(line), "{:?} missing from\n{}", line, text);
+        }
+    }
+
+    #[test]
+    fn site_author_without_authors_and_name_required() {
+        let config = SiteConfig { author: Some("Jane Doe".to_string()), ..SiteConfig::default() };
+        assert!(humans_txt(&config, &[]).starts_with("/* TEAM */\n\tAuthor: Jane Doe\n"));
+
+        let table = toml::parse("[[authors]]\nrole = \"Developer\"\n").unwrap();
+        assert!(read_authors(&table["authors"]).is_err());
+    }
+}
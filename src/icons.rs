@@ -0,0 +1,107 @@
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use crate::config::SiteConfig;
+use crate::html::escape_html;
+
+// Icon of the site in the project root, copied to public/favicon.ico
+pub const FAVICON_FILE: &str = "favicon.ico";
+
+// More icons in the project root, copied to public/icons/. Files named apple-touch-icon* are touch
+// icons, and a size in the name (icon-32x32.png) is given to browsers
+pub const ICONS_DIR: &str = "icons";
+
+// Media type of an icon, from its extension
+fn icon_type(name: &str) -> Option<&'static str> {
+    let extension = name.rsplit_once('.')?.1.to_ascii_lowercase();
+    Some(match extension.as_str() {
+        "ico" => "image/x-icon",
+        "png" => "image/png",
+        "svg" => "image/svg+xml",
+        "gif" => "image/gif",
+        "jpg" | "jpeg" => "image/jpeg",
+        "webp" => "image/webp",
+        _ => return None,
+    })
+}
+
+// A size such as 180x180 in an icon's file name
+fn icon_sizes(name: &str) -> Option<&str> {
+    let stem = name.rsplit_once('.').map(|(stem, _)| stem).unwrap_or(name);
+    stem.split(['-', '_', '.']).find(|part| {
+        part.split_once('x')
+            .is_some_and(|(width, height)| !width.is_empty() && !height.is_empty() && width.bytes().chain(height.bytes()).all(|b| b.is_ascii_digit()))
+    })
+}
+
+// <link> elements for $ICONS: favicon.ico, then the images of icons/ sorted by name, when they are in
+// extra_files and so copied to public/. Links are root-relative, or absolute with base_url, since the
+// same value is used at every depth. Empty when the project has no icons
+pub fn icon_links(config: &SiteConfig) -> io::Result<String> {
+    let copied = |name: &str| config.extra_files.iter().any(|path| path == Path::new(name));
+    let root = if config.base_url.is_empty() { "/".to_string() } else { config.url("") };
+    let mut links = String::new();
+
+    if copied(FAVICON_FILE) && config.path(FAVICON_FILE).is_file() {
+        links.push_str(&format!("<link rel=\"icon\" href=\"{}{}\">", root, FAVICON_FILE));
+    }
+
+    let icons_dir = config.path(ICONS_DIR);
+    if copied(ICONS_DIR) && icons_dir.is_dir() {
+        let mut names = Vec::new();
+        for entry in fs::read_dir(&icons_dir)? {
+            let entry = entry?;
+            if entry.file_type()?.is_file() {
+                if let Some(name) = entry.file_name().to_str() {
+                    names.push(name.to_string());
+                }
+            }
+        }
+        names.sort();
+        for name in names {
+            let Some(media_type) = icon_type(&name) else { continue };
+            let rel = if name.starts_with("apple-touch-icon") { "apple-touch-icon" } else { "icon" };
+            let mut link = format!("<link rel=\"{}\" href=\"{}{}/{}\"", rel, root, ICONS_DIR, escape_html(&name));
+            if rel == "icon" {
+                link.push_str(&format!(" type=\"{}\"", media_type));
+            }
+            if let Some(sizes) = icon_sizes(&name) {
+                link.push_str(&format!(" sizes=\"{}\"", sizes));
+            }
+            link.push('>');
+            links.push_str(&link);
+        }
+    }
+    Ok(links)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::{write, TempDir};
+
+    #[test]
+    fn links_for_favicon_and_icons() {
+        let dir = TempDir::new("icon-links");
+        let config = SiteConfig { root: dir.path().to_path_buf(), ..SiteConfig::default() };
+        assert_eq!(icon_links(&config).unwrap(), "");
+
+        write(&dir.path().join(FAVICON_FILE), "ico");
+        write(&dir.path().join(ICONS_DIR).join("icon-32x32.png"), "png");
+        write(&dir.path().join(ICONS_DIR).join("apple-touch-icon-180x180.png"), "png");
+        write(&dir.path().join(ICONS_DIR).join("icon.svg"), "svg");
+        write(&dir.path().join(ICONS_DIR).join("README.txt"), "notes");
+        assert_eq!(
+            icon_links(&config).unwrap(),
+            "<link rel=\"icon\" href=\"/favicon.ico\">\
+             <link rel=\"apple-touch-icon\" href=\"/icons/apple-touch-icon-180x180.png\" sizes=\"180x180\">\
+             <link rel=\"icon\" href=\"/icons/icon-32x32.png\" type=\"image/png\" sizes=\"32x32\">\
+             <link rel=\"icon\" href=\"/icons/icon.svg\" type=\"image/svg+xml\">"
+        );
+
+        // Icons that are not copied are not linked
+        let config = SiteConfig { base_url: "https://example.com".to_string(), extra_files: vec![FAVICON_FILE.into()], ..config };
+        assert_eq!(icon_links(&config).unwrap(), "<link rel=\"icon\" href=\"https://example.com/favicon.ico\">");
+    }
+}
@@ -0,0 +1,90 @@
+use std::fs;
+use std::path::Path;
+
+// Width and height of a PNG, GIF, JPEG or WebP image, read from its header. None for other formats
+// and for files too short or damaged to tell
+pub fn image_size(bytes: &[u8]) -> Option<(u32, u32)> {
+    let be16 = |at: usize| Some(u16::from_be_bytes(bytes.get(at..at + 2)?.try_into().ok()?) as u32);
+    let be32 = |at: usize| Some(u32::from_be_bytes(bytes.get(at..at + 4)?.try_into().ok()?));
+    let le16 = |at: usize| Some(u16::from_le_bytes(bytes.get(at..at + 2)?.try_into().ok()?) as u32);
+    let le24 = |at: usize| Some(le16(at)? | (*bytes.get(at + 2)? as u32) << 16);
+
+    if bytes.starts_with(b"\x89PNG\r\n\x1a\n") && bytes.get(12..16) == Some(b"IHDR") {
+        return Some((be32(16)?, be32(20)?));
+    }
+    if bytes.starts_with(b"GIF87a") || bytes.starts_with(b"GIF89a") {
+        return Some((le16(6)?, le16(8)?));
+    }
+    if bytes.starts_with(b"RIFF") && bytes.get(8..12) == Some(b"WEBP") {
+        return match bytes.get(12..16)? {
+            b"VP8 " => Some((le16(26)? & 0x3fff, le16(28)? & 0x3fff)),
+            b"VP8L" => {
+                let bits = u32::from_le_bytes(bytes.get(21..25)?.try_into().ok()?);
+                Some(((bits & 0x3fff) + 1, ((bits >> 14) & 0x3fff) + 1))
+            }
+            b"VP8X" => Some((le24(24)? + 1, le24(27)? + 1)),
+            _ => None,
+        };
+    }
+    if bytes.starts_with(&[0xff, 0xd8]) {
+        // Walk the segments up to a start of frame, which holds the size
+        let mut at = 2;
+        while *bytes.get(at)? == 0xff {
+            let marker = *bytes.get(at + 1)?;
+            if matches!(marker, 0xc0..=0xcf) && !matches!(marker, 0xc4 | 0xc8 | 0xcc) {
+                return Some((be16(at + 7)?, be16(at + 5)?));
+            }
+            at += 2 + be16(at + 2)? as usize;
+        }
+    }
+    None
+}
+
+// Size of the image at path, None when it cannot be read or measured
+pub fn image_file_size(path: &Path) -> Option<(u32, u32)> {
+    image_size(&fs::read(path).ok()?)
+}
+
+// Size of the image an entry's src points to, for images in the entry directory. URLs with a scheme,
+// protocol-relative and root-relative ones, and paths leaving the entry directory give None
+pub fn entry_image_size(entry_path: &Path, src: &str) -> Option<(u32, u32)> {
+    let path = src.split(['?', '#']).next().unwrap_or_default();
+    let has_scheme = path.split_once(':').is_some_and(|(scheme, _)| !scheme.contains('/'));
+    if path.is_empty() || has_scheme || path.starts_with('/') || path.split('/').any(|part| part == "..") {
+        return None;
+    }
+    image_file_size(&entry_path.join(path.trim_start_matches("./")))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::TempDir;
+
+    #[test]
+    fn sizes_from_image_headers() {
+        let mut png = b"\x89PNG\r\n\x1a\n\0\0\0\rIHDR".to_vec();
+        png.extend_from_slice(&[0, 0, 2, 128, 0, 0, 1, 224]);
+        assert_eq!(image_size(&png), Some((640, 480)));
+        assert_eq!(image_size(b"GIF89a\x10\x00\x20\x00"), Some((16, 32)));
+        // JPEG with an APP0 segment before the baseline start of frame
+        let jpeg = [0xff, 0xd8, 0xff, 0xe0, 0, 4, 0, 0, 0xff, 0xc0, 0, 17, 8, 0, 100, 0, 200];
+        assert_eq!(image_size(&jpeg), Some((200, 100)));
+        let mut webp = b"RIFF\0\0\0\0WEBPVP8X\0\0\0\0\0\0\0\0".to_vec();
+        webp.extend_from_slice(&[99, 0, 0, 49, 0, 0]);
+        assert_eq!(image_size(&webp), Some((100, 50)));
+        assert_eq!(image_size(b"<svg></svg>"), None);
+        assert_eq!(image_size(&png[..18]), None);
+    }
+
+    #[test]
+    fn only_images_of_the_entry_are_measured() {
+        let dir = TempDir::new("entry-image-size");
+        fs::write(dir.path().join("photo.gif"), b"GIF87a\x03\x00\x02\x00").unwrap();
+        assert_eq!(entry_image_size(dir.path(), "photo.gif"), Some((3, 2)));
+        assert_eq!(entry_image_size(dir.path(), "./photo.gif?v=2"), Some((3, 2)));
+        for src in ["https://example.com/photo.gif", "//example.com/photo.gif", "/photo.gif", "../photo.gif", "missing.gif", "data:image/gif;base64,R0lG"] {
+            assert_eq!(entry_image_size(dir.path(), src), None);
+        }
+    }
+}
@@ -0,0 +1,329 @@
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use crate::html::{resolve_reference, tag_end};
+
+// Stylesheets and scripts up to this many bytes are inlined by default
+pub const DEFAULT_INLINE_MAX_SIZE: u64 = 4096;
+
+// Entry pages made self-contained by embed_assets are reported when larger than this, in bytes
+pub const EMBEDDED_PAGE_WARNING_SIZE: u64 = 1024 * 1024;
+
+// Attributes of a tag, in order: name (lowercased) and value, None for a bare attribute
+fn tag_attributes(tag: &str) -> Vec<(String, Option<String>)> {
+    let inner = tag.trim_start_matches('<').trim_end_matches('>').trim_end_matches('/');
+    let mut chars = inner.char_indices().peekable();
+    // Skip the tag name
+    while chars.next_if(|(_, c)| !c.is_whitespace()).is_some() {}
+
+    let mut attributes = Vec::new();
+    loop {
+        while chars.next_if(|(_, c)| c.is_whitespace()).is_some() {}
+        let Some(&(start, _)) = chars.peek() else { break };
+        while chars.next_if(|(_, c)| !c.is_whitespace() && *c != '=').is_some() {}
+        let name_end = chars.peek().map(|(i, _)| *i).unwrap_or(inner.len());
+        let name = inner[start..name_end].to_ascii_lowercase();
+        while chars.next_if(|(_, c)| c.is_whitespace()).is_some() {}
+        if chars.next_if(|(_, c)| *c == '=').is_none() {
+            attributes.push((name, None));
+            continue;
+        }
+        while chars.next_if(|(_, c)| c.is_whitespace()).is_some() {}
+        let value = match chars.next_if(|(_, c)| *c == '"' || *c == '\'') {
+            Some((open, quote)) => {
+                while chars.next_if(|(_, c)| *c != quote).is_some() {}
+                let end = chars.next().map(|(i, _)| i).unwrap_or(inner.len());
+                inner[open + 1..end].to_string()
+            }
+            None => {
+                let value_start = chars.peek().map(|(i, _)| *i).unwrap_or(inner.len());
+                while chars.next_if(|(_, c)| !c.is_whitespace()).is_some() {}
+                let end = chars.peek().map(|(i, _)| *i).unwrap_or(inner.len());
+                inner[value_start..end].to_string()
+            }
+        };
+        attributes.push((name, Some(value)));
+    }
+    attributes
+}
+
+fn attribute<'a>(attributes: &'a [(String, Option<String>)], name: &str) -> Option<&'a str> {
+    attributes.iter().find(|(key, _)| key == name).and_then(|(_, value)| value.as_deref())
+}
+
+// Attribute value in double quotes, or single quotes when it holds a double quote
+fn quoted(value: &str) -> String {
+    if value.contains('"') {
+        format!("'{}'", value)
+    } else {
+        format!("\"{}\"", value)
+    }
+}
+
+// Whether html starts with the opening tag of the named element
+fn opens(html: &str, name: &str) -> bool {
+    let bytes = html.as_bytes();
+    let end = 1 + name.len();
+    bytes.len() > end && bytes[1..end].eq_ignore_ascii_case(name.as_bytes()) && matches!(bytes[end], b' ' | b'\t' | b'\n' | b'\r')
+}
+
+// Where inline_html reads assets from, and which ones it takes
+struct Inlining<'a> {
+    static_dir: &'a Path,
+    // Largest stylesheet or script inlined. None takes them whatever their size or content, to make
+    // the page self-contained
+    max_size: Option<u64>,
+    // Images are read from static_dir and this directory and become data: URIs. None leaves them alone
+    images_dir: Option<&'a Path>,
+}
+
+// Content of a local asset to inline. reference is resolved against page_dir, and must point into the
+// static directory. When inlining by size, stylesheets with url(...) stay external, since their
+// relative references would break. Content that would end the element early always stays external
+fn inlinable_content(reference: &str, page_dir: &str, inlining: &Inlining, closing: &str) -> Option<String> {
+    let resolved = resolve_reference(page_dir, reference)?;
+    let relative = resolved.strip_prefix("static/")?;
+    let path = inlining.static_dir.join(relative);
+    if inlining.max_size.is_some_and(|max_size| fs::metadata(&path).is_ok_and(|metadata| metadata.len() > max_size)) {
+        return None;
+    }
+    let content = fs::read_to_string(&path).ok()?;
+    let lower = content.to_ascii_lowercase();
+    if lower.contains(closing) || (inlining.max_size.is_some() && closing == "</style" && lower.contains("url(")) {
+        return None;
+    }
+    Some(content)
+}
+
+// Media type of an image, from its extension
+fn image_type(path: &Path) -> Option<&'static str> {
+    let extension = path.extension()?.to_str()?.to_ascii_lowercase();
+    Some(match extension.as_str() {
+        "png" => "image/png",
+        "jpg" | "jpeg" => "image/jpeg",
+        "gif" => "image/gif",
+        "svg" => "image/svg+xml",
+        "webp" => "image/webp",
+        "avif" => "image/avif",
+        "ico" => "image/x-icon",
+        _ => return None,
+    })
+}
+
+// data: URI of a local image of static/ or images/
+fn image_data_uri(reference: &str, page_dir: &str, static_dir: &Path, images_dir: &Path) -> Option<String> {
+    let resolved = resolve_reference(page_dir, reference)?;
+    let path = match resolved.strip_prefix("static/") {
+        Some(relative) => static_dir.join(relative),
+        None => images_dir.join(resolved.strip_prefix("images/")?),
+    };
+    let media_type = image_type(&path)?;
+    let bytes = fs::read(&path).ok()?;
+    Some(format!("data:{};base64,{}", media_type, base64(&bytes)))
+}
+
+const BASE64_ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+// Standard base64, with padding
+fn base64(bytes: &[u8]) -> String {
+    let mut encoded = String::with_capacity(bytes.len().div_ceil(3) * 4);
+    for chunk in bytes.chunks(3) {
+        let group = (chunk[0] as u32) << 16 | (*chunk.get(1).unwrap_or(&0) as u32) << 8 | *chunk.get(2).unwrap_or(&0) as u32;
+        for i in 0..4 {
+            if i <= chunk.len() {
+                encoded.push(BASE64_ALPHABET[(group >> (18 - 6 * i)) as usize & 63] as char);
+            } else {
+                encoded.push('=');
+            }
+        }
+    }
+    encoded
+}
+
+fn push_attributes<'a>(out: &mut String, attributes: impl Iterator<Item = (&'a str, Option<&'a str>)>) {
+    for (name, value) in attributes {
+        match value {
+            Some(value) => out.push_str(&format!(" {}={}", name, quoted(value))),
+            None => out.push_str(&format!(" {}", name)),
+        }
+    }
+}
+
+// Replace stylesheet links and external scripts pointing at small files of static/ with <style> and
+// <script> elements holding their content. page_dir is the directory of the page, relative to the
+// public directory. media on stylesheets is kept; on scripts, src, defer and async are dropped,
+// other attributes (type="module") are kept
+pub fn inline_assets_in_html(html: &str, page_dir: &str, static_dir: &Path, max_size: u64) -> String {
+    inline_html(html, page_dir, &Inlining { static_dir, max_size: Some(max_size), images_dir: None })
+}
+
+// Make a page self-contained: every stylesheet and script of static/ is inlined as with
+// inline_assets_in_html, whatever its size, and <img> elements showing images of static/ or
+// images_dir get their content as a data: URI
+pub fn embed_assets_in_html(html: &str, page_dir: &str, static_dir: &Path, images_dir: &Path) -> String {
+    inline_html(html, page_dir, &Inlining { static_dir, max_size: None, images_dir: Some(images_dir) })
+}
+
+fn inline_html(html: &str, page_dir: &str, inlining: &Inlining) -> String {
+    let mut result = String::with_capacity(html.len());
+    let mut rest = html;
+
+    while let Some(start) = rest.find('<') {
+        result.push_str(&rest[..start]);
+        rest = &rest[start..];
+        let end = tag_end(rest);
+        let tag = &rest[..end];
+
+        if opens(tag, "link") {
+            let attributes = tag_attributes(tag);
+            let is_stylesheet = attribute(&attributes, "rel").is_some_and(|rel| rel.split_whitespace().any(|rel| rel.eq_ignore_ascii_case("stylesheet")));
+            let content = attribute(&attributes, "href")
+                .filter(|_| is_stylesheet)
+                .and_then(|href| inlinable_content(href, page_dir, inlining, "</style"));
+            if let Some(content) = content {
+                match attribute(&attributes, "media") {
+                    Some(media) => result.push_str(&format!("<style media={}>", quoted(media))),
+                    None => result.push_str("<style>"),
+                }
+                result.push_str(&content);
+                result.push_str("</style>");
+                rest = &rest[end..];
+                continue;
+            }
+        } else if opens(tag, "script") {
+            let attributes = tag_attributes(tag);
+            let after = &rest[end..];
+            let body_end = after.len() - after.trim_start().len();
+            let closes = after[body_end..].get(..9).is_some_and(|closing| closing.eq_ignore_ascii_case("</script>"));
+            let content = attribute(&attributes, "src")
+                .filter(|_| closes)
+                .and_then(|src| inlinable_content(src, page_dir, inlining, "</script"));
+            if let Some(content) = content {
+                result.push_str("<script");
+                push_attributes(
+                    &mut result,
+                    attributes
+                        .iter()
+                        .filter(|(name, _)| !matches!(name.as_str(), "src" | "defer" | "async"))
+                        .map(|(name, value)| (name.as_str(), value.as_deref())),
+                );
+                result.push('>');
+                result.push_str(&content);
+                result.push_str("</script>");
+                rest = &after[body_end + 9..];
+                continue;
+            }
+        } else if let Some(images_dir) = inlining.images_dir.filter(|_| opens(tag, "img")) {
+            let attributes = tag_attributes(tag);
+            let uri = attribute(&attributes, "src").and_then(|src| image_data_uri(src, page_dir, inlining.static_dir, images_dir));
+            if let Some(uri) = uri {
+                result.push_str("<img");
+                push_attributes(
+                    &mut result,
+                    attributes
+                        .iter()
+                        .map(|(name, value)| if name == "src" { (name.as_str(), Some(uri.as_str())) } else { (name.as_str(), value.as_deref()) }),
+                );
+                result.push_str(if tag.ends_with("/>") { " />" } else { ">" });
+                rest = &rest[end..];
+                continue;
+            }
+        }
+
+        result.push_str(tag);
+        rest = &rest[end..];
+    }
+
+    result.push_str(rest);
+    result
+}
+
+// Inline small stylesheets and scripts in every HTML page under the public directory, leaving the
+// directories in skip (copied files) alone. Returns the number of pages changed
+pub fn inline_assets(public_dir: &Path, static_dir: &Path, max_size: u64, skip: &[&Path]) -> io::Result<usize> {
+    inline_directory(public_dir, public_dir, static_dir, max_size, skip)
+}
+
+fn inline_directory(public_dir: &Path, dir: &Path, static_dir: &Path, max_size: u64, skip: &[&Path]) -> io::Result<usize> {
+    let mut changed = 0;
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        if skip.contains(&path.as_path()) {
+            continue;
+        }
+        if path.is_dir() {
+            changed += inline_directory(public_dir, &path, static_dir, max_size, skip)?;
+        } else if path.extension().is_some_and(|ext| ext == "html") {
+            let page_dir = path
+                .parent()
+                .and_then(|parent| parent.strip_prefix(public_dir).ok())
+                .map(|parent| parent.to_string_lossy().replace('\\', "/"))
+                .unwrap_or_default();
+            let html = fs::read_to_string(&path)?;
+            let inlined = inline_assets_in_html(&html, &page_dir, static_dir, max_size);
+            if inlined != html {
+                fs::write(&path, inlined)?;
+                changed += 1;
+            }
+        }
+    }
+    Ok(changed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::{write, TempDir};
+
+    #[test]
+    fn small_local_assets_are_inlined() {
+        let dir = TempDir::new("inline-assets");
+        let static_dir = dir.path().join("static");
+        write(&static_dir.join("style.css"), "body { margin: 0 }");
+        write(&static_dir.join("app.js"), "console.log(1 < 2);");
+        write(&static_dir.join("big.css"), &"p { color: red }\n".repeat(100));
+        write(&static_dir.join("font.css"), "@font-face { src: url(font.woff) }");
+
+        let html = "<head><link rel=\"stylesheet\" href=\"../../static/style.css\" media=\"screen\">\
+                    <link rel=\"stylesheet\" href=\"../../static/big.css\"><link rel=\"stylesheet\" href=\"../../static/font.css\">\
+                    <link rel=\"icon\" href=\"../../static/style.css\"><LINK REL=stylesheet HREF=\"https://cdn.example.com/x.css\">\
+                    <script type=\"module\" src=\"/static/app.js\" defer></script><script src=\"../../static/missing.js\"></script></head>";
+        let inlined = inline_assets_in_html(html, "entries/first", &static_dir, 1024);
+        assert_eq!(
+            inlined,
+            "<head><style media=\"screen\">body { margin: 0 }</style>\
+             <link rel=\"stylesheet\" href=\"../../static/big.css\"><link rel=\"stylesheet\" href=\"../../static/font.css\">\
+             <link rel=\"icon\" href=\"../../static/style.css\"><LINK REL=stylesheet HREF=\"https://cdn.example.com/x.css\">\
+             <script type=\"module\">console.log(1 < 2);</script><script src=\"../../static/missing.js\"></script></head>"
+        );
+    }
+
+    #[test]
+    fn embedding_takes_every_asset_and_image() {
+        let dir = TempDir::new("embed-assets");
+        let static_dir = dir.path().join("static");
+        let images_dir = dir.path().join("images");
+        write(&static_dir.join("big.css"), &"p { color: red }\n".repeat(100));
+        write(&static_dir.join("font.css"), "@font-face { src: url(font.woff) }");
+        write(&images_dir.join("dot.png"), "Man");
+        write(&images_dir.join("notes.txt"), "text");
+
+        let html = "<link rel=\"stylesheet\" href=\"/static/big.css\"><link rel=\"stylesheet\" href=\"../../static/font.css\">\
+                    <img src=\"../../images/dot.png\" alt=\"A dot\"/><img src=\"../../images/notes.txt\"><img src=\"https://example.com/x.png\">";
+        let embedded = embed_assets_in_html(html, "entries/first", &static_dir, &images_dir);
+        assert!(!embedded.contains("<link"), "{}", embedded);
+        assert!(embedded.contains("<style>@font-face { src: url(font.woff) }</style>"), "{}", embedded);
+        assert!(embedded.contains("<img src=\"data:image/png;base64,TWFu\" alt=\"A dot\" />"), "{}", embedded);
+        assert!(embedded.contains("<img src=\"../../images/notes.txt\"><img src=\"https://example.com/x.png\">"), "{}", embedded);
+    }
+
+    #[test]
+    fn base64_pads_partial_groups() {
+        assert_eq!(base64(b""), "");
+        assert_eq!(base64(b"M"), "TQ==");
+        assert_eq!(base64(b"Ma"), "TWE=");
+        assert_eq!(base64(b"Man"), "TWFu");
+        assert_eq!(base64(&[0xff, 0xfe, 0xfd, 0x00]), "//79AA==");
+    }
+}
@@ -0,0 +1,55 @@
+pub mod brotli;
+pub mod build_info;
+pub mod compress;
+pub mod config;
+pub mod csv;
+pub mod date;
+pub mod export;
+pub mod error;
+pub mod feed;
+pub mod fingerprint;
+pub mod footnotes;
+pub mod frontmatter;
+pub mod generator;
+pub mod highlight;
+pub mod hook;
+pub mod git;
+pub mod html;
+pub mod humans;
+pub mod icons;
+pub mod images;
+pub mod inline;
+pub mod json;
+pub mod links;
+pub mod markdown;
+pub mod meta;
+pub mod outputs;
+pub mod permalink;
+pub mod plugin;
+pub mod profile;
+pub mod redirect;
+pub mod retry;
+pub mod series;
+pub mod shortcode;
+pub mod site;
+pub mod sitemap;
+pub mod sort;
+pub mod stats;
+pub mod tag_alias;
+pub mod toml;
+pub mod transform;
+pub mod validate;
+
+#[cfg(test)]
+mod test_util;
+
+pub use config::SiteConfig;
+pub use date::Date;
+pub use error::IroniteError;
+pub use generator::*;
+pub use meta::EntryMeta;
+pub use plugin::GeneratorPlugin;
+pub use profile::Profiler;
+pub use site::{Site, SiteBuilder, TagNormalizer};
+pub use stats::SiteStats;
+pub use transform::{Transform, TransformPipeline};
@@ -0,0 +1,131 @@
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use crate::html::{next_link_attribute, resolve_reference};
+
+// A link in a generated page whose target does not exist under the public directory
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BrokenLink {
+    // Page containing the link, relative to the public directory
+    pub page: String,
+    // The href or src value as written
+    pub link: String,
+    // Where the link points, relative to the public directory
+    pub target: String,
+}
+
+impl fmt::Display for BrokenLink {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "broken link in {}: {} (no {})", self.page, self.link, self.target)
+    }
+}
+
+// Check every href and src in the HTML files under public_dir. Relative links are resolved against
+// the page's directory and root-relative ones against public_dir. External links (http:, mailto:, ...)
+// and fragment-only links are ignored. A link to a directory needs an index.html in it. path_prefix is
+// the path_prefix setting, taken off the root-relative links it starts
+pub fn check_internal_links(public_dir: &Path, path_prefix: &str) -> io::Result<Vec<BrokenLink>> {
+    let mut broken = Vec::new();
+    check_directory(public_dir, public_dir, "", path_prefix.trim_start_matches('/'), &mut broken)?;
+    Ok(broken)
+}
+
+fn check_directory(public_dir: &Path, dir: &Path, relative_dir: &str, prefix: &str, broken: &mut Vec<BrokenLink>) -> io::Result<()> {
+    let mut entries: Vec<_> = fs::read_dir(dir)?.filter_map(Result::ok).collect();
+    entries.sort_by_key(|entry| entry.file_name());
+
+    for entry in entries {
+        let path = entry.path();
+        let file_name = entry.file_name().to_string_lossy().into_owned();
+        let relative = if relative_dir.is_empty() { file_name.clone() } else { format!("{}/{}", relative_dir, file_name) };
+
+        if path.is_dir() {
+            check_directory(public_dir, &path, &relative, prefix, broken)?;
+            continue;
+        }
+        if !file_name.ends_with(".html") {
+            continue;
+        }
+
+        let html = fs::read_to_string(&path)?;
+        let mut rest = html.as_str();
+        while let Some((start, end)) = next_link_attribute(rest) {
+            let link = &rest[start..end];
+            if let Some(target) = resolve_reference(relative_dir, link) {
+                let unprefixed = format!("{}/", target).strip_prefix(prefix).map(|rest| rest.trim_end_matches('/').to_string());
+                let target = match unprefixed {
+                    Some(unprefixed) if link.starts_with('/') && !prefix.is_empty() => unprefixed,
+                    _ => target,
+                };
+                if !target_exists(public_dir, &target) {
+                    broken.push(BrokenLink { page: relative.clone(), link: link.to_string(), target });
+                }
+            }
+            rest = &rest[end..];
+        }
+    }
+
+    Ok(())
+}
+
+fn target_exists(public_dir: &Path, target: &str) -> bool {
+    let path = public_dir.join(percent_decode(target));
+    if path.is_dir() {
+        path.join("index.html").is_file()
+    } else {
+        path.is_file()
+    }
+}
+
+// Decode %XX escapes so links to names with spaces or non-ASCII characters can be found on disk
+pub(crate) fn percent_decode(text: &str) -> String {
+    let bytes = text.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'%' && i + 2 < bytes.len() && bytes[i + 1].is_ascii_hexdigit() && bytes[i + 2].is_ascii_hexdigit() {
+            let hex = std::str::from_utf8(&bytes[i + 1..i + 3]).unwrap_or("0");
+            decoded.push(u8::from_str_radix(hex, 16).unwrap_or(0));
+            i += 3;
+            continue;
+        }
+        decoded.push(bytes[i]);
+        i += 1;
+    }
+    String::from_utf8_lossy(&decoded).into_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::{write, TempDir};
+
+    #[test]
+    fn reports_only_missing_internal_targets() {
+        let dir = TempDir::new("links");
+        write(&dir.path().join("index.html"), "<a href=\"entries/a/index.html\">a</a> <a href=\"entries/b/\">b</a>");
+        write(
+            &dir.path().join("entries").join("a").join("index.html"),
+            "<link href=\"/static/style.css\"><a href=\"../missing/index.html\">x</a> <a href=\"#top\">top</a> \
+             <a href=\"https://example.com/\">ext</a> <a href='mailto:me@example.com'>mail</a> \
+             <img src=\"../../images/my%20photo.png\"> <a href=\"../../index.html#intro\">home</a>",
+        );
+        write(&dir.path().join("static").join("style.css"), "");
+        write(&dir.path().join("images").join("my photo.png"), "");
+
+        let broken = check_internal_links(dir.path(), "").unwrap();
+        assert_eq!(
+            broken,
+            [
+                BrokenLink {
+                    page: "entries/a/index.html".to_string(),
+                    link: "../missing/index.html".to_string(),
+                    target: "entries/missing/index.html".to_string(),
+                },
+                BrokenLink { page: "index.html".to_string(), link: "entries/b/".to_string(), target: "entries/b".to_string() },
+            ]
+        );
+    }
+}
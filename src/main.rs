@@ -1,8 +1,5 @@
-mod generator;
-pub use generator::*;
+use ironite::*;
 
-use std::collections::HashSet;
-use std::collections::HashMap;
 use std::env;
 use std::fs;
 use std::io;
@@ -16,20 +13,28 @@ const HTML_CONTENT: &str = include_str!("base.html");
 // Basic CSS
 const CSS_CONTENT: &str = include_str!("style.css");
 
+// Default content of the homepage. $NAVCLOUD is replaced with links to each tag index
+const ABOUT_CONTENT: &str = "<p>This will be shown at the blog index. Edit me at static/about.html</p> $NAVCLOUD";
+
+// Example entry created by the init command, so that the scaffolded site generates right away
+const SAMPLE_ENTRY_NAME: &str = "hello_world";
+const SAMPLE_ENTRY_CONTENT: &str = "<p>This is an example entry. Edit me at entries/hello_world/content.html</p>\n";
+const SAMPLE_ENTRY_TAGS: &str = "welcome\n";
+
 // Subdirectories created when initializing a new project
 const PROJECT_SUBDIRECTORIES:&[&str] = &["entries", "images", "static"];
 // ENTRIES: contains blog entries.
 // IMAGES: contains all project images.
 // STATIC: contains CSS, basic HTML and other elements
 
-fn create_directories(root: &str, sub_dirs: &[&str]) -> io::Result<()> {
+fn create_directories<P: AsRef<Path>>(root: P, sub_dirs: &[&str]) -> io::Result<()> {
     // Create a PathBuf from the root directory
-    let root_path = PathBuf::from(root);
+    let root_path = PathBuf::from(root.as_ref());
 
     // Iterate over the subdirectories
     for dir in sub_dirs {
         // Create the full path for each subdirectory
-        let mut path_buf = root_path.join(dir);
+        let path_buf = root_path.join(dir);
         // Create the directory (and any necessary parent directories)
         fs::create_dir_all(&path_buf)?;
         println!("Created directory: {}", path_buf.display());
@@ -40,7 +45,7 @@ fn create_directories(root: &str, sub_dirs: &[&str]) -> io::Result<()> {
 
 fn initialize_project(project_name: &str) -> io::Result<()> {
     // Create project directories
-    create_directories(project_name, &PROJECT_SUBDIRECTORIES)?;
+    create_directories(project_name, PROJECT_SUBDIRECTORIES)?;
 
     // Path to the projectname.txt file in the root directory
     let projectname_file_path = Path::new(project_name).join("projectname.txt");
@@ -62,7 +67,7 @@ fn initialize_project(project_name: &str) -> io::Result<()> {
     // Path to the projectname.txt file in the root directory
     let about_file_path = Path::new(project_name).join("static").join("about.html");
     let mut about_file = File::create(about_file_path)?;
-    about_file.write_all("<p>This will be shown at the blog index. Edit me at static/about.html</p> $NAVCLOUD".as_bytes())?;
+    about_file.write_all(ABOUT_CONTENT.as_bytes())?;
 
     println!("HTML content saved to static/base.html");
     println!("Project name saved to projectname.txt");
@@ -70,6 +75,44 @@ fn initialize_project(project_name: &str) -> io::Result<()> {
     Ok(())
 }
 
+// Scaffold a complete site in an existing directory, including an example entry.
+// Existing files are never touched unless force is set
+fn initialize_site(root: &Path, project_name: &str, force: bool) -> io::Result<()> {
+    let sample_entry_path = root.join("entries").join(SAMPLE_ENTRY_NAME);
+    let files = [
+        (root.join("projectname.txt"), project_name),
+        (root.join("static").join("base.html"), HTML_CONTENT),
+        (root.join("static").join("style.css"), CSS_CONTENT),
+        (root.join("static").join("about.html"), ABOUT_CONTENT),
+        (sample_entry_path.join("content.html"), SAMPLE_ENTRY_CONTENT),
+        (sample_entry_path.join("tags.txt"), SAMPLE_ENTRY_TAGS),
+    ];
+
+    // Check every file before writing anything, so a refused init leaves the directory untouched
+    if !force {
+        let existing: Vec<String> = files
+            .iter()
+            .filter(|(path, _)| path.exists())
+            .map(|(path, _)| path.display().to_string())
+            .collect();
+        if !existing.is_empty() {
+            return Err(io::Error::new(
+                io::ErrorKind::AlreadyExists,
+                format!("Refusing to overwrite existing files (use --force): {}", existing.join(", ")),
+            ));
+        }
+    }
+
+    create_directories(root, PROJECT_SUBDIRECTORIES)?;
+    fs::create_dir_all(&sample_entry_path)?;
+
+    for (path, content) in &files {
+        fs::write(path, content.as_bytes())?;
+        println!("Created file: {}", path.display());
+    }
+
+    Ok(())
+}
 
 fn initialize_entry(entry_name: &str) -> io::Result<()> {
     // Define the path to the entries directory
@@ -126,7 +169,25 @@ fn main() -> io::Result<()> {
                 return Ok(());
             }
             let project_name = &args[2];
-            initialize_project(project_name);
+            if let Err(e) = initialize_project(project_name) {
+                eprintln!("Error creating project: {}", e);
+            }
+        }
+
+        "init" => {
+            let force = args[2..].iter().any(|arg| arg == "--force");
+            let root = env::current_dir()?;
+            // Default the project name to the name of the current directory
+            let project_name = match args[2..].iter().find(|arg| !arg.starts_with("--")) {
+                Some(name) => name.clone(),
+                None => root
+                    .file_name()
+                    .map(|name| name.to_string_lossy().into_owned())
+                    .unwrap_or_else(|| "ironite".to_string()),
+            };
+            if let Err(e) = initialize_site(&root, &project_name, force) {
+                eprintln!("Error initializing site: {}", e);
+            }
         }
 
         "new_entry" => {
@@ -139,11 +200,13 @@ fn main() -> io::Result<()> {
                 return Ok(());
             }
             let entry_name = &args[2];
-            initialize_entry(entry_name);
+            if let Err(e) = initialize_entry(entry_name) {
+                eprintln!("Error creating entry: {}", e);
+            }
         }
 
         "print_entries_by_tag" => {
-	    let tags_map = filter_entries_by_tag();
+	    let tags_map = filter_entries_by_tag(Path::new("entries"));
     
 	    for (tag, paths) in tags_map {
 	        println!("Tag: {}", tag);
@@ -154,8 +217,44 @@ fn main() -> io::Result<()> {
         }
 
         "generate" => {
-    		if let Err(e) = generate_site() {
-        		eprintln!("Error generating site: {}", e);
+    		// --profile prints how long each step took, like profile = true in ironite.toml
+    		let profile = args[2..].iter().any(|arg| arg == "--profile");
+    		// --force builds despite unsafe entry or tag names, like allow_unsafe_names = true
+    		let force = args[2..].iter().any(|arg| arg == "--force");
+    		// --drafts builds draft entries too, like include_drafts = true
+    		let drafts = args[2..].iter().any(|arg| arg == "--drafts");
+    		// --check-links and --strict-links, like check_links = true and strict_links = true
+    		let check_links = args[2..].iter().any(|arg| arg == "--check-links");
+    		let strict_links = args[2..].iter().any(|arg| arg == "--strict-links");
+    		// --single <entry> rebuilds only the page of that entry, and with --with-tags its tag pages
+    		let single = args[2..].iter().position(|arg| arg == "--single").map(|i| args.get(i + 3));
+    		let with_tags = args[2..].iter().any(|arg| arg == "--with-tags");
+    		if single == Some(None) {
+    		    eprintln!("Error: No entry name provided after --single.");
+    		    return Ok(());
+    		}
+    		// --limit <n> builds only the first n entries of the entries index, like entry_limit = n
+    		let limit = args[2..].iter().position(|arg| arg == "--limit").map(|i| args.get(i + 3).and_then(|n| n.parse::<usize>().ok()));
+    		if limit == Some(None) {
+    		    eprintln!("Error: Expected a number of entries after --limit.");
+    		    return Ok(());
+    		}
+    		let site = SiteConfig::load(config::CONFIG_FILE).map_err(IroniteError::from).and_then(|mut config| {
+    		    config.profile |= profile;
+    		    config.allow_unsafe_names |= force;
+    		    config.include_drafts |= drafts;
+    		    config.check_links |= check_links;
+    		    config.strict_links |= strict_links;
+    		    config.entry_limit = limit.flatten().or(config.entry_limit);
+    		    Site::builder().config(config).build()
+    		});
+    		let generated = site.and_then(|site| match single.flatten() {
+    		    Some(name) => site.generate_single(name, with_tags),
+    		    None => site.generate(),
+    		});
+    		match generated {
+        		Ok(stats) => print!("{}", stats),
+        		Err(e) => eprintln!("Error generating site: {}", e),
     		}
         }
 
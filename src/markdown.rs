@@ -0,0 +1,241 @@
+use crate::html::escape_html;
+
+// Convert Markdown to HTML. Covers the common part of CommonMark: ATX headings (# to ######),
+// paragraphs, fenced code blocks (``` or ~~~, with a language giving class="language-..."), block
+// quotes, flat bulleted and numbered lists, thematic breaks, and HTML blocks, which start with a tag
+// and are copied up to the next blank line. Inline, it knows code spans, *emphasis*, **strong**,
+// [links](url), ![images](src), <https://autolinks>, inline tags and backslash escapes. Everything
+// else is text, escaped
+pub fn markdown_to_html(markdown: &str) -> String {
+    let lines: Vec<&str> = markdown.lines().collect();
+    blocks(&lines)
+}
+
+fn blocks(lines: &[&str]) -> String {
+    let mut out = String::new();
+    let mut i = 0;
+    while i < lines.len() {
+        let line = lines[i].trim_start();
+        if line.is_empty() {
+            i += 1;
+        } else if let Some(fence) = ["```", "~~~"].into_iter().find(|fence| line.starts_with(fence)) {
+            let language = line.trim_start_matches(&fence[..1]).split_whitespace().next().unwrap_or_default();
+            let start = i + 1;
+            i = start;
+            while i < lines.len() && !lines[i].trim_start().starts_with(fence) {
+                i += 1;
+            }
+            let class = if language.is_empty() { String::new() } else { format!(" class=\"language-{}\"", escape_html(language)) };
+            let code: String = lines[start..i].iter().map(|line| format!("{}\n", line)).collect();
+            out.push_str(&format!("<pre><code{}>{}</code></pre>\n", class, escape_html(&code)));
+            i += 1;
+        } else if let Some((level, text)) = heading(line) {
+            out.push_str(&format!("<h{0}>{1}</h{0}>\n", level, inline(text)));
+            i += 1;
+        } else if is_rule(line) {
+            out.push_str("<hr>\n");
+            i += 1;
+        } else if line.starts_with('>') {
+            let mut quoted = Vec::new();
+            while i < lines.len() && lines[i].trim_start().starts_with('>') {
+                let text = &lines[i].trim_start()[1..];
+                quoted.push(text.strip_prefix(' ').unwrap_or(text));
+                i += 1;
+            }
+            out.push_str(&format!("<blockquote>\n{}</blockquote>\n", blocks(&quoted)));
+        } else if let Some((ordered, _)) = list_item(line) {
+            let tag = if ordered { "ol" } else { "ul" };
+            out.push_str(&format!("<{}>\n", tag));
+            while let Some((_, text)) = lines.get(i).and_then(|line| list_item(line.trim_start())).filter(|(kind, _)| *kind == ordered) {
+                let mut item = text.to_string();
+                i += 1;
+                // Indented lines continue the item
+                while i < lines.len() && lines[i].starts_with([' ', '\t']) && !lines[i].trim().is_empty() && list_item(lines[i].trim_start()).is_none() {
+                    item.push('\n');
+                    item.push_str(lines[i].trim());
+                    i += 1;
+                }
+                out.push_str(&format!("<li>{}</li>\n", inline(&item)));
+            }
+            out.push_str(&format!("</{}>\n", tag));
+        } else if is_tag_start(line) {
+            while i < lines.len() && !lines[i].trim().is_empty() {
+                out.push_str(lines[i]);
+                out.push('\n');
+                i += 1;
+            }
+        } else {
+            let mut paragraph = vec![line.trim_end()];
+            i += 1;
+            while i < lines.len() && !lines[i].trim().is_empty() && !interrupts_paragraph(lines[i].trim_start()) {
+                paragraph.push(lines[i].trim());
+                i += 1;
+            }
+            out.push_str(&format!("<p>{}</p>\n", inline(&paragraph.join("\n"))));
+        }
+    }
+    out
+}
+
+// Level and text of an ATX heading: "## Title ##"
+fn heading(line: &str) -> Option<(usize, &str)> {
+    let level = line.len() - line.trim_start_matches('#').len();
+    let text = &line[level..];
+    if !(1..=6).contains(&level) || !(text.is_empty() || text.starts_with([' ', '\t'])) {
+        return None;
+    }
+    Some((level, text.trim().trim_end_matches('#').trim_end()))
+}
+
+// Three or more -, * or _ alone on a line, spaces allowed between them
+fn is_rule(line: &str) -> bool {
+    let marks: Vec<char> = line.chars().filter(|c| !c.is_whitespace()).collect();
+    marks.len() >= 3 && matches!(marks[0], '-' | '*' | '_') && marks.iter().all(|c| *c == marks[0])
+}
+
+// Whether the item is numbered, and its text: "- item", "* item", "+ item", "1. item" or "1) item"
+fn list_item(line: &str) -> Option<(bool, &str)> {
+    if let Some(text) = line.strip_prefix(['-', '*', '+']) {
+        return text.starts_with([' ', '\t']).then(|| (false, text.trim()));
+    }
+    let digits = line.len() - line.trim_start_matches(|c: char| c.is_ascii_digit()).len();
+    let text = line[digits..].strip_prefix(['.', ')'])?;
+    ((1..=9).contains(&digits) && text.starts_with([' ', '\t'])).then(|| (true, text.trim()))
+}
+
+fn is_tag_start(text: &str) -> bool {
+    text.starts_with('<') && text[1..].starts_with(|c: char| c.is_ascii_alphabetic() || c == '/' || c == '!')
+}
+
+// Lines starting a block of another kind end a paragraph without a blank line
+fn interrupts_paragraph(line: &str) -> bool {
+    line.starts_with("```")
+        || line.starts_with("~~~")
+        || line.starts_with('>')
+        || heading(line).is_some()
+        || is_rule(line)
+        || list_item(line).is_some_and(|(ordered, _)| !ordered)
+}
+
+// Inline markup of text, escaped
+fn inline(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut rest = text;
+    // Character before rest, so that snake_case is not emphasis
+    let mut prev = ' ';
+    while let Some(c) = rest.chars().next() {
+        let after = &rest[c.len_utf8()..];
+        let (html, len) = match c {
+            '\\' if after.starts_with(|c: char| c.is_ascii_punctuation()) => (escape_html(&after[..1]), 2),
+            '`' => code_span(rest).unwrap_or_else(|| {
+                let ticks = rest.len() - rest.trim_start_matches('`').len();
+                (rest[..ticks].to_string(), ticks)
+            }),
+            '*' | '_' if c == '*' || !prev.is_alphanumeric() => emphasis(rest, c).unwrap_or_else(|| (c.to_string(), 1)),
+            '!' if after.starts_with('[') => match link(after) {
+                Some((alt, src, len)) => (format!("<img src=\"{}\" alt=\"{}\">", escape_html(src), escape_html(alt)), len + 1),
+                None => ("!".to_string(), 1),
+            },
+            '[' => match link(rest) {
+                Some((label, href, len)) => (format!("<a href=\"{}\">{}</a>", escape_html(href), inline(label)), len),
+                None => ("[".to_string(), 1),
+            },
+            '<' => autolink_or_tag(rest).unwrap_or_else(|| ("&lt;".to_string(), 1)),
+            c => (escape_html(&c.to_string()), c.len_utf8()),
+        };
+        out.push_str(&html);
+        prev = rest[..len].chars().next_back().unwrap_or(c);
+        rest = &rest[len..];
+    }
+    out
+}
+
+// `code`, with as many backticks around it as it needs
+fn code_span(text: &str) -> Option<(String, usize)> {
+    let ticks = text.len() - text.trim_start_matches('`').len();
+    let fence = &text[..ticks];
+    let end = ticks + text[ticks..].find(fence)?;
+    Some((format!("<code>{}</code>", escape_html(text[ticks..end].trim())), end + ticks))
+}
+
+// *em*, _em_, **strong** or __strong__ starting text. The text inside neither starts nor ends with a space
+fn emphasis(text: &str, mark: char) -> Option<(String, usize)> {
+    let strong = text[1..].starts_with(mark);
+    let marker = if strong { &text[..2] } else { &text[..1] };
+    let inner = &text[marker.len()..];
+    if inner.starts_with(char::is_whitespace) {
+        return None;
+    }
+    let end = inner.match_indices(marker).map(|(i, _)| i).find(|&i| {
+        i > 0 && !inner[..i].ends_with(char::is_whitespace) && (strong || !inner[i + 1..].starts_with(mark) && !inner[..i].ends_with(mark))
+    })?;
+    let tag = if strong { "strong" } else { "em" };
+    Some((format!("<{0}>{1}</{0}>", tag, inline(&inner[..end])), marker.len() + end + marker.len()))
+}
+
+// Label, destination and length of the [label](destination "title") starting text. The title is dropped
+fn link(text: &str) -> Option<(&str, &str, usize)> {
+    let mut depth = 0;
+    let close = text.char_indices().find_map(|(i, c)| {
+        match c {
+            '[' => depth += 1,
+            ']' => depth -= 1,
+            _ => {}
+        }
+        (depth == 0).then_some(i)
+    })?;
+    let destination = text[close + 1..].strip_prefix('(')?;
+    let end = destination.find(')')?;
+    let href = destination[..end].split_whitespace().next().unwrap_or_default();
+    Some((&text[1..close], href.trim_start_matches('<').trim_end_matches('>'), close + 2 + end + 1))
+}
+
+// <https://example.com> as a link, or an inline tag such as <br> or <span class="x"> as written
+fn autolink_or_tag(text: &str) -> Option<(String, usize)> {
+    let end = text.find('>')?;
+    let inner = &text[1..end];
+    let is_url = ["http://", "https://", "mailto:"].iter().any(|scheme| inner.starts_with(scheme)) && !inner.contains(char::is_whitespace);
+    if is_url {
+        let url = escape_html(inner);
+        Some((format!("<a href=\"{0}\">{0}</a>", url), end + 1))
+    } else if is_tag_start(text) {
+        Some((text[..end + 1].to_string(), end + 1))
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn blocks_and_inline_markup() {
+        let markdown = "# Rust *tags* #\n\
+                        Posts about **Rust**, the `Vec<T>` type\nand [the book](https://doc.rust-lang.org/book/ \"Book\").\n\
+                        \n\
+                        - first\n- second\n  continued\n1. one\n2) two\n\
+                        \n\
+                        > Quoted _text_\n> on two lines\n\
+                        \n\
+                        ```rust\nfn main() {}\n```\n\
+                        ***\n\
+                        <div class=\"note\">\nKept <b>as is</b>\n</div>\n\
+                        \n\
+                        ![A crab](crab.png) and snake_case_name, 2 * 3 * 4, \\*literal\\*, a < b, <https://example.com>, <br>\n";
+        assert_eq!(
+            markdown_to_html(markdown),
+            "<h1>Rust <em>tags</em></h1>\n\
+             <p>Posts about <strong>Rust</strong>, the <code>Vec&lt;T&gt;</code> type\nand <a href=\"https://doc.rust-lang.org/book/\">the book</a>.</p>\n\
+             <ul>\n<li>first</li>\n<li>second\ncontinued</li>\n</ul>\n<ol>\n<li>one</li>\n<li>two</li>\n</ol>\n\
+             <blockquote>\n<p>Quoted <em>text</em>\non two lines</p>\n</blockquote>\n\
+             <pre><code class=\"language-rust\">fn main() {}\n</code></pre>\n\
+             <hr>\n\
+             <div class=\"note\">\nKept <b>as is</b>\n</div>\n\
+             <p><img src=\"crab.png\" alt=\"A crab\"> and snake_case_name, 2 * 3 * 4, *literal*, a &lt; b, \
+             <a href=\"https://example.com\">https://example.com</a>, <br></p>\n"
+        );
+        assert_eq!(markdown_to_html(""), "");
+        assert_eq!(markdown_to_html("[^1] and [x]\n\n[^1]: Note"), "<p>[^1] and [x]</p>\n<p>[^1]: Note</p>\n");
+    }
+}
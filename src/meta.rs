@@ -0,0 +1,152 @@
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use crate::config::file_date;
+use crate::date::Date;
+use crate::generator::{get_tags, parse_tags};
+use crate::frontmatter::read_front_matter;
+use crate::toml::{self, expect_bool, expect_i32, expect_str, invalid_data, Table, Value};
+
+// Optional per-entry metadata file, next to content.html
+pub const META_FILE: &str = "meta.toml";
+
+// Empty file pinning its entry, like pinned = true in meta.toml
+pub const PINNED_FILE: &str = "pinned";
+
+// URL of an entry published on another site, in place of content.html. Listings link there directly
+pub const LINK_FILE: &str = "link.txt";
+
+// Entry settings read from meta.toml. Entries without the file get the defaults
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct EntryMeta {
+    // Name of the entry directory, used in its URL. Not read from meta.toml
+    pub slug: String,
+    // Directories between entries_dir and the entry directory, with recursive_entries. Not read from meta.toml
+    pub section: String,
+    // Display title. Defaults to the slug
+    pub title: Option<String>,
+    // Short summary, used in feeds
+    pub description: Option<String>,
+    pub author: Option<String>,
+    // Tags from tags.txt and the front matter, sorted
+    pub tags: Vec<String>,
+    // Modification time of the entry directory. Not read from meta.toml
+    pub modified: Option<Date>,
+    // Publication date, e.g. date = 2024-03-15 or date = "2024-03-15T10:00:00Z"
+    pub date: Option<Date>,
+    // Layout to use instead of base.html, resolved to static/<template>.html
+    pub template: Option<String>,
+    // Short alias such as "r1": public/<short_url>/index.html redirects to the entry
+    pub short_url: Option<String>,
+    // Last change from the git history, with git_dates. Not read from meta.toml
+    pub updated: Option<Date>,
+    // Left out of the build unless include_drafts is set, and always out of the feed and the sitemap
+    pub draft: bool,
+    // Listed first in the entries index, from pinned = true or a pinned file in the entry directory
+    pub pinned: bool,
+    // Manual order in listings: lighter entries come first, before the usual order applies. 0 by default
+    pub weight: i32,
+    // External URL from link.txt, for entries published elsewhere. Not read from meta.toml
+    pub link: Option<String>,
+}
+
+impl EntryMeta {
+    // Read the metadata of an entry directory: meta.toml when present, then the front matter of
+    // content.html, which wins over meta.toml, plus its name and tags
+    pub fn load(entry_path: &Path) -> io::Result<EntryMeta> {
+        let mut meta = EntryMeta::default();
+        let path = entry_path.join(META_FILE);
+        if path.exists() {
+            let content = fs::read_to_string(&path)?;
+            let table = toml::parse(&content).map_err(|e| invalid_data(format!("{}: {}", path.display(), e)))?;
+            meta.apply_table(&table)?;
+        }
+        if let Some(table) = read_front_matter(entry_path)? {
+            meta.apply_table(&table).map_err(|e| invalid_data(format!("{}: front matter {}", entry_path.display(), e)))?;
+        }
+
+        meta.slug = entry_path.file_name().unwrap_or_default().to_string_lossy().into_owned();
+        meta.modified = fs::metadata(entry_path).and_then(|metadata| metadata.modified()).ok().map(file_date);
+        let tags_file_path = entry_path.join("tags.txt");
+        if tags_file_path.exists() {
+            meta.tags.extend(get_tags(&tags_file_path));
+        }
+        meta.tags.sort();
+        meta.tags.dedup();
+        meta.pinned |= entry_path.join(PINNED_FILE).is_file();
+        meta.link = read_link(entry_path)?;
+        Ok(meta)
+    }
+
+    // Title to display: the title from meta.toml or the front matter, or the directory name
+    pub fn display_title(&self) -> &str {
+        self.title.as_deref().unwrap_or(&self.slug)
+    }
+
+    pub fn from_table(table: &Table) -> io::Result<EntryMeta> {
+        let mut meta = EntryMeta::default();
+        meta.apply_table(table)?;
+        Ok(meta)
+    }
+
+    // Set the fields present in a meta.toml or front matter table, adding to the tags
+    pub fn apply_table(&mut self, table: &Table) -> io::Result<()> {
+        if let Some(value) = table.get("title") {
+            self.title = Some(expect_str("title", value)?.to_string());
+        }
+        if let Some(value) = table.get("description") {
+            self.description = Some(expect_str("description", value)?.to_string());
+        }
+        if let Some(value) = table.get("tags") {
+            self.tags.extend(table_tags(value)?);
+        }
+        if let Some(value) = table.get("author") {
+            self.author = Some(expect_str("author", value)?.to_string());
+        }
+        if let Some(value) = table.get("date") {
+            let text = expect_str("date", value)?;
+            let date = Date::parse(text).ok_or_else(|| invalid_data(format!("date: invalid date \"{}\"", text)))?;
+            self.date = Some(date);
+        }
+        if let Some(value) = table.get("template") {
+            self.template = Some(expect_str("template", value)?.to_string());
+        }
+        if let Some(value) = table.get("short_url") {
+            let short_url = expect_str("short_url", value)?.trim_matches('/');
+            if short_url.is_empty() || short_url.contains(['/', '\\']) || short_url.starts_with('.') {
+                return Err(invalid_data(format!("short_url: invalid short URL \"{}\"", short_url)));
+            }
+            self.short_url = Some(short_url.to_string());
+        }
+        if let Some(value) = table.get("draft") {
+            self.draft = expect_bool("draft", value)?;
+        }
+        if let Some(value) = table.get("pinned") {
+            self.pinned = expect_bool("pinned", value)?;
+        }
+        if let Some(value) = table.get("weight") {
+            self.weight = expect_i32("weight", value)?;
+        }
+        Ok(())
+    }
+}
+
+// Read the link.txt of an entry: its first non-blank line. None when the entry has no link.txt, or an
+// empty one
+pub fn read_link(entry_path: &Path) -> io::Result<Option<String>> {
+    let path = entry_path.join(LINK_FILE);
+    if !path.is_file() {
+        return Ok(None);
+    }
+    Ok(fs::read_to_string(&path)?.lines().map(str::trim).find(|line| !line.is_empty()).map(str::to_string))
+}
+
+// Tags given as a list (tags = ["a", "b"]) or, like tags.txt, as one whitespace-separated string
+// in which multi-word tags are quoted
+pub fn table_tags(value: &Value) -> io::Result<Vec<String>> {
+    match value {
+        Value::Array(items) => items.iter().map(|item| expect_str("tags", item).map(str::to_string)).collect(),
+        _ => Ok(parse_tags(expect_str("tags", value)?)),
+    }
+}
@@ -0,0 +1,122 @@
+use std::collections::BTreeMap;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+// Every file the build intends to write, relative to the public directory, with what writes it
+#[derive(Debug, Clone, Default)]
+pub struct OutputPlan {
+    outputs: BTreeMap<String, Vec<String>>,
+}
+
+// Sources targeting the same file, or files whose paths differ only in case
+#[derive(Debug, Clone, PartialEq)]
+pub struct OutputCollision {
+    // One path for an exact collision, every spelling for a case collision
+    pub paths: Vec<String>,
+    // What writes them, e.g. "entry hello" or "tag rust"
+    pub sources: Vec<String>,
+}
+
+impl fmt::Display for OutputCollision {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.paths.len() == 1 {
+            write!(f, "{} is written by {}", self.paths[0], self.sources.join(", "))
+        } else {
+            write!(f, "{} differ only in case, written by {}", self.paths.join(", "), self.sources.join(", "))
+        }
+    }
+}
+
+impl OutputPlan {
+    pub fn new() -> OutputPlan {
+        OutputPlan::default()
+    }
+
+    pub fn add(&mut self, path: impl Into<String>, source: impl Into<String>) {
+        self.outputs.entry(path.into()).or_default().push(source.into());
+    }
+
+    // Add every file under dir as <prefix>/<relative path>. A missing dir adds nothing
+    pub fn add_directory(&mut self, dir: &Path, prefix: &str, source: &str) -> io::Result<()> {
+        if !dir.is_dir() {
+            return Ok(());
+        }
+        for entry in fs::read_dir(dir)? {
+            let path = entry?.path();
+            let name = path.file_name().unwrap_or_default().to_string_lossy();
+            let relative = format!("{}/{}", prefix, name);
+            if path.is_dir() {
+                self.add_directory(&path, &relative, source)?;
+            } else {
+                self.add(relative, source);
+            }
+        }
+        Ok(())
+    }
+
+    pub fn paths(&self) -> impl Iterator<Item = &String> {
+        self.outputs.keys()
+    }
+
+    // Paths planned by more than one source, in path order
+    pub fn collisions(&self) -> Vec<OutputCollision> {
+        self.outputs
+            .iter()
+            .filter(|(_, sources)| sources.len() > 1)
+            .map(|(path, sources)| OutputCollision { paths: vec![path.clone()], sources: sources.clone() })
+            .collect()
+    }
+
+    // Different paths that name the same file on a case-insensitive filesystem (macOS, Windows),
+    // checked whatever the filesystem of the build
+    pub fn case_collisions(&self) -> Vec<OutputCollision> {
+        let mut folded: BTreeMap<String, Vec<&String>> = BTreeMap::new();
+        for path in self.outputs.keys() {
+            folded.entry(path.to_lowercase()).or_default().push(path);
+        }
+        folded
+            .into_values()
+            .filter(|paths| paths.len() > 1)
+            .map(|paths| OutputCollision {
+                sources: paths.iter().flat_map(|path| self.outputs[*path].iter().cloned()).collect(),
+                paths: paths.into_iter().cloned().collect(),
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::{write, TempDir};
+
+    #[test]
+    fn exact_and_case_collisions_are_separate() {
+        let mut plan = OutputPlan::new();
+        plan.add("index.html", "index page");
+        plan.add("notes/index.html", "entry notes");
+        plan.add("notes/index.html", "tag notes");
+        plan.add("Rust/index.html", "tag Rust");
+        plan.add("rust/index.html", "tag rust");
+
+        let exact: Vec<String> = plan.collisions().iter().map(OutputCollision::to_string).collect();
+        assert_eq!(exact, vec!["notes/index.html is written by entry notes, tag notes"]);
+        let case: Vec<String> = plan.case_collisions().iter().map(OutputCollision::to_string).collect();
+        assert_eq!(case, vec!["Rust/index.html, rust/index.html differ only in case, written by tag Rust, tag rust"]);
+    }
+
+    #[test]
+    fn directories_add_their_files() {
+        let dir = TempDir::new("outputs");
+        write(&dir.path().join("css").join("site.css"), "");
+        write(&dir.path().join("logo.png"), "");
+
+        let mut plan = OutputPlan::new();
+        plan.add_directory(dir.path(), "static", "static file").unwrap();
+        plan.add_directory(&dir.path().join("missing"), "images", "image").unwrap();
+        let paths: Vec<&String> = plan.paths().collect();
+        assert_eq!(paths, vec!["static/css/site.css", "static/logo.png"]);
+    }
+}
@@ -0,0 +1,173 @@
+use std::collections::HashMap;
+
+use crate::config::SiteConfig;
+use crate::error::IroniteError;
+use crate::generator::{entry_section, published_entry_dirs};
+use crate::meta::EntryMeta;
+
+// Where entry pages go unless permalink_pattern says otherwise
+pub const DEFAULT_PERMALINK_PATTERN: &str = "entries/{section}/{slug}";
+
+// Maps entry slugs to the directory of their page, relative to the public directory
+pub type PermalinkMap = HashMap<String, String>;
+
+// Lowercase ASCII letters and digits, with every other run of characters replaced by a dash
+pub fn slugify(text: &str) -> String {
+    let mut slug = String::with_capacity(text.len());
+    for c in text.chars() {
+        if c.is_ascii_alphanumeric() {
+            slug.push(c.to_ascii_lowercase());
+        } else if !slug.is_empty() && !slug.ends_with('-') {
+            slug.push('-');
+        }
+    }
+    slug.trim_end_matches('-').to_string()
+}
+
+// Tokens of permalink_pattern taken from the entry's date
+const DATE_TOKENS: &[&str] = &["{year}", "{month}", "{day}"];
+
+// Expand a pattern such as "{year}/{month}/{slug}" for an entry. Supported tokens are {slug},
+// {section}, {year}, {month}, {day}, {title} and {author}. Date tokens need a date in the entry's
+// metadata: the modification time of its directory changes with any edit or fresh clone, and would move
+// the page. Empty, "." and ".." segments are dropped so the result stays inside public/
+pub fn expand_permalink(pattern: &str, meta: &EntryMeta, config: &SiteConfig) -> Result<String, IroniteError> {
+    let date = match meta.date {
+        Some(date) => date,
+        None if DATE_TOKENS.iter().any(|token| pattern.contains(token)) => {
+            return Err(IroniteError::UndatedPermalink { entry: meta.slug.clone(), pattern: pattern.to_string() });
+        }
+        None => Default::default(),
+    };
+    let author = meta.author.as_ref().or(config.author.as_ref()).map(|name| slugify(name));
+    let expanded = pattern
+        .replace("{slug}", &meta.slug)
+        .replace("{section}", &meta.section)
+        .replace("{year}", &format!("{:04}", date.year))
+        .replace("{month}", &format!("{:02}", date.month))
+        .replace("{day}", &format!("{:02}", date.day))
+        .replace("{title}", &slugify(meta.display_title()))
+        .replace("{author}", author.as_deref().unwrap_or("anonymous"));
+
+    let segments: Vec<&str> = expanded.split('/').filter(|segment| !matches!(*segment, "" | "." | "..")).collect();
+    if segments.is_empty() {
+        Ok(meta.slug.clone())
+    } else {
+        Ok(segments.join("/"))
+    }
+}
+
+// Path from an entry page back to the site root, e.g. "../../" for "entries/my-post"
+pub fn root_prefix(permalink: &str) -> String {
+    "../".repeat(permalink.split('/').count())
+}
+
+// Permalinks of every entry directory. Two entries resolving to the same path, or nested entries
+// sharing a name, is an error
+pub fn entry_permalinks(config: &SiteConfig) -> Result<PermalinkMap, IroniteError> {
+    let entries_dir = config.path(&config.entries_dir);
+    let entry_paths = published_entry_dirs(config)?;
+    let mut permalinks = PermalinkMap::new();
+    let mut owners: HashMap<String, String> = HashMap::new();
+    // Sections of the entries seen so far, by name
+    let mut sections: HashMap<String, String> = HashMap::new();
+    // Entries with unreadable metadata, reported together unless fail_fast is enabled
+    let mut failed = Vec::new();
+    for entry_path in entry_paths {
+        let mut meta = match EntryMeta::load(&entry_path) {
+            Ok(meta) => meta,
+            Err(e) if config.fail_fast => return Err(e.into()),
+            Err(e) => {
+                let slug = entry_path.file_name().unwrap_or_default().to_string_lossy().into_owned();
+                failed.push((slug, e.into()));
+                continue;
+            }
+        };
+        meta.section = entry_section(&entries_dir, &entry_path);
+        if let Some(other) = sections.get(&meta.slug) {
+            return Err(IroniteError::DuplicateEntryName { name: meta.slug, first: other.clone(), second: meta.section });
+        }
+        sections.insert(meta.slug.clone(), meta.section.clone());
+        let permalink = match expand_permalink(&config.permalink_pattern, &meta, config) {
+            Ok(permalink) => permalink,
+            Err(e) if config.fail_fast => return Err(e),
+            Err(e) => {
+                failed.push((meta.slug, e));
+                continue;
+            }
+        };
+        if let Some(other) = owners.get(&permalink) {
+            return Err(IroniteError::PermalinkConflict { permalink, first: other.clone(), second: meta.slug });
+        }
+        owners.insert(permalink.clone(), meta.slug.clone());
+        permalinks.insert(meta.slug, permalink);
+    }
+    if !failed.is_empty() {
+        return Err(IroniteError::EntryErrors(failed));
+    }
+    Ok(permalinks)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::date::Date;
+    use crate::test_util::{fixture_project, write, TempDir};
+
+    #[test]
+    fn expands_date_and_title_tokens() {
+        let config = SiteConfig { author: Some("Jane Doe".to_string()), ..SiteConfig::default() };
+        let meta = EntryMeta {
+            slug: "my post".to_string(),
+            title: Some("Hello, World!".to_string()),
+            date: Date::parse("2024-03-05"),
+            ..EntryMeta::default()
+        };
+        assert_eq!(expand_permalink(DEFAULT_PERMALINK_PATTERN, &meta, &config).unwrap(), "entries/my post");
+        assert_eq!(expand_permalink("/{year}/{month}/{day}/{title}/", &meta, &config).unwrap(), "2024/03/05/hello-world");
+        assert_eq!(expand_permalink("{author}/../{slug}", &meta, &config).unwrap(), "jane-doe/my post");
+        assert_eq!(root_prefix("2024/03/05/hello-world"), "../../../../");
+    }
+
+    #[test]
+    fn conflicting_permalinks_are_an_error() {
+        let dir = TempDir::new("permalink-conflict");
+        fixture_project(dir.path());
+        write(&dir.path().join("entries").join("first").join("meta.toml"), "date = 2024-03-05\n");
+        write(&dir.path().join("entries").join("second").join("meta.toml"), "date = 2024-03-05\n");
+        let config = SiteConfig {
+            root: dir.path().to_path_buf(),
+            permalink_pattern: "{year}/{month}/{day}".to_string(),
+            ..SiteConfig::default()
+        };
+
+        match entry_permalinks(&config) {
+            Err(IroniteError::PermalinkConflict { permalink, first, second }) => {
+                assert_eq!((permalink.as_str(), first.as_str(), second.as_str()), ("2024/03/05", "first", "second"));
+            }
+            other => panic!("expected a permalink conflict, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn date_tokens_need_a_date() {
+        let config = SiteConfig::default();
+        let meta = EntryMeta { slug: "undated".to_string(), modified: Date::parse("2024-03-05"), ..EntryMeta::default() };
+        assert_eq!(expand_permalink("posts/{slug}", &meta, &config).unwrap(), "posts/undated");
+        let error = expand_permalink("{year}/{slug}", &meta, &config).unwrap_err();
+        assert_eq!(error.to_string(), "entry undated has no date for the permalink pattern {year}/{slug}");
+
+        let dir = TempDir::new("permalink-undated");
+        fixture_project(dir.path());
+        write(&dir.path().join("entries").join("first").join("meta.toml"), "date = 2024-03-05\n");
+        let config = SiteConfig { root: dir.path().to_path_buf(), permalink_pattern: "{year}/{slug}".to_string(), ..SiteConfig::default() };
+        match entry_permalinks(&config) {
+            Err(IroniteError::EntryErrors(failed)) => {
+                let names: Vec<&str> = failed.iter().map(|(name, _)| name.as_str()).collect();
+                assert!(!names.contains(&"first") && !names.is_empty(), "{:?}", names);
+                assert!(failed.iter().all(|(_, e)| matches!(e, IroniteError::UndatedPermalink { .. })));
+            }
+            other => panic!("expected undated entries, got {:?}", other),
+        }
+    }
+}
@@ -0,0 +1,18 @@
+use crate::config::SiteConfig;
+use crate::error::IroniteError;
+use crate::stats::SiteStats;
+
+// Extension point for custom behavior around site generation, e.g. writing extra files or deploying.
+// Plugins run in registration order; an error from any of them aborts the build
+pub trait GeneratorPlugin: Send + Sync {
+    // Name used in error messages
+    fn name(&self) -> &str {
+        std::any::type_name::<Self>()
+    }
+
+    // Called before anything is generated
+    fn pre_generate(&self, config: &SiteConfig) -> Result<(), IroniteError>;
+
+    // Called once the site has been generated
+    fn post_generate(&self, config: &SiteConfig, stats: &SiteStats) -> Result<(), IroniteError>;
+}
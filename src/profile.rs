@@ -0,0 +1,72 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::time::{Duration, Instant};
+
+// Wall-clock time of named build steps, in the order they finish
+#[derive(Debug, Clone, Default)]
+pub struct Profiler {
+    running: HashMap<String, Instant>,
+    steps: Vec<(String, Duration)>,
+}
+
+impl Profiler {
+    pub fn new() -> Profiler {
+        Profiler::default()
+    }
+
+    // Start timing a step. Starting a running step again restarts it
+    pub fn start(&mut self, label: &str) {
+        self.running.insert(label.to_string(), Instant::now());
+    }
+
+    // Stop timing a step and record it. A step that was never started takes no time
+    pub fn stop(&mut self, label: &str) -> Duration {
+        let elapsed = self.running.remove(label).map(|started| started.elapsed()).unwrap_or_default();
+        self.steps.push((label.to_string(), elapsed));
+        elapsed
+    }
+
+    // Recorded steps with their durations
+    pub fn steps(&self) -> &[(String, Duration)] {
+        &self.steps
+    }
+
+    pub fn total(&self) -> Duration {
+        self.steps.iter().map(|(_, duration)| *duration).sum()
+    }
+}
+
+// A table of steps and their durations in milliseconds, followed by the total
+impl fmt::Display for Profiler {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let width = self.steps.iter().map(|(label, _)| label.len()).max().unwrap_or(0).max("total".len());
+        for (label, duration) in &self.steps {
+            writeln!(f, "{:<width$}  {:>10.3} ms", label, duration.as_secs_f64() * 1000.0, width = width)?;
+        }
+        writeln!(f, "{:<width$}  {:>10.3} ms", "total", self.total().as_secs_f64() * 1000.0, width = width)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn records_steps_in_order() {
+        let mut profiler = Profiler::new();
+        profiler.start("outer");
+        profiler.start("inner");
+        std::thread::sleep(Duration::from_millis(2));
+        let inner = profiler.stop("inner");
+        let outer = profiler.stop("outer");
+        assert!(inner >= Duration::from_millis(2));
+        assert!(outer >= inner);
+        assert_eq!(profiler.stop("never started"), Duration::ZERO);
+
+        let labels: Vec<&str> = profiler.steps().iter().map(|(label, _)| label.as_str()).collect();
+        assert_eq!(labels, vec!["inner", "outer", "never started"]);
+        let table = profiler.to_string();
+        assert!(table.starts_with("inner "));
+        assert!(table.lines().last().unwrap().starts_with("total "));
+    }
+}
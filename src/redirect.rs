@@ -0,0 +1,87 @@
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use crate::config::SiteConfig;
+use crate::error::IroniteError;
+use crate::generator::{create_directories, entry_name, index_link, published_entry_dirs};
+use crate::permalink::PermalinkMap;
+
+// Old paths of a renamed entry, one per line
+pub const ALIASES_FILE: &str = "aliases.txt";
+
+// Minimal page sending the browser to target, with a visible link for clients ignoring meta refresh
+pub fn redirect_page(target: &str) -> String {
+    format!(
+        "<!DOCTYPE html>\n<html><head><meta charset=\"utf-8\"><title>Redirecting</title>\
+         <meta http-equiv=\"refresh\" content=\"0; url={0}\"><link rel=\"canonical\" href=\"{0}\"></head>\
+         <body><p>This page has moved to <a href=\"{0}\">{0}</a>.</p></body></html>\n",
+        target
+    )
+}
+
+// Read aliases.txt from an entry directory. Blank lines and lines starting with # are skipped,
+// and aliases ending in / get index.html appended. Paths are relative to the site root
+pub fn read_aliases(entry_path: &Path) -> io::Result<Vec<String>> {
+    let path = entry_path.join(ALIASES_FILE);
+    if !path.exists() {
+        return Ok(Vec::new());
+    }
+
+    let mut aliases = Vec::new();
+    for line in fs::read_to_string(&path)?.lines() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let alias = line.trim_start_matches('/');
+        if alias.split('/').any(|segment| segment == "..") {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("{}: alias {} leaves the site root", path.display(), line),
+            ));
+        }
+        if alias.is_empty() || alias.ends_with('/') {
+            aliases.push(format!("{}index.html", alias));
+        } else {
+            aliases.push(alias.to_string());
+        }
+    }
+    Ok(aliases)
+}
+
+// Write a redirect page for every alias of every entry. generated holds the pages written by this
+// build (relative to the public directory); an alias landing on one of them, or claimed by two
+// entries, is an error. Returns the number of pages written
+pub fn generate_alias_pages(config: &SiteConfig, permalinks: &PermalinkMap, generated: &HashSet<String>) -> Result<usize, IroniteError> {
+    let public_dir = config.path(&config.public_dir);
+
+    let entry_paths = published_entry_dirs(config)?;
+
+    let mut claimed: HashMap<String, String> = HashMap::new();
+    for entry_path in &entry_paths {
+        let slug = entry_name(entry_path).unwrap_or_default().to_string();
+        for alias in read_aliases(entry_path)? {
+            if generated.contains(&alias) {
+                return Err(IroniteError::AliasConflict { alias, entry: slug, other: None });
+            }
+            if let Some(other) = claimed.get(&alias) {
+                return Err(IroniteError::AliasConflict { alias, entry: slug, other: Some(other.clone()) });
+            }
+            claimed.insert(alias, slug.clone());
+        }
+    }
+
+    let mut aliases: Vec<_> = claimed.into_iter().collect();
+    aliases.sort();
+    for (alias, slug) in &aliases {
+        let page = public_dir.join(alias);
+        if let Some(parent) = page.parent() {
+            create_directories(parent)?;
+        }
+        let target = config.url(&index_link(&permalinks[slug], config.pretty_urls));
+        fs::write(page, redirect_page(&target))?;
+    }
+    Ok(aliases.len())
+}
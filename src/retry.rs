@@ -0,0 +1,118 @@
+use std::cell::Cell;
+use std::io;
+use std::path::Path;
+use std::thread;
+use std::time::Duration;
+
+// How file writes and copies of a build retry errors that network filesystems and some VMs give
+// now and then and not on a second try. The default never retries
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct IoRetry {
+    // Attempts after the first
+    pub count: usize,
+    // Wait before each of them
+    pub delay: Duration,
+}
+
+thread_local! {
+    // Policy of the build running on this thread, see with_io_retry
+    static IO_RETRY: Cell<IoRetry> = Cell::new(IoRetry::default());
+}
+
+// Run build with retry as the policy of the file operations it does on this thread, restoring the
+// previous policy after. Builds on other threads, such as parallel tests, keep their own
+pub fn with_io_retry<T>(retry: IoRetry, build: impl FnOnce() -> T) -> T {
+    let previous = IO_RETRY.with(|policy| policy.replace(retry));
+    let result = build();
+    IO_RETRY.with(|policy| policy.set(previous));
+    result
+}
+
+// Policy of the build running on this thread, for the threads it starts to take with with_io_retry
+pub fn current_io_retry() -> IoRetry {
+    IO_RETRY.with(Cell::get)
+}
+
+// Errors worth another try: a busy resource, a permission denied while a lock or a scanner holds the
+// file, a timeout
+pub fn is_transient(error: &io::Error) -> bool {
+    matches!(error.kind(), io::ErrorKind::WouldBlock | io::ErrorKind::PermissionDenied | io::ErrorKind::TimedOut)
+}
+
+// Run op on path with the policy of the current build, see retry_with
+pub fn retry_io<T>(path: &Path, op: impl FnMut() -> io::Result<T>) -> io::Result<T> {
+    retry_with(current_io_retry(), path, op)
+}
+
+// Run op, and again up to retry.count times while it fails with a transient error, waiting retry.delay
+// in between. Every retry is reported on stderr. Once the retries are used up, the first error is returned
+pub fn retry_with<T>(retry: IoRetry, path: &Path, mut op: impl FnMut() -> io::Result<T>) -> io::Result<T> {
+    let error = match op() {
+        Err(error) if retry.count > 0 && is_transient(&error) => error,
+        result => return result,
+    };
+    for attempt in 1..=retry.count {
+        eprintln!("{}: {}, retrying ({}/{})", path.display(), error, attempt, retry.count);
+        thread::sleep(retry.delay);
+        match op() {
+            Err(again) if is_transient(&again) => {}
+            result => return result,
+        }
+    }
+    Err(error)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+
+    // Fails with kind for its first failures writes, then takes everything
+    struct FlakyWriter {
+        failures: usize,
+        kind: io::ErrorKind,
+        written: Vec<u8>,
+    }
+
+    impl Write for FlakyWriter {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            if self.failures > 0 {
+                self.failures -= 1;
+                return Err(io::Error::new(self.kind, "flaky"));
+            }
+            self.written.extend_from_slice(buf);
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn transient_errors_are_retried() {
+        let retry = IoRetry { count: 3, delay: Duration::from_millis(1) };
+        let path = Path::new("page.html");
+
+        let mut writer = FlakyWriter { failures: 2, kind: io::ErrorKind::WouldBlock, written: Vec::new() };
+        retry_with(retry, path, || writer.write_all(b"<p>page</p>")).unwrap();
+        assert_eq!(writer.written, b"<p>page</p>");
+
+        // More failures than retries, or none allowed, give the error
+        let mut writer = FlakyWriter { failures: 4, kind: io::ErrorKind::TimedOut, written: Vec::new() };
+        assert_eq!(retry_with(retry, path, || writer.write_all(b"x")).unwrap_err().kind(), io::ErrorKind::TimedOut);
+        assert_eq!(writer.failures, 0);
+        let mut writer = FlakyWriter { failures: 1, kind: io::ErrorKind::PermissionDenied, written: Vec::new() };
+        assert!(retry_with(IoRetry::default(), path, || writer.write_all(b"x")).is_err());
+
+        // Other errors fail at once
+        let mut writer = FlakyWriter { failures: 1, kind: io::ErrorKind::NotFound, written: Vec::new() };
+        assert!(retry_with(retry, path, || writer.write_all(b"x")).is_err());
+        assert!(writer.written.is_empty());
+
+        // The policy of a build applies inside it only
+        let mut writer = FlakyWriter { failures: 1, kind: io::ErrorKind::WouldBlock, written: Vec::new() };
+        with_io_retry(retry, || retry_io(path, || writer.write_all(b"x"))).unwrap();
+        assert_eq!(IO_RETRY.with(Cell::get), IoRetry::default());
+    }
+}
@@ -0,0 +1,220 @@
+use std::collections::BTreeMap;
+use std::collections::HashMap;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use crate::config::{PageKind, SiteConfig};
+use crate::date::Date;
+use crate::error::IroniteError;
+use crate::generator::{
+    cloud_tags, create_directories, entry_href, entry_name, index_link, nav_cloud, page_placeholders, replace_placeholders, sorted_tags,
+    write_html_file,
+};
+use crate::html::escape_html;
+use crate::meta::EntryMeta;
+use crate::permalink::{root_prefix, slugify, PermalinkMap};
+use crate::toml::invalid_data;
+
+// Series an entry belongs to: its name on the first line, and optionally its part number on the second
+pub const SERIES_FILE: &str = "series.txt";
+
+// Directory of the series pages, public/series/<series>/index.html
+pub const SERIES_DIR: &str = "series";
+
+// Entries making up one series, in reading order
+#[derive(Debug, Clone, PartialEq)]
+pub struct Series {
+    pub name: String,
+    pub parts: Vec<PathBuf>,
+}
+
+impl Series {
+    // Directory of the series page, relative to the public directory
+    pub fn path(&self) -> String {
+        format!("{}/{}", SERIES_DIR, slugify(&self.name))
+    }
+}
+
+// Read the series.txt of an entry: the series name and the part number, if any. None when the entry
+// is not part of a series
+pub fn read_series(entry_path: &Path) -> io::Result<Option<(String, Option<usize>)>> {
+    let path = entry_path.join(SERIES_FILE);
+    if !path.exists() {
+        return Ok(None);
+    }
+    let text = fs::read_to_string(&path)?;
+    let mut lines = text.lines().map(str::trim).filter(|line| !line.is_empty());
+    let Some(name) = lines.next() else { return Ok(None) };
+    if slugify(name).is_empty() {
+        return Err(invalid_data(format!("{}: the series name needs a letter or a digit", path.display())));
+    }
+    let part = match lines.next() {
+        Some(part) => Some(
+            part.parse::<usize>()
+                .ok()
+                .filter(|part| *part > 0)
+                .ok_or_else(|| invalid_data(format!("{}: expected a part number, found {}", path.display(), part)))?,
+        ),
+        None => None,
+    };
+    Ok(Some((name.to_string(), part)))
+}
+
+// A part of a series being grouped: its number, date and entry directory
+type Part = (Option<usize>, Option<Date>, PathBuf);
+
+// Group entries into series, sorted by name. Numbered parts come first, by number; parts without a
+// number follow, by date (undated ones last) then name. Two entries claiming the same part number of a
+// series is a SeriesPartConflict
+pub fn collect_series(entry_paths: &[PathBuf]) -> Result<Vec<Series>, IroniteError> {
+    let mut grouped: BTreeMap<String, Vec<Part>> = BTreeMap::new();
+    for entry_path in entry_paths {
+        if let Some((name, part)) = read_series(entry_path)? {
+            let date = EntryMeta::load(entry_path)?.date;
+            grouped.entry(name).or_default().push((part, date, entry_path.clone()));
+        }
+    }
+
+    let mut series = Vec::new();
+    for (name, mut parts) in grouped {
+        parts.sort_by(|a, b| match (a.0, b.0) {
+            (Some(x), Some(y)) => x.cmp(&y).then(a.2.cmp(&b.2)),
+            (Some(_), None) => std::cmp::Ordering::Less,
+            (None, Some(_)) => std::cmp::Ordering::Greater,
+            (None, None) => a.1.is_none().cmp(&b.1.is_none()).then(a.1.cmp(&b.1)).then(a.2.cmp(&b.2)),
+        });
+        for pair in parts.windows(2) {
+            if let (Some(part), true) = (pair[0].0, pair[0].0 == pair[1].0) {
+                return Err(IroniteError::SeriesPartConflict {
+                    series: name,
+                    part,
+                    first: entry_name(&pair[0].2).unwrap_or_default().to_string(),
+                    second: entry_name(&pair[1].2).unwrap_or_default().to_string(),
+                });
+            }
+        }
+        series.push(Series { name, parts: parts.into_iter().map(|(_, _, path)| path).collect() });
+    }
+    Ok(series)
+}
+
+// $SERIES_NAV of an entry page: the series name linking to its page, the position of the entry, and
+// every part in order, the entry itself unlinked. Empty for entries outside any series
+pub fn series_nav(series: &[Series], entry_path: &Path, root: &str, permalinks: &PermalinkMap, pretty: bool) -> String {
+    let Some((series, position)) = series
+        .iter()
+        .find_map(|series| series.parts.iter().position(|path| path == entry_path).map(|position| (series, position)))
+    else {
+        return String::new();
+    };
+    let mut nav = format!(
+        "<nav class=\"series\"><a href=\"{}\">{}</a>: part {} of {}<ol>",
+        index_link(&format!("{}{}", root, series.path()), pretty),
+        escape_html(&series.name),
+        position + 1,
+        series.parts.len()
+    );
+    for (i, path) in series.parts.iter().enumerate() {
+        let title = entry_name(path).unwrap_or("Untitled");
+        if i == position {
+            nav.push_str(&format!("<li>{}</li>", title));
+        } else {
+            nav.push_str(&format!("<li><a href=\"{}\">{}</a></li>", entry_href(root, path, permalinks, pretty), title));
+        }
+    }
+    nav.push_str("</ol></nav>");
+    nav
+}
+
+// Write the page of each series, listing its parts in order
+pub fn generate_series_pages(
+    base_html: &str,
+    config: &SiteConfig,
+    series: &[Series],
+    tags_map: &HashMap<String, Vec<PathBuf>>,
+    permalinks: &PermalinkMap,
+) -> io::Result<()> {
+    let public_dir = config.path(&config.public_dir);
+    let cloud = cloud_tags(&sorted_tags(tags_map), config);
+    for series in series {
+        let path = series.path();
+        let root = root_prefix(&path);
+        let series_dir = public_dir.join(&path);
+        create_directories(&series_dir)?;
+
+        let mut content = String::from("<ol class=\"series\">");
+        for part in &series.parts {
+            let title = entry_name(part).unwrap_or("Untitled");
+            content.push_str(&format!("<li><a href=\"{}\">{}</a></li>", entry_href(&root, part, permalinks, config.pretty_urls), title));
+        }
+        content.push_str("</ol>");
+
+        let html = replace_placeholders(
+            base_html,
+            &page_placeholders(config, PageKind::Tag, &[
+                ("$CONTENT".to_string(), content),
+                ("$TITLE".to_string(), escape_html(&series.name)),
+                ("$NAVCLOUD".to_string(), nav_cloud(&cloud, &root, config)),
+                ("$TAGS".to_string(), "".to_string()),
+                ("$BREADCRUMB".to_string(), "".to_string()),
+                ("$RELATED".to_string(), "".to_string()),
+                ("$UPDATED".to_string(), "".to_string()),
+                ("$DATE".to_string(), "".to_string()),
+            ]),
+        );
+        write_html_file(series_dir.join("index.html"), &html)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::{add_entry, write, TempDir};
+
+    #[test]
+    fn parts_are_ordered_by_number_then_date() {
+        let dir = TempDir::new("series-order");
+        for name in ["intro", "setup", "extras", "appendix", "other"] {
+            add_entry(dir.path(), name, "", "");
+        }
+        let entries_dir = dir.path().join("entries");
+        write(&entries_dir.join("setup").join(SERIES_FILE), "Rust from scratch\n2\n");
+        write(&entries_dir.join("intro").join(SERIES_FILE), "Rust from scratch\n1\n");
+        write(&entries_dir.join("extras").join(SERIES_FILE), "Rust from scratch\n");
+        write(&entries_dir.join("extras").join("meta.toml"), "date = 2024-02-01\n");
+        write(&entries_dir.join("appendix").join(SERIES_FILE), "Rust from scratch\n");
+
+        let paths: Vec<PathBuf> = ["appendix", "extras", "intro", "other", "setup"].iter().map(|name| entries_dir.join(name)).collect();
+        let series = collect_series(&paths).unwrap();
+        assert_eq!(series.len(), 1);
+        assert_eq!(series[0].path(), "series/rust-from-scratch");
+        let names: Vec<&str> = series[0].parts.iter().filter_map(|path| entry_name(path)).collect();
+        assert_eq!(names, ["intro", "setup", "extras", "appendix"]);
+
+        let nav = series_nav(&series, &entries_dir.join("setup"), "../../", &PermalinkMap::new(), true);
+        assert_eq!(
+            nav,
+            "<nav class=\"series\"><a href=\"../../series/rust-from-scratch/\">Rust from scratch</a>: part 2 of 4<ol>\
+             <li><a href=\"../../entries/intro/\">intro</a></li><li>setup</li>\
+             <li><a href=\"../../entries/extras/\">extras</a></li><li><a href=\"../../entries/appendix/\">appendix</a></li></ol></nav>"
+        );
+        assert_eq!(series_nav(&series, &entries_dir.join("other"), "../../", &PermalinkMap::new(), true), "");
+    }
+
+    #[test]
+    fn duplicate_part_numbers_are_reported() {
+        let dir = TempDir::new("series-conflict");
+        add_entry(dir.path(), "a", "", "");
+        add_entry(dir.path(), "b", "", "");
+        let entries_dir = dir.path().join("entries");
+        write(&entries_dir.join("a").join(SERIES_FILE), "Tour\n1\n");
+        write(&entries_dir.join("b").join(SERIES_FILE), "Tour\n1\n");
+        let error = collect_series(&[entries_dir.join("b"), entries_dir.join("a")]).unwrap_err();
+        assert_eq!(error.to_string(), "entries a and b are both part 1 of series Tour");
+
+        write(&entries_dir.join("b").join(SERIES_FILE), "Tour\nlast\n");
+        assert!(collect_series(&[entries_dir.join("b")]).unwrap_err().to_string().ends_with("expected a part number, found last"));
+    }
+}
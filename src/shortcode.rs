@@ -0,0 +1,160 @@
+use std::fs;
+
+use crate::config::SiteConfig;
+use crate::html::{content_chunk_end, escape_html};
+
+// What a shortcode can use to build its HTML
+pub struct ShortcodeContext<'a> {
+    pub config: &'a SiteConfig,
+    // Relative path from the page to the site root, "../../" for an entry
+    pub root: &'a str,
+}
+
+// A [[name:argument]] snippet of entry content. expand gives its HTML, or None when the argument is
+// not one it can use, which leaves the shortcode as written
+pub struct Shortcode {
+    pub name: &'static str,
+    pub expand: fn(argument: &str, context: &ShortcodeContext) -> Option<String>,
+}
+
+// Shortcodes known to expand_shortcodes. New ones only need an entry here
+pub const SHORTCODES: &[Shortcode] = &[Shortcode { name: "youtube", expand: youtube }, Shortcode { name: "gallery", expand: gallery }];
+
+// Image files listed by [[gallery:folder]]
+const GALLERY_EXTENSIONS: &[&str] = &["avif", "gif", "jpeg", "jpg", "png", "svg", "webp"];
+
+// Content with its shortcodes expanded, see expand_shortcodes
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Expanded {
+    pub html: String,
+    // Shortcodes left as written, because they are unknown or their argument is not valid
+    pub unexpanded: Vec<String>,
+}
+
+// Replace every [[name:argument]] of html naming one of shortcodes with its HTML. Tags, comments and
+// the text of <pre>, <code> and the other verbatim elements are left alone, so that shortcodes can
+// be shown in code samples
+pub fn expand_shortcodes(html: &str, shortcodes: &[Shortcode], context: &ShortcodeContext) -> Expanded {
+    let mut out = String::with_capacity(html.len());
+    let mut unexpanded = Vec::new();
+    let mut rest = html;
+    while !rest.is_empty() {
+        let (chunk, next) = rest.split_at(content_chunk_end(rest));
+        if chunk.starts_with('<') {
+            out.push_str(chunk);
+        } else {
+            let mut text = chunk;
+            while let Some(start) = text.find("[[") {
+                out.push_str(&text[..start]);
+                let Some((name, argument, len)) = shortcode(&text[start..]) else {
+                    out.push_str("[[");
+                    text = &text[start + 2..];
+                    continue;
+                };
+                let written = &text[start..start + len];
+                match shortcodes.iter().find(|shortcode| shortcode.name == name).and_then(|shortcode| (shortcode.expand)(argument, context)) {
+                    Some(expanded) => out.push_str(&expanded),
+                    None => {
+                        out.push_str(written);
+                        unexpanded.push(written.to_string());
+                    }
+                }
+                text = &text[start + len..];
+            }
+            out.push_str(text);
+        }
+        rest = next;
+    }
+    Expanded { html: out, unexpanded }
+}
+
+// Name, argument and length of the [[name:argument]] starting text. Names are lowercase letters,
+// digits, - and _, and arguments stay on one line
+fn shortcode(text: &str) -> Option<(&str, &str, usize)> {
+    let rest = text.strip_prefix("[[")?;
+    let end = rest.find("]]")?;
+    let (name, argument) = rest[..end].split_once(':')?;
+    let valid_name = !name.is_empty() && name.chars().all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-' || c == '_');
+    (valid_name && !argument.contains(['\n', '[', ']'])).then_some((name, argument.trim(), end + 4))
+}
+
+// [[youtube:VIDEOID]]: the video's embedded player
+fn youtube(id: &str, _: &ShortcodeContext) -> Option<String> {
+    if id.is_empty() || !id.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_') {
+        return None;
+    }
+    Some(format!(
+        "<iframe class=\"youtube\" src=\"https://www.youtube-nocookie.com/embed/{}\" width=\"560\" height=\"315\" \
+         title=\"YouTube video\" loading=\"lazy\" allow=\"encrypted-media; picture-in-picture\" allowfullscreen></iframe>",
+        id
+    ))
+}
+
+// [[gallery:folder]]: the images of images_dir/folder, by file name, each linking to itself
+fn gallery(folder: &str, context: &ShortcodeContext) -> Option<String> {
+    let folder = folder.trim_matches('/');
+    if folder.is_empty() || folder.split('/').any(|part| part.is_empty() || part == "." || part == "..") {
+        return None;
+    }
+    let mut names: Vec<String> = fs::read_dir(context.config.path(&context.config.images_dir).join(folder))
+        .ok()?
+        .filter_map(|entry| entry.ok()?.file_name().into_string().ok())
+        .filter(|name| name.rsplit_once('.').is_some_and(|(_, extension)| GALLERY_EXTENSIONS.contains(&extension.to_ascii_lowercase().as_str())))
+        .collect();
+    names.sort();
+    let mut html = String::from("<div class=\"gallery\">");
+    for name in names {
+        let src = escape_html(&format!("{}images/{}/{}", context.root, encode_path(folder), encode_path(&name)));
+        html.push_str(&format!("<a href=\"{0}\"><img src=\"{0}\" alt=\"\" loading=\"lazy\"></a>", src));
+    }
+    html.push_str("</div>");
+    Some(html)
+}
+
+// path with the characters URLs cannot hold percent-encoded, slashes kept
+fn encode_path(path: &str) -> String {
+    let mut encoded = String::with_capacity(path.len());
+    for byte in path.bytes() {
+        if byte.is_ascii_alphanumeric() || b"-._~/".contains(&byte) {
+            encoded.push(byte as char);
+        } else {
+            encoded.push_str(&format!("%{:02X}", byte));
+        }
+    }
+    encoded
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::{write, TempDir};
+
+    #[test]
+    fn known_shortcodes_are_expanded() {
+        let dir = TempDir::new("shortcodes");
+        let config = SiteConfig { root: dir.path().to_path_buf(), ..SiteConfig::default() };
+        let photos = dir.path().join("images").join("trip");
+        write(&photos.join("b beach.jpg"), "");
+        write(&photos.join("a.PNG"), "");
+        write(&photos.join("notes.txt"), "");
+        let context = ShortcodeContext { config: &config, root: "../../" };
+
+        let expanded = expand_shortcodes(
+            "<p>[[youtube:dQw4w9WgXcQ]]</p><p>[[gallery:trip]]</p><code>[[youtube:x]]</code>\
+             <p>[[map:paris]] [[youtube:not valid]] [[gallery:../secret]] [[no colon]]</p>",
+            SHORTCODES,
+            &context,
+        );
+        assert_eq!(
+            expanded.html,
+            "<p><iframe class=\"youtube\" src=\"https://www.youtube-nocookie.com/embed/dQw4w9WgXcQ\" width=\"560\" height=\"315\" \
+             title=\"YouTube video\" loading=\"lazy\" allow=\"encrypted-media; picture-in-picture\" allowfullscreen></iframe></p>\
+             <p><div class=\"gallery\">\
+             <a href=\"../../images/trip/a.PNG\"><img src=\"../../images/trip/a.PNG\" alt=\"\" loading=\"lazy\"></a>\
+             <a href=\"../../images/trip/b%20beach.jpg\"><img src=\"../../images/trip/b%20beach.jpg\" alt=\"\" loading=\"lazy\"></a>\
+             </div></p><code>[[youtube:x]]</code>\
+             <p>[[map:paris]] [[youtube:not valid]] [[gallery:../secret]] [[no colon]]</p>"
+        );
+        assert_eq!(expanded.unexpanded, ["[[map:paris]]", "[[youtube:not valid]]", "[[gallery:../secret]]"]);
+    }
+}
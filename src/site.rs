@@ -0,0 +1,2508 @@
+use std::collections::{BTreeSet, HashMap, HashSet};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+use crate::build_info::{build_hash, generate_build_info, BUILD_INFO_FILE, BUILD_VERSION_FILE};
+use crate::compress::{precompress, Codec};
+use crate::config::{env_placeholder_names, env_placeholders, load_vars, source_date_epoch, PageKind, SiteConfig, CONFIG_FILE, VARS_FILE};
+use crate::csv::expand_csv_tables;
+use crate::date::Date;
+use crate::error::IroniteError;
+use crate::feed::{entry_summary, entry_teaser, generate_atom_feed, generate_feed_json};
+use crate::fingerprint::{copy_fingerprinted, rewrite_asset_references};
+use crate::footnotes::footnotes;
+use crate::frontmatter::read_content_file_with_limit;
+use crate::generator::*;
+use crate::git::entry_updated_dates;
+use crate::highlight::{HIGHLIGHT_CSS, HIGHLIGHT_CSS_FILE};
+use crate::hook::run_build_hook;
+use crate::html::{escape_html, minify_html, prefix_links, pretty_html, rebase_links};
+use crate::humans::generate_humans_txt;
+use crate::icons::icon_links;
+use crate::inline::{inline_assets, EMBEDDED_PAGE_WARNING_SIZE};
+use crate::links::check_internal_links;
+use crate::meta::EntryMeta;
+use crate::outputs::{OutputCollision, OutputPlan};
+use crate::permalink::{entry_permalinks, PermalinkMap};
+use crate::plugin::GeneratorPlugin;
+use crate::profile::Profiler;
+use crate::redirect::{generate_alias_pages, redirect_page};
+use crate::retry::{retry_io, with_io_retry};
+use crate::series::{collect_series, generate_series_pages, Series};
+use crate::shortcode::{expand_shortcodes, ShortcodeContext, SHORTCODES};
+use crate::sitemap::{generate_sitemap_index, robots_txt, sitemap_xml, split_sitemaps, SitemapRef, SitemapUrl, SITEMAP_INDEX_FILE};
+use crate::sort::{compare_entries, index_order, read_order, IndexSort, ORDER_FILE};
+use crate::stats::SiteStats;
+use crate::tag_alias::{apply_tag_aliases, site_tag_aliases, TagAliases, TAG_ALIASES_FILE};
+use crate::toml::invalid_data;
+use crate::transform::{Transform, TransformPipeline};
+use crate::validate::{validate_link_entries, validate_names, validate_tags_whitelist, NameProblem};
+
+// Turns a tag as written in an entry into the tag the site uses, see normalize_tags
+pub type TagNormalizer = Box<dyn Fn(&str) -> String>;
+
+// Tags of a build with the entries of each, the aliases applied to them, and the configuration with
+// their display forms, as site_tags_map returns them
+type SiteTags = (HashMap<String, Vec<PathBuf>>, TagAliases, SiteConfig);
+
+// A configured site, ready to be generated
+pub struct Site {
+    config: SiteConfig,
+    plugins: Vec<Box<dyn GeneratorPlugin>>,
+    transforms: TransformPipeline,
+    tag_normalizer: Option<TagNormalizer>,
+    build_hash: String,
+}
+
+// Builder for Site. Without an explicit config, ironite.toml is read from the current directory
+#[derive(Default)]
+pub struct SiteBuilder {
+    config: Option<SiteConfig>,
+    plugins: Vec<Box<dyn GeneratorPlugin>>,
+    transforms: TransformPipeline,
+    tag_normalizer: Option<TagNormalizer>,
+}
+
+impl SiteBuilder {
+    pub fn config(mut self, config: SiteConfig) -> Self {
+        self.config = Some(config);
+        self
+    }
+
+    pub fn plugin<P: GeneratorPlugin + 'static>(mut self, plugin: P) -> Self {
+        self.plugins.push(Box::new(plugin));
+        self
+    }
+
+    pub fn transform<T: Transform + 'static>(mut self, transform: T) -> Self {
+        self.transforms.push(transform);
+        self
+    }
+
+    // Normalize tags with this function instead of lowercase_tags
+    pub fn tag_normalizer<F: Fn(&str) -> String + 'static>(mut self, normalize: F) -> Self {
+        self.tag_normalizer = Some(Box::new(normalize));
+        self
+    }
+
+    pub fn build(self) -> Result<Site, IroniteError> {
+        let mut config = match self.config {
+            Some(config) => config,
+            None => SiteConfig::load(CONFIG_FILE)?,
+        };
+        if config.minify_html && config.pretty_html {
+            return Err(invalid_data("minify_html and pretty_html cannot both be set".to_string()).into());
+        }
+        config.check_public_dir()?;
+        // Variables set on the config directly win over vars.txt
+        let mut vars = load_vars(&config.path(VARS_FILE))?;
+        vars.extend(config.vars.drain());
+        config.vars = vars;
+        // A pinned build date wins over SOURCE_DATE_EPOCH, which wins over the clock
+        if config.build_date.is_none() {
+            config.build_date = Some(source_date_epoch()?.unwrap_or_else(|| Date::from_system_time(SystemTime::now())));
+        }
+        if config.env_placeholders {
+            let names = referenced_env_vars(&config)?;
+            let env_vars = env_placeholders(&names).map_err(IroniteError::MissingEnvVars)?;
+            // Replacement is single-pass, so $ENV_ in the values of variables and placeholders is resolved here
+            for placeholders in [&mut config.vars, &mut config.placeholders, &mut config.entry_placeholders, &mut config.tag_placeholders, &mut config.index_placeholders] {
+                for value in placeholders.values_mut() {
+                    *value = replace_placeholders(value, &env_vars);
+                }
+            }
+            config.vars.extend(env_vars);
+        }
+        // Before the build writes anything, from the sources alone
+        let build_hash = build_hash(&config)?;
+        if !config.placeholders.contains_key("$BUILDHASH") {
+            config.placeholders.insert("$BUILDHASH".to_string(), build_hash.clone());
+        }
+        // Unless [placeholders] defines ICONS itself
+        if !config.placeholders.contains_key("$ICONS") {
+            let icons = icon_links(&config)?;
+            config.placeholders.insert("$ICONS".to_string(), icons);
+        }
+        let mut tag_normalizer = self.tag_normalizer;
+        if tag_normalizer.is_none() && (config.lowercase_tags || config.case_insensitive_tags) {
+            tag_normalizer = Some(Box::new(str::to_lowercase));
+        }
+        let mut transforms = self.transforms;
+        // Minification and pretty printing run after the custom transforms, on the final markup
+        if config.minify_html {
+            transforms.push(minify_html);
+        }
+        if config.pretty_html {
+            transforms.push(pretty_html);
+        }
+        Ok(Site { config, plugins: self.plugins, transforms, tag_normalizer, build_hash })
+    }
+}
+
+impl Site {
+    pub fn builder() -> SiteBuilder {
+        SiteBuilder::default()
+    }
+
+    pub fn config(&self) -> &SiteConfig {
+        &self.config
+    }
+
+    pub fn plugins(&self) -> &[Box<dyn GeneratorPlugin>] {
+        &self.plugins
+    }
+
+    // Hash of the sources, see build_info::build_hash
+    pub fn build_hash(&self) -> &str {
+        &self.build_hash
+    }
+
+    // Generate the site, running the pre_build and post_build commands and the plugin hooks around it.
+    // File writes and copies retry as io_retry_count says
+    pub fn generate(&self) -> Result<SiteStats, IroniteError> {
+        with_io_retry(self.config.io_retry(), || self.generate_with_hooks())
+    }
+
+    fn generate_with_hooks(&self) -> Result<SiteStats, IroniteError> {
+        if !self.config.pre_build.is_empty() {
+            run_build_hook("pre_build", &self.config.pre_build, &self.config.root)?;
+        }
+        for plugin in &self.plugins {
+            plugin.pre_generate(&self.config).map_err(|e| plugin_error(plugin.as_ref(), "pre_generate", e))?;
+        }
+
+        let stats = if self.config.languages.is_empty() { self.generate_pages(&self.config)? } else { self.generate_languages()? };
+        write_bytes_file(self.config.path(&self.config.public_dir).join(BUILD_VERSION_FILE), format!("{}\n", self.build_hash).as_bytes())?;
+
+        for plugin in &self.plugins {
+            plugin.post_generate(&self.config, &stats).map_err(|e| plugin_error(plugin.as_ref(), "post_generate", e))?;
+        }
+
+        // Last of all, so that its presence means the build completed
+        if self.config.emit_build_info {
+            generate_build_info(&self.config, &stats, &self.config.path(&self.config.public_dir).join(BUILD_INFO_FILE))?;
+        }
+
+        // Once the site is complete, so that a deployment gets all of it
+        if !self.config.post_build.is_empty() {
+            run_build_hook("post_build", &self.config.post_build, &self.config.root)?;
+        }
+
+        if let Some(profiler) = &stats.profile {
+            eprint!("{}", profiler);
+        }
+
+        Ok(stats)
+    }
+
+    // Rebuild the page of one entry, and with with_tags the pages of its tags, leaving the rest of the
+    // public directory as it is. Meant for editing one entry of a site built before: plugins, copies,
+    // feeds, fingerprinting, inlining, the link check and compression are left to full builds
+    pub fn generate_single(&self, name: &str, with_tags: bool) -> Result<SiteStats, IroniteError> {
+        with_io_retry(self.config.io_retry(), || self.generate_single_languages(name, with_tags))
+    }
+
+    fn generate_single_languages(&self, name: &str, with_tags: bool) -> Result<SiteStats, IroniteError> {
+        if self.config.languages.is_empty() {
+            return self.generate_single_page(&self.config, name, with_tags);
+        }
+        let mut stats = SiteStats::default();
+        for language in &self.config.languages {
+            let language_config = SiteConfig {
+                public_dir: self.config.public_dir.join(language),
+                base_url: if self.config.base_url.is_empty() { String::new() } else { format!("{}/{}", self.config.base_url, language) },
+                path_prefix: if self.config.path_prefix.is_empty() { String::new() } else { format!("{}{}/", self.config.path_prefix, language) },
+                language: language.clone(),
+                content_language: Some(language.clone()),
+                ..self.config.clone()
+            };
+            let language_stats = self.generate_single_page(&language_config, name, with_tags)?;
+            for warning in language_stats.warnings {
+                if !stats.warnings.contains(&warning) {
+                    stats.warnings.push(warning);
+                }
+            }
+            stats.entry_count = language_stats.entry_count;
+            stats.tag_count = language_stats.tag_count;
+        }
+        Ok(stats)
+    }
+
+    fn generate_single_page(&self, config: &SiteConfig, name: &str, with_tags: bool) -> Result<SiteStats, IroniteError> {
+        let mut stats = SiteStats::default();
+        let public_dir = config.path(&config.public_dir);
+        let base_html = read_file_to_string(config.path(&config.static_dir).join("base.html"))?;
+
+        let entry_paths = published_entry_dirs(config)?;
+        let Some(entry_path) = entry_paths.iter().find(|path| entry_name(path) == Some(name)).cloned() else {
+            return Err(io::Error::new(io::ErrorKind::NotFound, format!("no entry named {}", name)).into());
+        };
+        // Every entry is read for the tags, so that the nav cloud and the tag links match a full build
+        let (tags_map, _, config) = self.site_tags_map(config, &entry_paths, &mut stats)?;
+        let config = &config;
+        let updated = entry_updated_dates(config, &mut stats.warnings)?;
+        create_directories(&public_dir)?;
+        generate_entry_pages_for(&base_html, config, &tags_map, &updated, std::slice::from_ref(&entry_path))?;
+        stats.entry_count = 1;
+
+        let permalinks = entry_permalinks(config)?;
+        let mut pages = vec![public_dir.join(&permalinks[name]).join("index.html")];
+        if with_tags {
+            let entry_tags: Vec<String> = sorted_tags(&tags_map).into_iter().filter(|tag| tags_map[tag].contains(&entry_path)).collect();
+            generate_tag_pages_for(&base_html, config, &tags_map, &permalinks, &entry_tags)?;
+            pages.extend(entry_tags.iter().map(|tag| public_dir.join(tag_slug(tag)).join("index.html")));
+        }
+
+        if !self.transforms.is_empty() {
+            for page in pages.iter().filter(|page| page.exists()) {
+                write_html_file(page, &self.transforms.apply(&read_file_to_string(page)?))?;
+            }
+        }
+        if !config.path_prefix.is_empty() {
+            for page in pages.iter().filter(|page| page.exists()) {
+                let page_dir = page.parent().and_then(|dir| dir.strip_prefix(&public_dir).ok()).unwrap_or(Path::new(""));
+                let html = prefix_links(&read_file_to_string(page)?, &page_dir.to_string_lossy(), &config.path_prefix);
+                write_html_file(page, &html)?;
+            }
+        }
+        Ok(stats)
+    }
+
+    // Build each of languages to public/<language>/, the default language first, and point
+    // public/index.html at the default one. Warnings and broken links of every tree are reported
+    fn generate_languages(&self) -> Result<SiteStats, IroniteError> {
+        let config = &self.config;
+        let default = config.fallback_language().unwrap_or_default();
+        let mut languages: Vec<&String> = config.languages.iter().collect();
+        languages.sort_by_key(|language| language.as_str() != default);
+
+        let mut stats: Option<SiteStats> = None;
+        for language in languages {
+            let language_config = SiteConfig {
+                public_dir: config.public_dir.join(language),
+                base_url: if config.base_url.is_empty() { String::new() } else { format!("{}/{}", config.base_url, language) },
+                path_prefix: if config.path_prefix.is_empty() { String::new() } else { format!("{}{}/", config.path_prefix, language) },
+                language: language.clone(),
+                content_language: Some(language.clone()),
+                ..config.clone()
+            };
+            let language_stats = self.generate_pages(&language_config)?;
+            match &mut stats {
+                None => stats = Some(language_stats),
+                Some(stats) => {
+                    for warning in language_stats.warnings {
+                        if !stats.warnings.contains(&warning) {
+                            stats.warnings.push(warning);
+                        }
+                    }
+                    stats.broken_links.extend(language_stats.broken_links);
+                }
+            }
+        }
+
+        let public_dir = config.path(&config.public_dir);
+        write_html_file(public_dir.join("index.html"), &redirect_page(&index_link(default, config.pretty_urls)))?;
+        Ok(stats.unwrap_or_default())
+    }
+
+    fn generate_pages(&self, config: &SiteConfig) -> Result<SiteStats, IroniteError> {
+        let mut stats = SiteStats::default();
+        let public_dir = &config.path(&config.public_dir);
+        let entries_dir = public_dir.join("entries");
+        let root_entries_dir = &config.path(&config.entries_dir);
+        let static_dir = config.path(&config.static_dir);
+        let base_html_path = static_dir.join("base.html");
+        let about_txt_path = static_dir.join("about.html");
+        let projectname_path = config.path("projectname.txt");
+        let mut profiler = Profiler::new();
+
+        // Read base HTML
+        profiler.start("read templates");
+        let base_html = match read_file_to_string(&base_html_path) {
+            Ok(content) => content,
+            Err(e) => {
+                eprintln!("Failed to read base HTML file: {}", e);
+                return Err(e.into());
+            }
+        };
+
+        // Read other static content
+        let about_txt_content = match read_file_to_string(&about_txt_path) {
+            Ok(content) => content,
+            Err(e) => {
+                eprintln!("Failed to read about.html: {}", e);
+                return Err(e.into());
+            }
+        };
+
+        let project_name = match read_file_to_string(&projectname_path) {
+            Ok(content) => content,
+            Err(e) => {
+                eprintln!("Failed to read projectname.txt: {}", e);
+                return Err(e.into());
+            }
+        };
+
+        profiler.stop("read templates");
+
+        // Names are checked before anything is written. Tags are collected first: entry pages link to
+        // them, and every page carries the nav cloud
+        let skipped = if config.recursive_entries { skipped_entries_recursive(root_entries_dir)? } else { skipped_entries(root_entries_dir)? };
+        for skipped in skipped {
+            stats.warnings.push(format!("Skipped entry {}: its name is not valid UTF-8", skipped));
+        }
+        // A loop is a broken project rather than a shared file, so it stops the build whether or not
+        // symlinks are followed
+        for entry_path in site_entry_dirs(config)?.iter().filter(|path| has_symlinked_content(path)) {
+            if let Err(e @ IroniteError::SymlinkLoop(_)) = resolve_symlinks(&default_content_file(entry_path)) {
+                return Err(e);
+            }
+        }
+        if !config.follow_symlinks {
+            for entry_path in site_entry_dirs(config)?.iter().filter(|path| has_symlinked_content(path)) {
+                stats.warnings.push(format!("Skipped entry {}: its content.html is a symlink and follow_symlinks is off", entry_path.display()));
+            }
+        }
+        let entry_paths = published_entry_dirs(config)?;
+        if let Some(limit) = config.entry_limit {
+            stats.warnings.push(format!("entry_limit is set: building only the first {} entries of the entries index", limit));
+        }
+        if let Some(language) = &config.content_language {
+            let translation = format!("content.{}.html", language);
+            for entry_path in &entry_paths {
+                let content_path = content_file(entry_path, config);
+                let translated = content_path.file_name().is_some_and(|name| name.to_string_lossy().starts_with(&format!("content.{}.", language)));
+                if !translated && content_path.exists() {
+                    stats.warnings.push(format!(
+                        "Entry {} has no {}, using {}",
+                        entry_path.display(),
+                        translation,
+                        content_path.file_name().unwrap_or_default().to_string_lossy()
+                    ));
+                }
+            }
+        }
+        if config.footnotes || config.shortcodes {
+            // Unreadable content is the entry page's error to report
+            for entry_path in &entry_paths {
+                let Ok(content) = read_content_file_with_limit(&content_file(entry_path, config), config.max_file_size) else { continue };
+                if config.shortcodes {
+                    let context = ShortcodeContext { config, root: "" };
+                    for written in expand_shortcodes(&content, SHORTCODES, &context).unexpanded {
+                        stats.warnings.push(format!("Entry {}: unknown or invalid shortcode {}", entry_path.display(), written));
+                    }
+                    for (directive, reason) in expand_csv_tables(&content, entry_path).failed {
+                        stats.warnings.push(format!("Entry {}: {} left as written: {}", entry_path.display(), directive, reason));
+                    }
+                }
+                if config.footnotes {
+                    for label in footnotes(&content).undefined {
+                        stats.warnings.push(format!("Entry {}: footnote [^{}] has no definition", entry_path.display(), label));
+                    }
+                }
+            }
+        }
+        validate_link_entries(&entry_paths)?;
+        let (tags_map, tag_aliases, config) = self.site_tags_map(config, &entry_paths, &mut stats)?;
+        let config = &config;
+        // Retired tags whose replacement has a page, when their old page redirects there
+        let mut alias_redirects: Vec<(&String, &String)> = if config.tag_alias_redirects {
+            tag_aliases.iter().filter(|(alias, tag)| tags_map.contains_key(*tag) && !tags_map.contains_key(*alias)).collect()
+        } else {
+            Vec::new()
+        };
+        alias_redirects.sort();
+        let tags = sorted_tags(&tags_map);
+        let entry_names: Vec<&str> = entry_paths.iter().filter_map(|path| entry_name(path)).collect();
+        let problems = validate_names(&entry_names, &tags);
+        if !problems.is_empty() {
+            if !config.allow_unsafe_names {
+                return Err(IroniteError::InvalidNames(problems));
+            }
+            stats.warnings.extend(problems.iter().map(NameProblem::to_string));
+        }
+
+        // Every output is planned before the first one is written, so that two sources never
+        // silently overwrite each other
+        let permalinks = entry_permalinks(config)?;
+        let series = collect_series(&entry_paths)?;
+        let mut plan = planned_outputs(config, &entry_paths, &tags, &permalinks)?;
+        for series in &series {
+            plan.add(format!("{}/index.html", series.path()), format!("series {}", series.name));
+        }
+        for (alias, tag) in &alias_redirects {
+            plan.add(format!("{}/index.html", tag_slug(alias)), format!("alias of tag {}", tag));
+        }
+        let mut collisions = plan.collisions();
+        collisions.extend(plan.case_collisions());
+        if !collisions.is_empty() {
+            if !config.allow_output_collisions {
+                return Err(IroniteError::OutputCollisions(collisions));
+            }
+            stats.warnings.extend(collisions.iter().map(OutputCollision::to_string));
+        }
+
+        // Create public directories
+        create_directories(public_dir)?;
+        create_directories(&entries_dir)?;
+
+        // Copy static files
+        profiler.start("copy static files");
+        let static_dest = public_dir.join("static");
+        if config.fingerprint_assets {
+            stats.assets = copy_fingerprinted(&static_dir, &static_dest, "static")?;
+        } else {
+            copy_directory(&static_dir, &static_dest)?;
+        }
+        // The stylesheet of highlighted code, unless static/ brings its own
+        if config.highlight_code && !static_dest.join(HIGHLIGHT_CSS_FILE).exists() {
+            create_directories(&static_dest)?;
+            write_bytes_file(static_dest.join(HIGHLIGHT_CSS_FILE), HIGHLIGHT_CSS.as_bytes())?;
+        }
+
+        // Copy images files. They are never rewritten after the copy, so unchanged ones are kept
+        let images_dest = public_dir.join("images");
+        copy_directory_parallel(&config.path(&config.images_dir), &images_dest, config.copy_threads())?;
+        profiler.stop("copy static files");
+
+        // Read once, so that entry pages, the feed and the sitemap agree
+        let updated = entry_updated_dates(config, &mut stats.warnings)?;
+
+        // Generate entry pages
+        profiler.start("entry pages");
+        // Entries that failed are left out of the other pages, and reported once the rest of the site is built
+        let entry_errors = match generate_entry_pages(&base_html, config, &tags_map, &updated) {
+            Ok(()) => Vec::new(),
+            Err(IroniteError::EntryErrors(failed)) => failed,
+            Err(error) => return Err(error),
+        };
+        let failed: HashSet<&str> = entry_errors.iter().map(|(name, _)| name.as_str()).collect();
+        stats.entry_count = entry_paths.len() - entry_errors.len();
+        let is_failed = |entry_path: &Path| entry_path.file_name().and_then(|name| name.to_str()).is_some_and(|name| failed.contains(name));
+        if config.embed_assets {
+            let mut titles: Vec<&String> = permalinks.keys().filter(|title| !failed.contains(title.as_str())).collect();
+            titles.sort();
+            for title in titles {
+                let page = public_dir.join(&permalinks[title]).join("index.html");
+                if let Ok(metadata) = fs::metadata(&page) {
+                    if metadata.len() > EMBEDDED_PAGE_WARNING_SIZE {
+                        stats.warnings.push(format!("Entry {}: its page is {} bytes with embedded assets", title, metadata.len()));
+                    }
+                }
+            }
+        }
+        profiler.stop("entry pages");
+
+        // Generate tag pages
+        profiler.start("tag pages");
+        let listed_tags_map: HashMap<String, Vec<PathBuf>> = tags_map
+            .iter()
+            .map(|(tag, paths)| (tag.clone(), paths.iter().filter(|path| !is_failed(path)).cloned().collect()))
+            .collect();
+        generate_tag_pages(&base_html, config, &listed_tags_map, &permalinks)?;
+        for intersection in &config.tag_intersections {
+            let tags: Vec<&str> = intersection.iter().map(String::as_str).collect();
+            write_tag_intersection_page(&tags, &listed_tags_map, config, &base_html, &permalinks)?;
+        }
+        let listed_series: Vec<Series> = series
+            .iter()
+            .map(|series| Series { name: series.name.clone(), parts: series.parts.iter().filter(|path| !is_failed(path)).cloned().collect() })
+            .collect();
+        generate_series_pages(&base_html, config, &listed_series, &listed_tags_map, &permalinks)?;
+        for (alias, tag) in &alias_redirects {
+            let alias_dir = public_dir.join(tag_slug(alias));
+            create_directories(&alias_dir)?;
+            write_html_file(alias_dir.join("index.html"), &redirect_page(&config.url(&index_link(&tag_slug(tag), config.pretty_urls))))?;
+        }
+        profiler.stop("tag pages");
+
+        // Replace the $NAVCLOUD placeholder in about_txt_content with tags
+        profiler.start("index pages");
+        let parsed_about_txt_content = replace_placeholders(
+            &about_txt_content,
+            &page_placeholders(config, PageKind::Index, &[
+                ("$NAVCLOUD".to_string(), nav_cloud(&cloud_tags(&tags, config), "", config)),
+            ])
+        );
+        // Generate the root index.html
+        let root_index_html_content = replace_placeholders(
+            &base_html,
+            &page_placeholders(config, PageKind::Index, &[
+                ("$CONTENT".to_string(), parsed_about_txt_content),
+                ("$TITLE".to_string(), project_name),
+                // about.html places the nav cloud itself
+                ("$NAVCLOUD".to_string(), "".to_string()),
+                ("$TAGS".to_string(), "".to_string()),
+                ("$BREADCRUMB".to_string(), "".to_string()),
+                ("$RELATED".to_string(), "".to_string()),
+                ("$UPDATED".to_string(), "".to_string()),
+                ("$DATE".to_string(), "".to_string()),
+            ])
+        );
+        write_html_file(public_dir.join("index.html"), &root_index_html_content)?;
+
+        // Entries missing from order.txt are listed after the others
+        let manual_order = if config.index_sort == IndexSort::Manual { read_order(&config.path(ORDER_FILE))? } else { Vec::new() };
+        if config.index_sort == IndexSort::Manual {
+            for name in entry_paths.iter().filter_map(|path| entry_name(path)) {
+                if !manual_order.iter().any(|listed| listed == name) {
+                    stats.warnings.push(format!("Entry {} is not in {}, listed after the others", name, ORDER_FILE));
+                }
+            }
+        }
+        // Generate entries index.html: pinned entries first, then by weight, then in the order of index_sort.
+        // Drafts, only built with include_drafts, are marked
+        let drafts: HashSet<&str> = entry_paths.iter().filter(|path| is_draft(path)).filter_map(|path| entry_name(path)).collect();
+        let mut metas: HashMap<&str, EntryMeta> = HashMap::new();
+        for entry_path in &entry_paths {
+            if let Some(name) = entry_name(entry_path) {
+                metas.insert(name, EntryMeta::load(entry_path)?);
+            }
+        }
+        let titles: Vec<&String> = index_order(&entry_paths, config.index_sort, &manual_order)?
+            .iter()
+            .filter_map(|path| permalinks.get_key_value(entry_name(path)?).map(|(title, _)| title))
+            .filter(|title| !failed.contains(title.as_str()))
+            .collect();
+        let pinned: Vec<&str> = titles.iter().map(|title| title.as_str()).filter(|title| metas[title].pinned).collect();
+
+        let mut entries_index_content = String::new();
+        for title in titles {
+            // The index lives in entries/, next to the entry pages of the default pattern
+            let external = metas[title.as_str()].link.as_deref();
+            let link = match (external, permalinks[title].strip_prefix("entries/")) {
+                (Some(url), _) => escape_html(url),
+                (None, Some(relative)) => index_link(relative, config.pretty_urls),
+                (None, None) => entry_link("../", title, &permalinks, config.pretty_urls),
+            };
+            let mut label = if drafts.contains(title.as_str()) { format!("[DRAFT] {}", title) } else { title.to_string() };
+            if external.is_some() && !config.external_link_marker.is_empty() {
+                label = format!("{} {}", label, config.external_link_marker);
+            }
+            entries_index_content.push_str(&listing_link(&link, &label, pinned.contains(&title.as_str()), config));
+            // Entries published elsewhere have no content to tease
+            if config.entries_index_teasers && external.is_none() {
+                if let Some(entry_path) = entry_paths.iter().find(|path| entry_name(path) == Some(title.as_str())) {
+                    entries_index_content.push_str(&index_teaser(entry_path, &permalinks[title], &link, config)?);
+                }
+            }
+        }
+
+        let entries_index_html_content = replace_placeholders(
+            &base_html,
+            &page_placeholders(config, PageKind::Index, &[
+                ("$CONTENT".to_string(), entries_index_content),
+                ("$TITLE".to_string(), "Entries".to_string()),
+                ("$NAVCLOUD".to_string(), nav_cloud(&cloud_tags(&tags, config), "../", config)),
+                ("$TAGS".to_string(), "".to_string()),
+                ("$BREADCRUMB".to_string(), "".to_string()),
+                ("$RELATED".to_string(), "".to_string()),
+                ("$UPDATED".to_string(), "".to_string()),
+                ("$DATE".to_string(), "".to_string()),
+            ])
+        );
+        write_html_file(entries_dir.join("index.html"), &entries_index_html_content)?;
+
+        generate_not_found_page(&base_html, config, &tags)?;
+        profiler.stop("index pages");
+
+        // Feeds list the newest entries first
+        profiler.start("feeds");
+        let mut feed_entries = Vec::new();
+        let mut content_map = HashMap::new();
+        for entry_path in sort_entries_by_date(entry_paths.clone())? {
+            let content_path = content_file(&entry_path, config);
+            let mut meta = EntryMeta::load(&entry_path)?;
+            if (!content_path.exists() && meta.link.is_none()) || is_failed(&entry_path) {
+                continue;
+            }
+            meta.section = entry_section(root_entries_dir, &entry_path);
+            // Drafts are never published, even in a build that includes them
+            if meta.draft {
+                continue;
+            }
+            if config.git_dates {
+                meta.updated = updated.get(&meta.slug).copied();
+            }
+            if let Some(normalize) = &self.tag_normalizer {
+                meta.tags = meta.tags.iter().map(|tag| normalize(tag)).collect();
+            }
+            for tag in meta.tags.iter_mut() {
+                if let Some(target) = tag_aliases.get(tag) {
+                    *tag = target.clone();
+                }
+                *tag = config.tag_label(tag).to_string();
+            }
+            meta.tags.sort();
+            meta.tags.dedup();
+            // Entries published elsewhere are items linking there, without content
+            let content = if meta.link.is_some() { String::new() } else { read_content_file_with_limit(&content_path, config.max_file_size)? };
+            content_map.insert(meta.slug.clone(), strip_more_marker(&content, config));
+            feed_entries.push(meta);
+        }
+        feed_entries.sort_by(|a, b| compare_entries(a, b, config.index_sort.for_feeds(), &manual_order));
+        if config.feed_pinned {
+            feed_entries.sort_by_key(|meta| !meta.pinned);
+        }
+        generate_feed_json(&feed_entries, config, &content_map, &public_dir.join("feed.json"))?;
+        generate_atom_feed(&feed_entries, config, &content_map, &public_dir.join("atom.xml"))?;
+        profiler.stop("feeds");
+
+        // Redirects from the old paths of renamed entries, which must not replace real pages
+        profiler.start("redirects");
+        // Copied files are listed as written, which differs from the plan with fingerprinted names
+        let mut generated: HashSet<String> = plan.paths().cloned().collect();
+        for copied in [&static_dest, &images_dest] {
+            collect_relative_files(public_dir, copied, &mut generated)?;
+        }
+        generate_alias_pages(config, &permalinks, &generated)?;
+        profiler.stop("redirects");
+
+        // Sitemaps need absolute URLs, so there is only one with a base URL
+        profiler.start("sitemap");
+        let sitemap_url = if config.base_url.is_empty() {
+            None
+        } else {
+            let pages = vec![
+                SitemapUrl { loc: config.url(""), lastmod: None, hints: config.sitemap.hints(PageKind::Index, "") },
+                SitemapUrl {
+                    loc: config.url(&index_link("entries", config.pretty_urls)),
+                    lastmod: None,
+                    hints: config.sitemap.hints(PageKind::Index, "entries"),
+                },
+            ];
+            // Entries published elsewhere have no page here, at most a redirect
+            let entries: Vec<SitemapUrl> = feed_entries
+                .iter()
+                .filter(|meta| meta.link.is_none())
+                .map(|meta| SitemapUrl {
+                    loc: config.url(&index_link(&permalinks[&meta.slug], config.pretty_urls)),
+                    lastmod: meta.updated.or(meta.date).or(meta.modified),
+                    hints: config.sitemap.hints(PageKind::Entry, &permalinks[&meta.slug]),
+                })
+                .collect();
+            let tag_urls: Vec<SitemapUrl> = tags
+                .iter()
+                .map(|tag| tag_slug(tag))
+                .chain(listed_series.iter().map(Series::path))
+                .map(|path| SitemapUrl {
+                    loc: config.url(&index_link(&path, config.pretty_urls)),
+                    lastmod: None,
+                    hints: config.sitemap.hints(PageKind::Tag, &path),
+                })
+                .collect();
+            let groups = [("pages", pages), ("entries", entries), ("tags", tag_urls)];
+            if groups.iter().map(|(_, urls)| urls.len()).sum::<usize>() <= config.sitemap_max_urls {
+                let urls: Vec<SitemapUrl> = groups.into_iter().flat_map(|(_, urls)| urls).collect();
+                write_html_file(public_dir.join("sitemap.xml"), &sitemap_xml(&urls))?;
+                Some(config.url("sitemap.xml"))
+            } else {
+                // Too many URLs for one file: partial sitemaps, and an index pointing at them
+                let mut sitemaps = Vec::new();
+                for (name, urls) in split_sitemaps(&groups, config.sitemap_max_urls) {
+                    write_html_file(public_dir.join(&name), &sitemap_xml(&urls))?;
+                    let lastmod = urls.iter().filter_map(|url| url.lastmod).max().map(|date| date.to_string());
+                    sitemaps.push(SitemapRef { loc: config.url(&name), lastmod });
+                }
+                generate_sitemap_index(&sitemaps, &public_dir.join(SITEMAP_INDEX_FILE))?;
+                Some(config.url(SITEMAP_INDEX_FILE))
+            }
+        };
+        write_html_file(public_dir.join("robots.txt"), &robots_txt(sitemap_url.as_deref()))?;
+        generate_humans_txt(config, &config.authors, &public_dir.join("humans.txt"))?;
+        profiler.stop("sitemap");
+
+        // Run the custom transforms over every generated page, leaving copied files alone
+        profiler.start("transforms");
+        if !self.transforms.is_empty() {
+            self.apply_transforms(public_dir, &[&static_dest, &images_dest])?;
+        }
+
+        // Small assets are inlined before fingerprinting, while pages still use their original names
+        if config.inline_assets {
+            inline_assets(public_dir, &static_dir, config.inline_max_size, &[&static_dest, &images_dest])?;
+        }
+
+        // Point every page and stylesheet at the fingerprinted asset names
+        if config.fingerprint_assets {
+            let warnings = rewrite_asset_references(public_dir, &stats.assets)?;
+            stats.warnings.extend(warnings);
+        }
+
+        // Pages link relatively until here, where every page gets the same absolute links under path_prefix
+        if !config.path_prefix.is_empty() {
+            prefix_page_links(public_dir, "", &config.path_prefix, &[&static_dest, &images_dest])?;
+        }
+        profiler.stop("transforms");
+
+        // Root files and extra files are copied last and replace generated files of the same name
+        profiler.start("root files");
+        let root_files_dir = config.path(&config.root_files_dir);
+        if root_files_dir.is_dir() {
+            copy_directory(&root_files_dir, public_dir)?;
+        }
+        for extra in &config.extra_files {
+            let source = config.path(extra);
+            let destination = public_dir.join(extra);
+            if source.is_dir() {
+                copy_directory(&source, &destination)?;
+            } else if source.is_file() {
+                if let Some(parent) = destination.parent() {
+                    create_directories(parent)?;
+                }
+                retry_io(&source, || fs::copy(&source, &destination))?;
+            }
+        }
+        profiler.stop("root files");
+
+        // Links are checked on the final pages
+        if config.check_links || config.strict_links {
+            profiler.start("link check");
+            let broken = check_internal_links(public_dir, &config.path_prefix)?;
+            if config.strict_links && !broken.is_empty() {
+                return Err(IroniteError::BrokenLinks(broken));
+            }
+            stats.broken_links = broken;
+            profiler.stop("link check");
+        }
+
+        // Compress last, once every file has its final content
+        if config.gzip || config.brotli {
+            profiler.start("compression");
+            if config.gzip {
+                stats.gzip = Some(precompress(public_dir, Codec::Gzip, &config.compress_extensions, config.compress_min_size)?);
+            }
+            if config.brotli {
+                stats.brotli = Some(precompress(public_dir, Codec::Brotli, &config.compress_extensions, config.compress_min_size)?);
+            }
+            profiler.stop("compression");
+        }
+
+        if !entry_errors.is_empty() {
+            return Err(IroniteError::EntryErrors(entry_errors));
+        }
+        if config.profile {
+            stats.profile = Some(profiler);
+        }
+        Ok(stats)
+    }
+
+    // Tags of the published entries as the site uses them: normalized, aliased, without excluded tags and
+    // with the untagged entries under untagged_tag. Tags only drafts carry disappear with them. Returns the
+    // aliases too, for the redirects of retired tags, and the configuration with the display form of
+    // the tags filled in
+    fn site_tags_map(&self, config: &SiteConfig, entry_paths: &[PathBuf], stats: &mut SiteStats) -> Result<SiteTags, IroniteError> {
+        let mut tags_map = entries_by_tag(entry_paths);
+        let mut tag_display = HashMap::new();
+        if let Some(normalize) = &self.tag_normalizer {
+            if config.case_insensitive_tags {
+                tag_display = first_spellings(&tags_map, normalize.as_ref());
+            }
+            let (normalized, merges) = normalize_tags(&tags_map, normalize.as_ref());
+            tags_map = normalized;
+            // Merging is what case_insensitive_tags asks for, and not worth a warning then
+            if !config.case_insensitive_tags {
+                stats.warnings.extend(merges.iter().map(TagMerge::to_string));
+            }
+        }
+        // Aliases apply to the normalized tags
+        let tag_aliases = site_tag_aliases(&config.path(TAG_ALIASES_FILE), &config.tag_aliases)?;
+        let (tags_map, remaps) = apply_tag_aliases(&tags_map, &tag_aliases);
+        stats.tag_remaps = remaps;
+        // Tags of the configuration are spelled as in the tags files, so they go through the same normalizer and aliases
+        let site_tags = |tags: &[String]| -> Vec<String> {
+            tags.iter()
+                .map(|tag| self.tag_normalizer.as_ref().map_or_else(|| tag.clone(), |normalize| normalize(tag)))
+                .map(|tag| tag_aliases.get(&tag).cloned().unwrap_or(tag))
+                .collect()
+        };
+        // Spellings of [tag_display] win over the first ones
+        for (tag, display) in &config.tag_display {
+            if let Some(tag) = site_tags(std::slice::from_ref(tag)).pop() {
+                tag_display.insert(tag, display.clone());
+            }
+        }
+        let (mut tags_map, suppressed) = exclude_tags(&tags_map, &site_tags(&config.excluded_tags));
+        stats.excluded_tag_occurrences = suppressed;
+        if let Some(whitelist) = &config.tags_whitelist {
+            validate_tags_whitelist(&tags_map, &site_tags(whitelist))?;
+        }
+        stats.tag_count = tags_map.len();
+        // Entries without tags get a page of their own, so that tag navigation reaches them too
+        let untagged = untagged_entries(entry_paths, &tags_map);
+        stats.untagged_entries = untagged.iter().filter_map(|path| entry_name(path)).map(str::to_string).collect();
+        if config.require_tags && !untagged.is_empty() {
+            return Err(IroniteError::UntaggedEntries(stats.untagged_entries.clone()));
+        }
+        if !untagged.is_empty() && !config.untagged_tag.is_empty() {
+            let paths = tags_map.entry(config.untagged_tag.clone()).or_default();
+            paths.extend(untagged);
+            paths.sort();
+            paths.dedup();
+        }
+        Ok((tags_map, tag_aliases, SiteConfig { tag_display, ..config.clone() }))
+    }
+
+    fn apply_transforms(&self, dir: &Path, skip: &[&Path]) -> io::Result<()> {
+        for entry in fs::read_dir(dir)? {
+            let path = entry?.path();
+            if skip.contains(&path.as_path()) {
+                continue;
+            }
+            if path.is_dir() {
+                self.apply_transforms(&path, skip)?;
+            } else if path.extension().is_some_and(|ext| ext == "html") {
+                let html = read_file_to_string(&path)?;
+                write_html_file(&path, &self.transforms.apply(&html))?;
+            }
+        }
+        Ok(())
+    }
+}
+
+// Prefix the internal links of every HTML file under dir, relative_dir being its path in the public
+// directory, leaving the directories of skip alone
+fn prefix_page_links(dir: &Path, relative_dir: &str, prefix: &str, skip: &[&Path]) -> io::Result<()> {
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        if skip.contains(&path.as_path()) {
+            continue;
+        }
+        let name = path.file_name().unwrap_or_default().to_string_lossy();
+        if path.is_dir() {
+            let relative = if relative_dir.is_empty() { name.into_owned() } else { format!("{}/{}", relative_dir, name) };
+            prefix_page_links(&path, &relative, prefix, skip)?;
+        } else if path.extension().is_some_and(|ext| ext == "html") {
+            write_html_file(&path, &prefix_links(&read_file_to_string(&path)?, relative_dir, prefix))?;
+        }
+    }
+    Ok(())
+}
+
+// Teaser of an entry on the entries index, followed by a link to the entry: its content up to the more
+// marker, or else its summary. Links in it are rewritten for the index, which lives in entries/
+fn index_teaser(entry_path: &Path, permalink: &str, link: &str, config: &SiteConfig) -> Result<String, IroniteError> {
+    let content = replace_placeholders(
+        &read_content_file_with_limit(&content_file(entry_path, config), config.max_file_size)?,
+        &config.page_placeholders(PageKind::Entry),
+    );
+    let teaser = match entry_teaser(&content, &config.more_marker) {
+        Some(teaser) => teaser.to_string(),
+        None => entry_summary(&EntryMeta::load(entry_path)?, &content),
+    };
+    Ok(format!("{}<p><a href=\"{}\">Read more</a></p>", rebase_links(&teaser, permalink, "entries"), link))
+}
+
+// Files the build writes, relative to the public directory, with the source of each. Root files
+// and extra_files are left out: they replace generated files on purpose
+fn planned_outputs(config: &SiteConfig, entry_paths: &[PathBuf], tags: &[String], permalinks: &PermalinkMap) -> io::Result<OutputPlan> {
+    let mut plan = OutputPlan::new();
+    for page in ["index.html", "404.html", "entries/index.html", "feed.json", "atom.xml", "robots.txt", "humans.txt", "sitemap.xml"] {
+        plan.add(page, page);
+    }
+    for entry_path in entry_paths {
+        let Some(name) = entry_name(entry_path) else { continue };
+        if let Some(permalink) = permalinks.get(name) {
+            plan.add(format!("{}/index.html", permalink), format!("entry {}", name));
+        }
+        if let Some(short_url) = EntryMeta::load(entry_path)?.short_url {
+            plan.add(format!("{}/index.html", short_url), format!("short URL of entry {}", name));
+        }
+    }
+    for tag in tags {
+        plan.add(format!("{}/index.html", tag_slug(tag)), format!("tag {}", tag));
+    }
+    for intersection in &config.tag_intersections {
+        let tags: Vec<&str> = intersection.iter().map(String::as_str).collect();
+        plan.add(format!("{}/index.html", tag_intersection_path(&tags)), format!("tag intersection {}", tags.join("+")));
+    }
+    plan.add_directory(&config.path(&config.static_dir), "static", "static file")?;
+    plan.add_directory(&config.path(&config.images_dir), "images", "image")?;
+    Ok(plan)
+}
+
+fn collect_relative_files(public_dir: &Path, dir: &Path, files: &mut HashSet<String>) -> io::Result<()> {
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path.is_dir() {
+            collect_relative_files(public_dir, &path, files)?;
+        } else if let Ok(relative) = path.strip_prefix(public_dir) {
+            files.insert(relative.to_string_lossy().replace('\\', "/"));
+        }
+    }
+    Ok(())
+}
+
+// Environment variables referenced as $ENV_NAME by the templates and entries in static/, by
+// entry content, and by the values of variables and placeholders
+fn referenced_env_vars(config: &SiteConfig) -> io::Result<BTreeSet<String>> {
+    let mut texts: Vec<String> = Vec::new();
+    let static_dir = config.path(&config.static_dir);
+    if static_dir.is_dir() {
+        for entry in fs::read_dir(&static_dir)? {
+            let path = entry?.path();
+            if path.is_file() && path.extension().is_some_and(|ext| ext == "html") {
+                texts.push(read_file_to_string(&path)?);
+            }
+        }
+    }
+    if config.path(&config.entries_dir).is_dir() {
+        for entry_path in site_entry_dirs(config)? {
+            let content_path = content_file(&entry_path, config);
+            if content_path.is_file() {
+                texts.push(read_file_to_string(&content_path)?);
+            }
+        }
+    }
+    for placeholders in [&config.vars, &config.placeholders, &config.entry_placeholders, &config.tag_placeholders, &config.index_placeholders] {
+        texts.extend(placeholders.values().cloned());
+    }
+    Ok(texts.iter().flat_map(|text| env_placeholder_names(text)).collect())
+}
+
+fn plugin_error(plugin: &dyn GeneratorPlugin, hook: &'static str, error: IroniteError) -> IroniteError {
+    IroniteError::Plugin { plugin: plugin.name().to_string(), hook, source: Box::new(error) }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::{add_entry, env_lock, fixture_project, write, TempDir};
+    use std::collections::BTreeMap;
+    use std::path::PathBuf;
+    use std::time::Duration;
+
+    // Appends a line to a log file for each hook, recording whether the site existed at that point
+    struct LogPlugin {
+        log: PathBuf,
+        index: PathBuf,
+    }
+
+    impl LogPlugin {
+        fn append(&self, hook: &str) -> io::Result<()> {
+            let mut log = fs::read_to_string(&self.log).unwrap_or_default();
+            log.push_str(&format!("{} {}\n", hook, self.index.exists()));
+            fs::write(&self.log, log)
+        }
+    }
+
+    impl GeneratorPlugin for LogPlugin {
+        fn pre_generate(&self, _config: &SiteConfig) -> Result<(), IroniteError> {
+            Ok(self.append("pre")?)
+        }
+
+        fn post_generate(&self, _config: &SiteConfig, _stats: &SiteStats) -> Result<(), IroniteError> {
+            Ok(self.append("post")?)
+        }
+    }
+
+    struct FailingPlugin;
+
+    impl GeneratorPlugin for FailingPlugin {
+        fn name(&self) -> &str {
+            "failing"
+        }
+
+        fn pre_generate(&self, _config: &SiteConfig) -> Result<(), IroniteError> {
+            Err(io::Error::other("boom").into())
+        }
+
+        fn post_generate(&self, _config: &SiteConfig, _stats: &SiteStats) -> Result<(), IroniteError> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn builder_config_sets_output_directory() {
+        let dir = TempDir::new("site-builder");
+        fixture_project(dir.path());
+
+        let config = SiteConfig {
+            root: dir.path().to_path_buf(),
+            public_dir: "out".into(),
+            ..SiteConfig::default()
+        };
+        Site::builder().config(config).build().unwrap().generate().unwrap();
+
+        assert!(dir.path().join("out").join("index.html").exists());
+        assert!(dir.path().join("out").join("entries").join("first").join("index.html").exists());
+        assert!(!dir.path().join("public").exists());
+    }
+
+    #[test]
+    fn path_prefix_makes_links_absolute() {
+        let dir = TempDir::new("site-path-prefix");
+        fixture_project(dir.path());
+        write(&dir.path().join("static").join("base.html"), "<html><head><link href=\"/static/base.html\"></head><body>$NAVCLOUD $CONTENT</body></html>");
+        write(&dir.path().join(CONFIG_FILE), "path_prefix = \"blog\"\nstrict_links = true\n");
+        let first = dir.path().join("entries").join("first");
+        write(&first.join("meta.toml"), "short_url = \"r1\"\n");
+        write(&first.join("aliases.txt"), "entries/old/\n");
+
+        let config = SiteConfig { root: dir.path().to_path_buf(), ..SiteConfig::load(dir.path().join(CONFIG_FILE)).unwrap() };
+        assert_eq!(config.path_prefix, "/blog/");
+        let stats = Site::builder().config(config).build().unwrap().generate().unwrap();
+        assert!(stats.broken_links.is_empty(), "{:?}", stats.broken_links);
+        let public = dir.path().join("public");
+        let entry = fs::read_to_string(public.join("entries").join("first").join("index.html")).unwrap();
+        assert!(entry.contains("<link href=\"/blog/static/base.html\">") && entry.contains("href=\"/blog/rust/index.html\""), "{}", entry);
+        let rust = fs::read_to_string(public.join("rust").join("index.html")).unwrap();
+        assert!(rust.contains("href=\"/blog/entries/first/index.html\""), "{}", rust);
+        assert!(!rust.contains("../"), "{}", rust);
+        // Redirects send browsers under the prefix too, once only
+        for redirect in [public.join("r1").join("index.html"), public.join("entries").join("old").join("index.html")] {
+            let page = fs::read_to_string(&redirect).unwrap();
+            assert!(page.contains("content=\"0; url=/blog/entries/first/index.html\""), "{}", page);
+            assert!(page.contains("<link rel=\"canonical\" href=\"/blog/entries/first/index.html\">"), "{}", page);
+        }
+
+        write(&dir.path().join(CONFIG_FILE), "path_prefix = \"https://example.com/blog\"\n");
+        assert!(SiteConfig::load(dir.path().join(CONFIG_FILE)).is_err());
+    }
+
+    #[test]
+    fn transforms_apply_to_generated_pages_only() {
+        let dir = TempDir::new("site-transform");
+        fixture_project(dir.path());
+
+        let config = SiteConfig { root: dir.path().to_path_buf(), ..SiteConfig::default() };
+        let site = Site::builder()
+            .config(config)
+            .transform(|html: &str| html.replace("<body>", "<body class=\"t\">"))
+            .build()
+            .unwrap();
+        site.generate().unwrap();
+
+        let public = dir.path().join("public");
+        let entry = fs::read_to_string(public.join("entries").join("first").join("index.html")).unwrap();
+        assert!(entry.contains("<body class=\"t\">"));
+        let copied_base = fs::read_to_string(public.join("static").join("base.html")).unwrap();
+        assert!(!copied_base.contains("class=\"t\""));
+    }
+
+    #[test]
+    fn failed_entries_leave_the_rest_of_the_site_built() {
+        let dir = TempDir::new("site-entry-errors");
+        fixture_project(dir.path());
+        add_entry(dir.path(), "third", &"<p>Too long</p>".repeat(20), "rust");
+
+        let config = SiteConfig { root: dir.path().to_path_buf(), max_file_size: 100, ..SiteConfig::default() };
+        match Site::builder().config(config).build().unwrap().generate() {
+            Err(IroniteError::EntryErrors(failed)) => {
+                assert_eq!(failed.iter().map(|(name, _)| name.as_str()).collect::<Vec<_>>(), vec!["third"]);
+            }
+            other => panic!("expected entry errors, got {:?}", other.map(|_| ())),
+        }
+
+        let public = dir.path().join("public");
+        let rust = fs::read_to_string(public.join("rust").join("index.html")).unwrap();
+        assert!(rust.contains("entries/first/index.html") && rust.contains("entries/second/index.html"), "{}", rust);
+        assert!(!rust.contains("third"), "{}", rust);
+        for page in ["entries/index.html", "feed.json", "atom.xml"] {
+            let text = fs::read_to_string(public.join(page)).unwrap();
+            assert!(text.contains("second") && !text.contains("third"), "{}: {}", page, text);
+        }
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn symlink_loops_stop_the_build() {
+        use std::os::unix::fs::symlink;
+        let dir = TempDir::new("site-symlink-loop");
+        fixture_project(dir.path());
+        let entries_dir = dir.path().join("entries");
+        for (name, target) in [("a", "../b/content.html"), ("b", "../a/content.html")] {
+            fs::create_dir_all(entries_dir.join(name)).unwrap();
+            symlink(target, entries_dir.join(name).join("content.html")).unwrap();
+        }
+
+        for follow_symlinks in [true, false] {
+            let config = SiteConfig { root: dir.path().to_path_buf(), follow_symlinks, ..SiteConfig::default() };
+            match Site::builder().config(config).build().unwrap().generate() {
+                Err(IroniteError::SymlinkLoop(links)) => {
+                    assert!(links[0].ends_with("a/content.html") && links[1].ends_with("b/content.html"), "{:?}", links);
+                }
+                other => panic!("follow_symlinks = {}: expected a symlink loop, got {:?}", follow_symlinks, other.map(|_| ())),
+            }
+        }
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn non_utf8_entry_is_skipped_with_a_warning() {
+        use std::os::unix::ffi::OsStrExt;
+        let dir = TempDir::new("site-non-utf8");
+        fixture_project(dir.path());
+        let bad = dir.path().join("entries").join(std::ffi::OsStr::from_bytes(b"bad\xffname"));
+        write(&bad.join("content.html"), "<p>Bad</p>");
+        write(&bad.join("tags.txt"), "rust");
+
+        let config = SiteConfig { root: dir.path().to_path_buf(), ..SiteConfig::default() };
+        let stats = Site::builder().config(config).build().unwrap().generate().unwrap();
+        assert_eq!(stats.warnings, vec![format!("Skipped entry {}: its name is not valid UTF-8", bad.to_string_lossy())]);
+        let rust = fs::read_to_string(dir.path().join("public").join("rust").join("index.html")).unwrap();
+        assert!(!rust.contains("bad"), "{}", rust);
+    }
+
+    #[test]
+    fn drafts_are_built_only_with_include_drafts() {
+        let dir = TempDir::new("site-drafts");
+        fixture_project(dir.path());
+        add_entry(dir.path(), "third", "<p>Work in progress</p>", "rust draftonly");
+        write(&dir.path().join("entries").join("third").join("meta.toml"), "draft = true\n");
+
+        let config = SiteConfig { root: dir.path().to_path_buf(), ..SiteConfig::default() };
+        Site::builder().config(config).build().unwrap().generate().unwrap();
+        let public = dir.path().join("public");
+        let index = fs::read_to_string(public.join("entries").join("index.html")).unwrap();
+        assert!(index.contains("second") && !index.contains("third"), "{}", index);
+        assert!(!public.join("entries").join("third").exists());
+        assert!(!public.join("draftonly").exists());
+        let rust = fs::read_to_string(public.join("rust").join("index.html")).unwrap();
+        assert!(!rust.contains("third"), "{}", rust);
+
+        let config = SiteConfig { root: dir.path().to_path_buf(), include_drafts: true, ..SiteConfig::default() };
+        Site::builder().config(config).build().unwrap().generate().unwrap();
+        let index = fs::read_to_string(public.join("entries").join("index.html")).unwrap();
+        assert!(index.contains(">[DRAFT] third</a>") && index.contains(">second</a>"), "{}", index);
+        assert!(public.join("entries").join("third").join("index.html").exists());
+        let feed = fs::read_to_string(public.join("feed.json")).unwrap();
+        assert!(!feed.contains("third"), "{}", feed);
+    }
+
+    #[test]
+    fn recursive_entries_mirror_their_directories() {
+        let dir = TempDir::new("site-recursive");
+        fixture_project(dir.path());
+        add_entry(dir.path(), "rust/fearless-concurrency", "<p>Threads</p>", "rust");
+
+        let config = SiteConfig { root: dir.path().to_path_buf(), recursive_entries: true, ..SiteConfig::default() };
+        Site::builder().config(config).build().unwrap().generate().unwrap();
+        let public = dir.path().join("public");
+        let entry = fs::read_to_string(public.join("entries").join("rust").join("fearless-concurrency").join("index.html")).unwrap();
+        assert!(entry.contains("<p>Threads</p>") && entry.contains("href=\"../../../rust/index.html\""), "{}", entry);
+        let index = fs::read_to_string(public.join("entries").join("index.html")).unwrap();
+        assert!(index.contains("<a href=\"rust/fearless-concurrency/index.html\">fearless-concurrency</a>"), "{}", index);
+        let rust = fs::read_to_string(public.join("rust").join("index.html")).unwrap();
+        assert!(rust.contains("entries/rust/fearless-concurrency/index.html"), "{}", rust);
+
+        // Markdown entries are found and converted at any depth too
+        let svelte = dir.path().join("entries").join("web").join("sveltekit-intro");
+        write(&svelte.join("content.md"), "---\ntitle: SvelteKit\n---\n# Intro\n\nA *compiler*, see [the docs](https://svelte.dev).\n");
+        write(&svelte.join("tags.txt"), "web\n");
+        let config = SiteConfig { root: dir.path().to_path_buf(), recursive_entries: true, ..SiteConfig::default() };
+        Site::builder().config(config).build().unwrap().generate().unwrap();
+        let entry = fs::read_to_string(public.join("entries").join("web").join("sveltekit-intro").join("index.html")).unwrap();
+        assert!(entry.contains("<title>SvelteKit</title>"), "{}", entry);
+        assert!(entry.contains("<h1>Intro</h1>\n<p>A <em>compiler</em>, see <a href=\"https://svelte.dev\">the docs</a>.</p>"), "{}", entry);
+        let web = fs::read_to_string(public.join("web").join("index.html")).unwrap();
+        assert!(web.contains("entries/web/sveltekit-intro/index.html"), "{}", web);
+
+        // Two nested entries of the same name cannot share the name-keyed maps
+        add_entry(dir.path(), "web/first", "<p>Again</p>", "web");
+        let config = SiteConfig { root: dir.path().to_path_buf(), recursive_entries: true, ..SiteConfig::default() };
+        match Site::builder().config(config).build().unwrap().generate() {
+            Err(error @ IroniteError::DuplicateEntryName { .. }) => {
+                assert_eq!(error.to_string(), "entries first and web/first have the same name");
+            }
+            other => panic!("expected a duplicate entry name, got {:?}", other.map(|_| ())),
+        }
+    }
+
+    #[test]
+    fn lowercase_tags_merge_tag_pages() {
+        let dir = TempDir::new("site-lowercase-tags");
+        fixture_project(dir.path());
+        add_entry(dir.path(), "third", "<p>Third</p>", "Rust");
+
+        let config = SiteConfig { root: dir.path().to_path_buf(), lowercase_tags: true, ..SiteConfig::default() };
+        let stats = Site::builder().config(config).build().unwrap().generate().unwrap();
+        assert_eq!(stats.warnings, vec!["tags Rust (third), rust (first, second) were merged into rust"]);
+        let public = dir.path().join("public");
+        assert!(!public.join("Rust").exists());
+        let rust = fs::read_to_string(public.join("rust").join("index.html")).unwrap();
+        assert!(rust.contains("entries/first/") && rust.contains("entries/third/"), "{}", rust);
+        let third = fs::read_to_string(public.join("entries").join("third").join("index.html")).unwrap();
+        assert!(third.contains("../../rust/index.html"), "{}", third);
+        let feed = fs::read_to_string(public.join("feed.json")).unwrap();
+        assert!(!feed.contains("\"Rust\""), "{}", feed);
+    }
+
+    #[test]
+    fn case_insensitive_tags_show_their_first_spelling() {
+        let dir = TempDir::new("site-case-insensitive-tags");
+        fixture_project(dir.path());
+        add_entry(dir.path(), "a-first", "<p>A</p>", "RUST Web");
+        add_entry(dir.path(), "third", "<p>Third</p>", "Rust");
+
+        let config = SiteConfig {
+            root: dir.path().to_path_buf(),
+            case_insensitive_tags: true,
+            tag_display: HashMap::from([("WEB".to_string(), "The Web".to_string())]),
+            ..SiteConfig::default()
+        };
+        let stats = Site::builder().config(config).build().unwrap().generate().unwrap();
+        assert!(stats.warnings.is_empty(), "{:?}", stats.warnings);
+        let public = dir.path().join("public");
+        assert!(!public.join("RUST").exists() && !public.join("Rust").exists());
+        let rust = fs::read_to_string(public.join("rust").join("index.html")).unwrap();
+        assert!(rust.contains("<title>RUST</title>"), "{}", rust);
+        assert!(rust.contains("entries/first/") && rust.contains("entries/third/"), "{}", rust);
+        assert!(rust.contains("<a href=\"../web/index.html\">The Web</a>"), "{}", rust);
+        let feed = fs::read_to_string(public.join("feed.json")).unwrap();
+        assert!(feed.contains("\"RUST\"") && !feed.contains("\"rust\""), "{}", feed);
+    }
+
+    #[test]
+    fn multi_word_tags_use_slugified_urls() {
+        let dir = TempDir::new("site-multi-word-tags");
+        fixture_project(dir.path());
+        add_entry(dir.path(), "third", "<p>Third</p>", "\"machine learning\" rust");
+
+        let config = SiteConfig { root: dir.path().to_path_buf(), base_url: "https://example.com".to_string(), ..SiteConfig::default() };
+        Site::builder().config(config).build().unwrap().generate().unwrap();
+        let public = dir.path().join("public");
+        let tag = fs::read_to_string(public.join("machine-learning").join("index.html")).unwrap();
+        assert!(tag.contains("<title>machine learning</title>") && tag.contains("entries/third/"), "{}", tag);
+        let third = fs::read_to_string(public.join("entries").join("third").join("index.html")).unwrap();
+        assert!(third.contains("<a href=\"../../machine-learning/index.html\">machine learning</a>"), "{}", third);
+        let sitemap = fs::read_to_string(public.join("sitemap.xml")).unwrap();
+        assert!(sitemap.contains("https://example.com/machine-learning/index.html"), "{}", sitemap);
+    }
+
+    #[test]
+    fn nested_tags_get_nested_pages() {
+        let dir = TempDir::new("site-nested-tags");
+        fixture_project(dir.path());
+        add_entry(dir.path(), "third", "<p>Third</p>", "programming/rust");
+        add_entry(dir.path(), "fourth", "<p>Fourth</p>", "programming/go web");
+
+        let config = SiteConfig { root: dir.path().to_path_buf(), ..SiteConfig::default() };
+        Site::builder().config(config.clone()).build().unwrap().generate().unwrap();
+        let public = dir.path().join("public");
+        let rust = fs::read_to_string(public.join("programming").join("rust").join("index.html")).unwrap();
+        assert!(rust.contains("<title>programming/rust</title>") && rust.contains("\"../../entries/third/index.html\""), "{}", rust);
+        assert!(rust.contains("<a href=\"../../programming/index.html\">programming</a>"), "{}", rust);
+        let programming = fs::read_to_string(public.join("programming").join("index.html")).unwrap();
+        assert!(
+            programming.contains("<nav class=\"child-tags\"><a href=\"go/index.html\">go</a><a href=\"rust/index.html\">rust</a></nav>"),
+            "{}",
+            programming
+        );
+        assert!(programming.contains("../entries/third/index.html") && programming.contains("../entries/fourth/index.html"), "{}", programming);
+        let index = fs::read_to_string(public.join("entries").join("index.html")).unwrap();
+        assert!(index.contains("../programming/index.html") && !index.contains("programming/rust"), "{}", index);
+
+        let config = SiteConfig { nav_cloud_nested_tags: true, ..config };
+        Site::builder().config(config).build().unwrap().generate().unwrap();
+        let index = fs::read_to_string(public.join("entries").join("index.html")).unwrap();
+        assert!(index.contains("<a href=\"../programming/rust/index.html\">programming/rust</a>"), "{}", index);
+    }
+
+    #[test]
+    fn languages_get_their_own_trees() {
+        let dir = TempDir::new("site-languages");
+        fixture_project(dir.path());
+        let third = dir.path().join("entries").join("third");
+        write(&third.join("content.en.html"), "<p>Hello</p>");
+        write(&third.join("content.es.html"), "<p>Hola</p>");
+        write(&third.join("tags.txt"), "rust");
+        write(&dir.path().join("ironite.toml"), "languages = [\"es\", \"en\"]\ndefault_language = \"en\"\n");
+
+        let config = SiteConfig { root: dir.path().to_path_buf(), ..SiteConfig::load(dir.path().join("ironite.toml")).unwrap() };
+        let stats = Site::builder().config(config).build().unwrap().generate().unwrap();
+        let public = dir.path().join("public");
+        let page = |language: &str, name: &str| fs::read_to_string(public.join(language).join("entries").join(name).join("index.html")).unwrap();
+        assert!(page("en", "third").contains("<p>Hello</p>") && page("es", "third").contains("<p>Hola</p>"));
+        assert!(page("es", "first").contains("<p>First entry</p>"), "{}", page("es", "first"));
+        let rust = fs::read_to_string(public.join("es").join("rust").join("index.html")).unwrap();
+        assert!(rust.contains("../entries/third/index.html"), "{}", rust);
+        assert!(public.join("es").join("entries").join("index.html").exists());
+        assert!(fs::read_to_string(public.join("index.html")).unwrap().contains("en/index.html"));
+        assert!(fs::read_to_string(public.join("es").join("humans.txt")).unwrap().contains("Language: es"));
+
+        let first = dir.path().join("entries").join("first");
+        assert!(stats.warnings.contains(&format!("Entry {} has no content.es.html, using content.html", first.display())), "{:?}", stats.warnings);
+        assert!(!stats.warnings.iter().any(|warning| warning.contains("third")), "{:?}", stats.warnings);
+
+        write(&dir.path().join("ironite.toml"), "languages = [\"en\"]\ndefault_language = \"fr\"\n");
+        assert!(SiteConfig::load(dir.path().join("ironite.toml")).is_err());
+    }
+
+    #[test]
+    fn single_rebuilds_one_entry_and_its_tags() {
+        let dir = TempDir::new("site-single");
+        fixture_project(dir.path());
+        let config = SiteConfig { root: dir.path().to_path_buf(), ..SiteConfig::default() };
+        let site = Site::builder().config(config).build().unwrap();
+
+        // Without a previous build, only the pages asked for are written
+        let stats = site.generate_single("second", false).unwrap();
+        let public = dir.path().join("public");
+        let second = fs::read_to_string(public.join("entries").join("second").join("index.html")).unwrap();
+        assert!(second.contains("rust/index.html"), "{}", second);
+        assert!(second.contains("web/index.html"), "{}", second);
+        assert_eq!(stats.entry_count, 1);
+        assert!(!public.join("entries").join("first").exists());
+        assert!(!public.join("rust").exists());
+        assert!(!public.join("index.html").exists());
+
+        site.generate_single("second", true).unwrap();
+        assert!(public.join("rust").join("index.html").exists());
+        assert!(!public.join("web").exists());
+        assert!(site.generate_single("missing", false).unwrap_err().to_string().contains("no entry named missing"));
+    }
+
+    #[test]
+    fn link_entries_point_listings_and_feeds_elsewhere() {
+        let dir = TempDir::new("site-link-entries");
+        fixture_project(dir.path());
+        let elsewhere = dir.path().join("entries").join("elsewhere");
+        write(&elsewhere.join("link.txt"), "https://example.org/talk?id=1&lang=en\n");
+        write(&elsewhere.join("tags.txt"), "rust\n");
+        let config = SiteConfig { root: dir.path().to_path_buf(), base_url: "https://example.com".to_string(), ..SiteConfig::default() };
+        Site::builder().config(config.clone()).build().unwrap().generate().unwrap();
+
+        let public = dir.path().join("public");
+        let link = "<a href=\"https://example.org/talk?id=1&amp;lang=en\">elsewhere \u{2197}</a>";
+        assert!(fs::read_to_string(public.join("rust").join("index.html")).unwrap().contains(link));
+        assert!(fs::read_to_string(public.join("entries").join("index.html")).unwrap().contains(link));
+        assert!(fs::read_to_string(public.join("feed.json")).unwrap().contains("\"url\": \"https://example.org/talk?id=1&lang=en\""));
+        assert!(!fs::read_to_string(public.join("sitemap.xml")).unwrap().contains("elsewhere"));
+        assert!(!public.join("entries").join("elsewhere").exists());
+
+        let config = SiteConfig { external_link_stubs: true, ..config };
+        Site::builder().config(config.clone()).build().unwrap().generate().unwrap();
+        let stub = fs::read_to_string(public.join("entries").join("elsewhere").join("index.html")).unwrap();
+        assert!(stub.contains("url=https://example.org/talk?id=1&amp;lang=en"), "{}", stub);
+
+        write(&elsewhere.join("content.html"), "<p>Both</p>");
+        let error = Site::builder().config(config).build().unwrap().generate().unwrap_err();
+        assert_eq!(error.to_string(), "entries with both link.txt and content.html: elsewhere");
+    }
+
+    #[test]
+    fn excluded_tags_get_no_page_and_no_links() {
+        let dir = TempDir::new("site-excluded-tags");
+        fixture_project(dir.path());
+        add_entry(dir.path(), "third", "<p>Third</p>", "Todo rust");
+        write(&dir.path().join("static").join("base.html"), "<html><body>$NAVCLOUD|$TAGS|$CONTENT</body></html>");
+
+        let config = SiteConfig {
+            root: dir.path().to_path_buf(),
+            lowercase_tags: true,
+            excluded_tags: vec!["TODO".to_string(), "web".to_string()],
+            ..SiteConfig::default()
+        };
+        let stats = Site::builder().config(config).build().unwrap().generate().unwrap();
+        let public = dir.path().join("public");
+        assert!(!public.join("todo").exists());
+        assert!(!public.join("web").exists());
+        assert!(public.join("rust").join("index.html").exists());
+        let third = fs::read_to_string(public.join("entries").join("third").join("index.html")).unwrap();
+        assert_eq!(third, "<html><body><a href=\"../../rust/index.html\">rust</a>|<a href=\"../../rust/index.html\">rust</a>|<p>Third</p></body></html>");
+        assert_eq!(stats.excluded_tag_occurrences, 2);
+        assert_eq!(stats.tag_count, 1);
+        assert!(stats.to_string().contains("Excluded tags: 2 tag occurrences suppressed"), "{}", stats);
+    }
+
+    #[test]
+    fn series_get_a_page_and_a_nav_on_their_parts() {
+        let dir = TempDir::new("site-series");
+        fixture_project(dir.path());
+        write(&dir.path().join("static").join("base.html"), "<html><body>$SERIES_NAV|$CONTENT</body></html>");
+        write(&dir.path().join("entries").join("first").join("series.txt"), "Getting started\n2\n");
+        write(&dir.path().join("entries").join("second").join("series.txt"), "Getting started\n1\n");
+
+        let config = SiteConfig { root: dir.path().to_path_buf(), ..SiteConfig::default() };
+        Site::builder().config(config.clone()).build().unwrap().generate().unwrap();
+        let public = dir.path().join("public");
+        let series_page = fs::read_to_string(public.join("series").join("getting-started").join("index.html")).unwrap();
+        assert_eq!(
+            series_page,
+            "<html><body>|<ol class=\"series\"><li><a href=\"../../entries/second/index.html\">second</a></li>\
+             <li><a href=\"../../entries/first/index.html\">first</a></li></ol></body></html>"
+        );
+        let first = fs::read_to_string(public.join("entries").join("first").join("index.html")).unwrap();
+        assert!(first.contains(": part 2 of 2<ol><li><a href=\"../../entries/second/index.html\">second</a></li><li>first</li></ol>"), "{}", first);
+
+        write(&dir.path().join("entries").join("first").join("series.txt"), "Getting started\n1\n");
+        match Site::builder().config(config).build().unwrap().generate() {
+            Err(IroniteError::SeriesPartConflict { part: 1, .. }) => {}
+            other => panic!("expected a part conflict, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn tags_outside_the_whitelist_stop_the_build() {
+        let dir = TempDir::new("site-tags-whitelist");
+        fixture_project(dir.path());
+
+        let config = SiteConfig { root: dir.path().to_path_buf(), tags_whitelist: Some(vec!["rust".to_string()]), ..SiteConfig::default() };
+        match Site::builder().config(config.clone()).build().unwrap().generate() {
+            Err(IroniteError::InvalidTags(errors)) => {
+                assert_eq!(errors.len(), 1);
+                assert!(errors[0].to_string().starts_with("tag web of entry "), "{}", errors[0]);
+            }
+            other => panic!("expected InvalidTags, got {:?}", other),
+        }
+        assert!(!dir.path().join("public").join("index.html").exists());
+
+        // Excluded tags need no place in the whitelist
+        let config = SiteConfig { excluded_tags: vec!["web".to_string()], ..config };
+        Site::builder().config(config).build().unwrap().generate().unwrap();
+    }
+
+    #[test]
+    fn untagged_entries_get_a_page_last_in_the_nav_cloud() {
+        let dir = TempDir::new("site-untagged");
+        fixture_project(dir.path());
+        add_entry(dir.path(), "loose", "<p>Loose</p>", " \n");
+        write(&dir.path().join("entries").join("bare").join("content.html"), "<p>Bare</p>");
+
+        let config = SiteConfig { root: dir.path().to_path_buf(), ..SiteConfig::default() };
+        let stats = Site::builder().config(config.clone()).build().unwrap().generate().unwrap();
+        assert_eq!(stats.untagged_entries, vec!["bare", "loose"]);
+        assert!(stats.to_string().contains("Untagged entry loose\n"));
+        let public = dir.path().join("public");
+        let untagged = fs::read_to_string(public.join("untagged").join("index.html")).unwrap();
+        assert!(untagged.contains("../entries/bare/index.html") && untagged.contains("../entries/loose/index.html"), "{}", untagged);
+        assert!(!untagged.contains("entries/first/"), "{}", untagged);
+        let index = fs::read_to_string(public.join("entries").join("index.html")).unwrap();
+        assert!(index.contains("<a href=\"../web/index.html\">web</a><a href=\"../untagged/index.html\">untagged</a>"), "{}", index);
+
+        // The page can be renamed, or left out
+        fs::remove_dir_all(&public).unwrap();
+        let renamed = SiteConfig { untagged_tag: "uncategorized".to_string(), ..config.clone() };
+        Site::builder().config(renamed).build().unwrap().generate().unwrap();
+        assert!(public.join("uncategorized").join("index.html").exists());
+        assert!(!public.join("untagged").exists());
+        fs::remove_dir_all(&public).unwrap();
+        let stats = Site::builder().config(SiteConfig { untagged_tag: String::new(), ..config.clone() }).build().unwrap().generate().unwrap();
+        assert!(!public.join("untagged").exists());
+        assert_eq!(stats.untagged_entries, vec!["bare", "loose"]);
+
+        let config = SiteConfig { require_tags: true, ..config };
+        match Site::builder().config(config).build().unwrap().generate() {
+            Err(error @ IroniteError::UntaggedEntries(_)) => assert_eq!(error.to_string(), "entries without tags: bare, loose"),
+            other => panic!("expected untagged entries, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn build_hash_is_a_placeholder_and_written_to_build_version() {
+        let dir = TempDir::new("site-build-hash");
+        fixture_project(dir.path());
+        write(&dir.path().join("static").join("base.html"), "<html><body data-build=\"$BUILDHASH\">$CONTENT</body></html>");
+
+        let config = SiteConfig { root: dir.path().to_path_buf(), ..SiteConfig::default() };
+        let site = Site::builder().config(config.clone()).build().unwrap();
+        site.generate().unwrap();
+        let hash = site.build_hash().to_string();
+        let public = dir.path().join("public");
+        assert_eq!(fs::read_to_string(public.join(BUILD_VERSION_FILE)).unwrap(), format!("{}\n", hash));
+        assert!(fs::read_to_string(public.join("index.html")).unwrap().contains(&format!("data-build=\"{}\"", hash)));
+
+        // A second build of the same sources, over the first one's output, has the same hash
+        assert_eq!(Site::builder().config(config).build().unwrap().build_hash(), hash);
+    }
+
+    #[test]
+    fn build_info_is_written_with_emit_build_info() {
+        let dir = TempDir::new("site-build-info");
+        fixture_project(dir.path());
+        let path = dir.path().join("public").join(BUILD_INFO_FILE);
+
+        let config = SiteConfig { root: dir.path().to_path_buf(), build_date: Date::parse("2024-05-01"), ..SiteConfig::default() };
+        Site::builder().config(config.clone()).build().unwrap().generate().unwrap();
+        assert!(!path.exists());
+
+        let config = SiteConfig { emit_build_info: true, ..config };
+        Site::builder().config(config).build().unwrap().generate().unwrap();
+        let info = fs::read_to_string(&path).unwrap();
+        assert!(info.starts_with("{\n  \"build_timestamp\": \"2024-05-01T00:00:00Z\",\n  \"entries_count\": 2,\n  \"tags_count\": 2,\n"), "{}", info);
+        assert!(info.contains(&format!("\"generator_version\": \"{}\"", env!("CARGO_PKG_VERSION"))), "{}", info);
+        assert!(info.trim_end().ends_with('}'), "{}", info);
+    }
+
+    #[test]
+    fn entries_index_teasers_end_at_the_more_marker() {
+        let dir = TempDir::new("site-teasers");
+        fixture_project(dir.path());
+        add_entry(dir.path(), "third", "<p>Intro <img src=\"../../images/a.png\"></p>\n<!--more-->\n<p>The rest</p>", "rust");
+
+        let config = SiteConfig { root: dir.path().to_path_buf(), entries_index_teasers: true, ..SiteConfig::default() };
+        Site::builder().config(config).build().unwrap().generate().unwrap();
+        let public = dir.path().join("public");
+        let index = fs::read_to_string(public.join("entries").join("index.html")).unwrap();
+        assert!(
+            index.contains("<a href=\"third/index.html\">third</a><br><p>Intro <img src=\"../images/a.png\"></p><p><a href=\"third/index.html\">Read more</a></p>"),
+            "{}",
+            index
+        );
+        assert!(!index.contains("The rest"), "{}", index);
+        // Without a marker, the first paragraph
+        assert!(index.contains("<p>First entry</p><p><a href=\"first/index.html\">Read more</a></p>"), "{}", index);
+        let third = fs::read_to_string(public.join("entries").join("third").join("index.html")).unwrap();
+        assert!(third.contains("The rest") && !third.contains("<!--more-->"), "{}", third);
+    }
+
+    #[test]
+    fn tag_descriptions_head_their_tag_pages() {
+        let dir = TempDir::new("site-tag-descriptions");
+        fixture_project(dir.path());
+        write(
+            &dir.path().join("static").join("base.html"),
+            "<html><head><meta name=\"description\" content=\"$DESCRIPTION\"></head><body>$TAG_DESCRIPTION$CONTENT</body></html>",
+        );
+        add_entry(dir.path(), "third", "<p>Third</p>", "C++ \"machine learning\"");
+        write(&dir.path().join("tagdesc").join("C++.html"), "<p>The \"C++\" language</p>\n");
+        write(&dir.path().join("tagdesc").join("machine-learning.txt"), "Models\nand data");
+
+        let config = SiteConfig { root: dir.path().to_path_buf(), ..SiteConfig::default() };
+        Site::builder().config(config).build().unwrap().generate().unwrap();
+        let public = dir.path().join("public");
+        let cpp = fs::read_to_string(public.join("C++").join("index.html")).unwrap();
+        assert!(cpp.contains("content=\"The &quot;C++&quot; language\"></head><body><p>The \"C++\" language</p><a href="), "{}", cpp);
+        let ml = fs::read_to_string(public.join("machine-learning").join("index.html")).unwrap();
+        assert!(ml.contains("content=\"Models and data\"></head><body><p>Models</p><p>and data</p><a href="), "{}", ml);
+        let rust = fs::read_to_string(public.join("rust").join("index.html")).unwrap();
+        assert!(rust.contains("content=\"\"></head><body><a href="), "{}", rust);
+        let entry = fs::read_to_string(public.join("entries").join("third").join("index.html")).unwrap();
+        assert!(!entry.contains("$TAG_DESCRIPTION") && !entry.contains("$DESCRIPTION"), "{}", entry);
+    }
+
+    #[test]
+    fn favicon_and_icons_are_copied_and_linked() {
+        let dir = TempDir::new("site-icons");
+        fixture_project(dir.path());
+        write(&dir.path().join("static").join("base.html"), "<html><head>$ICONS</head><body>$CONTENT</body></html>");
+
+        let config = SiteConfig { root: dir.path().to_path_buf(), ..SiteConfig::default() };
+        Site::builder().config(config.clone()).build().unwrap().generate().unwrap();
+        let public = dir.path().join("public");
+        assert!(fs::read_to_string(public.join("index.html")).unwrap().starts_with("<html><head></head>"));
+
+        write(&dir.path().join("favicon.ico"), "ico");
+        write(&dir.path().join("icons").join("apple-touch-icon.png"), "png");
+        Site::builder().config(config).build().unwrap().generate().unwrap();
+        assert_eq!(fs::read_to_string(public.join("favicon.ico")).unwrap(), "ico");
+        assert!(public.join("icons").join("apple-touch-icon.png").exists());
+        let first = fs::read_to_string(public.join("entries").join("first").join("index.html")).unwrap();
+        assert!(
+            first.starts_with("<html><head><link rel=\"icon\" href=\"/favicon.ico\"><link rel=\"apple-touch-icon\" href=\"/icons/apple-touch-icon.png\"></head>"),
+            "{}",
+            first
+        );
+    }
+
+    #[test]
+    fn inline_assets_replaces_small_stylesheets() {
+        let dir = TempDir::new("site-inline");
+        fixture_project(dir.path());
+        write(
+            &dir.path().join("static").join("base.html"),
+            "<html><head><link rel=\"stylesheet\" href=\"/static/style.css\"></head><body>$CONTENT</body></html>",
+        );
+        write(&dir.path().join("static").join("style.css"), "body { margin: 0 }");
+
+        let config = SiteConfig { root: dir.path().to_path_buf(), inline_assets: true, ..SiteConfig::default() };
+        Site::builder().config(config).build().unwrap().generate().unwrap();
+        let public = dir.path().join("public");
+        let entry = fs::read_to_string(public.join("entries").join("first").join("index.html")).unwrap();
+        assert!(entry.contains("<head><style>body { margin: 0 }</style></head>"), "{}", entry);
+        // The copy in public/static/ is left alone
+        let base = fs::read_to_string(public.join("static").join("base.html")).unwrap();
+        assert!(base.contains("<link rel="), "{}", base);
+    }
+
+    #[test]
+    fn embed_assets_makes_entry_pages_self_contained() {
+        let dir = TempDir::new("site-embed");
+        fixture_project(dir.path());
+        write(
+            &dir.path().join("static").join("base.html"),
+            "<html><head><link rel=\"stylesheet\" href=\"/static/style.css\"></head><body>$CONTENT</body></html>",
+        );
+        write(&dir.path().join("static").join("style.css"), &"body { margin: 0 }\n".repeat(60_000));
+
+        let config = SiteConfig { root: dir.path().to_path_buf(), embed_assets: true, ..SiteConfig::default() };
+        let stats = Site::builder().config(config).build().unwrap().generate().unwrap();
+        let public = dir.path().join("public");
+        let first = fs::read_to_string(public.join("entries").join("first").join("index.html")).unwrap();
+        assert!(!first.contains("<link") && first.starts_with("<html><head><style>body { margin: 0 }"));
+        assert!(stats.warnings.iter().any(|warning| warning.starts_with("Entry first: its page is ")), "{:?}", stats.warnings);
+        // Only entry pages are embedded
+        assert!(fs::read_to_string(public.join("rust").join("index.html")).unwrap().contains("<link"));
+    }
+
+    #[test]
+    fn tag_aliases_merge_pages_and_redirect() {
+        let dir = TempDir::new("site-tag-aliases");
+        fixture_project(dir.path());
+        add_entry(dir.path(), "third", "<p>Third</p>", "golang");
+        write(&dir.path().join("tag-aliases.txt"), "golang = go
+web = rust
+");
+
+        let config = SiteConfig { root: dir.path().to_path_buf(), tag_alias_redirects: true, ..SiteConfig::default() };
+        let stats = Site::builder().config(config).build().unwrap().generate().unwrap();
+        let remaps: Vec<String> = stats.tag_remaps.iter().map(ToString::to_string).collect();
+        assert_eq!(remaps, vec!["golang => go: 1 entries", "web => rust: 1 entries"]);
+        assert!(stats.to_string().contains("Tag alias golang => go: 1 entries\n"), "{}", stats);
+
+        let public = dir.path().join("public");
+        let go = fs::read_to_string(public.join("go").join("index.html")).unwrap();
+        assert!(go.contains("entries/third/"), "{}", go);
+        let golang = fs::read_to_string(public.join("golang").join("index.html")).unwrap();
+        assert!(golang.contains("url=/go/index.html"), "{}", golang);
+        let rust = fs::read_to_string(public.join("rust").join("index.html")).unwrap();
+        assert!(rust.contains("entries/first/") && rust.contains("entries/second/"), "{}", rust);
+        let feed = fs::read_to_string(public.join("feed.json")).unwrap();
+        assert!(!feed.contains("\"golang\"") && feed.contains("\"go\""), "{}", feed);
+    }
+
+    #[test]
+    fn configured_tag_aliases_join_the_file_ones() {
+        let dir = TempDir::new("site-configured-tag-aliases");
+        fixture_project(dir.path());
+        add_entry(dir.path(), "third", "<p>Third</p>", "js");
+        add_entry(dir.path(), "fourth", "<p>Fourth</p>", "ecmascript");
+        write(&dir.path().join("tag-aliases.txt"), "ecmascript = js\n");
+
+        let config = SiteConfig {
+            root: dir.path().to_path_buf(),
+            tag_aliases: HashMap::from([("js".to_string(), "javascript".to_string())]),
+            ..SiteConfig::default()
+        };
+        Site::builder().config(config).build().unwrap().generate().unwrap();
+        let public = dir.path().join("public");
+        let javascript = fs::read_to_string(public.join("javascript").join("index.html")).unwrap();
+        assert!(javascript.contains("entries/third/") && javascript.contains("entries/fourth/"), "{}", javascript);
+        assert!(!public.join("js").exists());
+        assert!(!public.join("ecmascript").exists());
+    }
+
+    #[test]
+    fn pinned_entries_lead_the_entries_index() {
+        let dir = TempDir::new("site-pinned");
+        fixture_project(dir.path());
+        add_entry(dir.path(), "old-news", "<p>Old</p>", "news");
+        add_entry(dir.path(), "announcement", "<p>New</p>", "news");
+        write(&dir.path().join("entries").join("old-news").join("meta.toml"), "pinned = true\ndate = 2020-01-01\n");
+        write(&dir.path().join("entries").join("announcement").join("meta.toml"), "date = 2024-06-01\n");
+        write(&dir.path().join("entries").join("second").join("pinned"), "");
+
+        let config = SiteConfig { root: dir.path().to_path_buf(), ..SiteConfig::default() };
+        Site::builder().config(config).build().unwrap().generate().unwrap();
+        let public = dir.path().join("public");
+        let index = fs::read_to_string(public.join("entries").join("index.html")).unwrap();
+        // Pinned entries are ordered like the feed: dated ones first
+        let order: Vec<usize> = [">old-news<", ">second<", ">announcement<", ">first<"].iter().map(|name| index.find(name).unwrap()).collect();
+        assert!(order.windows(2).all(|pair| pair[0] < pair[1]), "{}", index);
+        // The feed ignores pinning unless feed_pinned is set
+        let feed = fs::read_to_string(public.join("feed.json")).unwrap();
+        assert!(feed.find("announcement").unwrap() < feed.find("old-news").unwrap(), "{}", feed);
+
+        let config = SiteConfig { root: dir.path().to_path_buf(), feed_pinned: true, ..SiteConfig::default() };
+        Site::builder().config(config).build().unwrap().generate().unwrap();
+        let feed = fs::read_to_string(public.join("feed.json")).unwrap();
+        assert!(feed.find("old-news").unwrap() < feed.find("announcement").unwrap(), "{}", feed);
+    }
+
+    #[test]
+    fn pinned_entries_lead_tag_pages_with_a_marker() {
+        let dir = TempDir::new("site-pinned-tags");
+        fixture_project(dir.path());
+        write(&dir.path().join("entries").join("second").join("pinned"), "");
+
+        let config = SiteConfig {
+            root: dir.path().to_path_buf(),
+            pinned_marker: "\u{1F4CC}".to_string(),
+            pinned_class: "pinned".to_string(),
+            ..SiteConfig::default()
+        };
+        Site::builder().config(config).build().unwrap().generate().unwrap();
+        let public = dir.path().join("public");
+        let rust = fs::read_to_string(public.join("rust").join("index.html")).unwrap();
+        assert!(
+            rust.contains("<a href=\"../entries/second/index.html\" class=\"pinned\">\u{1F4CC} second</a><br><a href=\"../entries/first/index.html\">first</a><br>"),
+            "{}",
+            rust
+        );
+        let index = fs::read_to_string(public.join("entries").join("index.html")).unwrap();
+        assert!(index.contains("<a href=\"second/index.html\" class=\"pinned\">\u{1F4CC} second</a><br><a href=\"first/index.html\">first</a>"), "{}", index);
+    }
+
+    #[test]
+    fn lighter_entries_come_first_in_listings() {
+        let dir = TempDir::new("site-weight");
+        fixture_project(dir.path());
+        add_entry(dir.path(), "advanced", "<p>Advanced</p>", "rust");
+        add_entry(dir.path(), "introduction", "<p>Introduction</p>", "rust");
+        write(&dir.path().join("entries").join("advanced").join("meta.toml"), "date = 2024-01-01
+");
+        write(&dir.path().join("entries").join("introduction").join("meta.toml"), "date = 2024-06-01
+weight = -1
+");
+
+        let config = SiteConfig { root: dir.path().to_path_buf(), ..SiteConfig::default() };
+        Site::builder().config(config).build().unwrap().generate().unwrap();
+        let public = dir.path().join("public");
+        for page in [public.join("entries").join("index.html"), public.join("rust").join("index.html")] {
+            let html = fs::read_to_string(&page).unwrap();
+            let order: Vec<usize> = [">introduction<", ">advanced<", ">first<", ">second<"].iter().map(|name| html.find(name).unwrap()).collect();
+            assert!(order.windows(2).all(|pair| pair[0] < pair[1]), "{}", html);
+        }
+    }
+
+    #[test]
+    fn index_sort_orders_the_index_and_the_feeds() {
+        let dir = TempDir::new("site-index-sort");
+        fixture_project(dir.path());
+        add_entry(dir.path(), "third", "<p>Third</p>", "rust");
+        write(&dir.path().join("entries").join("first").join("meta.toml"), "date = 2024-01-01\n");
+        write(&dir.path().join("entries").join("second").join("meta.toml"), "date = 2024-03-01\n");
+        write(&dir.path().join("entries").join("third").join("meta.toml"), "date = 2024-02-01\n");
+        let public = dir.path().join("public");
+        let order = |file: &str, names: &[&str]| {
+            let text = fs::read_to_string(public.join(file)).unwrap();
+            let positions: Vec<usize> = names.iter().map(|name| text.find(&format!("{}/index.html", name)).unwrap()).collect();
+            assert!(positions.windows(2).all(|pair| pair[0] < pair[1]), "{}: {}", file, text);
+        };
+
+        let config = SiteConfig { root: dir.path().to_path_buf(), ..SiteConfig::default() };
+        Site::builder().config(config.clone()).build().unwrap().generate().unwrap();
+        order("entries/index.html", &["first", "second", "third"]);
+        order("feed.json", &["second", "third", "first"]);
+
+        let config = SiteConfig { index_sort: IndexSort::Oldest, ..config };
+        Site::builder().config(config.clone()).build().unwrap().generate().unwrap();
+        order("entries/index.html", &["first", "third", "second"]);
+        order("feed.json", &["first", "third", "second"]);
+
+        write(&dir.path().join(ORDER_FILE), "third\nfirst\n");
+        let config = SiteConfig { index_sort: IndexSort::Manual, ..config };
+        let stats = Site::builder().config(config).build().unwrap().generate().unwrap();
+        order("entries/index.html", &["third", "first", "second"]);
+        order("atom.xml", &["third", "first", "second"]);
+        assert!(stats.warnings.contains(&"Entry second is not in order.txt, listed after the others".to_string()), "{:?}", stats.warnings);
+    }
+
+    #[test]
+    fn pretty_html_indents_pages_and_excludes_minify() {
+        let dir = TempDir::new("site-pretty");
+        fixture_project(dir.path());
+
+        let config = SiteConfig { root: dir.path().to_path_buf(), pretty_html: true, ..SiteConfig::default() };
+        Site::builder().config(config).build().unwrap().generate().unwrap();
+        let entry = fs::read_to_string(dir.path().join("public").join("entries").join("first").join("index.html")).unwrap();
+        assert!(entry.starts_with("<html>\n  <head>\n    <title>first</title>\n  </head>\n  <body>\n"), "{}", entry);
+        assert!(entry.contains("\n    <p>First entry</p>\n"), "{}", entry);
+
+        let config = SiteConfig { root: dir.path().to_path_buf(), pretty_html: true, minify_html: true, ..SiteConfig::default() };
+        assert!(Site::builder().config(config).build().is_err());
+    }
+
+    #[test]
+    fn entry_template_override_with_fallback() {
+        let dir = TempDir::new("site-template");
+        fixture_project(dir.path());
+        write(&dir.path().join("static").join("wide.html"), "<div class=\"wide\">$CONTENT</div>");
+        write(&dir.path().join("entries").join("first").join("meta.toml"), "template = \"wide\"\n");
+        write(&dir.path().join("entries").join("second").join("meta.toml"), "template = \"missing\"\n");
+
+        let config = SiteConfig { root: dir.path().to_path_buf(), ..SiteConfig::default() };
+        Site::builder().config(config).build().unwrap().generate().unwrap();
+
+        let entries = dir.path().join("public").join("entries");
+        let first = fs::read_to_string(entries.join("first").join("index.html")).unwrap();
+        assert_eq!(first, "<div class=\"wide\"><p>First entry</p></div>");
+        let second = fs::read_to_string(entries.join("second").join("index.html")).unwrap();
+        assert!(second.starts_with("<html>"));
+    }
+
+    #[test]
+    fn plugins_run_before_and_after_generation() {
+        let dir = TempDir::new("site-plugin");
+        fixture_project(dir.path());
+        let log = dir.path().join("plugin.log");
+        let index = dir.path().join("public").join("index.html");
+
+        let config = SiteConfig { root: dir.path().to_path_buf(), ..SiteConfig::default() };
+        Site::builder()
+            .config(config)
+            .plugin(LogPlugin { log: log.clone(), index: index.clone() })
+            .plugin(LogPlugin { log: log.clone(), index })
+            .build()
+            .unwrap()
+            .generate()
+            .unwrap();
+
+        assert_eq!(fs::read_to_string(log).unwrap(), "pre false\npre false\npost true\npost true\n");
+    }
+
+    #[test]
+    fn entry_limit_builds_the_first_entries_only() {
+        let dir = TempDir::new("site-entry-limit");
+        fixture_project(dir.path());
+        add_entry(dir.path(), "third", "<p>Third</p>", "python");
+        add_entry(dir.path(), "fourth", "<p>Fourth</p>", "go");
+        add_entry(dir.path(), "fifth", "<p>Fifth</p>", "go");
+
+        let config = SiteConfig { root: dir.path().to_path_buf(), entry_limit: Some(2), ..SiteConfig::default() };
+        let stats = Site::builder().config(config).build().unwrap().generate().unwrap();
+        assert_eq!(stats.entry_count, 2);
+        assert!(stats.warnings.iter().any(|warning| warning.starts_with("entry_limit is set")), "{:?}", stats.warnings);
+
+        let public = dir.path().join("public");
+        let mut built: Vec<String> = fs::read_dir(public.join("entries"))
+            .unwrap()
+            .map(|entry| entry.unwrap())
+            .filter(|entry| entry.path().is_dir())
+            .map(|entry| entry.file_name().to_string_lossy().into_owned())
+            .collect();
+        built.sort();
+        assert_eq!(built, ["fifth", "first"]);
+        assert!(public.join("go").exists() && public.join("web").exists() && !public.join("python").exists());
+        let index = fs::read_to_string(public.join("entries").join("index.html")).unwrap();
+        assert!(index.contains("fifth") && !index.contains("fourth"), "{}", index);
+    }
+
+    #[test]
+    fn build_commands_run_around_the_build() {
+        let dir = TempDir::new("site-build-hooks");
+        fixture_project(dir.path());
+
+        let config = SiteConfig { root: dir.path().to_path_buf(), post_build: "echo deployed> deployed.txt".to_string(), ..SiteConfig::default() };
+        Site::builder().config(config.clone()).build().unwrap().generate().unwrap();
+        assert!(dir.path().join("deployed.txt").exists());
+
+        fs::remove_dir_all(dir.path().join("public")).unwrap();
+        let config = SiteConfig { pre_build: "exit 1".to_string(), ..config };
+        let error = Site::builder().config(config).build().unwrap().generate().unwrap_err();
+        assert!(matches!(error, IroniteError::BuildHook { hook: "pre_build", .. }), "{:?}", error);
+        assert!(!dir.path().join("public").exists());
+    }
+
+    #[test]
+    fn plugin_error_aborts_generation() {
+        let dir = TempDir::new("site-plugin-error");
+        fixture_project(dir.path());
+
+        let config = SiteConfig { root: dir.path().to_path_buf(), ..SiteConfig::default() };
+        let result = Site::builder().config(config).plugin(FailingPlugin).build().unwrap().generate();
+
+        match result {
+            Err(IroniteError::Plugin { plugin, hook, .. }) => {
+                assert_eq!(plugin, "failing");
+                assert_eq!(hook, "pre_generate");
+            }
+            other => panic!("expected a plugin error, got {:?}", other.map(|_| ())),
+        }
+        assert!(!dir.path().join("public").exists());
+    }
+
+    #[test]
+    fn aliases_redirect_to_renamed_entries() {
+        let dir = TempDir::new("site-aliases");
+        fixture_project(dir.path());
+        write(&dir.path().join("entries").join("first").join("aliases.txt"), "entries/old-first/index.html\n/2019/first/\n");
+
+        let config = SiteConfig { root: dir.path().to_path_buf(), ..SiteConfig::default() };
+        Site::builder().config(config).build().unwrap().generate().unwrap();
+
+        let public = dir.path().join("public");
+        for alias in [public.join("entries").join("old-first").join("index.html"), public.join("2019").join("first").join("index.html")] {
+            let page = fs::read_to_string(alias).unwrap();
+            assert!(page.contains("<meta http-equiv=\"refresh\" content=\"0; url=/entries/first/index.html\">"));
+            assert!(page.contains("<link rel=\"canonical\" href=\"/entries/first/index.html\">"));
+        }
+    }
+
+    #[test]
+    fn alias_colliding_with_generated_page_is_an_error() {
+        let dir = TempDir::new("site-alias-conflict");
+        fixture_project(dir.path());
+        write(&dir.path().join("entries").join("first").join("aliases.txt"), "entries/second/index.html\n");
+
+        let config = SiteConfig { root: dir.path().to_path_buf(), ..SiteConfig::default() };
+        let result = Site::builder().config(config).build().unwrap().generate();
+
+        match result {
+            Err(IroniteError::AliasConflict { alias, entry, other: None }) => {
+                assert_eq!(alias, "entries/second/index.html");
+                assert_eq!(entry, "first");
+            }
+            other => panic!("expected an alias conflict, got {:?}", other.map(|_| ())),
+        }
+        let second = fs::read_to_string(dir.path().join("public").join("entries").join("second").join("index.html")).unwrap();
+        assert!(second.contains("Second entry"));
+    }
+
+    #[test]
+    fn pretty_urls_link_to_directories() {
+        let dir = TempDir::new("site-pretty");
+        fixture_project(dir.path());
+
+        let config = SiteConfig { root: dir.path().to_path_buf(), pretty_urls: true, check_links: true, ..SiteConfig::default() };
+        let stats = Site::builder().config(config).build().unwrap().generate().unwrap();
+        assert!(stats.broken_links.is_empty());
+
+        let public = dir.path().join("public");
+        let tag = fs::read_to_string(public.join("rust").join("index.html")).unwrap();
+        assert!(tag.contains("<a href=\"../entries/first/\">first</a>"));
+        assert!(tag.contains("<a href=\"../web/\">web</a>"));
+        let entries = fs::read_to_string(public.join("entries").join("index.html")).unwrap();
+        assert!(entries.contains("<a href=\"first/\">first</a>"));
+        assert!(!entries.contains("index.html"));
+        assert!(public.join("entries").join("first").join("index.html").exists());
+    }
+
+    #[test]
+    fn not_found_page_uses_root_relative_links() {
+        let dir = TempDir::new("site-404");
+        fixture_project(dir.path());
+        write(
+            &dir.path().join("static").join("base.html"),
+            "<html><head><title>$TITLE</title><link rel=\"stylesheet\" href=\"../../static/style.css\"></head><body>$NAVCLOUD $CONTENT</body></html>",
+        );
+
+        let config = SiteConfig { root: dir.path().to_path_buf(), ..SiteConfig::default() };
+        Site::builder().config(config).build().unwrap().generate().unwrap();
+
+        let page = fs::read_to_string(dir.path().join("public").join("404.html")).unwrap();
+        assert!(page.contains("<title>Not found</title>"));
+        assert!(page.contains("href=\"/static/style.css\""));
+        assert!(page.contains("<a href=\"/rust/index.html\">rust</a>"));
+        assert!(page.contains(DEFAULT_NOT_FOUND_CONTENT));
+
+        write(&dir.path().join("static").join("404.html"), "<p>Lost?</p>");
+        let config = SiteConfig { root: dir.path().to_path_buf(), base_url: "https://example.com".to_string(), ..SiteConfig::default() };
+        Site::builder().config(config).build().unwrap().generate().unwrap();
+
+        let page = fs::read_to_string(dir.path().join("public").join("404.html")).unwrap();
+        assert!(page.contains("<p>Lost?</p>"));
+        assert!(page.contains("<a href=\"https://example.com/web/index.html\">web</a>"));
+    }
+
+    #[test]
+    fn permalink_pattern_sets_entry_paths() {
+        let dir = TempDir::new("site-permalink");
+        fixture_project(dir.path());
+        write(&dir.path().join("entries").join("first").join("meta.toml"), "date = 2024-03-05\n");
+        write(&dir.path().join("entries").join("second").join("meta.toml"), "date = 2023-11-20\n");
+
+        let config = SiteConfig {
+            root: dir.path().to_path_buf(),
+            permalink_pattern: "{year}/{month}/{slug}".to_string(),
+            check_links: true,
+            ..SiteConfig::default()
+        };
+        let stats = Site::builder().config(config).build().unwrap().generate().unwrap();
+        assert!(stats.broken_links.is_empty(), "{:?}", stats.broken_links);
+
+        let public = dir.path().join("public");
+        let first = fs::read_to_string(public.join("2024").join("03").join("first").join("index.html")).unwrap();
+        assert!(first.contains("<a href=\"../../../rust/index.html\">rust</a>"));
+        let tag = fs::read_to_string(public.join("rust").join("index.html")).unwrap();
+        assert!(tag.contains("<a href=\"../2023/11/second/index.html\">second</a>"));
+        assert!(!public.join("entries").join("first").exists());
+    }
+
+    #[test]
+    fn text_entries_are_rendered() {
+        let dir = TempDir::new("site-text-entry");
+        fixture_project(dir.path());
+        let notes = dir.path().join("entries").join("notes");
+        write(&notes.join("content.txt"), "---\ntitle: Notes\n---\nSee https://example.com/a?b=1&c=2.\n<b>Not a tag</b>\n");
+        write(&notes.join("tags.txt"), "rust\n");
+
+        for recursive_entries in [false, true] {
+            let config = SiteConfig { root: dir.path().to_path_buf(), recursive_entries, ..SiteConfig::default() };
+            Site::builder().config(config).build().unwrap().generate().unwrap();
+            let public = dir.path().join("public");
+            let page = fs::read_to_string(public.join("entries").join("notes").join("index.html")).unwrap();
+            assert!(page.contains("<title>Notes</title>"), "{}", page);
+            assert!(
+                page.contains("<p>See <a href=\"https://example.com/a?b=1&amp;c=2\">https://example.com/a?b=1&amp;c=2</a>.</p><p>&lt;b&gt;Not a tag&lt;/b&gt;</p>"),
+                "{}",
+                page
+            );
+            for listing in [public.join("entries").join("index.html"), public.join("rust").join("index.html")] {
+                assert!(fs::read_to_string(&listing).unwrap().contains("notes/index.html"), "{}", listing.display());
+            }
+        }
+    }
+
+    #[test]
+    fn front_matter_sets_title_and_merges_tags() {
+        let dir = TempDir::new("site-front-matter");
+        fixture_project(dir.path());
+        write(
+            &dir.path().join("entries").join("second").join("content.html"),
+            "---\ntitle: Second post & <more>\ntags: [cli]\n---\n<p>Second entry</p>",
+        );
+
+        let config = SiteConfig { root: dir.path().to_path_buf(), ..SiteConfig::default() };
+        Site::builder().config(config).build().unwrap().generate().unwrap();
+
+        let public = dir.path().join("public");
+        let second = fs::read_to_string(public.join("entries").join("second").join("index.html")).unwrap();
+        assert!(second.contains("<title>Second post &amp; &lt;more&gt;</title>"), "{}", second);
+        assert!(second.contains("<p>Second entry</p>"));
+        assert!(!second.contains("---"));
+        for tag in ["cli", "rust"] {
+            let page = fs::read_to_string(public.join(tag).join("index.html")).unwrap();
+            assert!(page.contains("entries/second/index.html"), "{} page", tag);
+        }
+    }
+
+    #[test]
+    fn robots_sitemap_and_root_files() {
+        let dir = TempDir::new("site-root-files");
+        fixture_project(dir.path());
+        write(&dir.path().join("root_files").join("CNAME"), "blog.example.com\n");
+        write(&dir.path().join("root_files").join(".nojekyll"), "");
+
+        let config = SiteConfig { root: dir.path().to_path_buf(), base_url: "https://example.com".to_string(), ..SiteConfig::default() };
+        Site::builder().config(config.clone()).build().unwrap().generate().unwrap();
+
+        let public = dir.path().join("public");
+        let robots = fs::read_to_string(public.join("robots.txt")).unwrap();
+        assert!(robots.contains("Sitemap: https://example.com/sitemap.xml"));
+        let sitemap = fs::read_to_string(public.join("sitemap.xml")).unwrap();
+        assert!(sitemap.contains("<loc>https://example.com/entries/first/index.html</loc>"));
+        assert!(fs::read_to_string(public.join("humans.txt")).unwrap().contains("/* SITE */"));
+        assert_eq!(fs::read_to_string(public.join("CNAME")).unwrap(), "blog.example.com\n");
+        assert!(public.join(".nojekyll").exists());
+
+        // A root file wins over the generated file of the same name
+        write(&dir.path().join("root_files").join("robots.txt"), "User-agent: *\nDisallow: /\n");
+        Site::builder().config(config).build().unwrap().generate().unwrap();
+        assert_eq!(fs::read_to_string(public.join("robots.txt")).unwrap(), "User-agent: *\nDisallow: /\n");
+    }
+
+    #[test]
+    fn large_sites_get_a_sitemap_index() {
+        let dir = TempDir::new("site-sitemap-index");
+        fixture_project(dir.path());
+        add_entry(dir.path(), "third", "<p>Third</p>", "rust");
+
+        let config = SiteConfig {
+            root: dir.path().to_path_buf(),
+            base_url: "https://example.com".to_string(),
+            sitemap_max_urls: 2,
+            ..SiteConfig::default()
+        };
+        Site::builder().config(config).build().unwrap().generate().unwrap();
+        let public = dir.path().join("public");
+        assert!(fs::read_to_string(public.join("robots.txt")).unwrap().contains("Sitemap: https://example.com/sitemap_index.xml"));
+        let index = fs::read_to_string(public.join(SITEMAP_INDEX_FILE)).unwrap();
+        for name in ["sitemap-pages.xml", "sitemap-entries-1.xml", "sitemap-entries-2.xml", "sitemap-tags.xml"] {
+            assert!(index.contains(&format!("<loc>https://example.com/{}</loc>", name)), "{}", index);
+            assert!(public.join(name).exists(), "{}", name);
+        }
+        assert_eq!(index.matches("<sitemap>").count(), 4);
+
+        let entries: Vec<String> = ["sitemap-entries-1.xml", "sitemap-entries-2.xml"]
+            .iter()
+            .map(|name| fs::read_to_string(public.join(name)).unwrap())
+            .collect();
+        assert_eq!(entries[0].matches("<url>").count(), 2);
+        assert_eq!(entries[1].matches("<url>").count(), 1);
+        for entry in ["first", "second", "third"] {
+            assert!(entries.iter().any(|xml| xml.contains(&format!("/entries/{}/index.html</loc>", entry))), "{}", entry);
+        }
+        let tags = fs::read_to_string(public.join("sitemap-tags.xml")).unwrap();
+        assert!(tags.contains("<loc>https://example.com/rust/index.html</loc>") && tags.contains("<loc>https://example.com/web/index.html</loc>"));
+    }
+
+    #[test]
+    fn extra_files_are_copied_over_generated_ones() {
+        let dir = TempDir::new("site-extra-files");
+        fixture_project(dir.path());
+        write(&dir.path().join("humans.txt"), "/* TEAM */\n\tMe\n");
+        write(&dir.path().join(".well-known").join("security.txt"), "Contact: mailto:me@example.com\n");
+
+        let config = SiteConfig { root: dir.path().to_path_buf(), ..SiteConfig::default() };
+        Site::builder().config(config).build().unwrap().generate().unwrap();
+        let public = dir.path().join("public");
+        assert_eq!(fs::read_to_string(public.join("humans.txt")).unwrap(), "/* TEAM */\n\tMe\n");
+        assert_eq!(fs::read_to_string(public.join(".well-known").join("security.txt")).unwrap(), "Contact: mailto:me@example.com\n");
+
+        write(&dir.path().join("ironite.toml"), "extra_files = [\"docs/keys.asc\"]\n");
+        write(&dir.path().join("docs").join("keys.asc"), "KEY");
+        let config = SiteConfig { root: dir.path().to_path_buf(), ..SiteConfig::load(dir.path().join("ironite.toml")).unwrap() };
+        Site::builder().config(config).build().unwrap().generate().unwrap();
+        assert_eq!(fs::read_to_string(public.join("docs").join("keys.asc")).unwrap(), "KEY");
+
+        write(&dir.path().join("ironite.toml"), "extra_files = [\"../secrets\"]\n");
+        assert!(SiteConfig::load(dir.path().join("ironite.toml")).is_err());
+    }
+
+    #[test]
+    fn configured_tag_intersections_are_generated() {
+        let dir = TempDir::new("site-intersections");
+        fixture_project(dir.path());
+        write(&dir.path().join("ironite.toml"), "[[tag_intersections]]\ntags = [\"web\", \"rust\"]\n");
+
+        let config = SiteConfig { root: dir.path().to_path_buf(), ..SiteConfig::load(dir.path().join("ironite.toml")).unwrap() };
+        Site::builder().config(config).build().unwrap().generate().unwrap();
+        let page = fs::read_to_string(dir.path().join("public").join("tags").join("rust+web").join("index.html")).unwrap();
+        assert!(page.contains("<title>rust \u{2229} web</title>"), "{}", page);
+        assert!(page.contains("../../entries/first/index.html") && !page.contains("second"), "{}", page);
+    }
+
+    #[test]
+    fn unsafe_names_stop_the_build_before_writing() {
+        let dir = TempDir::new("site-unsafe-names");
+        fixture_project(dir.path());
+        add_entry(dir.path(), "static", "<p>Clash</p>", "entries");
+
+        let config = SiteConfig { root: dir.path().to_path_buf(), ..SiteConfig::default() };
+        match Site::builder().config(config).build().unwrap().generate() {
+            Err(IroniteError::InvalidNames(problems)) => {
+                let names: Vec<(&str, &str)> = problems.iter().map(|problem| (problem.kind, problem.name.as_str())).collect();
+                assert_eq!(names, vec![("entry", "static"), ("tag", "entries")]);
+            }
+            other => panic!("expected invalid names, got {:?}", other.map(|_| ())),
+        }
+        assert!(!dir.path().join("public").exists());
+
+        // The tag page of entries is also the entries index
+        let config = SiteConfig { root: dir.path().to_path_buf(), allow_unsafe_names: true, allow_output_collisions: true, ..SiteConfig::default() };
+        let stats = Site::builder().config(config).build().unwrap().generate().unwrap();
+        assert_eq!(stats.warnings.len(), 3, "{:?}", stats.warnings);
+        assert_eq!(stats.warnings[2], "entries/index.html is written by entries/index.html, tag entries");
+        assert!(dir.path().join("public").join("entries").join("static").join("index.html").exists());
+    }
+
+    #[test]
+    fn public_dir_must_stay_clear_of_the_sources() {
+        let dir = TempDir::new("site-public-dir");
+        fixture_project(dir.path());
+        for (public_dir, message) in [
+            ("", "public_dir:  is the project root"),
+            ("public/..", "public_dir: public/.. is the project root"),
+            ("..", "public_dir: .. contains the project root"),
+            ("./static", "public_dir: ./static is the static directory"),
+            ("entries/out", "public_dir: entries/out is inside the entries directory entries"),
+        ] {
+            let config = SiteConfig { root: dir.path().to_path_buf(), public_dir: PathBuf::from(public_dir), ..SiteConfig::default() };
+            match Site::builder().config(config).build() {
+                Err(IroniteError::Io(e)) => assert_eq!(e.to_string(), message),
+                other => panic!("expected {:?}, got {:?}", message, other.map(|_| ())),
+            }
+        }
+
+        let config = SiteConfig { root: dir.path().to_path_buf(), images_dir: PathBuf::from("site/images"), public_dir: PathBuf::from("site"), ..SiteConfig::default() };
+        assert!(Site::builder().config(config).build().is_err());
+        let config = SiteConfig { root: dir.path().to_path_buf(), public_dir: PathBuf::from("build/site"), ..SiteConfig::default() };
+        Site::builder().config(config).build().unwrap().generate().unwrap();
+        assert!(dir.path().join("build").join("site").join("index.html").exists());
+    }
+
+    #[test]
+    fn output_collisions_stop_the_build_before_writing() {
+        let dir = TempDir::new("site-collisions");
+        fixture_project(dir.path());
+        add_entry(dir.path(), "third", "<p>Third</p>", "Rust");
+        write(&dir.path().join("entries").join("second").join("meta.toml"), "short_url = \"web\"\n");
+
+        let config = SiteConfig { root: dir.path().to_path_buf(), ..SiteConfig::default() };
+        match Site::builder().config(config).build().unwrap().generate() {
+            Err(IroniteError::OutputCollisions(collisions)) => {
+                let collisions: Vec<String> = collisions.iter().map(|collision| collision.to_string()).collect();
+                assert_eq!(
+                    collisions,
+                    vec![
+                        "web/index.html is written by short URL of entry second, tag web",
+                        "Rust/index.html, rust/index.html differ only in case, written by tag Rust, tag rust",
+                    ]
+                );
+            }
+            other => panic!("expected output collisions, got {:?}", other.map(|_| ())),
+        }
+        assert!(!dir.path().join("public").exists());
+
+        let config = SiteConfig { root: dir.path().to_path_buf(), allow_output_collisions: true, ..SiteConfig::default() };
+        let stats = Site::builder().config(config).build().unwrap().generate().unwrap();
+        assert_eq!(stats.warnings.len(), 2, "{:?}", stats.warnings);
+    }
+
+    #[test]
+    fn config_placeholders_per_page_kind() {
+        let dir = TempDir::new("site-placeholders");
+        fixture_project(dir.path());
+        write(
+            &dir.path().join("static").join("base.html"),
+            "<html><head><title>$TITLE</title></head><body>$HEADERIMAGE|$FOOTER|$CONTENT</body></html>",
+        );
+        let table = crate::toml::parse(
+            "[placeholders]\nHEADERIMAGE = \"default.png\"\nFOOTER = \"(c) me\"\n\
+             [tag.placeholders]\nHEADERIMAGE = \"tag.png\"\n\
+             [entry.placeholders]\n\"$HEADERIMAGE\" = \"entry.png\"\nTITLE = \"ignored\"\n",
+        )
+        .unwrap();
+        let config = SiteConfig { root: dir.path().to_path_buf(), ..SiteConfig::from_table(&table).unwrap() };
+        Site::builder().config(config).build().unwrap().generate().unwrap();
+
+        let public = dir.path().join("public");
+        let entry = fs::read_to_string(public.join("entries").join("first").join("index.html")).unwrap();
+        assert!(entry.contains("<title>first</title></head><body>entry.png|(c) me|"));
+        let tag = fs::read_to_string(public.join("rust").join("index.html")).unwrap();
+        assert!(tag.contains("tag.png|(c) me|"));
+        let index = fs::read_to_string(public.join("index.html")).unwrap();
+        assert!(index.contains("default.png|(c) me|"));
+    }
+
+    #[test]
+    fn vars_file_fills_placeholders_on_every_page() {
+        let dir = TempDir::new("site-vars");
+        fixture_project(dir.path());
+        write(
+            &dir.path().join("static").join("base.html"),
+            "<html><head><title>$TITLE</title></head><body>$CONTENT <footer>$AUTHOR $FOOTER_YEAR</footer></body></html>",
+        );
+        write(&dir.path().join("vars.txt"), "# site variables\nAUTHOR = Jane Doe\nFOOTER_YEAR=2024\nTITLE=Not used\n");
+
+        let config = SiteConfig { root: dir.path().to_path_buf(), ..SiteConfig::default() };
+        Site::builder().config(config).build().unwrap().generate().unwrap();
+
+        let public = dir.path().join("public");
+        for page in [public.join("entries").join("first").join("index.html"), public.join("rust").join("index.html"), public.join("index.html")] {
+            let html = fs::read_to_string(&page).unwrap();
+            assert!(html.contains("<footer>Jane Doe 2024</footer>"), "{:?}", page);
+            assert!(!html.contains("Not used"), "{:?}", page);
+        }
+
+        write(&dir.path().join("vars.txt"), "author=lowercase\n");
+        let config = SiteConfig { root: dir.path().to_path_buf(), ..SiteConfig::default() };
+        assert!(Site::builder().config(config).build().is_err());
+    }
+
+    #[test]
+    fn gzip_and_brotli_final_pass() {
+        let dir = TempDir::new("site-compress");
+        fixture_project(dir.path());
+        write(&dir.path().join("static").join("style.css"), &"body { margin: 0; }\n".repeat(50));
+        write(&dir.path().join("static").join("about.html"), &"<p>About this site</p>\n".repeat(20));
+        write(&dir.path().join("root_files").join("small.html"), "<p>Below the threshold</p>");
+        write(
+            &dir.path().join("ironite.toml"),
+            "gzip = true\nbrotli = true\ncompress_extensions = [\"html\"]\ncompress_min_size = 200\n",
+        );
+
+        let config = SiteConfig::load(dir.path().join("ironite.toml")).unwrap();
+        let config = SiteConfig { root: dir.path().to_path_buf(), ..config };
+        let stats = Site::builder().config(config).build().unwrap().generate().unwrap();
+
+        let public = dir.path().join("public");
+        assert!(public.join("index.html.gz").exists());
+        assert!(public.join("index.html.br").exists());
+        assert!(!public.join("style.css.br").exists());
+        assert!(!public.join("small.html.gz").exists());
+        assert_eq!(stats.gzip.unwrap().written, stats.brotli.unwrap().written);
+    }
+
+    #[test]
+    fn env_placeholders_resolve_or_list_missing_variables() {
+        let dir = TempDir::new("site-env");
+        fixture_project(dir.path());
+        write(
+            &dir.path().join("static").join("base.html"),
+            "<html><head><title>$TITLE</title></head><body>$CONTENT <script>track(\"$ENV_IRONITE_TEST_ANALYTICS\")</script></body></html>",
+        );
+        std::env::set_var("IRONITE_TEST_ANALYTICS", "UA-1234");
+
+        // Off by default: the text stays as it is
+        let config = SiteConfig { root: dir.path().to_path_buf(), ..SiteConfig::default() };
+        Site::builder().config(config).build().unwrap().generate().unwrap();
+        let index = fs::read_to_string(dir.path().join("public").join("index.html")).unwrap();
+        assert!(index.contains("track(\"$ENV_IRONITE_TEST_ANALYTICS\")"));
+
+        let config = SiteConfig { root: dir.path().to_path_buf(), env_placeholders: true, ..SiteConfig::default() };
+        Site::builder().config(config).build().unwrap().generate().unwrap();
+        let entry = fs::read_to_string(dir.path().join("public").join("entries").join("first").join("index.html")).unwrap();
+        assert!(entry.contains("track(\"UA-1234\")"));
+
+        write(
+            &dir.path().join("entries").join("second").join("content.html"),
+            "<p>$ENV_IRONITE_TEST_UNSET_B $ENV_IRONITE_TEST_UNSET_A $ENV_IRONITE_TEST_UNSET_B</p>",
+        );
+        let config = SiteConfig { root: dir.path().to_path_buf(), env_placeholders: true, ..SiteConfig::default() };
+        match Site::builder().config(config).build() {
+            Err(IroniteError::MissingEnvVars(names)) => {
+                assert_eq!(names, vec!["IRONITE_TEST_UNSET_A".to_string(), "IRONITE_TEST_UNSET_B".to_string()]);
+            }
+            _ => panic!("expected missing environment variables"),
+        }
+    }
+
+    #[test]
+    fn profile_times_every_step() {
+        let dir = TempDir::new("site-profile");
+        fixture_project(dir.path());
+
+        let config = SiteConfig { root: dir.path().to_path_buf(), ..SiteConfig::default() };
+        let stats = Site::builder().config(config).build().unwrap().generate().unwrap();
+        assert!(stats.profile.is_none());
+
+        let config = SiteConfig { root: dir.path().to_path_buf(), profile: true, gzip: true, ..SiteConfig::default() };
+        let stats = Site::builder().config(config).build().unwrap().generate().unwrap();
+        let table = stats.profile.unwrap().to_string();
+        for label in ["read templates", "copy static files", "entry pages", "tag pages", "index pages", "feeds", "redirects", "sitemap", "transforms", "root files", "compression", "total"] {
+            assert!(table.contains(label), "{} missing from\n{}", label, table);
+        }
+        assert!(!table.contains("link check"));
+    }
+
+    #[test]
+    fn build_date_and_version_placeholders() {
+        let dir = TempDir::new("site-build-date");
+        fixture_project(dir.path());
+        write(
+            &dir.path().join("static").join("base.html"),
+            "<html><head><title>$TITLE</title></head><body>$CONTENT <footer>$BUILD_DATE $BUILD_TIMESTAMP $IRONITE_VERSION</footer></body></html>",
+        );
+        write(&dir.path().join("ironite.toml"), "build_date = \"2024-03-15T10:00:00Z\"\n");
+
+        // The pinned date wins over SOURCE_DATE_EPOCH
+        let _env = env_lock();
+        std::env::set_var("SOURCE_DATE_EPOCH", "1700000000");
+        let config = SiteConfig { root: dir.path().to_path_buf(), ..SiteConfig::load(dir.path().join("ironite.toml")).unwrap() };
+        let pinned = Site::builder().config(config).build().and_then(|site| site.generate());
+        let config = SiteConfig { root: dir.path().to_path_buf(), public_dir: PathBuf::from("public-epoch"), ..SiteConfig::default() };
+        let from_epoch = Site::builder().config(config).build().and_then(|site| site.generate());
+        std::env::remove_var("SOURCE_DATE_EPOCH");
+        pinned.unwrap();
+        from_epoch.unwrap();
+
+        let footer = format!("<footer>2024-03-15T10:00:00Z 1710496800 {}</footer>", env!("CARGO_PKG_VERSION"));
+        for page in ["index.html", "404.html", "rust/index.html", "entries/first/index.html"] {
+            let html = fs::read_to_string(dir.path().join("public").join(page)).unwrap();
+            assert!(html.contains(&footer), "{}: {}", page, html);
+        }
+
+        let index = fs::read_to_string(dir.path().join("public-epoch").join("index.html")).unwrap();
+        assert!(index.contains("<footer>2023-11-14T22:13:20Z 1700000000 "), "{}", index);
+    }
+
+    // Every file under dir with its content, by path relative to dir
+    fn read_tree(dir: &Path) -> BTreeMap<String, Vec<u8>> {
+        let mut files = HashSet::new();
+        collect_relative_files(dir, dir, &mut files).unwrap();
+        files.into_iter().map(|file| (file.clone(), fs::read(dir.join(file)).unwrap())).collect()
+    }
+
+    fn assert_same_tree(expected: &Path, actual: &Path) {
+        let (expected, actual) = (read_tree(expected), read_tree(actual));
+        assert_eq!(expected.keys().collect::<Vec<_>>(), actual.keys().collect::<Vec<_>>());
+        for (file, content) in &expected {
+            assert!(actual[file] == *content, "{} differs", file);
+        }
+    }
+
+    // A project using every generated list: entries with and without a date sharing tags, variables,
+    // placeholders, the feed and the sitemap. Entries are created in the given order, each with an
+    // older modification time than the one before
+    fn reproducible_project(root: &Path, order: &[&str]) {
+        write(&root.join("projectname.txt"), "Test site");
+        write(
+            &root.join("static").join("base.html"),
+            "<html><head><title>$TITLE</title></head><body>$NAVCLOUD $BREADCRUMB $CONTENT $TAGS $RELATED $DATE $UPDATED \
+             <footer>$AUTHOR $HEADER $BUILD_DATE</footer></body></html>",
+        );
+        write(&root.join("static").join("about.html"), &format!("{}$NAVCLOUD", "<p>About this site</p>\n".repeat(20)));
+        write(&root.join("vars.txt"), "AUTHOR=Jane Doe\n");
+        write(&root.join("ironite.toml"), "base_url = \"https://example.com\"\ngzip = true\n\n[placeholders]\nHEADER = \"Header\"\n");
+        fs::create_dir_all(root.join("images")).unwrap();
+        for (i, name) in order.iter().enumerate() {
+            let tags = match *name {
+                "alpha" => "rust web",
+                "beta" => "rust",
+                "gamma" => "web notes",
+                _ => "rust notes",
+            };
+            add_entry(root, name, &format!("<p>{} by $AUTHOR</p>", name), tags);
+            let entry = root.join("entries").join(name);
+            if *name == "beta" {
+                write(&entry.join("meta.toml"), "date = 2024-01-10\n");
+            }
+            let modified = SystemTime::now() - Duration::from_secs(60 * (i as u64 + 1));
+            for path in [entry.join("content.html"), entry] {
+                fs::File::open(path).unwrap().set_modified(modified).unwrap();
+            }
+        }
+    }
+
+    #[test]
+    fn reproducible_builds_match_byte_for_byte() {
+        let dir = TempDir::new("site-reproducible");
+        let (first, second) = (dir.path().join("first"), dir.path().join("second"));
+        reproducible_project(&first, &["alpha", "beta", "gamma", "delta"]);
+        reproducible_project(&second, &["gamma", "delta", "beta", "alpha"]);
+        let build = |root: &Path, public_dir: &str| {
+            let config = SiteConfig {
+                root: root.to_path_buf(),
+                public_dir: PathBuf::from(public_dir),
+                ..SiteConfig::load(root.join("ironite.toml")).unwrap()
+            };
+            Site::builder().config(config).build().and_then(|site| site.generate())
+        };
+
+        let _env = env_lock();
+        std::env::set_var("SOURCE_DATE_EPOCH", "1700000000");
+        let results = [build(&first, "public-a"), build(&first, "public-b"), build(&second, "public")];
+        std::env::remove_var("SOURCE_DATE_EPOCH");
+        for result in results {
+            result.unwrap();
+        }
+
+        let files = read_tree(&first.join("public-a"));
+        assert!(files.contains_key("feed.json") && files.contains_key("sitemap.xml") && files.contains_key("index.html.gz"));
+        assert_same_tree(&first.join("public-a"), &first.join("public-b"));
+        assert_same_tree(&first.join("public-a"), &second.join("public"));
+    }
+
+    #[test]
+    fn git_dates_feed_entry_pages_sitemap_and_feed() {
+        if std::process::Command::new("git").arg("--version").output().is_err() {
+            return;
+        }
+        let dir = TempDir::new("site-git-dates");
+        fixture_project(dir.path());
+        write(&dir.path().join("static").join("base.html"), "<html><head><title>$TITLE</title></head><body>$CONTENT $UPDATED</body></html>");
+        let git = |args: &[&str]| {
+            let status = std::process::Command::new("git")
+                .arg("-C")
+                .arg(dir.path())
+                .args(["-c", "user.name=Test", "-c", "user.email=test@example.com"])
+                .args(args)
+                .env("GIT_COMMITTER_DATE", "2024-03-15T10:00:00Z")
+                .env("GIT_AUTHOR_DATE", "2024-03-15T10:00:00Z")
+                .output()
+                .unwrap()
+                .status;
+            assert!(status.success(), "git {:?}", args);
+        };
+        git(&["init", "-q"]);
+        git(&["add", "entries/first"]);
+        git(&["commit", "-q", "-m", "First entry"]);
+
+        let config = SiteConfig { root: dir.path().to_path_buf(), git_dates: true, base_url: "https://example.com".to_string(), ..SiteConfig::default() };
+        let stats = Site::builder().config(config).build().unwrap().generate().unwrap();
+        assert!(stats.warnings.is_empty(), "{:?}", stats.warnings);
+
+        let public = dir.path().join("public");
+        let entry = fs::read_to_string(public.join("entries").join("first").join("index.html")).unwrap();
+        assert!(entry.contains("2024-03-15</body>"), "{}", entry);
+        let sitemap = fs::read_to_string(public.join("sitemap.xml")).unwrap();
+        assert!(sitemap.contains("<lastmod>2024-03-15"), "{}", sitemap);
+        let feed = fs::read_to_string(public.join("feed.json")).unwrap();
+        assert!(feed.contains("\"date_modified\": \"2024-03-15T10:00:00Z\""), "{}", feed);
+    }
+}
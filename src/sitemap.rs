@@ -0,0 +1,236 @@
+use std::io;
+use std::path::Path;
+
+use crate::config::PageKind;
+use crate::date::Date;
+use crate::generator::write_html_file;
+use crate::html::escape_html;
+
+// Sitemap listing the partial sitemaps, written instead of sitemap.xml when a site has more URLs than
+// sitemap_max_urls
+pub const SITEMAP_INDEX_FILE: &str = "sitemap_index.xml";
+
+// URLs per sitemap file unless sitemap_max_urls says otherwise. The protocol allows up to 50,000
+pub const DEFAULT_SITEMAP_MAX_URLS: usize = 1000;
+
+// Values of <changefreq> the protocol allows
+pub const CHANGEFREQS: &[&str] = &["always", "hourly", "daily", "weekly", "monthly", "yearly", "never"];
+
+// How often a page changes and how it ranks among the pages of the site, for crawlers. None leaves
+// the element out
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct SitemapHints {
+    // One of CHANGEFREQS
+    pub changefreq: Option<String>,
+    // From 0.0 to 1.0
+    pub priority: Option<f64>,
+}
+
+impl SitemapHints {
+    // These hints, with the ones other sets
+    fn overridden_by(&self, other: &SitemapHints) -> SitemapHints {
+        SitemapHints { changefreq: other.changefreq.clone().or(self.changefreq.clone()), priority: other.priority.or(self.priority) }
+    }
+}
+
+// Hints for the pages of a path pattern, from [[sitemap.paths]]
+#[derive(Debug, Clone, PartialEq)]
+pub struct SitemapRule {
+    // Page directory relative to the site root, where * stands for any characters: "entries/2019-*".
+    // "/" is the home page
+    pub pattern: String,
+    pub hints: SitemapHints,
+}
+
+// Sitemap hints of ironite.toml: [sitemap] for every page, [sitemap.index], [sitemap.entry] and
+// [sitemap.tag] for a kind of page, and [[sitemap.paths]] for pages matching a pattern, the first
+// matching one winning. Each level overrides the one before it
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct SitemapSettings {
+    pub defaults: SitemapHints,
+    pub index: SitemapHints,
+    pub entry: SitemapHints,
+    pub tag: SitemapHints,
+    pub paths: Vec<SitemapRule>,
+}
+
+impl SitemapSettings {
+    // Hints of the page of kind at path, its directory relative to the site root ("" for the home page)
+    pub fn hints(&self, kind: PageKind, path: &str) -> SitemapHints {
+        let kind_hints = match kind {
+            PageKind::Index => &self.index,
+            PageKind::Entry => &self.entry,
+            PageKind::Tag => &self.tag,
+            PageKind::Other => &SitemapHints::default(),
+        };
+        let hints = self.defaults.overridden_by(kind_hints);
+        let path = path.trim_matches('/');
+        match self.paths.iter().find(|rule| pattern_matches(rule.pattern.trim_matches('/'), path)) {
+            Some(rule) => hints.overridden_by(&rule.hints),
+            None => hints,
+        }
+    }
+}
+
+// Whether text matches pattern, where * matches any run of characters, / included
+pub(crate) fn pattern_matches(pattern: &str, text: &str) -> bool {
+    match pattern.split_once('*') {
+        None => pattern == text,
+        Some((prefix, rest)) => {
+            let Some(text) = text.strip_prefix(prefix) else { return false };
+            (0..=text.len()).filter(|i| text.is_char_boundary(*i)).any(|i| pattern_matches(rest, &text[i..]))
+        }
+    }
+}
+
+// One page listed in sitemap.xml
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct SitemapUrl {
+    // Absolute URL of the page
+    pub loc: String,
+    pub lastmod: Option<Date>,
+    pub hints: SitemapHints,
+}
+
+// sitemap.xml listing the given pages (https://www.sitemaps.org/protocol.html)
+pub fn sitemap_xml(urls: &[SitemapUrl]) -> String {
+    let mut xml = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+    xml.push_str("<urlset xmlns=\"http://www.sitemaps.org/schemas/sitemap/0.9\">\n");
+    for url in urls {
+        xml.push_str("  <url>\n");
+        xml.push_str(&format!("    <loc>{}</loc>\n", escape_html(&url.loc)));
+        if let Some(date) = &url.lastmod {
+            xml.push_str(&format!("    <lastmod>{}</lastmod>\n", date));
+        }
+        if let Some(changefreq) = &url.hints.changefreq {
+            xml.push_str(&format!("    <changefreq>{}</changefreq>\n", changefreq));
+        }
+        if let Some(priority) = url.hints.priority {
+            xml.push_str(&format!("    <priority>{:?}</priority>\n", priority));
+        }
+        xml.push_str("  </url>\n");
+    }
+    xml.push_str("</urlset>\n");
+    xml
+}
+
+// One partial sitemap listed in the sitemap index
+#[derive(Debug, Clone, PartialEq)]
+pub struct SitemapRef {
+    // Absolute URL of the sitemap
+    pub loc: String,
+    // Latest lastmod of its pages
+    pub lastmod: Option<String>,
+}
+
+// sitemap_index.xml listing the given sitemaps
+pub fn sitemap_index_xml(sitemaps: &[SitemapRef]) -> String {
+    let mut xml = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+    xml.push_str("<sitemapindex xmlns=\"http://www.sitemaps.org/schemas/sitemap/0.9\">\n");
+    for sitemap in sitemaps {
+        xml.push_str("  <sitemap>\n");
+        xml.push_str(&format!("    <loc>{}</loc>\n", escape_html(&sitemap.loc)));
+        if let Some(lastmod) = &sitemap.lastmod {
+            xml.push_str(&format!("    <lastmod>{}</lastmod>\n", escape_html(lastmod)));
+        }
+        xml.push_str("  </sitemap>\n");
+    }
+    xml.push_str("</sitemapindex>\n");
+    xml
+}
+
+// Write the sitemap index to output_path
+pub fn generate_sitemap_index(sitemaps: &[SitemapRef], output_path: &Path) -> io::Result<()> {
+    write_html_file(output_path, &sitemap_index_xml(sitemaps))
+}
+
+// Split the URLs of each group (pages, entries, tags, ...) into sitemap files of at most max_urls URLs,
+// named sitemap-<group>.xml, or sitemap-<group>-1.xml, -2 and so on when the group needs several files.
+// Entries are always numbered, since they are what grows. Empty groups get no file
+pub fn split_sitemaps(groups: &[(&str, Vec<SitemapUrl>)], max_urls: usize) -> Vec<(String, Vec<SitemapUrl>)> {
+    let mut files = Vec::new();
+    for (group, urls) in groups {
+        let chunks: Vec<&[SitemapUrl]> = urls.chunks(max_urls.max(1)).collect();
+        for (i, chunk) in chunks.iter().enumerate() {
+            let name = if chunks.len() == 1 && *group != "entries" {
+                format!("sitemap-{}.xml", group)
+            } else {
+                format!("sitemap-{}-{}.xml", group, i + 1)
+            };
+            files.push((name, chunk.to_vec()));
+        }
+    }
+    files
+}
+
+// robots.txt allowing every crawler, pointing at the sitemap when there is one
+pub fn robots_txt(sitemap_url: Option<&str>) -> String {
+    let mut robots = String::from("User-agent: *\nAllow: /\n");
+    if let Some(url) = sitemap_url {
+        robots.push_str(&format!("\nSitemap: {}\n", url));
+    }
+    robots
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sitemap_lists_escaped_urls() {
+        let urls = vec![
+            SitemapUrl { loc: "https://example.com/".to_string(), ..SitemapUrl::default() },
+            SitemapUrl { loc: "https://example.com/entries/a&b/".to_string(), lastmod: Date::parse("2024-03-15"), ..SitemapUrl::default() },
+        ];
+        let xml = sitemap_xml(&urls);
+        assert!(xml.contains("<loc>https://example.com/entries/a&amp;b/</loc>\n    <lastmod>2024-03-15</lastmod>"));
+        assert_eq!(xml.matches("<url>").count(), 2);
+    }
+
+    #[test]
+    fn hints_by_kind_and_path() {
+        let hints = |changefreq: Option<&str>, priority: Option<f64>| SitemapHints { changefreq: changefreq.map(str::to_string), priority };
+        let settings = SitemapSettings {
+            defaults: hints(Some("monthly"), Some(0.5)),
+            index: hints(Some("daily"), None),
+            entry: hints(None, Some(0.8)),
+            paths: vec![
+                SitemapRule { pattern: "/".to_string(), hints: hints(None, Some(1.0)) },
+                SitemapRule { pattern: "entries/2019-*".to_string(), hints: hints(Some("never"), Some(0.2)) },
+            ],
+            ..SitemapSettings::default()
+        };
+        assert_eq!(settings.hints(PageKind::Index, ""), hints(Some("daily"), Some(1.0)));
+        assert_eq!(settings.hints(PageKind::Index, "entries"), hints(Some("daily"), Some(0.5)));
+        assert_eq!(settings.hints(PageKind::Entry, "entries/2024-news"), hints(Some("monthly"), Some(0.8)));
+        assert_eq!(settings.hints(PageKind::Entry, "entries/2019-old/"), hints(Some("never"), Some(0.2)));
+        assert_eq!(settings.hints(PageKind::Tag, "rust"), hints(Some("monthly"), Some(0.5)));
+
+        let xml = sitemap_xml(&[SitemapUrl { loc: "https://example.com/".to_string(), hints: hints(Some("daily"), Some(1.0)), ..SitemapUrl::default() }]);
+        assert!(xml.contains("<loc>https://example.com/</loc>\n    <changefreq>daily</changefreq>\n    <priority>1.0</priority>\n"), "{}", xml);
+    }
+
+    #[test]
+    fn large_sitemaps_are_split_and_indexed() {
+        let url = |path: &str| SitemapUrl { loc: format!("https://example.com/{}", path), ..SitemapUrl::default() };
+        let groups = vec![
+            ("pages", vec![url(""), url("entries/")]),
+            ("entries", (1..=5).map(|i| url(&format!("entries/{}/", i))).collect()),
+            ("tags", vec![url("rust/")]),
+            ("series", Vec::new()),
+        ];
+        let files = split_sitemaps(&groups, 2);
+        let names: Vec<&str> = files.iter().map(|(name, _)| name.as_str()).collect();
+        assert_eq!(names, ["sitemap-pages.xml", "sitemap-entries-1.xml", "sitemap-entries-2.xml", "sitemap-entries-3.xml", "sitemap-tags.xml"]);
+        assert_eq!(files[3].1, vec![url("entries/5/")]);
+
+        let refs = vec![
+            SitemapRef { loc: "https://example.com/sitemap-pages.xml".to_string(), lastmod: None },
+            SitemapRef { loc: "https://example.com/sitemap-entries-1.xml".to_string(), lastmod: Some("2024-03-15".to_string()) },
+        ];
+        let xml = sitemap_index_xml(&refs);
+        assert!(xml.contains("<sitemapindex xmlns=\"http://www.sitemaps.org/schemas/sitemap/0.9\">"));
+        assert!(xml.contains("<sitemap>\n    <loc>https://example.com/sitemap-entries-1.xml</loc>\n    <lastmod>2024-03-15</lastmod>\n  </sitemap>"));
+        assert_eq!(xml.matches("<sitemap>").count(), 2);
+    }
+}
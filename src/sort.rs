@@ -0,0 +1,119 @@
+use std::cmp::Ordering;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use crate::meta::EntryMeta;
+
+// Entry names in the order of the entries index, one per line, with index_sort = "manual"
+pub const ORDER_FILE: &str = "order.txt";
+
+// How the entries index and the feeds order entries
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IndexSort {
+    // By title
+    Alphabetical,
+    // Dated entries first, newest first, then undated ones by the modification time of their directory
+    Newest,
+    // Dated entries first, oldest first, then undated ones the same way
+    Oldest,
+    // As listed in order.txt, entries missing from it following by title
+    Manual,
+}
+
+impl IndexSort {
+    // Order of the feeds: the same, except that feeds are never alphabetical and list the newest first then
+    pub fn for_feeds(self) -> IndexSort {
+        match self {
+            IndexSort::Alphabetical => IndexSort::Newest,
+            other => other,
+        }
+    }
+}
+
+// Read order.txt: entry names, one per line. Blank lines and lines starting with # are skipped, and a
+// missing file lists no entry
+pub fn read_order(path: &Path) -> io::Result<Vec<String>> {
+    if !path.exists() {
+        return Ok(Vec::new());
+    }
+    Ok(fs::read_to_string(path)?
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(str::to_string)
+        .collect())
+}
+
+// Compare two entries for sort. order is the content of order.txt, only used by Manual. Ties are broken
+// by name, so every listing using this gets the same order
+pub fn compare_entries(a: &EntryMeta, b: &EntryMeta, sort: IndexSort, order: &[String]) -> Ordering {
+    // Dated entries first, then by date, or by modification time for undated ones
+    let when = |meta: &EntryMeta| (meta.date.is_none(), meta.date.or(meta.modified).unwrap_or_default());
+    let by_name = a.slug.cmp(&b.slug);
+    match sort {
+        IndexSort::Alphabetical => by_name,
+        IndexSort::Newest => {
+            let (a_undated, a_date) = when(a);
+            let (b_undated, b_date) = when(b);
+            a_undated.cmp(&b_undated).then(b_date.cmp(&a_date)).then(by_name)
+        }
+        IndexSort::Oldest => when(a).cmp(&when(b)).then(by_name),
+        IndexSort::Manual => {
+            let position = |meta: &EntryMeta| order.iter().position(|name| *name == meta.slug).unwrap_or(order.len());
+            position(a).cmp(&position(b)).then(by_name)
+        }
+    }
+}
+
+// Entries in the order of the entries index: pinned entries first, then by weight, then as sort says
+pub fn index_order(entry_paths: &[PathBuf], sort: IndexSort, order: &[String]) -> io::Result<Vec<PathBuf>> {
+    let mut entries = Vec::new();
+    for entry_path in entry_paths {
+        entries.push((EntryMeta::load(entry_path)?, entry_path.clone()));
+    }
+    entries.sort_by(|(a, _), (b, _)| (!a.pinned, a.weight).cmp(&(!b.pinned, b.weight)).then_with(|| compare_entries(a, b, sort, order)));
+    Ok(entries.into_iter().map(|(_, path)| path).collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::date::Date;
+    use crate::test_util::{write, TempDir};
+
+    fn entry(slug: &str, date: Option<&str>, modified: &str) -> EntryMeta {
+        EntryMeta { slug: slug.to_string(), date: date.and_then(Date::parse), modified: Date::parse(modified), ..EntryMeta::default() }
+    }
+
+    fn sorted(entries: &[EntryMeta], sort: IndexSort, order: &[String]) -> Vec<String> {
+        let mut entries = entries.to_vec();
+        entries.sort_by(|a, b| compare_entries(a, b, sort, order));
+        entries.into_iter().map(|meta| meta.slug).collect()
+    }
+
+    #[test]
+    fn every_strategy_orders_the_same_entries() {
+        let entries = vec![
+            entry("beta", Some("2024-01-01"), "2024-05-01"),
+            entry("alpha", None, "2024-03-01"),
+            entry("gamma", Some("2023-06-01"), "2024-05-01"),
+            entry("delta", None, "2024-04-01"),
+        ];
+        assert_eq!(sorted(&entries, IndexSort::Alphabetical, &[]), ["alpha", "beta", "delta", "gamma"]);
+        assert_eq!(sorted(&entries, IndexSort::Newest, &[]), ["beta", "gamma", "delta", "alpha"]);
+        assert_eq!(sorted(&entries, IndexSort::Oldest, &[]), ["gamma", "beta", "alpha", "delta"]);
+        let order = vec!["gamma".to_string(), "alpha".to_string()];
+        assert_eq!(sorted(&entries, IndexSort::Manual, &order), ["gamma", "alpha", "beta", "delta"]);
+        assert_eq!(IndexSort::Alphabetical.for_feeds(), IndexSort::Newest);
+    }
+
+    #[test]
+    fn order_txt_skips_comments_and_blank_lines() {
+        let dir = TempDir::new("order-txt");
+        let path = dir.path().join(ORDER_FILE);
+        assert!(read_order(&path).unwrap().is_empty());
+        write(&path, "# start here\nintroduction\n\n  setup  \n");
+        assert_eq!(read_order(&path).unwrap(), ["introduction", "setup"]);
+    }
+}
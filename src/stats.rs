@@ -0,0 +1,75 @@
+use std::fmt;
+
+use crate::compress::CompressionStats;
+use crate::fingerprint::AssetMap;
+use crate::links::BrokenLink;
+use crate::profile::Profiler;
+use crate::tag_alias::TagRemap;
+
+// Report of a site build, printed once generation has finished
+#[derive(Debug, Clone, Default)]
+pub struct SiteStats {
+    // Problems that did not stop the build
+    pub warnings: Vec<String>,
+    // Entry pages written
+    pub entry_count: usize,
+    // Tags with a page, the untagged page left out
+    pub tag_count: usize,
+    // Fingerprinted assets, empty unless fingerprint_assets is enabled
+    pub assets: AssetMap,
+    // Precompression results, None unless gzip is enabled
+    pub gzip: Option<CompressionStats>,
+    // Same for brotli
+    pub brotli: Option<CompressionStats>,
+    // Result of the link check, empty unless check_links is enabled
+    pub broken_links: Vec<BrokenLink>,
+    // Entries moved by each alias of tag-aliases.txt
+    pub tag_remaps: Vec<TagRemap>,
+    // Entries dropped from the tags of excluded_tags, counted once per tag
+    pub excluded_tag_occurrences: usize,
+    // Names of the entries without any tag, listed on the untagged page
+    pub untagged_entries: Vec<String>,
+    // Duration of each build step, None unless profile is enabled
+    pub profile: Option<Profiler>,
+}
+
+impl fmt::Display for SiteStats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if !self.assets.is_empty() {
+            writeln!(f, "Fingerprinted assets:")?;
+            for (original, fingerprinted) in &self.assets {
+                writeln!(f, "  {} => {}", original, fingerprinted)?;
+            }
+        }
+        if let Some(gzip) = &self.gzip {
+            writeln!(
+                f,
+                "Gzip: {} files compressed, {} up to date, {} bytes saved",
+                gzip.written, gzip.up_to_date, gzip.bytes_saved
+            )?;
+        }
+        if let Some(brotli) = &self.brotli {
+            writeln!(
+                f,
+                "Brotli: {} files compressed, {} up to date, {} bytes saved",
+                brotli.written, brotli.up_to_date, brotli.bytes_saved
+            )?;
+        }
+        for remap in &self.tag_remaps {
+            writeln!(f, "Tag alias {}", remap)?;
+        }
+        if self.excluded_tag_occurrences > 0 {
+            writeln!(f, "Excluded tags: {} tag occurrences suppressed", self.excluded_tag_occurrences)?;
+        }
+        for name in &self.untagged_entries {
+            writeln!(f, "Untagged entry {}", name)?;
+        }
+        for link in &self.broken_links {
+            writeln!(f, "Warning: {}", link)?;
+        }
+        for warning in &self.warnings {
+            writeln!(f, "Warning: {}", warning)?;
+        }
+        Ok(())
+    }
+}
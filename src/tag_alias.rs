@@ -0,0 +1,144 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::error::IroniteError;
+use crate::toml::invalid_data;
+
+// Retired tags and the tag replacing each, one "old = new" per line, in the project root
+pub const TAG_ALIASES_FILE: &str = "tag-aliases.txt";
+
+// Maps each retired tag to the tag that replaces it, chains already followed to their end
+pub type TagAliases = HashMap<String, String>;
+
+// How many entries an alias moved to its tag, for the build report
+#[derive(Debug, Clone, PartialEq)]
+pub struct TagRemap {
+    pub alias: String,
+    pub tag: String,
+    pub entries: usize,
+}
+
+impl fmt::Display for TagRemap {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} => {}: {} entries", self.alias, self.tag, self.entries)
+    }
+}
+
+// Read tag-aliases.txt. Blank lines and lines starting with # are skipped. A missing file has no
+// aliases. Chains (a = b, b = c) resolve to their last tag; a chain coming back on itself is a
+// TagAliasCycle
+pub fn read_tag_aliases(path: &Path) -> Result<TagAliases, IroniteError> {
+    resolve_tag_aliases(&read_direct_aliases(path)?)
+}
+
+// Aliases of tag-aliases.txt together with those of [tag_aliases] in ironite.toml, which win for a tag
+// listed in both. Chains may go from one to the other
+pub fn site_tag_aliases(path: &Path, configured: &HashMap<String, String>) -> Result<TagAliases, IroniteError> {
+    let mut direct = read_direct_aliases(path)?;
+    direct.extend(configured.iter().map(|(alias, tag)| (alias.clone(), tag.clone())));
+    resolve_tag_aliases(&direct)
+}
+
+// The old = new lines of tag-aliases.txt, chains not followed
+fn read_direct_aliases(path: &Path) -> Result<HashMap<String, String>, IroniteError> {
+    let mut direct = HashMap::new();
+    if !path.exists() {
+        return Ok(direct);
+    }
+    for (number, line) in fs::read_to_string(path)?.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let (alias, tag) = line
+            .split_once('=')
+            .map(|(alias, tag)| (alias.trim(), tag.trim()))
+            .filter(|(alias, tag)| !alias.is_empty() && !tag.is_empty())
+            .ok_or_else(|| invalid_data(format!("{}: line {}: expected old = new", path.display(), number + 1)))?;
+        direct.insert(alias.to_string(), tag.to_string());
+    }
+    Ok(direct)
+}
+
+// Follow every alias to the tag at the end of its chain
+pub fn resolve_tag_aliases(direct: &HashMap<String, String>) -> Result<TagAliases, IroniteError> {
+    let mut aliases = TagAliases::new();
+    for alias in direct.keys() {
+        let mut chain = vec![alias.clone()];
+        let mut tag = alias;
+        while let Some(next) = direct.get(tag) {
+            if chain.contains(next) {
+                chain.push(next.clone());
+                return Err(IroniteError::TagAliasCycle(chain));
+            }
+            chain.push(next.clone());
+            tag = next;
+        }
+        aliases.insert(alias.clone(), tag.clone());
+    }
+    Ok(aliases)
+}
+
+// Move the entries of aliased tags to the tags replacing them. Returns the new map, and for each alias
+// used by some entry the number of entries it moved, sorted by alias
+pub fn apply_tag_aliases(tags_map: &HashMap<String, Vec<PathBuf>>, aliases: &TagAliases) -> (HashMap<String, Vec<PathBuf>>, Vec<TagRemap>) {
+    let mut remapped: HashMap<String, Vec<PathBuf>> = HashMap::new();
+    let mut remaps = Vec::new();
+    for (tag, paths) in tags_map {
+        match aliases.get(tag) {
+            Some(target) => {
+                remapped.entry(target.clone()).or_default().extend(paths.iter().cloned());
+                remaps.push(TagRemap { alias: tag.clone(), tag: target.clone(), entries: paths.len() });
+            }
+            None => remapped.entry(tag.clone()).or_default().extend(paths.iter().cloned()),
+        }
+    }
+    for paths in remapped.values_mut() {
+        paths.sort();
+        paths.dedup();
+    }
+    remaps.sort_by(|a, b| a.alias.cmp(&b.alias));
+    (remapped, remaps)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::{write, TempDir};
+
+    #[test]
+    fn chained_aliases_resolve_and_cycles_fail() {
+        let dir = TempDir::new("tag-aliases");
+        let path = dir.path().join(TAG_ALIASES_FILE);
+        write(&path, "# retired tags\ngolang = go\n\nlang-go = golang\n");
+        let aliases = read_tag_aliases(&path).unwrap();
+        assert_eq!((aliases["golang"].as_str(), aliases["lang-go"].as_str()), ("go", "go"));
+        assert!(read_tag_aliases(&dir.path().join("missing.txt")).unwrap().is_empty());
+
+        write(&path, "a = b\nb = c\nc = a\n");
+        match read_tag_aliases(&path) {
+            Err(error @ IroniteError::TagAliasCycle(_)) => assert!(error.to_string().starts_with("tag alias cycle: "), "{}", error),
+            other => panic!("expected a cycle, got {:?}", other),
+        }
+
+        write(&path, "golang\n");
+        assert!(read_tag_aliases(&path).unwrap_err().to_string().ends_with("line 1: expected old = new"));
+    }
+
+    #[test]
+    fn aliased_tags_move_their_entries() {
+        let mut tags_map = HashMap::new();
+        tags_map.insert("golang".to_string(), vec![PathBuf::from("a"), PathBuf::from("b")]);
+        tags_map.insert("go".to_string(), vec![PathBuf::from("b"), PathBuf::from("c")]);
+        tags_map.insert("rust".to_string(), vec![PathBuf::from("d")]);
+        let aliases = TagAliases::from([("golang".to_string(), "go".to_string()), ("unused".to_string(), "go".to_string())]);
+
+        let (remapped, remaps) = apply_tag_aliases(&tags_map, &aliases);
+        assert!(!remapped.contains_key("golang"));
+        assert_eq!(remapped["go"], vec![PathBuf::from("a"), PathBuf::from("b"), PathBuf::from("c")]);
+        assert_eq!(remapped["rust"], vec![PathBuf::from("d")]);
+        assert_eq!(remaps.iter().map(TagRemap::to_string).collect::<Vec<_>>(), vec!["golang => go: 2 entries"]);
+    }
+}
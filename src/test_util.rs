@@ -0,0 +1,64 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Mutex, MutexGuard};
+
+static COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+// Held by tests that set SOURCE_DATE_EPOCH, and by tests that depend on file modification times,
+// which it clamps. Tests run in parallel threads of one process and share its environment
+pub fn env_lock() -> MutexGuard<'static, ()> {
+    static LOCK: Mutex<()> = Mutex::new(());
+    LOCK.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+// Temporary directory removed when dropped
+pub struct TempDir {
+    path: PathBuf,
+}
+
+impl TempDir {
+    pub fn new(name: &str) -> TempDir {
+        let unique = COUNTER.fetch_add(1, Ordering::SeqCst);
+        let path = std::env::temp_dir().join(format!("ironite-{}-{}-{}", name, std::process::id(), unique));
+        let _ = fs::remove_dir_all(&path);
+        fs::create_dir_all(&path).unwrap();
+        TempDir { path }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+}
+
+impl Drop for TempDir {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.path);
+    }
+}
+
+// Write a file, creating its parent directories
+pub fn write(path: &Path, content: &str) {
+    fs::create_dir_all(path.parent().unwrap()).unwrap();
+    fs::write(path, content).unwrap();
+}
+
+// Add an entry with its content and tags
+pub fn add_entry(root: &Path, name: &str, content: &str, tags: &str) {
+    let entry = root.join("entries").join(name);
+    write(&entry.join("content.html"), content);
+    write(&entry.join("tags.txt"), tags);
+}
+
+// Minimal project with two entries: "first" (tags: rust, web) and "second" (tags: rust)
+pub fn fixture_project(root: &Path) {
+    write(&root.join("projectname.txt"), "Test site");
+    write(
+        &root.join("static").join("base.html"),
+        "<html><head><title>$TITLE</title></head><body>$NAVCLOUD $CONTENT</body></html>",
+    );
+    write(&root.join("static").join("about.html"), "<p>About</p> $NAVCLOUD");
+    fs::create_dir_all(root.join("images")).unwrap();
+    add_entry(root, "first", "<p>First entry</p>", "rust web");
+    add_entry(root, "second", "<p>Second entry</p>", "rust");
+}
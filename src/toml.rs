@@ -0,0 +1,597 @@
+use std::collections::BTreeMap;
+use std::fmt;
+use std::io;
+
+// Minimal TOML reader for ironite.toml and meta.toml.
+// Supports comments, [tables], [[arrays of tables]], dotted keys, strings, integers,
+// floats, booleans, bare dates (kept as text), arrays and inline tables.
+
+pub type Table = BTreeMap<String, Value>;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    String(String),
+    Integer(i64),
+    Float(f64),
+    Boolean(bool),
+    // Dates and times are not interpreted here, only kept as written
+    Datetime(String),
+    Array(Vec<Value>),
+    Table(Table),
+}
+
+impl Value {
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            Value::String(s) | Value::Datetime(s) => Some(s),
+            _ => None,
+        }
+    }
+
+    pub fn as_bool(&self) -> Option<bool> {
+        match self {
+            Value::Boolean(b) => Some(*b),
+            _ => None,
+        }
+    }
+
+    pub fn as_integer(&self) -> Option<i64> {
+        match self {
+            Value::Integer(i) => Some(*i),
+            _ => None,
+        }
+    }
+
+    pub fn as_array(&self) -> Option<&Vec<Value>> {
+        match self {
+            Value::Array(a) => Some(a),
+            _ => None,
+        }
+    }
+
+    pub fn as_table(&self) -> Option<&Table> {
+        match self {
+            Value::Table(t) => Some(t),
+            _ => None,
+        }
+    }
+
+    // Name of the value type, for error messages
+    pub fn type_name(&self) -> &'static str {
+        match self {
+            Value::String(_) => "string",
+            Value::Integer(_) => "integer",
+            Value::Float(_) => "float",
+            Value::Boolean(_) => "boolean",
+            Value::Datetime(_) => "datetime",
+            Value::Array(_) => "array",
+            Value::Table(_) => "table",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ParseError {
+    pub line: usize,
+    pub message: String,
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "line {}: {}", self.line, self.message)
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+// Helpers for reading typed settings out of a parsed file
+pub(crate) fn invalid_data(message: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+pub(crate) fn expect_str<'a>(key: &str, value: &'a Value) -> io::Result<&'a str> {
+    value
+        .as_str()
+        .ok_or_else(|| invalid_data(format!("{}: expected string, found {}", key, value.type_name())))
+}
+
+pub(crate) fn expect_bool(key: &str, value: &Value) -> io::Result<bool> {
+    value
+        .as_bool()
+        .ok_or_else(|| invalid_data(format!("{}: expected boolean, found {}", key, value.type_name())))
+}
+
+pub(crate) fn expect_usize(key: &str, value: &Value) -> io::Result<usize> {
+    match value {
+        Value::Integer(i) if *i >= 0 => Ok(*i as usize),
+        _ => Err(invalid_data(format!("{}: expected a non-negative integer, found {}", key, value.type_name()))),
+    }
+}
+
+pub(crate) fn expect_i32(key: &str, value: &Value) -> io::Result<i32> {
+    value
+        .as_integer()
+        .and_then(|i| i32::try_from(i).ok())
+        .ok_or_else(|| invalid_data(format!("{}: expected an integer, found {}", key, value.type_name())))
+}
+
+// Parse a TOML document into its root table
+pub fn parse(input: &str) -> Result<Table, ParseError> {
+    let mut parser = Parser { chars: input.chars().collect(), pos: 0, line: 1 };
+    parser.document()
+}
+
+struct Parser {
+    chars: Vec<char>,
+    pos: usize,
+    line: usize,
+}
+
+impl Parser {
+    fn error<T>(&self, message: impl Into<String>) -> Result<T, ParseError> {
+        Err(ParseError { line: self.line, message: message.into() })
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.pos).copied()
+    }
+
+    fn bump(&mut self) -> Option<char> {
+        let c = self.chars.get(self.pos).copied();
+        if let Some(c) = c {
+            self.pos += 1;
+            if c == '\n' {
+                self.line += 1;
+            }
+        }
+        c
+    }
+
+    fn eat(&mut self, expected: char) -> bool {
+        if self.peek() == Some(expected) {
+            self.bump();
+            true
+        } else {
+            false
+        }
+    }
+
+    // Skip spaces and tabs on the current line
+    fn skip_blank(&mut self) {
+        while matches!(self.peek(), Some(' ') | Some('\t')) {
+            self.bump();
+        }
+    }
+
+    // Skip whitespace, newlines and comments (used inside arrays and between statements)
+    fn skip_trivia(&mut self) {
+        loop {
+            match self.peek() {
+                Some(' ') | Some('\t') | Some('\r') | Some('\n') => {
+                    self.bump();
+                }
+                Some('#') => self.skip_comment(),
+                _ => break,
+            }
+        }
+    }
+
+    fn skip_comment(&mut self) {
+        while let Some(c) = self.peek() {
+            if c == '\n' {
+                break;
+            }
+            self.bump();
+        }
+    }
+
+    // After a statement only a comment and the end of the line may follow
+    fn end_of_line(&mut self) -> Result<(), ParseError> {
+        self.skip_blank();
+        if self.peek() == Some('#') {
+            self.skip_comment();
+        }
+        self.eat('\r');
+        match self.peek() {
+            None => Ok(()),
+            Some('\n') => {
+                self.bump();
+                Ok(())
+            }
+            Some(c) => self.error(format!("unexpected character '{}'", c)),
+        }
+    }
+
+    fn document(&mut self) -> Result<Table, ParseError> {
+        let mut root = Table::new();
+        // Path of the table currently receiving key/value pairs
+        let mut current: Vec<String> = Vec::new();
+
+        loop {
+            self.skip_trivia();
+            match self.peek() {
+                None => break,
+                Some('[') => {
+                    self.bump();
+                    let array_of_tables = self.eat('[');
+                    self.skip_blank();
+                    let path = self.key_path()?;
+                    self.skip_blank();
+                    if !self.eat(']') || (array_of_tables && !self.eat(']')) {
+                        return self.error("unterminated table header");
+                    }
+                    // Checked before the end of line, so that errors report the line of the header
+                    if array_of_tables {
+                        self.push_array_table(&mut root, &path)?;
+                    } else {
+                        self.table_at(&mut root, &path)?;
+                    }
+                    self.end_of_line()?;
+                    current = path;
+                }
+                Some(_) => {
+                    let path = self.key_path()?;
+                    self.skip_blank();
+                    if !self.eat('=') {
+                        return self.error("expected '=' after key");
+                    }
+                    self.skip_blank();
+                    let value = self.value()?;
+                    let table = self.table_at(&mut root, &current)?;
+                    self.insert(table, &path, value)?;
+                    self.end_of_line()?;
+                }
+            }
+        }
+
+        Ok(root)
+    }
+
+    // Resolve a table path, creating intermediate tables. For arrays of tables the last element is used
+    fn table_at<'a>(&self, root: &'a mut Table, path: &[String]) -> Result<&'a mut Table, ParseError> {
+        let mut table = root;
+        for key in path {
+            let entry = table.entry(key.clone()).or_insert_with(|| Value::Table(Table::new()));
+            table = match entry {
+                Value::Table(t) => t,
+                Value::Array(items) => match items.last_mut() {
+                    Some(Value::Table(t)) => t,
+                    _ => return self.error(format!("key '{}' is not a table", key)),
+                },
+                _ => return self.error(format!("key '{}' is not a table", key)),
+            };
+        }
+        Ok(table)
+    }
+
+    fn push_array_table(&self, root: &mut Table, path: &[String]) -> Result<(), ParseError> {
+        let (last, parents) = match path.split_last() {
+            Some(split) => split,
+            None => return self.error("empty table header"),
+        };
+        let parent = self.table_at(root, parents)?;
+        let entry = parent.entry(last.clone()).or_insert_with(|| Value::Array(Vec::new()));
+        match entry {
+            Value::Array(items) => {
+                items.push(Value::Table(Table::new()));
+                Ok(())
+            }
+            _ => self.error(format!("key '{}' is not an array of tables", last)),
+        }
+    }
+
+    fn insert(&self, table: &mut Table, path: &[String], value: Value) -> Result<(), ParseError> {
+        let (last, parents) = match path.split_last() {
+            Some(split) => split,
+            None => return self.error("empty key"),
+        };
+        let table = self.table_at(table, parents)?;
+        if table.contains_key(last) {
+            return self.error(format!("duplicate key '{}'", last));
+        }
+        table.insert(last.clone(), value);
+        Ok(())
+    }
+
+    fn key_path(&mut self) -> Result<Vec<String>, ParseError> {
+        let mut path = vec![self.key()?];
+        loop {
+            self.skip_blank();
+            if !self.eat('.') {
+                break;
+            }
+            self.skip_blank();
+            path.push(self.key()?);
+        }
+        Ok(path)
+    }
+
+    fn key(&mut self) -> Result<String, ParseError> {
+        match self.peek() {
+            Some('"') => {
+                self.bump();
+                self.basic_string()
+            }
+            Some('\'') => {
+                self.bump();
+                self.literal_string()
+            }
+            _ => {
+                let mut key = String::new();
+                while let Some(c) = self.peek() {
+                    if c.is_ascii_alphanumeric() || c == '_' || c == '-' {
+                        key.push(c);
+                        self.bump();
+                    } else {
+                        break;
+                    }
+                }
+                if key.is_empty() {
+                    return self.error("expected a key");
+                }
+                Ok(key)
+            }
+        }
+    }
+
+    fn value(&mut self) -> Result<Value, ParseError> {
+        match self.peek() {
+            Some('"') => {
+                self.bump();
+                Ok(Value::String(self.basic_string()?))
+            }
+            Some('\'') => {
+                self.bump();
+                Ok(Value::String(self.literal_string()?))
+            }
+            Some('[') => {
+                self.bump();
+                self.array()
+            }
+            Some('{') => {
+                self.bump();
+                self.inline_table()
+            }
+            Some(_) => self.bare_value(),
+            None => self.error("expected a value"),
+        }
+    }
+
+    fn basic_string(&mut self) -> Result<String, ParseError> {
+        let mut s = String::new();
+        loop {
+            // Peeked first, so that the error is on the line of the string
+            let Some(c) = self.peek().filter(|&c| c != '\n') else {
+                return self.error("unterminated string");
+            };
+            self.bump();
+            match c {
+                '"' => return Ok(s),
+                '\\' => match self.bump() {
+                    Some('n') => s.push('\n'),
+                    Some('t') => s.push('\t'),
+                    Some('r') => s.push('\r'),
+                    Some('"') => s.push('"'),
+                    Some('\\') => s.push('\\'),
+                    Some('u') => s.push(self.unicode_escape(4)?),
+                    Some('U') => s.push(self.unicode_escape(8)?),
+                    _ => return self.error("invalid escape sequence"),
+                },
+                c => s.push(c),
+            }
+        }
+    }
+
+    fn unicode_escape(&mut self, digits: usize) -> Result<char, ParseError> {
+        let mut code = 0u32;
+        for _ in 0..digits {
+            let digit = self.bump().and_then(|c| c.to_digit(16));
+            match digit {
+                Some(d) => code = code * 16 + d,
+                None => return self.error("invalid unicode escape"),
+            }
+        }
+        match char::from_u32(code) {
+            Some(c) => Ok(c),
+            None => self.error("invalid unicode escape"),
+        }
+    }
+
+    fn literal_string(&mut self) -> Result<String, ParseError> {
+        let mut s = String::new();
+        loop {
+            let Some(c) = self.peek().filter(|&c| c != '\n') else {
+                return self.error("unterminated string");
+            };
+            self.bump();
+            match c {
+                '\'' => return Ok(s),
+                c => s.push(c),
+            }
+        }
+    }
+
+    fn array(&mut self) -> Result<Value, ParseError> {
+        let mut items = Vec::new();
+        loop {
+            self.skip_trivia();
+            if self.eat(']') {
+                return Ok(Value::Array(items));
+            }
+            items.push(self.value()?);
+            self.skip_trivia();
+            if self.eat(',') {
+                continue;
+            }
+            self.skip_trivia();
+            if self.eat(']') {
+                return Ok(Value::Array(items));
+            }
+            return self.error("expected ',' or ']' in array");
+        }
+    }
+
+    fn inline_table(&mut self) -> Result<Value, ParseError> {
+        let mut table = Table::new();
+        self.skip_blank();
+        if self.eat('}') {
+            return Ok(Value::Table(table));
+        }
+        loop {
+            self.skip_blank();
+            let path = self.key_path()?;
+            self.skip_blank();
+            if !self.eat('=') {
+                return self.error("expected '=' after key");
+            }
+            self.skip_blank();
+            let value = self.value()?;
+            self.insert(&mut table, &path, value)?;
+            self.skip_blank();
+            if self.eat(',') {
+                continue;
+            }
+            if self.eat('}') {
+                return Ok(Value::Table(table));
+            }
+            return self.error("expected ',' or '}' in inline table");
+        }
+    }
+
+    // Booleans, numbers and dates
+    fn bare_value(&mut self) -> Result<Value, ParseError> {
+        let mut token = String::new();
+        while let Some(c) = self.peek() {
+            // A single space is allowed between a date and a time
+            let date_time_gap = c == ' '
+                && token.len() == 10
+                && token.as_bytes()[4] == b'-'
+                && self.chars.get(self.pos + 1).is_some_and(|c| c.is_ascii_digit());
+            if c.is_ascii_alphanumeric() || "+-_.:".contains(c) || date_time_gap {
+                token.push(c);
+                self.bump();
+            } else {
+                break;
+            }
+        }
+
+        match token.as_str() {
+            "" => return self.error("expected a value"),
+            "true" => return Ok(Value::Boolean(true)),
+            "false" => return Ok(Value::Boolean(false)),
+            _ => {}
+        }
+
+        let bytes = token.as_bytes();
+        let is_date = (bytes.len() >= 10 && bytes[4] == b'-' && bytes[7] == b'-')
+            || (bytes.len() >= 8 && bytes[2] == b':');
+        if is_date {
+            return Ok(Value::Datetime(token));
+        }
+
+        let digits = token.replace('_', "");
+        if let Ok(i) = digits.parse::<i64>() {
+            return Ok(Value::Integer(i));
+        }
+        if let Ok(f) = digits.parse::<f64>() {
+            return Ok(Value::Float(f));
+        }
+        self.error(format!("invalid value '{}'", token))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn table(entries: &[(&str, Value)]) -> Value {
+        Value::Table(entries.iter().map(|(key, value)| (key.to_string(), value.clone())).collect())
+    }
+
+    fn string(text: &str) -> Value {
+        Value::String(text.to_string())
+    }
+
+    #[test]
+    fn documents_parse_into_tables() {
+        let document = parse(
+            "# Site settings\n\
+             title = \"Ironite\" # trailing comment\n\
+             site.owner.name = 'Jane'\n\
+             tags = [\"rust\", 'web',\n  \"ssg\",]\n\
+             point = { x = 1, y = -2 }\n\
+             \n\
+             [feed]\n\
+             limit = 1_000\n\
+             ratio = 0.5\n\
+             exponent = 1e3\n\
+             enabled = true\n\
+             \n\
+             [[authors]]\n\
+             name = \"Ada\"\n\
+             [[authors]]\n\
+             name = \"Grace\"\n\
+             [authors.links]\n\
+             site = \"https://example.com\"\n",
+        )
+        .unwrap();
+        assert_eq!(document["title"], string("Ironite"));
+        assert_eq!(document["site"], table(&[("owner", table(&[("name", string("Jane"))]))]));
+        assert_eq!(document["tags"], Value::Array(vec![string("rust"), string("web"), string("ssg")]));
+        assert_eq!(document["point"], table(&[("x", Value::Integer(1)), ("y", Value::Integer(-2))]));
+        assert_eq!(
+            document["feed"],
+            table(&[("limit", Value::Integer(1000)), ("ratio", Value::Float(0.5)), ("exponent", Value::Float(1000.0)), ("enabled", Value::Boolean(true))])
+        );
+        // [authors.links] belongs to the last [[authors]]
+        assert_eq!(
+            document["authors"],
+            Value::Array(vec![
+                table(&[("name", string("Ada"))]),
+                table(&[("name", string("Grace")), ("links", table(&[("site", string("https://example.com"))]))]),
+            ])
+        );
+    }
+
+    #[test]
+    fn strings_dates_and_numbers() {
+        let document = parse(
+            "escaped = \"tab\\there \\\"quoted\\\" back\\\\slash \\u00e9\\U0001F600\"\n\
+             literal = 'C:\\path\\n'\n\
+             date = 2024-03-01\n\
+             datetime = 2024-03-01 10:30:00\n\
+             offset = 2024-03-01T10:30:00+02:00\n\
+             time = 07:32:00\n\
+             negative = -17\n\
+             positive = +3\n\
+             float = -0.25\n",
+        )
+        .unwrap();
+        assert_eq!(document["escaped"], string("tab\there \"quoted\" back\\slash \u{e9}\u{1F600}"));
+        assert_eq!(document["literal"], string("C:\\path\\n"));
+        assert_eq!(document["date"], Value::Datetime("2024-03-01".to_string()));
+        assert_eq!(document["datetime"], Value::Datetime("2024-03-01 10:30:00".to_string()));
+        assert_eq!(document["offset"], Value::Datetime("2024-03-01T10:30:00+02:00".to_string()));
+        assert_eq!(document["time"], Value::Datetime("07:32:00".to_string()));
+        assert_eq!(document["negative"], Value::Integer(-17));
+        assert_eq!(document["positive"], Value::Integer(3));
+        assert_eq!(document["float"], Value::Float(-0.25));
+        assert_eq!(document["date"].as_str(), Some("2024-03-01"));
+    }
+
+    #[test]
+    fn errors_name_the_line() {
+        let error = |input: &str| parse(input).unwrap_err().to_string();
+        assert_eq!(error("a = 1\nb = 2\na = 3\n"), "line 3: duplicate key 'a'");
+        assert_eq!(error("[t]\nx = 1\n[t.x]\n"), "line 3: key 'x' is not a table");
+        assert_eq!(error("title = \"open\n"), "line 1: unterminated string");
+        assert_eq!(error("\n\ntitle = 'open\n"), "line 3: unterminated string");
+        assert_eq!(error("title = \"bad \\q escape\"\n"), "line 1: invalid escape sequence");
+        assert_eq!(error("title = \"\\u12\"\n"), "line 1: invalid unicode escape");
+        assert_eq!(error("[feed\n"), "line 1: unterminated table header");
+        assert_eq!(error("title \"x\"\n"), "line 1: expected '=' after key");
+        assert_eq!(error("title = 1 2\n"), "line 1: unexpected character '2'");
+        assert_eq!(error("size = big\n"), "line 1: invalid value 'big'");
+        assert_eq!(error("tags = [1 2]\n"), "line 1: expected ',' or ']' in array");
+    }
+}
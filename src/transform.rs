@@ -0,0 +1,42 @@
+// A transformation applied to the HTML of every generated page before it is final
+pub trait Transform: Send + Sync {
+    fn apply(&self, html: &str) -> String;
+}
+
+// Plain functions and closures can be used as transforms
+impl<F> Transform for F
+where
+    F: Fn(&str) -> String + Send + Sync,
+{
+    fn apply(&self, html: &str) -> String {
+        self(html)
+    }
+}
+
+// Transforms applied in the order they were added
+#[derive(Default)]
+pub struct TransformPipeline {
+    transforms: Vec<Box<dyn Transform>>,
+}
+
+impl TransformPipeline {
+    pub fn new() -> Self {
+        TransformPipeline::default()
+    }
+
+    pub fn push<T: Transform + 'static>(&mut self, transform: T) {
+        self.transforms.push(Box::new(transform));
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.transforms.is_empty()
+    }
+
+    pub fn apply(&self, html: &str) -> String {
+        let mut result = html.to_string();
+        for transform in &self.transforms {
+            result = transform.apply(&result);
+        }
+        result
+    }
+}
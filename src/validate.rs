@@ -0,0 +1,208 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::path::PathBuf;
+
+use crate::error::IroniteError;
+use crate::generator::{entry_name, has_content_file, tag_ancestors};
+use crate::meta::LINK_FILE;
+
+// Names that would collide with what the build writes at the top of the public directory
+pub const RESERVED_NAMES: &[&str] = &[
+    "entries", "static", "images", "tags", "index.html", "404.html", "feed.json", "atom.xml", "robots.txt",
+    "humans.txt", "sitemap.xml", "series",
+];
+
+// Device names Windows will not create files or directories for, even with an extension (con.txt)
+const WINDOWS_DEVICE_NAMES: &[&str] = &[
+    "con", "prn", "aux", "nul", "com1", "com2", "com3", "com4", "com5", "com6", "com7", "com8", "com9", "lpt1",
+    "lpt2", "lpt3", "lpt4", "lpt5", "lpt6", "lpt7", "lpt8", "lpt9",
+];
+
+// Characters that break URLs or paths when used in an entry or tag name
+const UNSAFE_CHARACTERS: &[char] = &['#', '?', '%', '/', '\\', '<', '>', '"', '|', '*', ':'];
+
+// An entry directory or tag whose name cannot be used as a URL
+#[derive(Debug, Clone, PartialEq)]
+pub struct NameProblem {
+    // "entry" or "tag"
+    pub kind: &'static str,
+    pub name: String,
+    pub reason: &'static str,
+    // A name that would pass
+    pub suggestion: String,
+}
+
+impl fmt::Display for NameProblem {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} \"{}\": {}, rename it to \"{}\"", self.kind, self.name, self.reason, self.suggestion)
+    }
+}
+
+// Check entry directory names and tags, returning every problem found, entries first. Nested tags
+// (programming/rust) are checked one segment at a time
+pub fn validate_names(entry_names: &[&str], tags: &[String]) -> Vec<NameProblem> {
+    let entries = entry_names.iter().filter_map(|name| {
+        let reason = name_problem(name)?;
+        Some(NameProblem { kind: "entry", name: name.to_string(), reason, suggestion: suggest_name(name, "entry") })
+    });
+    let tags = tags.iter().filter_map(|tag| {
+        let reason = tag.split('/').find_map(|segment| if segment.is_empty() { Some("empty tag segment") } else { name_problem(segment) })?;
+        Some(NameProblem { kind: "tag", name: tag.to_string(), reason, suggestion: suggest_tag(tag) })
+    });
+    entries.chain(tags).collect()
+}
+
+// Why a name cannot be used, if it cannot
+fn name_problem(name: &str) -> Option<&'static str> {
+    if name.is_empty() {
+        Some("empty name")
+    } else if name.starts_with('.') {
+        Some("hidden name")
+    } else if name.chars().any(|c| c.is_control() || UNSAFE_CHARACTERS.contains(&c)) {
+        Some("unsafe characters")
+    } else if is_reserved(name) {
+        Some("reserved name")
+    } else {
+        None
+    }
+}
+
+fn is_reserved(name: &str) -> bool {
+    let lower = name.to_lowercase();
+    let stem = lower.split('.').next().unwrap_or("");
+    RESERVED_NAMES.contains(&lower.as_str()) || WINDOWS_DEVICE_NAMES.contains(&stem)
+}
+
+// Unsafe characters become dashes and leading dots go. A reserved result gets the kind appended
+fn suggest_name(name: &str, kind: &str) -> String {
+    let mut suggestion = String::new();
+    for c in name.chars() {
+        let c = if c.is_control() || UNSAFE_CHARACTERS.contains(&c) { '-' } else { c };
+        if !(c == '-' && suggestion.ends_with('-')) {
+            suggestion.push(c);
+        }
+    }
+    let suggestion = suggestion.trim_start_matches(['.', '-']).trim_end_matches('-');
+    if suggestion.is_empty() {
+        kind.to_string()
+    } else if is_reserved(suggestion) {
+        // Before the extension, which does not help with device names: LPT1.txt becomes LPT1-tag.txt
+        let (stem, extension) = suggestion.split_at(suggestion.find('.').unwrap_or(suggestion.len()));
+        format!("{}-{}{}", stem, kind, extension)
+    } else {
+        suggestion.to_string()
+    }
+}
+
+// suggest_name for each segment of a tag, empty segments dropped
+fn suggest_tag(tag: &str) -> String {
+    let segments: Vec<String> = tag.split('/').filter(|segment| !segment.is_empty()).map(|segment| suggest_name(segment, "tag")).collect();
+    if segments.is_empty() {
+        "tag".to_string()
+    } else {
+        segments.join("/")
+    }
+}
+
+// Check every tag of tags_map against tags_whitelist, returning all violations together as InvalidTags,
+// sorted by tag then entry. The parents of a whitelisted nested tag (programming for programming/rust)
+// are allowed too, since entries are listed under them
+pub fn validate_tags_whitelist(tags_map: &HashMap<String, Vec<PathBuf>>, whitelist: &[String]) -> Result<(), IroniteError> {
+    let allowed = |tag: &str| whitelist.iter().any(|listed| listed == tag || tag_ancestors(listed).contains(&tag));
+    let mut violations: Vec<(&String, &PathBuf)> = tags_map
+        .iter()
+        .filter(|(tag, _)| !allowed(tag))
+        .flat_map(|(tag, paths)| paths.iter().map(move |path| (tag, path)))
+        .collect();
+    if violations.is_empty() {
+        return Ok(());
+    }
+    violations.sort();
+    Err(IroniteError::InvalidTags(
+        violations.into_iter().map(|(tag, entry)| IroniteError::InvalidTag { tag: tag.clone(), entry: entry.clone() }).collect(),
+    ))
+}
+
+// Entries are either a page of the site or a link to another one. Entries with both a link.txt and a
+// content file are returned together as LinkWithContent
+pub fn validate_link_entries(entry_paths: &[PathBuf]) -> Result<(), IroniteError> {
+    let names: Vec<String> = entry_paths
+        .iter()
+        .filter(|path| path.join(LINK_FILE).is_file() && has_content_file(path))
+        .filter_map(|path| entry_name(path))
+        .map(str::to_string)
+        .collect();
+    if names.is_empty() {
+        Ok(())
+    } else {
+        Err(IroniteError::LinkWithContent(names))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reports_every_problem_with_a_suggestion() {
+        let entries = ["hello_world", "con", "static", ".hidden", "what?#now", "Über uns"];
+        let tags = vec![
+            "rust".to_string(),
+            "entries".to_string(),
+            "c/c++".to_string(),
+            "rust//embedded".to_string(),
+            "programming/.hidden".to_string(),
+            "LPT1.txt".to_string(),
+        ];
+        let problems: Vec<String> = validate_names(&entries, &tags).iter().map(NameProblem::to_string).collect();
+        assert_eq!(
+            problems,
+            vec![
+                "entry \"con\": reserved name, rename it to \"con-entry\"",
+                "entry \"static\": reserved name, rename it to \"static-entry\"",
+                "entry \".hidden\": hidden name, rename it to \"hidden\"",
+                "entry \"what?#now\": unsafe characters, rename it to \"what-now\"",
+                "tag \"entries\": reserved name, rename it to \"entries-tag\"",
+                "tag \"rust//embedded\": empty tag segment, rename it to \"rust/embedded\"",
+                "tag \"programming/.hidden\": hidden name, rename it to \"programming/hidden\"",
+                "tag \"LPT1.txt\": reserved name, rename it to \"LPT1-tag.txt\"",
+            ]
+        );
+    }
+
+    #[test]
+    fn suggestions_are_valid_names() {
+        for name in ["", "...", "a//b", "nul", "%%", "index.html", "com1.tar.gz"] {
+            let suggestion = suggest_name(name, "tag");
+            assert_eq!(name_problem(&suggestion), None, "{:?} -> {:?}", name, suggestion);
+        }
+    }
+
+    #[test]
+    fn tags_outside_the_whitelist_are_all_reported() {
+        let mut tags_map = HashMap::new();
+        tags_map.insert("programming".to_string(), vec![PathBuf::from("entries/a")]);
+        tags_map.insert("programming/rust".to_string(), vec![PathBuf::from("entries/a")]);
+        tags_map.insert("web".to_string(), vec![PathBuf::from("entries/b")]);
+        let whitelist = vec!["programming/rust".to_string(), "web".to_string()];
+        assert!(validate_tags_whitelist(&tags_map, &whitelist).is_ok());
+
+        tags_map.insert("misc".to_string(), vec![PathBuf::from("entries/b"), PathBuf::from("entries/a")]);
+        match validate_tags_whitelist(&tags_map, &whitelist) {
+            Err(IroniteError::InvalidTags(errors)) => {
+                let errors: Vec<String> = errors.iter().map(IroniteError::to_string).collect();
+                assert_eq!(errors, vec![
+                    "tag misc of entry entries/a is not in tags_whitelist",
+                    "tag misc of entry entries/b is not in tags_whitelist",
+                ]);
+            }
+            other => panic!("expected InvalidTags, got {:?}", other),
+        }
+
+        // An empty whitelist allows no tag at all
+        match validate_tags_whitelist(&tags_map, &[]) {
+            Err(IroniteError::InvalidTags(errors)) => assert_eq!(errors.len(), 5),
+            other => panic!("expected InvalidTags, got {:?}", other),
+        }
+    }
+}